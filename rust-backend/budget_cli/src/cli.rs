@@ -0,0 +1,758 @@
+use budget_core::app_error::AppError;
+use budget_core::csv_import::{self, ColumnMapping};
+use budget_core::export::{self, ExportFormat};
+use budget_core::ofx_import;
+use budget_core::qif::{self, QifOptions};
+use budget_core::hledger;
+use budget_core::icalendar;
+use budget_core::pdf_report::{self, ReportPeriod};
+use budget_core::workbook::{self, WorkbookFormat};
+use budget_core::models::{Frequency, ImportReport, LedgerImportReport, RecordType};
+use budget_core::pool::Pool;
+use budget_core::encryption::{self, DbKey, KdfAlgorithm, KdfSettings};
+use budget_core::backup::{BackupStore, Retention};
+use budget_core::archive::{self, ArchiveFormat, ArchiveImportReport, ImportMode, OnConflict, TableReport};
+use budget_core::attachment_store::AttachmentStore;
+use budget_core::service;
+
+use chrono::{Local, Utc};
+use clap::{Args, Parser, Subcommand};
+use directories::ProjectDirs;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Parser)]
+#[command(name = "budget_cli", about = "Overkill budget app: command line import, export and report tools")]
+pub struct Cli {
+    #[command(flatten)]
+    pub database: DatabaseArgs,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+// Where the database is and, when it's encrypted, how its key is derived.
+// The passphrase itself is asked for, or read from BUDGET_DB_PASSPHRASE
+#[derive(Args)]
+pub struct DatabaseArgs {
+    /// SQLite database file
+    #[arg(long, global = true, default_value = "budget.db")]
+    pub db: String,
+    /// Create the database encrypted if it doesn't exist yet. Existing
+    /// encrypted databases are recognized without it
+    #[arg(long, global = true)]
+    pub encrypted: bool,
+    /// PBKDF2 iterations the database key is derived with; an encrypted
+    /// database only opens with the settings it was encrypted with
+    #[arg(long, global = true, default_value_t = KdfSettings::default().iterations)]
+    pub kdf_iter: u32,
+    /// Hash the database key is derived with: sha1, sha256 or sha512
+    #[arg(long, global = true, default_value = "sha512")]
+    pub kdf_algorithm: KdfAlgorithm,
+}
+
+impl DatabaseArgs {
+    fn kdf(&self) -> KdfSettings {
+        KdfSettings { iterations: self.kdf_iter, algorithm: self.kdf_algorithm }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Import records from a CSV file with a header row
+    ImportCsv(ImportCsvArgs),
+    /// Import transactions from an OFX/QFX bank or card statement
+    ImportOfx(ImportOfxArgs),
+    /// Import budget categories and transactions from a QIF file
+    ImportQif(ImportQifArgs),
+    /// Export records and ledger transactions as QIF
+    ExportQif(ExportQifArgs),
+    /// Import the periodic transactions of a ledger/hledger journal as records
+    ImportJournal(ImportJournalArgs),
+    /// Export records and transactions as a ledger/hledger journal
+    ExportJournal(ExportJournalArgs),
+    /// Export recurring records as an iCalendar (.ics) file
+    ExportCalendar(ExportCalendarArgs),
+    /// Export a spreadsheet workbook with the records, summary, per-type
+    /// breakdown and a 12-month projection
+    ExportWorkbook(ExportWorkbookArgs),
+    /// Write the printable PDF budget report for a period
+    ReportPdf(ReportPdfArgs),
+    /// Export records (or the monthly summary) as CSV or JSON
+    Export(ExportArgs),
+    /// Encrypt a plaintext database in place, with the --kdf-* settings.
+    /// Nothing else may have the database open meanwhile
+    Encrypt,
+    /// Change the passphrase (and key derivation settings) of an encrypted
+    /// database. Nothing else may have the database open meanwhile
+    Rekey(RekeyArgs),
+    /// Snapshot the database into the backup directory and rotate out old
+    /// snapshots. Safe while the server is running
+    Backup(BackupArgs),
+    /// List the snapshots in the backup directory, newest first
+    ListBackups(BackupDirArgs),
+    /// Replace the database with a snapshot, after checking it's intact. The
+    /// database as it was is snapshotted first
+    Restore(RestoreArgs),
+    /// Export the whole database (records, trash, history, transactions,
+    /// rules and attachments) as a versioned archive, for moving it to
+    /// another machine
+    ExportArchive(ExportArchiveArgs),
+    /// Import an archive written by export-archive, merging it into the
+    /// database or replacing everything in it. All or nothing
+    ImportArchive(ImportArchiveArgs),
+}
+
+#[derive(Args)]
+pub struct ImportCsvArgs {
+    /// CSV file to import
+    pub file: PathBuf,
+
+    /// Column (header name or 1-based position) holding the record name
+    #[arg(long)]
+    pub name_column: Option<String>,
+    /// Column holding the amount
+    #[arg(long)]
+    pub amount_column: Option<String>,
+    /// Column holding the frequency
+    #[arg(long)]
+    pub frequency_column: Option<String>,
+    /// Column holding the record type (income/expense/debt)
+    #[arg(long)]
+    pub type_column: Option<String>,
+    /// Column holding notes
+    #[arg(long)]
+    pub notes_column: Option<String>,
+    /// Column holding the payee
+    #[arg(long)]
+    pub payee_column: Option<String>,
+    /// Column holding the first due date (YYYY-MM-DD)
+    #[arg(long)]
+    pub starts_on_column: Option<String>,
+
+    /// Frequency for rows that don't have one
+    #[arg(long, default_value = "Monthly", value_parser = parse_frequency)]
+    pub default_frequency: Frequency,
+    /// Record type for rows that don't have one [default: from the amount's sign]
+    #[arg(long, value_parser = parse_record_type)]
+    pub default_type: Option<RecordType>,
+    /// Field delimiter
+    #[arg(long, default_value_t = ',')]
+    pub delimiter: char,
+
+    /// Only show what would be imported
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Import the valid rows even if some rows have errors
+    #[arg(long)]
+    pub skip_invalid: bool,
+}
+
+#[derive(Args)]
+pub struct ImportOfxArgs {
+    /// OFX or QFX file to import
+    pub file: PathBuf,
+
+    /// Only show what would be imported
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args)]
+pub struct ImportQifArgs {
+    /// QIF file to import
+    pub file: PathBuf,
+
+    /// Account for transactions that aren't in an !Account block
+    #[arg(long, default_value = "QIF")]
+    pub account: String,
+    /// Dates are DD/MM/YYYY instead of MM/DD/YYYY
+    #[arg(long)]
+    pub day_first: bool,
+
+    /// Only show what would be imported
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Import the valid categories even if some have errors
+    #[arg(long)]
+    pub skip_invalid: bool,
+}
+
+#[derive(Args)]
+pub struct ExportQifArgs {
+    /// Write to this file instead of stdout
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ImportJournalArgs {
+    /// Journal file to import
+    pub file: PathBuf,
+
+    /// Only show what would be imported
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Import the valid periodic transactions even if some have errors
+    #[arg(long)]
+    pub skip_invalid: bool,
+}
+
+#[derive(Args)]
+pub struct ExportJournalArgs {
+    /// Leave out the imported (actual) transactions, only write the budget
+    #[arg(long)]
+    pub no_actuals: bool,
+    /// Write to this file instead of stdout
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ExportCalendarArgs {
+    /// Write to this file instead of stdout
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ExportWorkbookArgs {
+    /// xlsx or ods, by default taken from the output file's extension
+    #[arg(long)]
+    pub format: Option<WorkbookFormat>,
+    /// Workbook file to write
+    #[arg(long, short)]
+    pub output: PathBuf,
+}
+
+#[derive(Args)]
+pub struct ExportArchiveArgs {
+    /// json, or zip to include the attachment contents; by default taken
+    /// from the output file's extension
+    #[arg(long)]
+    pub format: Option<ArchiveFormat>,
+    /// Archive file to write
+    #[arg(long, short)]
+    pub output: PathBuf,
+    #[command(flatten)]
+    pub attachments: AttachmentDirArgs,
+}
+
+#[derive(Args)]
+pub struct ImportArchiveArgs {
+    /// Archive file (json or zip) to import
+    pub file: PathBuf,
+    /// Delete everything in the database first, so it ends up as the archive
+    #[arg(long)]
+    pub replace: bool,
+    /// When merging, what to do with records, transactions, rules and
+    /// attachments already in the database: keep, overwrite or new-id
+    #[arg(long, default_value = "keep", conflicts_with = "replace")]
+    pub on_conflict: OnConflict,
+    #[command(flatten)]
+    pub attachments: AttachmentDirArgs,
+}
+
+#[derive(Args)]
+pub struct AttachmentDirArgs {
+    /// Directory the attachment contents are stored in [default: the app
+    /// data directory's, as the server uses]
+    #[arg(long)]
+    pub attachments_dir: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ReportPdfArgs {
+    /// First month of the period, YYYY-MM (defaults to this month)
+    #[arg(long)]
+    pub from: Option<String>,
+    /// Last month of the period, YYYY-MM (defaults to --from)
+    #[arg(long)]
+    pub to: Option<String>,
+    /// PDF file to write
+    #[arg(long, short)]
+    pub output: PathBuf,
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// csv or json
+    #[arg(long, default_value = "csv")]
+    pub format: ExportFormat,
+    /// Only export records of this type
+    #[arg(long = "type", value_parser = parse_record_type)]
+    pub record_type: Option<RecordType>,
+    /// Only export records with this frequency
+    #[arg(long, value_parser = parse_frequency)]
+    pub frequency: Option<Frequency>,
+    /// Export the normalized monthly summary instead of the records
+    #[arg(long, conflicts_with_all = ["record_type", "frequency"])]
+    pub summary: bool,
+    /// Write to this file instead of stdout
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct RekeyArgs {
+    /// PBKDF2 iterations for the new key [default: --kdf-iter]
+    #[arg(long)]
+    pub new_kdf_iter: Option<u32>,
+    /// Hash for the new key [default: --kdf-algorithm]
+    #[arg(long)]
+    pub new_kdf_algorithm: Option<KdfAlgorithm>,
+}
+
+#[derive(Args)]
+pub struct BackupDirArgs {
+    /// Directory the snapshots are kept in [default: BUDGET_BACKUP_DIR, or
+    /// backups/ in the app data directory, like the server]
+    #[arg(long)]
+    pub backup_dir: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct BackupArgs {
+    #[command(flatten)]
+    pub dir: BackupDirArgs,
+    /// Keep the newest snapshot of this many days
+    #[arg(long, default_value_t = Retention::default().daily)]
+    pub keep_daily: usize,
+    /// Keep the newest snapshot of this many weeks
+    #[arg(long, default_value_t = Retention::default().weekly)]
+    pub keep_weekly: usize,
+    /// Keep the newest snapshot of this many months
+    #[arg(long, default_value_t = Retention::default().monthly)]
+    pub keep_monthly: usize,
+}
+
+#[derive(Args)]
+pub struct RestoreArgs {
+    /// Snapshot to restore, by name as list-backups shows it
+    pub name: String,
+    #[command(flatten)]
+    pub dir: BackupDirArgs,
+}
+
+fn parse_frequency(s: &str) -> Result<Frequency, String> {
+    Frequency::parse_lenient(s).ok_or_else(|| format!("unknown frequency `{}`", s))
+}
+
+fn parse_record_type(s: &str) -> Result<RecordType, String> {
+    RecordType::parse_lenient(s).ok_or_else(|| format!("unknown record type `{}`", s))
+}
+
+// who CLI changes are attributed to in the history
+fn cli_actor() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .map(|user| format!("cli:{}", user))
+        .unwrap_or_else(|_| "cli".to_string())
+}
+
+// each command runs a query or two one after the other, so there's no use
+// for reader connections
+fn open_db(database: &DatabaseArgs) -> Result<Pool, AppError> {
+    let key = database_key(database)?;
+    Ok(Pool::open_with_key(&database.db, 0, key.as_ref())?)
+}
+
+// the key for an encrypted database, or for a new one with --encrypted
+fn database_key(database: &DatabaseArgs) -> Result<Option<DbKey>, AppError> {
+    let path = Path::new(&database.db);
+    if encryption::is_encrypted(path)? {
+        let passphrase = passphrase("BUDGET_DB_PASSPHRASE", &format!("Passphrase for {}: ", database.db))?;
+        Ok(Some(DbKey::new(passphrase, database.kdf())))
+    } else if database.encrypted && !path.exists() {
+        let passphrase = new_passphrase("BUDGET_DB_PASSPHRASE", &format!("New passphrase for {}: ", database.db))?;
+        Ok(Some(DbKey::new(passphrase, database.kdf())))
+    } else {
+        Ok(None)
+    }
+}
+
+// a passphrase from the environment variable `env`, asked for if it's unset
+fn passphrase(env: &str, prompt: &str) -> Result<String, AppError> {
+    match std::env::var(env) {
+        Ok(passphrase) => Ok(passphrase),
+        Err(_) => prompt_passphrase(env, prompt),
+    }
+}
+
+// without a terminal to ask on, the passphrase has to come from `env`
+fn prompt_passphrase(env: &str, prompt: &str) -> Result<String, AppError> {
+    rpassword::prompt_password(prompt)
+        .map_err(|e| AppError(format!("can't ask for the passphrase ({}), set {} instead", e, env)))
+}
+
+// a passphrase to encrypt with: asked for twice, so a typo doesn't lock the
+// database for good
+fn new_passphrase(env: &str, prompt: &str) -> Result<String, AppError> {
+    let passphrase = match std::env::var(env) {
+        Ok(passphrase) => passphrase,
+        Err(_) => {
+            let passphrase = prompt_passphrase(env, prompt)?;
+            if prompt_passphrase(env, "Repeat it: ")? != passphrase {
+                return Err(AppError("the passphrases don't match".to_string()));
+            }
+            passphrase
+        }
+    };
+    if passphrase.is_empty() {
+        return Err(AppError("the passphrase can't be empty".to_string()));
+    }
+    Ok(passphrase)
+}
+
+pub fn run(command: Command, database: &DatabaseArgs) -> Result<(), AppError> {
+    match command {
+        Command::ImportCsv(args) => import_csv(args, database),
+        Command::ImportOfx(args) => import_ofx(args, database),
+        Command::ImportQif(args) => import_qif(args, database),
+        Command::ExportQif(args) => export_qif(args, database),
+        Command::ImportJournal(args) => import_journal(args, database),
+        Command::ExportJournal(args) => export_journal(args, database),
+        Command::ExportCalendar(args) => export_calendar(args, database),
+        Command::ExportWorkbook(args) => export_workbook(args, database),
+        Command::ReportPdf(args) => report_pdf(args, database),
+        Command::Export(args) => export(args, database),
+        Command::Encrypt => encrypt(database),
+        Command::Rekey(args) => rekey(args, database),
+        Command::Backup(args) => backup(args, database),
+        Command::ListBackups(args) => list_backups(args),
+        Command::Restore(args) => restore(args, database),
+        Command::ExportArchive(args) => export_archive(args, database),
+        Command::ImportArchive(args) => import_archive(args, database),
+    }
+}
+
+fn backup_dir(args: &BackupDirArgs) -> Result<PathBuf, AppError> {
+    if let Some(dir) = &args.backup_dir {
+        return Ok(dir.clone());
+    }
+    if let Ok(dir) = std::env::var("BUDGET_BACKUP_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+    let dirs = ProjectDirs::from("com", "overkill", "budget")
+        .ok_or_else(|| AppError("could not determine the app data directory, pass --backup-dir".to_string()))?;
+    Ok(dirs.data_dir().join("backups"))
+}
+
+fn backup(args: BackupArgs, database: &DatabaseArgs) -> Result<(), AppError> {
+    let key = database_key(database)?;
+    let db = Pool::open_with_key(&database.db, 0, key.as_ref())?;
+    let backups = BackupStore::new(backup_dir(&args.dir)?, key);
+
+    let backup = service::backup_database(&db, &backups)?;
+    println!("backed up {} to {}", database.db, backups.dir().join(&backup.name).display());
+    let retention = Retention { daily: args.keep_daily, weekly: args.keep_weekly, monthly: args.keep_monthly };
+    for removed in backups.rotate(&retention)? {
+        println!("removed {}", removed.name);
+    }
+    Ok(())
+}
+
+fn list_backups(args: BackupDirArgs) -> Result<(), AppError> {
+    let dir = backup_dir(&args)?;
+    let backups = BackupStore::new(&dir, None).list()?;
+    if backups.is_empty() {
+        println!("no backups in {}", dir.display());
+    }
+    for backup in backups {
+        println!(
+            "{}  {}  {:>8} KiB",
+            backup.name,
+            backup.taken_at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+            backup.size.div_ceil(1024)
+        );
+    }
+    Ok(())
+}
+
+fn restore(args: RestoreArgs, database: &DatabaseArgs) -> Result<(), AppError> {
+    let key = database_key(database)?;
+    let db = Pool::open_with_key(&database.db, 0, key.as_ref())?;
+    let backups = BackupStore::new(backup_dir(&args.dir)?, key);
+
+    let (restored, before) = service::restore_backup(&db, &backups, &args.name)?;
+    println!("restored {} from {}", database.db, restored.name);
+    println!("the database as it was is backed up as {}", before.name);
+    Ok(())
+}
+
+fn attachment_store(args: &AttachmentDirArgs) -> Result<AttachmentStore, AppError> {
+    if let Some(dir) = &args.attachments_dir {
+        return Ok(AttachmentStore::new(dir));
+    }
+    let dirs = ProjectDirs::from("com", "overkill", "budget")
+        .ok_or_else(|| AppError("could not determine the app data directory, pass --attachments-dir".to_string()))?;
+    Ok(AttachmentStore::new(dirs.data_dir().join("attachments")))
+}
+
+fn export_archive(args: ExportArchiveArgs, database: &DatabaseArgs) -> Result<(), AppError> {
+    let format = match args.format {
+        Some(format) => format,
+        None => match args.output.extension().and_then(|e| e.to_str()) {
+            Some(extension) => ArchiveFormat::from_str(extension).map_err(AppError)?,
+            None => ArchiveFormat::Zip,
+        },
+    };
+    let db = open_db(database)?;
+    let archive = service::export_archive(&db)?;
+    let mut out = io::BufWriter::new(File::create(&args.output)?);
+    match format {
+        ArchiveFormat::Json => archive::write_json(&archive, &mut out)?,
+        ArchiveFormat::Zip => archive::write_zip(&archive, &attachment_store(&args.attachments)?, &mut out)?,
+    }
+    out.flush()?;
+    println!(
+        "exported {} records, {} transactions, {} rules and {} attachments to {}",
+        archive.records.len(),
+        archive.ledger_entries.len(),
+        archive.rules.len(),
+        archive.attachments.len(),
+        args.output.display()
+    );
+    Ok(())
+}
+
+fn import_archive(args: ImportArchiveArgs, database: &DatabaseArgs) -> Result<(), AppError> {
+    let file = archive::read(&std::fs::read(&args.file)?)?;
+    let mode = if args.replace { ImportMode::Replace } else { ImportMode::Merge(args.on_conflict) };
+    let db = open_db(database)?;
+    let report = service::import_archive(&db, &attachment_store(&args.attachments)?, &file, mode)?;
+    print_archive_report(&report);
+    Ok(())
+}
+
+fn print_archive_report(report: &ArchiveImportReport) {
+    let tables: [(&str, &TableReport); 4] = [
+        ("records", &report.records),
+        ("transactions", &report.ledger_entries),
+        ("rules", &report.rules),
+        ("attachments", &report.attachments),
+    ];
+    for (name, table) in tables {
+        println!(
+            "{:<13} {} added, {} overwritten, {} added under a new id, {} skipped",
+            name, table.added, table.replaced, table.renamed, table.skipped
+        );
+    }
+    println!("{:<13} {} added", "history", report.history);
+    println!("{:<13} {} added", "undo journal", report.journal);
+    if report.missing_contents > 0 {
+        println!("{} attachments left out, their contents weren't in the archive", report.missing_contents);
+    }
+}
+
+fn encrypt(database: &DatabaseArgs) -> Result<(), AppError> {
+    if !Path::new(&database.db).exists() {
+        return Err(AppError(format!("{} doesn't exist", database.db)));
+    }
+    if encryption::is_encrypted(Path::new(&database.db))? {
+        return Err(AppError(format!("{} is already encrypted, use rekey to change its passphrase", database.db)));
+    }
+    let passphrase = new_passphrase("BUDGET_DB_NEW_PASSPHRASE", &format!("New passphrase for {}: ", database.db))?;
+    encryption::encrypt(&database.db, &DbKey::new(passphrase, database.kdf()))?;
+    println!("encrypted {}", database.db);
+    Ok(())
+}
+
+fn rekey(args: RekeyArgs, database: &DatabaseArgs) -> Result<(), AppError> {
+    if !encryption::is_encrypted(Path::new(&database.db))? {
+        return Err(AppError(format!("{} isn't encrypted, use encrypt first", database.db)));
+    }
+    let current = DbKey::new(
+        passphrase("BUDGET_DB_PASSPHRASE", &format!("Current passphrase for {}: ", database.db))?,
+        database.kdf(),
+    );
+    let kdf = KdfSettings {
+        iterations: args.new_kdf_iter.unwrap_or(database.kdf_iter),
+        algorithm: args.new_kdf_algorithm.unwrap_or(database.kdf_algorithm),
+    };
+    let new = DbKey::new(new_passphrase("BUDGET_DB_NEW_PASSPHRASE", "New passphrase: ")?, kdf);
+    encryption::rekey(&database.db, &current, &new)?;
+    println!("re-encrypted {}", database.db);
+    Ok(())
+}
+
+fn export(args: ExportArgs, database: &DatabaseArgs) -> Result<(), AppError> {
+    let db = open_db(database)?;
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(io::BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+
+    if args.summary {
+        export::write_summary(&service::get_summary(&db)?, args.format, &mut out)?;
+    } else {
+        let records = service::get_records_filtered(&db, args.record_type, args.frequency)?;
+        export::write_records(&records, args.format, &mut out)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn export_qif(args: ExportQifArgs, database: &DatabaseArgs) -> Result<(), AppError> {
+    let db = open_db(database)?;
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(io::BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+    qif::write(&service::get_all_records(&db)?, &service::get_all_ledger_entries(&db)?, &mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+fn export_journal(args: ExportJournalArgs, database: &DatabaseArgs) -> Result<(), AppError> {
+    let db = open_db(database)?;
+    let entries = if args.no_actuals { Vec::new() } else { service::get_all_ledger_entries(&db)? };
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(io::BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+    hledger::write(&service::get_all_records(&db)?, &entries, &mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+fn export_calendar(args: ExportCalendarArgs, database: &DatabaseArgs) -> Result<(), AppError> {
+    let db = open_db(database)?;
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(io::BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+    icalendar::write(&service::get_calendar_schedule(&db)?, Utc::now(), &mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+fn export_workbook(args: ExportWorkbookArgs, database: &DatabaseArgs) -> Result<(), AppError> {
+    let format = match args.format {
+        Some(format) => format,
+        None => match args.output.extension().and_then(|e| e.to_str()) {
+            Some(extension) => WorkbookFormat::from_str(extension).map_err(AppError)?,
+            None => WorkbookFormat::Xlsx,
+        },
+    };
+    let db = open_db(database)?;
+    let book = workbook::build(&service::get_all_records(&db)?, Local::now().date_naive());
+    let mut out = io::BufWriter::new(File::create(&args.output)?);
+    workbook::write(&book, format, &mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+fn report_pdf(args: ReportPdfArgs, database: &DatabaseArgs) -> Result<(), AppError> {
+    let today = Local::now().date_naive();
+    let period = ReportPeriod::parse(args.from.as_deref(), args.to.as_deref(), today).map_err(AppError)?;
+    let db = open_db(database)?;
+    let mut out = io::BufWriter::new(File::create(&args.output)?);
+    pdf_report::write(&service::get_all_records(&db)?, &period, today, &mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+fn import_journal(args: ImportJournalArgs, database: &DatabaseArgs) -> Result<(), AppError> {
+    let rows = hledger::parse(&std::fs::read_to_string(&args.file)?);
+    let db = open_db(database)?;
+    let report = service::import_records(&db, rows, args.dry_run, args.skip_invalid, &cli_actor())?;
+    print_import_report(&report)
+}
+
+fn import_csv(args: ImportCsvArgs, database: &DatabaseArgs) -> Result<(), AppError> {
+    if !args.delimiter.is_ascii() {
+        return Err(AppError(format!("delimiter `{}` must be an ASCII character", args.delimiter)));
+    }
+    let mapping = ColumnMapping {
+        name: args.name_column,
+        amount: args.amount_column,
+        frequency: args.frequency_column,
+        record_type: args.type_column,
+        notes: args.notes_column,
+        payee: args.payee_column,
+        starts_on: args.starts_on_column,
+        default_frequency: args.default_frequency,
+        default_record_type: args.default_type,
+        delimiter: args.delimiter as u8,
+    };
+
+    let rows = csv_import::parse(File::open(&args.file)?, &mapping)?;
+    let db = open_db(database)?;
+    let report = service::import_records(&db, rows, args.dry_run, args.skip_invalid, &cli_actor())?;
+    print_import_report(&report)
+}
+
+fn print_import_report(report: &ImportReport) -> Result<(), AppError> {
+    for row in &report.rows {
+        match &row.outcome {
+            Ok(r) => println!(
+                "line {:>4}  ok     {} | ${:.2} | {} | {}",
+                row.line, r.name, r.amount, r.frequency, r.record_type
+            ),
+            Err(e) => println!("line {:>4}  error  {}", row.line, e),
+        }
+    }
+
+    let errors = report.error_count();
+    if report.dry_run {
+        println!("dry run: {} rows would be imported, {} have errors", report.rows.len() - errors, errors);
+    } else if report.imported == 0 && errors > 0 {
+        return Err(AppError(format!(
+            "nothing imported: {} rows have errors (fix them or pass --skip-invalid)",
+            errors
+        )));
+    } else {
+        println!("imported {} records, skipped {} rows with errors", report.imported, errors);
+    }
+    Ok(())
+}
+
+fn import_ofx(args: ImportOfxArgs, database: &DatabaseArgs) -> Result<(), AppError> {
+    let bytes = std::fs::read(&args.file)?;
+    let entries = ofx_import::parse(&String::from_utf8_lossy(&bytes))?;
+    let db = open_db(database)?;
+    let report = service::import_ledger_entries(&db, entries, args.dry_run)?;
+    print_ledger_import_report(&report);
+    Ok(())
+}
+
+fn print_ledger_import_report(report: &LedgerImportReport) {
+    for row in &report.rows {
+        let entry = &row.entry;
+        let matched = match &row.suggestion {
+            Some(m) if entry.record_id == Some(m.record_id) => format!("-> {}", m.record_name),
+            Some(m) => format!("-> {}? ({:.0}%)", m.record_name, m.score * 100.0),
+            None => String::new(),
+        };
+        println!(
+            "{}  {:<9}  {:>10.2}  {} {}",
+            entry.posted_on,
+            if row.duplicate { "duplicate" } else { "new" },
+            entry.amount,
+            entry.payee,
+            matched
+        );
+    }
+
+    if report.dry_run {
+        println!(
+            "dry run: {} transactions would be imported, {} were imported before",
+            report.rows.len() - report.duplicates, report.duplicates
+        );
+    } else {
+        println!("imported {} transactions, skipped {} already imported", report.imported, report.duplicates);
+    }
+}
+
+fn import_qif(args: ImportQifArgs, database: &DatabaseArgs) -> Result<(), AppError> {
+    let bytes = std::fs::read(&args.file)?;
+    let options = QifOptions { account: args.account, day_first: args.day_first };
+    let file = qif::parse(&String::from_utf8_lossy(&bytes), &options)?;
+    let db = open_db(database)?;
+    let (records, transactions) = service::import_qif(&db, file, args.dry_run, args.skip_invalid, &cli_actor())?;
+    print_ledger_import_report(&transactions);
+    print_import_report(&records)
+}
@@ -0,0 +1,14 @@
+mod cli;
+
+use clap::Parser;
+use cli::Cli;
+
+fn main() {
+    env_logger::init();
+    let cli = Cli::parse();
+
+    if let Err(e) = cli::run(cli.command, &cli.database) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
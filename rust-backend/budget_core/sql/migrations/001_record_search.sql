@@ -0,0 +1,40 @@
+-- Free-text fields that take part in search
+ALTER TABLE financial_record ADD COLUMN notes TEXT;
+ALTER TABLE financial_record ADD COLUMN payee TEXT;
+
+-- Full-text index over record names, notes and payees. Kept in sync with
+-- financial_record by the triggers below, so nothing outside SQLite has to
+-- remember to touch it.
+CREATE VIRTUAL TABLE IF NOT EXISTS record_search USING fts5(
+    record_id UNINDEXED,
+    name,
+    notes,
+    payee,
+    tokenize = 'unicode61 remove_diacritics 2',
+    prefix = '2 3'
+);
+
+CREATE TRIGGER IF NOT EXISTS financial_record_search_insert
+AFTER INSERT ON financial_record
+BEGIN
+    INSERT INTO record_search (record_id, name, notes, payee)
+    VALUES (new.id, new.name, new.notes, new.payee);
+END;
+
+CREATE TRIGGER IF NOT EXISTS financial_record_search_update
+AFTER UPDATE OF name, notes, payee ON financial_record
+BEGIN
+    DELETE FROM record_search WHERE record_id = old.id;
+    INSERT INTO record_search (record_id, name, notes, payee)
+    VALUES (new.id, new.name, new.notes, new.payee);
+END;
+
+CREATE TRIGGER IF NOT EXISTS financial_record_search_delete
+AFTER DELETE ON financial_record
+BEGIN
+    DELETE FROM record_search WHERE record_id = old.id;
+END;
+
+-- Index whatever was already in the table
+INSERT INTO record_search (record_id, name, notes, payee)
+SELECT id, name, notes, payee FROM financial_record;
@@ -0,0 +1,14 @@
+-- Files (receipts, statements, ...) attached to a record. The content lives
+-- in the attachment store on disk, addressed by its sha256.
+CREATE TABLE IF NOT EXISTS attachment (
+    id BLOB PRIMARY KEY,
+    record_id BLOB NOT NULL REFERENCES financial_record (id) ON DELETE CASCADE,
+    file_name TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    sha256 TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS attachment_record_id ON attachment (record_id);
+CREATE INDEX IF NOT EXISTS attachment_sha256 ON attachment (sha256);
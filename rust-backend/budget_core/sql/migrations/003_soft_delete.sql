@@ -0,0 +1,5 @@
+-- Deleting a record only stamps deleted_at; the row sits in the trash until
+-- it is restored or purged (by hand or once the retention window passes).
+ALTER TABLE financial_record ADD COLUMN deleted_at TEXT;
+
+CREATE INDEX IF NOT EXISTS financial_record_deleted_at ON financial_record (deleted_at);
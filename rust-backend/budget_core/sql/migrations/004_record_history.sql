@@ -0,0 +1,27 @@
+-- Append-only log of every change made to financial_record through the
+-- record repository. Snapshots are the JSON serialized FinancialRecord
+-- before and after the change (NULL where there is no such side, e.g. the
+-- "before" of an insert). No foreign key: history outlives purged records.
+CREATE TABLE IF NOT EXISTS record_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    record_id BLOB NOT NULL,
+    action TEXT NOT NULL,
+    actor TEXT NOT NULL,
+    changed_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    before_json TEXT,
+    after_json TEXT
+);
+
+CREATE INDEX IF NOT EXISTS record_history_record_id ON record_history (record_id);
+
+CREATE TRIGGER IF NOT EXISTS record_history_no_update
+BEFORE UPDATE ON record_history
+BEGIN
+    SELECT RAISE(ABORT, 'record_history is append-only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS record_history_no_delete
+BEFORE DELETE ON record_history
+BEGIN
+    SELECT RAISE(ABORT, 'record_history is append-only');
+END;
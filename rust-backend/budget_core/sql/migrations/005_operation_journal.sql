@@ -0,0 +1,14 @@
+-- Operations made through the service layer that can be undone/redone.
+-- Entries with undone = 0 form the undo stack (newest on top), entries with
+-- undone = 1 the redo stack; they are always a suffix of the journal since
+-- any new operation drops the redo stack.
+CREATE TABLE IF NOT EXISTS operation_journal (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    record_id BLOB NOT NULL,
+    action TEXT NOT NULL,
+    actor TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    before_json TEXT,
+    after_json TEXT,
+    undone INTEGER NOT NULL DEFAULT 0
+);
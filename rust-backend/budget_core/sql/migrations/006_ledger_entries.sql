@@ -0,0 +1,21 @@
+-- Actual transactions imported from bank/card statements, as opposed to the
+-- budgeted financial_record rows. record_id links an entry to the budget
+-- record it pays/receives, when one has been recognised.
+CREATE TABLE IF NOT EXISTS ledger_entry (
+    id BLOB PRIMARY KEY,
+    account TEXT NOT NULL,
+    -- the bank's unique transaction id (OFX FITID), NULL if the source has none
+    fitid TEXT,
+    posted_on TEXT NOT NULL,
+    -- signed: negative is money leaving the account
+    amount REAL NOT NULL,
+    payee TEXT NOT NULL,
+    memo TEXT,
+    record_id BLOB REFERENCES financial_record (id) ON DELETE SET NULL,
+    imported_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+);
+
+-- re-importing an overlapping statement must not duplicate transactions
+CREATE UNIQUE INDEX IF NOT EXISTS ledger_entry_account_fitid ON ledger_entry (account, fitid);
+CREATE INDEX IF NOT EXISTS ledger_entry_posted_on ON ledger_entry (posted_on);
+CREATE INDEX IF NOT EXISTS ledger_entry_record_id ON ledger_entry (record_id);
@@ -0,0 +1,3 @@
+-- what sort of account a ledger entry was posted to (Bank, CreditCard, Cash),
+-- so statement formats that care (QIF) can write it back out the same way
+ALTER TABLE ledger_entry ADD COLUMN account_kind TEXT NOT NULL DEFAULT 'Bank';
@@ -0,0 +1,32 @@
+-- what the rules made of an imported transaction: a clean name, the kind of
+-- money it is, a category and tags (a JSON array of strings)
+ALTER TABLE ledger_entry ADD COLUMN name TEXT;
+ALTER TABLE ledger_entry ADD COLUMN record_type TEXT;
+ALTER TABLE ledger_entry ADD COLUMN category TEXT;
+ALTER TABLE ledger_entry ADD COLUMN tags TEXT NOT NULL DEFAULT '[]';
+
+-- User defined rules for imported transactions, applied in position order.
+-- A rule matches when all of its conditions do; its set_* / add_tags
+-- actions are then applied to the transaction.
+CREATE TABLE IF NOT EXISTS rule (
+    id BLOB PRIMARY KEY,
+    name TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    enabled INTEGER NOT NULL DEFAULT 1,
+
+    payee_contains TEXT,
+    payee_regex TEXT,
+    -- compared against the absolute amount
+    min_amount REAL,
+    max_amount REAL,
+    account TEXT,
+
+    set_name TEXT,
+    set_record_type TEXT,
+    set_category TEXT,
+    add_tags TEXT NOT NULL DEFAULT '[]',
+
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS rule_position ON rule (position);
@@ -0,0 +1,3 @@
+-- date a record first falls due (or is paid), anchors its schedule for the
+-- calendar feed. NULL when nobody has said.
+ALTER TABLE financial_record ADD COLUMN starts_on TEXT;
@@ -0,0 +1,40 @@
+-- Key record_search rows by the rowid of their financial_record row, so the
+-- triggers find the row to change with a rowid lookup instead of scanning
+-- the UNINDEXED record_id column. record_id stays for joining search hits
+-- back to their records.
+DROP TRIGGER IF EXISTS financial_record_search_insert;
+DROP TRIGGER IF EXISTS financial_record_search_update;
+DROP TRIGGER IF EXISTS financial_record_search_delete;
+DROP TABLE IF EXISTS record_search;
+
+CREATE VIRTUAL TABLE record_search USING fts5(
+    record_id UNINDEXED,
+    name,
+    notes,
+    payee,
+    tokenize = 'unicode61 remove_diacritics 2',
+    prefix = '2 3'
+);
+
+CREATE TRIGGER financial_record_search_insert
+AFTER INSERT ON financial_record
+BEGIN
+    INSERT INTO record_search (rowid, record_id, name, notes, payee)
+    VALUES (new.rowid, new.id, new.name, new.notes, new.payee);
+END;
+
+CREATE TRIGGER financial_record_search_update
+AFTER UPDATE OF name, notes, payee ON financial_record
+BEGIN
+    UPDATE record_search SET name = new.name, notes = new.notes, payee = new.payee
+    WHERE rowid = old.rowid;
+END;
+
+CREATE TRIGGER financial_record_search_delete
+AFTER DELETE ON financial_record
+BEGIN
+    DELETE FROM record_search WHERE rowid = old.rowid;
+END;
+
+INSERT INTO record_search (rowid, record_id, name, notes, payee)
+SELECT rowid, id, name, notes, payee FROM financial_record;
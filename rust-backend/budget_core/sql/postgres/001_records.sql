@@ -0,0 +1,70 @@
+-- The record storage of the SQLite schema (schema.sql and migrations 001,
+-- 003, 004, 005 and 009) as a single PostgreSQL migration. Ledger, rules
+-- and attachments are not stored in PostgreSQL.
+
+CREATE TYPE frequency AS ENUM ('Daily', 'Weekly', 'Monthly', 'Quarterly', 'Yearly');
+CREATE TYPE record_type AS ENUM ('Income', 'Expense', 'Debt');
+
+-- Deleting a record only stamps deleted_at; the row sits in the trash until
+-- it is restored or purged.
+CREATE TABLE financial_record (
+    id UUID PRIMARY KEY,
+    name TEXT NOT NULL,
+    amount DOUBLE PRECISION NOT NULL,
+    frequency frequency NOT NULL,
+    record_type record_type NOT NULL,
+    notes TEXT,
+    payee TEXT,
+    starts_on DATE,
+    deleted_at TIMESTAMPTZ,
+    -- the order records were added in, which SQLite keeps as the rowid
+    seq BIGSERIAL NOT NULL,
+    -- what search matches against, weighted name (A) over payee (B) over
+    -- notes (C)
+    search tsvector GENERATED ALWAYS AS (
+        setweight(to_tsvector('simple', name), 'A')
+        || setweight(to_tsvector('simple', coalesce(payee, '')), 'B')
+        || setweight(to_tsvector('simple', coalesce(notes, '')), 'C')
+    ) STORED
+);
+
+CREATE INDEX financial_record_deleted_at ON financial_record (deleted_at);
+CREATE INDEX financial_record_search ON financial_record USING GIN (search);
+
+-- Append-only log of every change made to financial_record. Snapshots are
+-- the serialized FinancialRecord before and after the change. No foreign
+-- key: history outlives purged records.
+CREATE TABLE record_history (
+    id BIGSERIAL PRIMARY KEY,
+    record_id UUID NOT NULL,
+    action TEXT NOT NULL,
+    actor TEXT NOT NULL,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    before_json JSONB,
+    after_json JSONB
+);
+
+CREATE INDEX record_history_record_id ON record_history (record_id);
+
+CREATE FUNCTION record_history_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'record_history is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER record_history_no_change
+BEFORE UPDATE OR DELETE ON record_history
+FOR EACH ROW EXECUTE FUNCTION record_history_append_only();
+
+-- Operations that can be undone/redone. Entries with undone = false form the
+-- undo stack (newest on top), entries with undone = true the redo stack.
+CREATE TABLE operation_journal (
+    id BIGSERIAL PRIMARY KEY,
+    record_id UUID NOT NULL,
+    action TEXT NOT NULL,
+    actor TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    before_json JSONB,
+    after_json JSONB,
+    undone BOOLEAN NOT NULL DEFAULT false
+);
@@ -0,0 +1,906 @@
+use crate::app_error::AppError;
+use crate::attachment_store::AttachmentStore;
+use crate::db::{self, with_savepoint};
+use crate::ledger_repository;
+use crate::models::{Attachment, ChangeAction, FinancialRecord, LedgerEntry, Rule};
+use crate::record_repository;
+use crate::rule_repository;
+
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use rusqlite::{params, Connection, OptionalExtension, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::io::{Cursor, Read, Seek, Write};
+use uuid::Uuid;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+// what every archive says it is, so that any other JSON is turned away
+const FORMAT: &str = "overkill-budget-archive";
+
+// Version of the archive layout below, not of the database schema. Bump it
+// when a change would trip up an older importer; archives from newer versions
+// are refused.
+pub const ARCHIVE_VERSION: u32 = 1;
+
+// In a zip archive the JSON is this file, and each attachment's contents are
+// stored next to it as attachments/<sha256>.
+const ARCHIVE_JSON: &str = "archive.json";
+const ATTACHMENTS_DIR: &str = "attachments/";
+
+/// Everything in the database, in a form that doesn't depend on how SQLite
+/// stores it: for moving a budget between machines.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Archive {
+    pub format: String,
+    pub version: u32,
+    /// schema version of the database it was exported from, for reference
+    pub schema_version: usize,
+    pub exported_at: DateTime<Utc>,
+
+    #[serde(default)]
+    pub records: Vec<ArchivedRecord>,
+    /// record history, oldest first
+    #[serde(default)]
+    pub history: Vec<ArchivedChange>,
+    /// the undo/redo journal, oldest first
+    #[serde(default)]
+    pub journal: Vec<ArchivedOperation>,
+    #[serde(default)]
+    pub ledger_entries: Vec<LedgerEntry>,
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    /// attachment metadata; the contents only travel in zip archives
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+}
+
+/// A record, live or in the trash
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedRecord {
+    #[serde(flatten)]
+    pub record: FinancialRecord,
+    #[serde(default)]
+    pub deleted_at: Option<String>,
+}
+
+/// A record_history entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedChange {
+    pub record_id: Uuid,
+    pub action: ChangeAction,
+    pub actor: String,
+    pub changed_at: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+/// An operation_journal entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedOperation {
+    pub record_id: Uuid,
+    pub action: ChangeAction,
+    pub actor: String,
+    pub created_at: String,
+    pub before: Option<FinancialRecord>,
+    pub after: Option<FinancialRecord>,
+    pub undone: bool,
+}
+
+/// An archive read back from a file, with the attachment contents a zip
+/// archive carries, by hash
+#[derive(Debug)]
+pub struct ArchiveFile {
+    pub archive: Archive,
+    pub contents: HashMap<String, Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    /// the archive alone, without attachment contents
+    Json,
+    /// the archive and the contents of its attachments
+    Zip,
+}
+
+impl std::str::FromStr for ArchiveFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "json" => Ok(ArchiveFormat::Json),
+            "zip" => Ok(ArchiveFormat::Zip),
+            other => Err(format!("unknown archive format `{}` (expected json or zip)", other)),
+        }
+    }
+}
+
+/// What to do with a record, transaction, rule or attachment whose id is
+/// already in the database when merging
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnConflict {
+    /// leave the one in the database alone
+    Keep,
+    /// overwrite it with the one from the archive
+    Overwrite,
+    /// import the archive's as a copy with a new id
+    NewId,
+}
+
+impl std::str::FromStr for OnConflict {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(OnConflict::Keep),
+            "overwrite" => Ok(OnConflict::Overwrite),
+            "new-id" => Ok(OnConflict::NewId),
+            _ => Err(format!("unknown conflict resolution `{}` (keep, overwrite or new-id)", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Add the archive to what's in the database. The database's undo
+    /// journal is kept and the archive's is left out: undoing operations
+    /// made on another machine could trample changes made here.
+    Merge(OnConflict),
+    /// Empty the database first, so it ends up as the archive
+    Replace,
+}
+
+/// How one table's rows fared in an import
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TableReport {
+    pub added: usize,
+    /// overwritten in place (OnConflict::Overwrite)
+    pub replaced: usize,
+    /// added under a new id (OnConflict::NewId)
+    pub renamed: usize,
+    /// already there (OnConflict::Keep), or duplicates
+    pub skipped: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ArchiveImportReport {
+    pub records: TableReport,
+    pub ledger_entries: TableReport,
+    pub rules: TableReport,
+    pub attachments: TableReport,
+    pub history: usize,
+    pub journal: usize,
+    /// attachments left out as their contents were neither in the archive
+    /// nor in the attachment store
+    pub missing_contents: usize,
+}
+
+// Read the whole database into an archive, in one transaction so that it's
+// consistent even while others write.
+pub fn export(conn: &Connection) -> Result<Archive> {
+    with_savepoint(conn, || {
+        let mut records: Vec<ArchivedRecord> = record_repository::get_records(conn)?
+            .into_iter()
+            .map(|record| ArchivedRecord { record, deleted_at: None })
+            .collect();
+        records.extend(
+            record_repository::get_trashed_records(conn)?
+                .into_iter()
+                .map(|trashed| ArchivedRecord { record: trashed.record, deleted_at: Some(trashed.deleted_at) }),
+        );
+
+        Ok(Archive {
+            format: FORMAT.to_string(),
+            version: ARCHIVE_VERSION,
+            schema_version: db::schema_version(),
+            exported_at: Utc::now(),
+            records,
+            history: get_history(conn)?,
+            journal: get_journal(conn)?,
+            ledger_entries: ledger_repository::get_all_entries(conn)?,
+            rules: rule_repository::get_rules(conn)?,
+            attachments: get_attachments(conn)?,
+        })
+    })
+}
+
+pub fn write_json<W: Write>(archive: &Archive, out: W) -> Result<(), AppError> {
+    serde_json::to_writer_pretty(out, archive).map_err(|e| AppError(format!("json error: {}", e)))
+}
+
+// The archive as a zip, with the contents of its attachments from `store`.
+// Contents missing from the store are left out (with a warning), as they
+// would be anyway.
+pub fn write_zip<W: Write + Seek>(archive: &Archive, store: &AttachmentStore, out: W) -> Result<(), AppError> {
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut zip = ZipWriter::new(out);
+
+    zip.start_file(ARCHIVE_JSON, options)?;
+    write_json(archive, &mut zip)?;
+
+    let mut written = HashSet::new();
+    for attachment in &archive.attachments {
+        if !written.insert(attachment.sha256.as_str()) {
+            continue;
+        }
+        match store.get(&attachment.sha256) {
+            Ok(bytes) => {
+                zip.start_file(format!("{}{}", ATTACHMENTS_DIR, attachment.sha256), options)?;
+                zip.write_all(&bytes)?;
+            }
+            Err(e) => warn!("Leaving out the contents of {} ({}): {}", attachment.file_name, attachment.sha256, e),
+        }
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+// Parse a JSON or zip archive, checking it's one this version can import
+pub fn read(bytes: &[u8]) -> Result<ArchiveFile, AppError> {
+    let (json, contents) = if bytes.starts_with(b"PK\x03\x04") {
+        let mut zip = ZipArchive::new(Cursor::new(bytes))?;
+        let mut json = Vec::new();
+        zip.by_name(ARCHIVE_JSON)?.read_to_end(&mut json)?;
+
+        let mut contents = HashMap::new();
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i)?;
+            if let Some(hash) = file.name().strip_prefix(ATTACHMENTS_DIR).map(str::to_string) {
+                let mut bytes = Vec::new();
+                file.read_to_end(&mut bytes)?;
+                contents.insert(hash, bytes);
+            }
+        }
+        (json, contents)
+    } else {
+        (bytes.to_vec(), HashMap::new())
+    };
+
+    // check the header before the rest, which may not parse if it's from a
+    // newer version
+    #[derive(Deserialize)]
+    struct Header {
+        format: Option<String>,
+        version: Option<u32>,
+    }
+    let header: Header = serde_json::from_slice(&json).map_err(|e| AppError(format!("not a budget archive: {}", e)))?;
+    if header.format.as_deref() != Some(FORMAT) {
+        return Err(AppError("not a budget archive".to_string()));
+    }
+    match header.version {
+        Some(version) if version <= ARCHIVE_VERSION => {}
+        version => {
+            return Err(AppError(format!(
+                "archive version {:?} is newer than this app's {}, update the app to import it",
+                version, ARCHIVE_VERSION
+            )));
+        }
+    }
+
+    let archive = serde_json::from_slice(&json).map_err(|e| AppError(format!("invalid archive: {}", e)))?;
+    Ok(ArchiveFile { archive, contents })
+}
+
+// Import `file` into the database, all or nothing. Attachment contents go
+// into `store` (they are content addressed, so a failed import only leaves
+// unused files behind).
+pub fn import(
+    conn: &Connection,
+    store: &AttachmentStore,
+    file: &ArchiveFile,
+    mode: ImportMode,
+) -> Result<ArchiveImportReport, AppError> {
+    with_savepoint(conn, || {
+        let on_conflict = match mode {
+            ImportMode::Merge(on_conflict) => on_conflict,
+            ImportMode::Replace => {
+                db::clear_all(conn)?;
+                // nothing left to conflict with
+                OnConflict::Keep
+            }
+        };
+
+        let mut importer =
+            Importer { conn, on_conflict, report: ArchiveImportReport::default(), record_ids: HashMap::new() };
+        let archive = &file.archive;
+        importer.records(&archive.records)?;
+        importer.history(&archive.history, &archive.records)?;
+        if mode == ImportMode::Replace {
+            importer.journal(&archive.journal)?;
+        }
+        importer.ledger_entries(&archive.ledger_entries)?;
+        importer.rules(&archive.rules)?;
+        importer.attachments(&archive.attachments, store, &file.contents)?;
+
+        info!("Imported archive: {:?}", importer.report);
+        Ok(importer.report)
+    })
+}
+
+struct Importer<'a> {
+    conn: &'a Connection,
+    on_conflict: OnConflict,
+    report: ArchiveImportReport,
+    /// what the archive's records are called in the database now; records
+    /// left as they were (OnConflict::Keep) aren't in here
+    record_ids: HashMap<Uuid, Uuid>,
+}
+
+// what became of one row of the archive
+enum Outcome {
+    Added,
+    Replaced,
+    Renamed,
+    Skipped,
+}
+
+impl TableReport {
+    fn count(&mut self, outcome: Outcome) {
+        match outcome {
+            Outcome::Added => self.added += 1,
+            Outcome::Replaced => self.replaced += 1,
+            Outcome::Renamed => self.renamed += 1,
+            Outcome::Skipped => self.skipped += 1,
+        }
+    }
+}
+
+impl Importer<'_> {
+    fn records(&mut self, records: &[ArchivedRecord]) -> Result<()> {
+        for archived in records {
+            let mut record = archived.record.clone();
+            let deleted_at = archived.deleted_at.as_deref();
+            let outcome = if !self.exists("financial_record", &record.id)? {
+                insert_record(self.conn, &record, deleted_at)?;
+                Outcome::Added
+            } else {
+                match self.on_conflict {
+                    OnConflict::Keep => Outcome::Skipped,
+                    OnConflict::Overwrite => {
+                        self.conn.execute(
+                            "UPDATE financial_record SET name = ?2, amount = ?3, frequency = ?4, record_type = ?5,
+                                notes = ?6, payee = ?7, starts_on = ?8, deleted_at = ?9
+                            WHERE id = ?1",
+                            params![
+                                &record.id,
+                                &record.name,
+                                &record.amount,
+                                &record.frequency,
+                                &record.record_type,
+                                &record.notes,
+                                &record.payee,
+                                &record.starts_on,
+                                deleted_at
+                            ],
+                        )?;
+                        Outcome::Replaced
+                    }
+                    OnConflict::NewId => {
+                        record.id = Uuid::new_v4();
+                        insert_record(self.conn, &record, deleted_at)?;
+                        Outcome::Renamed
+                    }
+                }
+            };
+            if !matches!(outcome, Outcome::Skipped) {
+                self.record_ids.insert(archived.record.id, record.id);
+            }
+            self.report.records.count(outcome);
+        }
+        Ok(())
+    }
+
+    // The history of the imported records, and of records only the history
+    // remembers (purged ones), less the changes the database already has.
+    fn history(&mut self, history: &[ArchivedChange], records: &[ArchivedRecord]) -> Result<()> {
+        let archived: HashSet<Uuid> = records.iter().map(|archived| archived.record.id).collect();
+        for change in history {
+            let record_id = match self.record_ids.get(&change.record_id) {
+                Some(id) => *id,
+                // a record that was kept, so is its history
+                None if archived.contains(&change.record_id) => continue,
+                None => change.record_id,
+            };
+            let known = self
+                .conn
+                .query_row(
+                    "SELECT 1 FROM record_history WHERE record_id = ?1 AND action = ?2 AND changed_at = ?3",
+                    params![&record_id, &change.action, &change.changed_at],
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some();
+            if known {
+                continue;
+            }
+
+            self.conn.execute(
+                "INSERT INTO record_history (record_id, action, actor, changed_at, before_json, after_json)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    &record_id,
+                    &change.action,
+                    &change.actor,
+                    &change.changed_at,
+                    snapshot_json(change.before.as_ref(), &record_id)?,
+                    snapshot_json(change.after.as_ref(), &record_id)?
+                ],
+            )?;
+            self.report.history += 1;
+        }
+        Ok(())
+    }
+
+    fn journal(&mut self, journal: &[ArchivedOperation]) -> Result<()> {
+        for operation in journal {
+            self.conn.execute(
+                "INSERT INTO operation_journal (record_id, action, actor, created_at, before_json, after_json, undone)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    &operation.record_id,
+                    &operation.action,
+                    &operation.actor,
+                    &operation.created_at,
+                    to_json(operation.before.as_ref())?,
+                    to_json(operation.after.as_ref())?,
+                    &operation.undone
+                ],
+            )?;
+            self.report.journal += 1;
+        }
+        Ok(())
+    }
+
+    fn ledger_entries(&mut self, entries: &[LedgerEntry]) -> Result<()> {
+        for entry in entries {
+            let mut entry = entry.clone();
+            entry.record_id = self.linked_record(entry.record_id)?;
+
+            // the same bank transaction (account and FITID) under another id
+            // is already there whatever the conflict resolution
+            let same_transaction: Option<Uuid> = match &entry.fitid {
+                Some(fitid) => self
+                    .conn
+                    .query_row(
+                        "SELECT id FROM ledger_entry WHERE account = ?1 AND fitid = ?2",
+                        params![&entry.account, fitid],
+                        |row| row.get(0),
+                    )
+                    .optional()?,
+                None => None,
+            };
+            if same_transaction.is_some_and(|id| id != entry.id) {
+                self.report.ledger_entries.count(Outcome::Skipped);
+                continue;
+            }
+
+            let outcome = if !self.exists("ledger_entry", &entry.id)? {
+                ledger_repository::insert_entry(self.conn, &entry)?;
+                Outcome::Added
+            } else {
+                match self.on_conflict {
+                    OnConflict::Keep => Outcome::Skipped,
+                    OnConflict::Overwrite => {
+                        self.conn.execute("DELETE FROM ledger_entry WHERE id = ?1", params![&entry.id])?;
+                        ledger_repository::insert_entry(self.conn, &entry)?;
+                        Outcome::Replaced
+                    }
+                    // a copy of a transaction with a FITID is ignored as a
+                    // duplicate, one without can't be told apart from a new one
+                    OnConflict::NewId => {
+                        entry.id = Uuid::new_v4();
+                        if ledger_repository::insert_entry(self.conn, &entry)? {
+                            Outcome::Renamed
+                        } else {
+                            Outcome::Skipped
+                        }
+                    }
+                }
+            };
+            self.report.ledger_entries.count(outcome);
+        }
+        Ok(())
+    }
+
+    fn rules(&mut self, rules: &[Rule]) -> Result<()> {
+        let mut rules = rules.to_vec();
+        rules.sort_by_key(|rule| rule.position);
+        for mut rule in rules {
+            let existing = match rule_repository::get_rule_by_id(self.conn, &rule.id) {
+                Ok(existing) => Some(existing),
+                Err(rusqlite::Error::QueryReturnedNoRows) => None,
+                Err(e) => return Err(e),
+            };
+            let outcome = match (existing, self.on_conflict) {
+                (None, _) => {
+                    // after the database's own rules, in the archive's order
+                    rule.position = rule_repository::next_position(self.conn)?;
+                    rule_repository::insert_rule(self.conn, &rule)?;
+                    Outcome::Added
+                }
+                (Some(_), OnConflict::Keep) => Outcome::Skipped,
+                (Some(existing), OnConflict::Overwrite) => {
+                    // where the database has it in the order
+                    rule.position = existing.position;
+                    rule_repository::update_rule(self.conn, &rule)?;
+                    Outcome::Replaced
+                }
+                (Some(_), OnConflict::NewId) => {
+                    rule.id = Uuid::new_v4();
+                    rule.position = rule_repository::next_position(self.conn)?;
+                    rule_repository::insert_rule(self.conn, &rule)?;
+                    Outcome::Renamed
+                }
+            };
+            self.report.rules.count(outcome);
+        }
+        Ok(())
+    }
+
+    fn attachments(
+        &mut self,
+        attachments: &[Attachment],
+        store: &AttachmentStore,
+        contents: &HashMap<String, Vec<u8>>,
+    ) -> Result<(), AppError> {
+        for attachment in attachments {
+            let mut attachment = attachment.clone();
+            let Some(record_id) = self.linked_record(Some(attachment.record_id))? else {
+                debug!("Skipping attachment {}, its record wasn't imported", attachment.id);
+                self.report.attachments.count(Outcome::Skipped);
+                continue;
+            };
+            attachment.record_id = record_id;
+
+            let exists = self.exists("attachment", &attachment.id)?;
+            if exists && self.on_conflict == OnConflict::Keep {
+                self.report.attachments.count(Outcome::Skipped);
+                continue;
+            }
+
+            // the contents first, so that nothing is overwritten with an
+            // attachment that has none
+            match contents.get(&attachment.sha256) {
+                Some(bytes) => {
+                    if store.put(bytes)? != attachment.sha256 {
+                        return Err(AppError(format!(
+                            "the contents of attachment {} don't match its hash",
+                            attachment.file_name
+                        )));
+                    }
+                }
+                None if store.get(&attachment.sha256).is_ok() => {}
+                None => {
+                    warn!("No contents for attachment {} ({})", attachment.file_name, attachment.sha256);
+                    self.report.missing_contents += 1;
+                    continue;
+                }
+            }
+
+            let outcome = match (exists, self.on_conflict) {
+                (false, _) => Outcome::Added,
+                (true, OnConflict::NewId) => {
+                    attachment.id = Uuid::new_v4();
+                    Outcome::Renamed
+                }
+                (true, _) => {
+                    self.conn.execute("DELETE FROM attachment WHERE id = ?1", params![&attachment.id])?;
+                    Outcome::Replaced
+                }
+            };
+            self.conn.execute(
+                "INSERT INTO attachment (id, record_id, file_name, content_type, size, sha256, created_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    &attachment.id,
+                    &attachment.record_id,
+                    &attachment.file_name,
+                    &attachment.content_type,
+                    &attachment.size,
+                    &attachment.sha256,
+                    &attachment.created_at
+                ],
+            )?;
+            self.report.attachments.count(outcome);
+        }
+        Ok(())
+    }
+
+    // the id an archived record has in the database now, or None if it isn't
+    // there
+    fn linked_record(&self, record_id: Option<Uuid>) -> Result<Option<Uuid>> {
+        let Some(record_id) = record_id else {
+            return Ok(None);
+        };
+        let id = self.record_ids.get(&record_id).copied().unwrap_or(record_id);
+        Ok(self.exists("financial_record", &id)?.then_some(id))
+    }
+
+    fn exists(&self, table: &str, id: &Uuid) -> Result<bool> {
+        self.conn
+            .query_row(&format!("SELECT 1 FROM {} WHERE id = ?1", table), params![id], |_| Ok(()))
+            .optional()
+            .map(|found| found.is_some())
+    }
+}
+
+fn insert_record(conn: &Connection, record: &FinancialRecord, deleted_at: Option<&str>) -> Result<()> {
+    conn.execute(
+        "INSERT INTO financial_record (id, name, amount, frequency, record_type, notes, payee, starts_on, deleted_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            &record.id,
+            &record.name,
+            &record.amount,
+            &record.frequency,
+            &record.record_type,
+            &record.notes,
+            &record.payee,
+            &record.starts_on,
+            deleted_at
+        ],
+    )?;
+    Ok(())
+}
+
+// a history snapshot as stored, with the id of the record it's of, which
+// differs from the archive's for records imported under a new id
+fn snapshot_json(snapshot: Option<&Value>, record_id: &Uuid) -> Result<Option<String>> {
+    let snapshot = snapshot.cloned().map(|mut value| {
+        if let Some(object) = value.as_object_mut() {
+            object.insert("id".to_string(), Value::String(record_id.to_string()));
+        }
+        value
+    });
+    to_json(snapshot.as_ref())
+}
+
+fn to_json<T: Serialize>(value: Option<&T>) -> Result<Option<String>> {
+    value
+        .map(|v| serde_json::to_string(v).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e))))
+        .transpose()
+}
+
+fn get_history(conn: &Connection) -> Result<Vec<ArchivedChange>> {
+    conn.prepare(
+        "SELECT record_id, action, actor, changed_at, before_json, after_json FROM record_history ORDER BY id",
+    )?
+    .query_map([], |row| {
+        Ok(ArchivedChange {
+            record_id: row.get(0)?,
+            action: row.get(1)?,
+            actor: row.get(2)?,
+            changed_at: row.get(3)?,
+            before: json_column(row, 4)?,
+            after: json_column(row, 5)?,
+        })
+    })?
+    .collect()
+}
+
+fn get_journal(conn: &Connection) -> Result<Vec<ArchivedOperation>> {
+    conn.prepare(
+        "SELECT record_id, action, actor, created_at, before_json, after_json, undone FROM operation_journal
+        ORDER BY id",
+    )?
+    .query_map([], |row| {
+        Ok(ArchivedOperation {
+            record_id: row.get(0)?,
+            action: row.get(1)?,
+            actor: row.get(2)?,
+            created_at: row.get(3)?,
+            before: json_column(row, 4)?,
+            after: json_column(row, 5)?,
+            undone: row.get(6)?,
+        })
+    })?
+    .collect()
+}
+
+fn get_attachments(conn: &Connection) -> Result<Vec<Attachment>> {
+    conn.prepare(
+        "SELECT id, record_id, file_name, content_type, size, sha256, created_at FROM attachment ORDER BY created_at",
+    )?
+    .query_map([], |row| {
+        Ok(Attachment {
+            id: row.get(0)?,
+            record_id: row.get(1)?,
+            file_name: row.get(2)?,
+            content_type: row.get(3)?,
+            size: row.get(4)?,
+            sha256: row.get(5)?,
+            created_at: row.get(6)?,
+        })
+    })?
+    .collect()
+}
+
+fn json_column<T: serde::de::DeserializeOwned>(row: &rusqlite::Row, index: usize) -> Result<Option<T>> {
+    let json: Option<String> = row.get(index)?;
+    json.map(|j| {
+        serde_json::from_str(&j).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(e))
+        })
+    })
+    .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Frequency, RecordType};
+    use crate::pool::Pool;
+    use crate::service;
+    use chrono::NaiveDate;
+    use tempfile::TempDir;
+
+    struct Budget {
+        _dir: TempDir,
+        pool: Pool,
+        store: AttachmentStore,
+    }
+
+    fn budget() -> Budget {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = Pool::open(dir.path().join("budget.db").to_str().unwrap(), 0).unwrap();
+        let store = AttachmentStore::new(dir.path().join("attachments"));
+        Budget { _dir: dir, pool, store }
+    }
+
+    // a bit of everything: a record with history and an attachment, one in
+    // the trash, a linked transaction and a rule
+    fn populated() -> (Budget, FinancialRecord) {
+        let budget = budget();
+        let rent = FinancialRecord::new("Rent", 1500.0, Frequency::Monthly, RecordType::Expense);
+        let mut rent = service::add_record(&budget.pool, &rent, "alice").unwrap();
+        rent.amount = 1600.0;
+        service::update_record(&budget.pool, &rent, "alice").unwrap();
+
+        let gym = FinancialRecord::new("Gym", 40.0, Frequency::Monthly, RecordType::Expense);
+        let gym = service::add_record(&budget.pool, &gym, "alice").unwrap();
+        service::delete_record(&budget.pool, &gym.id, "alice").unwrap();
+
+        let posted_on = NaiveDate::from_ymd_opt(2026, 10, 1).unwrap();
+        let mut entry = LedgerEntry::new("checking", posted_on, -1600.0, "LANDLORD");
+        entry.fitid = Some("T1".to_string());
+        entry.record_id = Some(rent.id);
+        ledger_repository::insert_entry(&budget.pool.write().unwrap(), &entry).unwrap();
+
+        let mut rule = Rule::new("Landlord");
+        rule.payee_contains = Some("landlord".to_string());
+        rule.set_category = Some("Housing".to_string());
+        service::add_rule(&budget.pool, rule).unwrap();
+
+        service::add_attachment(&budget.pool, &budget.store, &rent.id, "lease.txt", "text/plain", b"the lease")
+            .unwrap();
+        (budget, rent)
+    }
+
+    fn export(budget: &Budget) -> Archive {
+        service::export_archive(&budget.pool).unwrap()
+    }
+
+    fn zipped(budget: &Budget) -> ArchiveFile {
+        let mut bytes = Cursor::new(Vec::new());
+        write_zip(&export(budget), &budget.store, &mut bytes).unwrap();
+        read(bytes.get_ref()).unwrap()
+    }
+
+    fn without_time(mut archive: Archive) -> Archive {
+        archive.exported_at = DateTime::UNIX_EPOCH;
+        archive
+    }
+
+    #[test]
+    fn test_replace_reproduces_the_database() {
+        let (original, _) = populated();
+        let file = zipped(&original);
+
+        let (copy, _) = populated();
+        let report = service::import_archive(&copy.pool, &copy.store, &file, ImportMode::Replace).unwrap();
+
+        assert_eq!(report.records.added, 2);
+        assert_eq!(report.missing_contents, 0);
+        assert_eq!(without_time(export(&copy)), without_time(file.archive));
+        // and history is append-only again
+        assert!(copy.pool.write().unwrap().execute("DELETE FROM record_history", []).is_err());
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let (original, _) = populated();
+        let archive = export(&original);
+        let mut json = Vec::new();
+        write_json(&archive, &mut json).unwrap();
+
+        let file = read(&json).unwrap();
+        assert_eq!(file.archive, archive);
+        assert!(file.contents.is_empty());
+    }
+
+    #[test]
+    fn test_merge_keep_leaves_conflicts_alone() {
+        let (budget, mut rent) = populated();
+        let file = zipped(&budget);
+        rent.amount = 1700.0;
+        service::update_record(&budget.pool, &rent, "bob").unwrap();
+
+        let report =
+            service::import_archive(&budget.pool, &budget.store, &file, ImportMode::Merge(OnConflict::Keep)).unwrap();
+
+        assert_eq!(report.records, TableReport { skipped: 2, ..Default::default() });
+        assert_eq!(report.ledger_entries.skipped, 1);
+        assert_eq!(report.rules.skipped, 1);
+        assert_eq!(report.attachments.skipped, 1);
+        assert_eq!(report.history, 0);
+        assert_eq!(service::get_all_records(&budget.pool).unwrap()[0].amount, 1700.0);
+    }
+
+    #[test]
+    fn test_merge_overwrite_takes_the_archives_version() {
+        let (budget, mut rent) = populated();
+        let file = zipped(&budget);
+        rent.amount = 1700.0;
+        service::update_record(&budget.pool, &rent, "bob").unwrap();
+
+        let report =
+            service::import_archive(&budget.pool, &budget.store, &file, ImportMode::Merge(OnConflict::Overwrite))
+                .unwrap();
+
+        assert_eq!(report.records.replaced, 2);
+        assert_eq!(report.rules.replaced, 1);
+        // the history was there already
+        assert_eq!(report.history, 0);
+        let records = service::get_all_records(&budget.pool).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].amount, 1600.0);
+    }
+
+    #[test]
+    fn test_merge_new_id_adds_copies() {
+        let (budget, rent) = populated();
+        let file = zipped(&budget);
+
+        let report =
+            service::import_archive(&budget.pool, &budget.store, &file, ImportMode::Merge(OnConflict::NewId)).unwrap();
+
+        assert_eq!(report.records.renamed, 2);
+        assert_eq!(report.rules.renamed, 1);
+        assert_eq!(report.attachments.renamed, 1);
+        // the same bank transaction, whatever its id
+        assert_eq!(report.ledger_entries.skipped, 1);
+
+        let archive = export(&budget);
+        let copy = archive.records.iter().find(|r| r.record.name == "Rent" && r.record.id != rent.id).unwrap();
+        let copy_history: Vec<_> = archive.history.iter().filter(|c| c.record_id == copy.record.id).collect();
+        assert_eq!(copy_history.len(), 2);
+        assert_eq!(copy_history[1].after.as_ref().unwrap()["id"], copy.record.id.to_string());
+        assert!(archive.attachments.iter().any(|a| a.record_id == copy.record.id));
+    }
+
+    #[test]
+    fn test_attachments_without_contents_are_left_out() {
+        let (original, _) = populated();
+        let file = ArchiveFile { archive: export(&original), contents: HashMap::new() };
+
+        let empty = budget();
+        let report = service::import_archive(&empty.pool, &empty.store, &file, ImportMode::Replace).unwrap();
+
+        assert_eq!(report.missing_contents, 1);
+        assert!(export(&empty).attachments.is_empty());
+    }
+
+    #[test]
+    fn test_read_refuses_other_files_and_newer_versions() {
+        assert!(read(b"{\"records\": []}").is_err());
+
+        let (budget, _) = populated();
+        let mut archive = export(&budget);
+        archive.version = ARCHIVE_VERSION + 1;
+        let json = serde_json::to_vec(&archive).unwrap();
+        let err = read(&json).unwrap_err();
+        assert!(err.0.contains("newer"), "{}", err.0);
+    }
+}
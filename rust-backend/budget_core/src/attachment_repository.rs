@@ -0,0 +1,104 @@
+use crate::models::Attachment;
+
+use log::debug;
+use rusqlite::{params, Connection, Result, Row};
+use uuid::Uuid;
+
+const ATTACHMENT_COLUMNS: &str = "id, record_id, file_name, content_type, size, sha256, created_at";
+
+fn attachment_from_row(row: &Row) -> Result<Attachment> {
+    Ok(Attachment {
+        id: row.get(0)?,
+        record_id: row.get(1)?,
+        file_name: row.get(2)?,
+        content_type: row.get(3)?,
+        size: row.get(4)?,
+        sha256: row.get(5)?,
+        created_at: row.get(6)?,
+    })
+}
+
+// insert the metadata row and return it with its database generated fields
+pub fn insert_attachment(
+    conn: &Connection,
+    record_id: &Uuid,
+    file_name: &str,
+    content_type: &str,
+    size: i64,
+    sha256: &str,
+) -> Result<Attachment> {
+    debug!("insert_attachment(record_id={}, file_name={})", record_id, file_name);
+    let id = Uuid::new_v4();
+    conn.execute(
+        "INSERT INTO attachment (id, record_id, file_name, content_type, size, sha256)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![id, record_id, file_name, content_type, size, sha256],
+    )?;
+    get_attachment_by_id(conn, &id)
+}
+
+pub fn get_attachment_by_id(conn: &Connection, id: &Uuid) -> Result<Attachment> {
+    debug!("get_attachment_by_id(id={})", id);
+    conn.query_row(
+        &format!("SELECT {} FROM attachment WHERE id = ?1", ATTACHMENT_COLUMNS),
+        params![id],
+        attachment_from_row,
+    )
+}
+
+pub fn get_attachments_for_record(conn: &Connection, record_id: &Uuid) -> Result<Vec<Attachment>> {
+    debug!("get_attachments_for_record(record_id={})", record_id);
+    conn.prepare(&format!(
+        "SELECT {} FROM attachment WHERE record_id = ?1 ORDER BY created_at, file_name",
+        ATTACHMENT_COLUMNS
+    ))?
+    .query_map(params![record_id], attachment_from_row)?
+    .collect()
+}
+
+// number of attachments (on any record) pointing at the given content
+pub fn count_by_hash(conn: &Connection, sha256: &str) -> Result<i64> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM attachment WHERE sha256 = ?1",
+        params![sha256],
+        |row| row.get(0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{FinancialRecord, Frequency, RecordType};
+    use crate::record_repository;
+
+    fn setup_conn() -> Connection {
+        crate::db::init_db(":memory:").expect("failed to init memory db")
+    }
+
+    #[test]
+    fn test_insert_and_list_attachments() {
+        let conn = setup_conn();
+        let record = FinancialRecord::new("Electric Bill", 127.33, Frequency::Monthly, RecordType::Expense);
+        record_repository::insert_record(&conn, &record, "test").unwrap();
+
+        let inserted = insert_attachment(&conn, &record.id, "june.pdf", "application/pdf", 1024, &"a".repeat(64)).unwrap();
+
+        let listed = get_attachments_for_record(&conn, &record.id).unwrap();
+        assert_eq!(listed, vec![inserted]);
+        assert_eq!(count_by_hash(&conn, &"a".repeat(64)).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_attachments_removed_with_purged_record() {
+        let conn = setup_conn();
+        let record = FinancialRecord::new("Electric Bill", 127.33, Frequency::Monthly, RecordType::Expense);
+        record_repository::insert_record(&conn, &record, "test").unwrap();
+        insert_attachment(&conn, &record.id, "june.pdf", "application/pdf", 1024, &"a".repeat(64)).unwrap();
+
+        record_repository::delete_record(&conn, &record.id, "test").unwrap();
+        assert_eq!(get_attachments_for_record(&conn, &record.id).unwrap().len(), 1);
+        record_repository::purge_record(&conn, &record.id, "test").unwrap();
+
+        assert!(get_attachments_for_record(&conn, &record.id).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,118 @@
+use log::debug;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// Content-addressed file store for attachments. Every file is saved under the
+// hex sha256 of its bytes, fanned out by the first two characters:
+//
+//   <root>/ab/cdef0123...
+//
+// so uploading the same receipt twice only stores it once.
+#[derive(Clone, Debug)]
+pub struct AttachmentStore {
+    root: PathBuf,
+}
+
+impl AttachmentStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    // the hash `bytes` are stored under
+    pub fn hash(bytes: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(bytes))
+    }
+
+    // write `bytes` to the store (if not already there) and return their hash
+    pub fn put(&self, bytes: &[u8]) -> io::Result<String> {
+        let hash = Self::hash(bytes);
+        let path = self.path_for(&hash)?;
+        if path.exists() {
+            debug!("attachment {} already stored", hash);
+            return Ok(hash);
+        }
+
+        let dir = path.parent().expect("hash path always has a parent");
+        fs::create_dir_all(dir)?;
+
+        // write to a temp file first so a crash never leaves a truncated
+        // file behind under a valid hash
+        let tmp = dir.join(format!("{}.tmp", hash));
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        fs::rename(&tmp, &path)?;
+
+        debug!("stored attachment {} ({} bytes)", hash, bytes.len());
+        Ok(hash)
+    }
+
+    pub fn get(&self, hash: &str) -> io::Result<Vec<u8>> {
+        fs::read(self.path_for(hash)?)
+    }
+
+    pub fn remove(&self, hash: &str) -> io::Result<()> {
+        debug!("removing attachment {}", hash);
+        match fs::remove_file(self.path_for(hash)?) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            other => other,
+        }
+    }
+
+    fn path_for(&self, hash: &str) -> io::Result<PathBuf> {
+        // hashes come back from the database and URLs, never let one
+        // escape the store directory
+        if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid attachment hash `{}`", hash),
+            ));
+        }
+        Ok(self.root.join(&hash[..2]).join(&hash[2..]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_put_is_content_addressed() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = AttachmentStore::new(dir.path());
+
+        let a = store.put(b"receipt").unwrap();
+        let b = store.put(b"receipt").unwrap();
+        let c = store.put(b"statement").unwrap();
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(store.get(&a).unwrap(), b"receipt");
+        assert!(dir.path().join(&a[..2]).join(&a[2..]).exists());
+    }
+
+    #[test]
+    fn test_remove_is_idempotent() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = AttachmentStore::new(dir.path());
+
+        let hash = store.put(b"receipt").unwrap();
+        store.remove(&hash).unwrap();
+        store.remove(&hash).unwrap();
+
+        assert!(store.get(&hash).is_err());
+    }
+
+    #[test]
+    fn test_rejects_invalid_hash() {
+        let store = AttachmentStore::new("/tmp/unused");
+        let err = store.get("../../etc/passwd").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,380 @@
+use crate::app_error::AppError;
+use crate::db;
+use crate::record_repository;
+use crate::encryption::{self, DbKey};
+
+use chrono::{DateTime, Datelike, NaiveDateTime, SubsecRound, Utc};
+use log::{debug, info};
+use rusqlite::backup::Backup as OnlineBackup;
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// snapshots are named after when they were taken, in UTC, to the
+// millisecond so that a backup right before a restore doesn't clash with one
+// taken just before:
+//
+//   <dir>/budget-20261019T080000.000Z.db
+const NAME_PREFIX: &str = "budget-";
+const NAME_SUFFIX: &str = ".db";
+const NAME_TIME_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+
+// The online backup copies this many pages at a time and lets go of the
+// source in between, so writers only ever wait for one step, not the whole
+// copy.
+const PAGES_PER_STEP: i32 = 256;
+const STEP_PAUSE: Duration = Duration::from_millis(5);
+
+/// A snapshot of the database in a [`BackupStore`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Backup {
+    /// file name in the backup directory, which is also how a backup is
+    /// picked for restoring
+    pub name: String,
+    pub taken_at: DateTime<Utc>,
+    /// bytes
+    pub size: u64,
+}
+
+/// How many snapshots rotation keeps: the newest one of each of the last
+/// `daily` days, `weekly` weeks and `monthly` months. The newest snapshot is
+/// always kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Retention {
+    pub daily: usize,
+    pub weekly: usize,
+    pub monthly: usize,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self {
+            daily: 7,
+            weekly: 4,
+            monthly: 12,
+        }
+    }
+}
+
+// Snapshots of the database, one SQLite file each, in a directory of their
+// own. Snapshots of an encrypted database are encrypted with the same key,
+// which is also what they're restored with. Attachment contents live outside
+// the database and are not part of a snapshot.
+#[derive(Clone, Debug)]
+pub struct BackupStore {
+    dir: PathBuf,
+    key: Option<DbKey>,
+}
+
+impl BackupStore {
+    pub fn new(dir: impl Into<PathBuf>, key: Option<DbKey>) -> Self {
+        Self { dir: dir.into(), key }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // Snapshot the database `source` is connected to with SQLite's online
+    // backup, which lets others keep reading and writing meanwhile. The
+    // snapshot is checked before it shows up under its name.
+    pub fn take(&self, source: &Connection, now: DateTime<Utc>) -> Result<Backup, AppError> {
+        fs::create_dir_all(&self.dir)?;
+        // as precise as the name
+        let now = now.trunc_subsecs(3);
+        let name = format!("{}{}{}", NAME_PREFIX, now.format(NAME_TIME_FORMAT), NAME_SUFFIX);
+        let path = self.dir.join(&name);
+        if path.exists() {
+            return Err(AppError(format!("backup {} already exists", name)));
+        }
+
+        // copy to a temp file first, so an interrupted backup never looks
+        // like a finished one
+        let partial = self.dir.join(format!("{}.partial", name));
+        remove_if_exists(&partial)?;
+        {
+            let mut dest = Connection::open(&partial)?;
+            if let Some(key) = &self.key {
+                encryption::apply_key(&dest, key)?;
+            }
+            OnlineBackup::new(source, &mut dest)?.run_to_completion(PAGES_PER_STEP, STEP_PAUSE, None)?;
+            check(&dest).map_err(|e| AppError(format!("backup {} is unusable: {}", name, e)))?;
+        }
+        fs::rename(&partial, &path)?;
+
+        let backup = Backup { name, taken_at: now, size: fs::metadata(&path)?.len() };
+        info!("Backed up the database to {}", path.display());
+        Ok(backup)
+    }
+
+    /// Every snapshot in the directory, newest first
+    pub fn list(&self) -> io::Result<Vec<Backup>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut backups = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            // anything else in the directory isn't ours
+            if let Some(taken_at) = parse_name(&name) {
+                backups.push(Backup { name, taken_at, size: entry.metadata()?.len() });
+            }
+        }
+        backups.sort_by_key(|b| Reverse(b.taken_at));
+        Ok(backups)
+    }
+
+    /// Deletes the snapshots `retention` doesn't keep and returns them
+    pub fn rotate(&self, retention: &Retention) -> io::Result<Vec<Backup>> {
+        let backups = self.list()?;
+        let keep = kept(&backups, retention);
+
+        let mut removed = Vec::new();
+        for backup in backups {
+            if !keep.contains(&backup.name) {
+                debug!("Removing backup {}", backup.name);
+                fs::remove_file(self.dir.join(&backup.name))?;
+                removed.push(backup);
+            }
+        }
+        Ok(removed)
+    }
+
+    // Replace everything in the database `target` is connected to with the
+    // snapshot `name`, after checking the snapshot is intact. The copy goes
+    // through the online backup too, so other connections to the database
+    // see the restored contents as soon as it's done. An older snapshot is
+    // migrated to the current schema, and its search index brought in step.
+    pub fn restore(&self, name: &str, target: &mut Connection) -> Result<Backup, AppError> {
+        let (backup, snapshot) = self.open_checked(name)?;
+        OnlineBackup::new(&snapshot, target)?.run_to_completion(PAGES_PER_STEP, STEP_PAUSE, None)?;
+        db::migrate(target)?;
+        record_repository::sync_search_index(target)?;
+        info!("Restored the database from {}", self.dir.join(name).display());
+        Ok(backup)
+    }
+
+    /// The snapshot `name`, if it exists and would restore
+    pub fn verify(&self, name: &str) -> Result<Backup, AppError> {
+        self.open_checked(name).map(|(backup, _)| backup)
+    }
+
+    fn open_checked(&self, name: &str) -> Result<(Backup, Connection), AppError> {
+        // names come from URLs too, never let one escape the directory
+        let taken_at = parse_name(name).ok_or_else(|| AppError(format!("`{}` is not a backup name", name)))?;
+        let path = self.dir.join(name);
+        if !path.exists() {
+            return Err(AppError(format!("no backup {} in {}", name, self.dir.display())));
+        }
+
+        let snapshot = Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        if let Some(key) = &self.key {
+            encryption::apply_key(&snapshot, key)?;
+        }
+        check(&snapshot).map_err(|e| AppError(format!("not restoring {}: {}", name, e)))?;
+        let backup = Backup { name: name.to_string(), taken_at, size: fs::metadata(&path)?.len() };
+        Ok((backup, snapshot))
+    }
+}
+
+// a snapshot is only good if SQLite finds nothing wrong with it and this
+// version of the app can migrate it
+fn check(conn: &Connection) -> Result<(), AppError> {
+    let integrity: String = conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+    if integrity != "ok" {
+        return Err(AppError(format!("integrity check failed: {}", integrity)));
+    }
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version > db::schema_version() {
+        return Err(AppError(format!(
+            "it has schema version {}, newer than this app's {}",
+            version,
+            db::schema_version()
+        )));
+    }
+    Ok(())
+}
+
+fn parse_name(name: &str) -> Option<DateTime<Utc>> {
+    let time = name.strip_prefix(NAME_PREFIX)?.strip_suffix(NAME_SUFFIX)?;
+    NaiveDateTime::parse_from_str(time, NAME_TIME_FORMAT).ok().map(|t| t.and_utc())
+}
+
+// the day, week or month a snapshot was taken in
+type PeriodOf = fn(&DateTime<Utc>) -> (i32, u32);
+
+// the names of the `backups` (newest first) that `retention` keeps
+fn kept(backups: &[Backup], retention: &Retention) -> HashSet<String> {
+    let mut keep: HashSet<String> = backups.first().map(|b| b.name.clone()).into_iter().collect();
+
+    let periods: [(usize, PeriodOf); 3] = [
+        (retention.daily, |t| (t.year(), t.ordinal())),
+        (retention.weekly, |t| (t.iso_week().year(), t.iso_week().week())),
+        (retention.monthly, |t| (t.year(), t.month())),
+    ];
+    for (count, period_of) in periods {
+        let mut periods_seen = Vec::new();
+        for backup in backups {
+            let period = period_of(&backup.taken_at);
+            if periods_seen.contains(&period) {
+                continue;
+            }
+            if periods_seen.len() == count {
+                break;
+            }
+            // the first (newest) snapshot of each period stands for it
+            periods_seen.push(period);
+            keep.insert(backup.name.clone());
+        }
+    }
+    keep
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{FinancialRecord, Frequency, RecordType};
+    use crate::pool::Pool;
+    use crate::service;
+    use chrono::TimeZone;
+
+    fn at(y: i32, m: u32, d: u32, h: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, 0, 0).unwrap()
+    }
+
+    fn backups_at(times: &[DateTime<Utc>]) -> Vec<Backup> {
+        let mut backups: Vec<Backup> = times
+            .iter()
+            .map(|t| Backup {
+                name: format!("{}{}{}", NAME_PREFIX, t.format(NAME_TIME_FORMAT), NAME_SUFFIX),
+                taken_at: *t,
+                size: 0,
+            })
+            .collect();
+        backups.sort_by_key(|b| Reverse(b.taken_at));
+        backups
+    }
+
+    fn add(pool: &Pool, name: &str) {
+        let record = FinancialRecord::new(name, 100.0, Frequency::Monthly, RecordType::Expense);
+        service::add_record(pool, &record, "test").unwrap();
+    }
+
+    fn names(pool: &Pool) -> Vec<String> {
+        let mut names: Vec<String> = service::get_all_records(pool).unwrap().into_iter().map(|r| r.name).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_rotation_keeps_newest_per_day_week_and_month() {
+        // four a day for sixty days
+        let times: Vec<_> = (0..60)
+            .flat_map(|day| [0, 6, 12, 18].map(move |h| at(2026, 8, 1, h) + chrono::Duration::days(day)))
+            .collect();
+        let backups = backups_at(&times);
+        let retention = Retention { daily: 2, weekly: 2, monthly: 2 };
+
+        let keep = kept(&backups, &retention);
+        let expected = [
+            // this month's newest is also today's and this week's
+            "budget-20260929T180000.000Z.db",
+            // yesterday
+            "budget-20260928T180000.000Z.db",
+            // last week (Mon 2026-09-21 .. Sun 09-27)
+            "budget-20260927T180000.000Z.db",
+            // last month
+            "budget-20260831T180000.000Z.db",
+        ];
+        assert_eq!(keep, expected.into_iter().map(String::from).collect::<HashSet<_>>());
+    }
+
+    #[test]
+    fn test_rotation_always_keeps_newest() {
+        let backups = backups_at(&[at(2026, 1, 1, 0), at(2026, 1, 2, 0)]);
+        let retention = Retention { daily: 0, weekly: 0, monthly: 0 };
+        assert_eq!(kept(&backups, &retention), HashSet::from(["budget-20260102T000000.000Z.db".to_string()]));
+        assert!(kept(&[], &retention).is_empty());
+    }
+
+    #[test]
+    fn test_take_list_rotate_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = Pool::open(dir.path().join("budget.db").to_str().unwrap(), 2).unwrap();
+        let store = BackupStore::new(dir.path().join("backups"), None);
+
+        add(&pool, "Rent");
+        let first = store.take(&pool.read().unwrap(), at(2026, 10, 1, 8)).unwrap();
+        add(&pool, "Gym");
+        let second = store.take(&pool.read().unwrap(), at(2026, 10, 2, 8)).unwrap();
+        assert!(store.take(&pool.read().unwrap(), at(2026, 10, 2, 8)).is_err());
+        fs::write(store.dir().join("notes.txt"), "not a backup").unwrap();
+
+        assert_eq!(store.list().unwrap(), vec![second.clone(), first.clone()]);
+
+        add(&pool, "Netflix");
+        store.restore(&first.name, &mut pool.write().unwrap()).unwrap();
+        // the readers see the restored database too
+        assert_eq!(names(&pool), vec!["Rent"]);
+
+        let removed = store.rotate(&Retention { daily: 1, weekly: 0, monthly: 0 }).unwrap();
+        assert_eq!(removed, vec![first]);
+        assert_eq!(store.list().unwrap(), vec![second]);
+    }
+
+    #[test]
+    fn test_restore_refuses_damaged_or_unknown_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = Pool::open(dir.path().join("budget.db").to_str().unwrap(), 0).unwrap();
+        let store = BackupStore::new(dir.path().join("backups"), None);
+        add(&pool, "Rent");
+        let backup = store.take(&pool.read().unwrap(), at(2026, 10, 1, 8)).unwrap();
+
+        // scribble over the second page, a table's b-tree root
+        let path = store.dir().join(&backup.name);
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[4096..8192].fill(0xAB);
+        fs::write(&path, bytes).unwrap();
+
+        add(&pool, "Gym");
+        assert!(store.restore(&backup.name, &mut pool.write().unwrap()).is_err());
+        assert!(store.restore("../budget.db", &mut pool.write().unwrap()).is_err());
+        assert!(store.restore("budget-20200101T000000.000Z.db", &mut pool.write().unwrap()).is_err());
+        assert_eq!(names(&pool), vec!["Gym", "Rent"]);
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn test_backups_of_encrypted_databases_are_encrypted() {
+        use crate::encryption::{KdfAlgorithm, KdfSettings};
+
+        let dir = tempfile::tempdir().unwrap();
+        let key = DbKey::new("hunter2", KdfSettings { iterations: 1000, algorithm: KdfAlgorithm::Sha512 });
+        let pool = Pool::open_with_key(dir.path().join("budget.db").to_str().unwrap(), 1, Some(&key)).unwrap();
+        let store = BackupStore::new(dir.path().join("backups"), Some(key));
+        add(&pool, "Rent");
+
+        let backup = store.take(&pool.read().unwrap(), at(2026, 10, 1, 8)).unwrap();
+        assert!(encryption::is_encrypted(&store.dir().join(&backup.name)).unwrap());
+
+        add(&pool, "Gym");
+        store.restore(&backup.name, &mut pool.write().unwrap()).unwrap();
+        assert_eq!(names(&pool), vec!["Rent"]);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::models::{FinancialRecord, RecordType};
+use crate::schedule;
+
+use chrono::{Months, NaiveDate};
+use serde::Serialize;
+
+/// What is due in one month, per record type
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct MonthTotals {
+    /// 1st of the month
+    pub month: NaiveDate,
+    pub income: f64,
+    pub expenses: f64,
+    pub debt: f64,
+}
+
+impl MonthTotals {
+    // expenses plus debt payments
+    pub fn outgoing(&self) -> f64 {
+        self.expenses + self.debt
+    }
+
+    pub fn net(&self) -> f64 {
+        self.income - self.outgoing()
+    }
+}
+
+/// One slice of a breakdown: a record, or everything too small to show
+/// separately lumped together
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slice {
+    pub label: String,
+    pub monthly: f64,
+}
+
+/// Totals for `months` months starting with the one `first_month` is in,
+/// following each record's schedule (see schedule::amount_in_month)
+pub fn month_totals(records: &[FinancialRecord], first_month: NaiveDate, months: u32) -> Vec<MonthTotals> {
+    (0..months)
+        .map(|i| {
+            let month = first_month + Months::new(i);
+            let total = |record_type: RecordType| {
+                records
+                    .iter()
+                    .filter(|r| r.record_type == record_type)
+                    .fold(0.0, |sum, r| sum + schedule::amount_in_month(r, month))
+            };
+            MonthTotals {
+                month,
+                income: total(RecordType::Income),
+                expenses: total(RecordType::Expense),
+                debt: total(RecordType::Debt),
+            }
+        })
+        .collect()
+}
+
+/// Records of one type by monthly amount, largest first. Past `max_slices`
+/// the rest are combined into an "Other" slice.
+pub fn breakdown(records: &[FinancialRecord], record_type: RecordType, max_slices: usize) -> Vec<Slice> {
+    let mut slices: Vec<Slice> = records
+        .iter()
+        .filter(|r| r.record_type == record_type && r.monthly_amount() > 0.0)
+        .map(|r| Slice { label: r.name.clone(), monthly: r.monthly_amount() })
+        .collect();
+    slices.sort_by(|a, b| b.monthly.total_cmp(&a.monthly).then_with(|| a.label.cmp(&b.label)));
+    if slices.len() > max_slices {
+        let rest: f64 = slices.drain(max_slices - 1..).map(|s| s.monthly).sum();
+        slices.push(Slice { label: "Other".to_string(), monthly: rest });
+    }
+    slices
+}
+
+/// A round step for a value axis, giving 3 to 6 gridlines up to `max`
+pub fn axis_step(max: f64) -> f64 {
+    if max <= 0.0 {
+        return 1.0;
+    }
+    let magnitude = 10f64.powf((max / 4.0).log10().floor());
+    [1.0, 2.0, 2.5, 5.0, 10.0]
+        .into_iter()
+        .map(|m| m * magnitude)
+        .find(|step| max / step <= 5.0)
+        .unwrap_or(10.0 * magnitude)
+}
+
+/// $1,234.56
+pub fn money(amount: f64) -> String {
+    let cents = (amount.abs() * 100.0).round() as u64;
+    let digits = (cents / 100).to_string();
+    let mut grouped = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    let sign = if amount < 0.0 && cents > 0 { "-" } else { "" };
+    format!("{}${}.{:02}", sign, grouped, cents % 100)
+}
+
+// axis labels: whole dollars, $1.5k from a thousand up
+pub fn short_money(amount: f64) -> String {
+    let sign = if amount < 0.0 { "-" } else { "" };
+    let amount = amount.abs();
+    if amount >= 1000.0 {
+        let thousands = format!("{:.1}", amount / 1000.0);
+        format!("{}${}k", sign, thousands.trim_end_matches(".0"))
+    } else {
+        format!("{}${:.0}", sign, amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Frequency;
+
+    #[test]
+    fn test_money_and_axis() {
+        assert_eq!(money(1234567.891), "$1,234,567.89");
+        assert_eq!(money(-45.5), "-$45.50");
+        assert_eq!(money(-0.001), "$0.00");
+        assert_eq!(short_money(2500.0), "$2.5k");
+        assert_eq!(short_money(-3000.0), "-$3k");
+        assert_eq!(short_money(750.0), "$750");
+        assert_eq!(axis_step(3200.0), 1000.0);
+        assert_eq!(axis_step(90.0), 20.0);
+    }
+
+    #[test]
+    fn test_breakdown_groups_small_slices() {
+        let records: Vec<FinancialRecord> = (1..=5)
+            .map(|i| FinancialRecord::new(format!("E{}", i), f64::from(i) * 10.0, Frequency::Monthly, RecordType::Expense))
+            .chain([FinancialRecord::new("Pay", 100.0, Frequency::Monthly, RecordType::Income)])
+            .collect();
+        let slices = breakdown(&records, RecordType::Expense, 3);
+        let labels: Vec<&str> = slices.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(labels, ["E5", "E4", "Other"]);
+        assert_eq!(slices[2].monthly, 60.0);
+    }
+}
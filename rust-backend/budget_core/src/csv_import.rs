@@ -0,0 +1,336 @@
+use crate::models::{FinancialRecord, Frequency, ImportRow, RecordType};
+use crate::app_error::AppError;
+
+use chrono::NaiveDate;
+use csv::{ReaderBuilder, StringRecord};
+use log::debug;
+use std::io::Read;
+
+// Header names recognised for each field when no explicit mapping is given
+const NAME_HEADERS: &[&str] = &["name", "description", "title", "item"];
+const AMOUNT_HEADERS: &[&str] = &["amount", "value", "sum", "cost", "price"];
+const FREQUENCY_HEADERS: &[&str] = &["frequency", "freq", "interval", "period", "recurrence"];
+const RECORD_TYPE_HEADERS: &[&str] = &["record_type", "type", "kind"];
+const NOTES_HEADERS: &[&str] = &["notes", "note", "memo", "comment", "comments"];
+const PAYEE_HEADERS: &[&str] = &["payee", "merchant", "vendor", "counterparty"];
+const STARTS_ON_HEADERS: &[&str] = &["starts_on", "start_date", "start", "first_due"];
+
+// Which CSV column feeds which FinancialRecord field. Columns are given by
+// header name (case-insensitive) or 1-based position; a None column is
+// looked up among the usual header names for that field.
+#[derive(Clone, Debug)]
+pub struct ColumnMapping {
+    pub name: Option<String>,
+    pub amount: Option<String>,
+    pub frequency: Option<String>,
+    pub record_type: Option<String>,
+    pub notes: Option<String>,
+    pub payee: Option<String>,
+    /// first due date, as YYYY-MM-DD
+    pub starts_on: Option<String>,
+
+    /// used for rows without a frequency column/value
+    pub default_frequency: Frequency,
+    /// used for rows without a type column/value. When None, negative
+    /// amounts are expenses and positive ones income.
+    pub default_record_type: Option<RecordType>,
+
+    pub delimiter: u8,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        Self {
+            name: None,
+            amount: None,
+            frequency: None,
+            record_type: None,
+            notes: None,
+            payee: None,
+            starts_on: None,
+            default_frequency: Frequency::Monthly,
+            default_record_type: None,
+            delimiter: b',',
+        }
+    }
+}
+
+// column positions resolved against the actual header row
+struct Columns {
+    name: Option<usize>,
+    amount: usize,
+    frequency: Option<usize>,
+    record_type: Option<usize>,
+    notes: Option<usize>,
+    payee: Option<usize>,
+    starts_on: Option<usize>,
+}
+
+/// Parse a CSV file (with a header row) into records according to `mapping`.
+///
+/// Problems with individual rows are reported per row in the result; only a
+/// mapping that doesn't fit the file at all (e.g. no amount column) or
+/// unreadable CSV fail the whole parse.
+pub fn parse(reader: impl Read, mapping: &ColumnMapping) -> Result<Vec<ImportRow>, AppError> {
+    let mut csv = ReaderBuilder::new()
+        .delimiter(mapping.delimiter)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(reader);
+
+    let headers = csv.headers()?.clone();
+    debug!("csv import headers: {:?}", headers);
+    let columns = resolve_columns(&headers, mapping)?;
+
+    let mut rows = Vec::new();
+    for result in csv.records() {
+        let record = result?;
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+        rows.push(ImportRow {
+            line,
+            outcome: parse_row(&record, &columns, mapping),
+        });
+    }
+    Ok(rows)
+}
+
+fn resolve_columns(headers: &StringRecord, mapping: &ColumnMapping) -> Result<Columns, AppError> {
+    let amount = find_column(headers, mapping.amount.as_deref(), AMOUNT_HEADERS, "amount")?
+        .ok_or_else(|| AppError(format!("no amount column found in headers {:?}", headers)))?;
+    let columns = Columns {
+        name: find_column(headers, mapping.name.as_deref(), NAME_HEADERS, "name")?,
+        amount,
+        frequency: find_column(headers, mapping.frequency.as_deref(), FREQUENCY_HEADERS, "frequency")?,
+        record_type: find_column(headers, mapping.record_type.as_deref(), RECORD_TYPE_HEADERS, "record type")?,
+        notes: find_column(headers, mapping.notes.as_deref(), NOTES_HEADERS, "notes")?,
+        payee: find_column(headers, mapping.payee.as_deref(), PAYEE_HEADERS, "payee")?,
+        starts_on: find_column(headers, mapping.starts_on.as_deref(), STARTS_ON_HEADERS, "start date")?,
+    };
+    if columns.name.is_none() && columns.payee.is_none() {
+        return Err(AppError(format!("no name or payee column found in headers {:?}", headers)));
+    }
+    Ok(columns)
+}
+
+// An explicitly mapped column must exist; otherwise fall back to the first
+// header matching one of `known` (None if there's none).
+fn find_column(
+    headers: &StringRecord,
+    mapped: Option<&str>,
+    known: &[&str],
+    field: &str,
+) -> Result<Option<usize>, AppError> {
+    let position = |wanted: &str| headers.iter().position(|h| h.eq_ignore_ascii_case(wanted.trim()));
+
+    match mapped {
+        Some(column) => position(column)
+            .or_else(|| {
+                column.trim().parse::<usize>().ok()
+                    .filter(|n| (1..=headers.len()).contains(n))
+                    .map(|n| n - 1)
+            })
+            .map(Some)
+            .ok_or_else(|| AppError(format!("{} column `{}` not found in headers {:?}", field, column, headers))),
+        None => Ok(known.iter().find_map(|k| position(k))),
+    }
+}
+
+fn parse_row(row: &StringRecord, columns: &Columns, mapping: &ColumnMapping) -> Result<FinancialRecord, String> {
+    let field = |idx: Option<usize>| idx.and_then(|i| row.get(i)).filter(|v| !v.is_empty());
+
+    let payee = field(columns.payee).map(str::to_string);
+    let name = field(columns.name)
+        .map(str::to_string)
+        .or_else(|| payee.clone())
+        .ok_or("missing name")?;
+
+    let raw_amount = field(Some(columns.amount)).ok_or("missing amount")?;
+    let amount = parse_amount(raw_amount)?;
+    if amount == 0.0 {
+        return Err("amount is zero".to_string());
+    }
+
+    let frequency = match field(columns.frequency) {
+        Some(value) => Frequency::parse_lenient(value)
+            .ok_or_else(|| format!("unknown frequency `{}`", value))?,
+        None => mapping.default_frequency,
+    };
+
+    let record_type = match field(columns.record_type) {
+        Some(value) => RecordType::parse_lenient(value)
+            .ok_or_else(|| format!("unknown record type `{}`", value))?,
+        None => mapping.default_record_type.unwrap_or(if amount < 0.0 {
+            RecordType::Expense
+        } else {
+            RecordType::Income
+        }),
+    };
+
+    let starts_on = field(columns.starts_on)
+        .map(|value| value.parse::<NaiveDate>().map_err(|_| format!("start date `{}` isn't YYYY-MM-DD", value)))
+        .transpose()?;
+
+    Ok(FinancialRecord {
+        notes: field(columns.notes).map(str::to_string),
+        payee,
+        starts_on,
+        // the sign only says which way the money goes, that's the record type
+        ..FinancialRecord::new(name, amount.abs(), frequency, record_type)
+    })
+}
+
+// Accepts "1,234.56", "1.234,56", "$12.00", "-45", "(45.00)" and "45.00-".
+// Whichever of '.' and ',' comes last is the decimal mark; a lone comma is
+// one only before one or two digits ("12,50"), before three it groups
+// thousands ("1,234").
+pub fn parse_amount(raw: &str) -> Result<f64, String> {
+    let mut s: String = raw
+        .chars()
+        .filter(|c| !matches!(c, '$' | '€' | '£' | ' ' | '\u{a0}'))
+        .collect();
+
+    let mut negative = false;
+    if s.starts_with('(') && s.ends_with(')') {
+        negative = true;
+        s = s[1..s.len() - 1].to_string();
+    }
+    if let Some(stripped) = s.strip_suffix('-') {
+        negative = true;
+        s = stripped.to_string();
+    }
+
+    let invalid = || format!("invalid amount `{}`", raw);
+    let value: f64 = normalize_separators(&s).ok_or_else(invalid)?.parse().map_err(|_| invalid())?;
+    if !value.is_finite() {
+        return Err(invalid());
+    }
+    Ok(if negative { -value } else { value })
+}
+
+// `number` with its thousands separators dropped and a '.' for a decimal
+// mark, or None if the separators don't add up to either format
+fn normalize_separators(number: &str) -> Option<String> {
+    let decimal = match (number.rfind('.'), number.rfind(',')) {
+        (None, None) => return Some(number.to_string()),
+        (Some(dot), Some(comma)) => if dot > comma { '.' } else { ',' },
+        (None, Some(comma)) => match number[comma + 1..].len() {
+            _ if number.matches(',').count() > 1 => '.',
+            1 | 2 => ',',
+            3 => '.',
+            _ => return None,
+        },
+        // "1.234.567" groups with dots, "1.5" is a decimal point
+        (Some(_), None) => if number.matches('.').count() > 1 { ',' } else { '.' },
+    };
+    let group = if decimal == '.' { ',' } else { '.' };
+
+    let (whole, fraction) = match number.rsplit_once(decimal) {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (number, None),
+    };
+    if whole.contains(decimal) || fraction.is_some_and(|f| f.contains(group)) {
+        return None;
+    }
+    // groups of three digits after the first
+    let mut groups = whole.split(group);
+    let first = groups.next().unwrap_or_default().trim_start_matches(['-', '+']);
+    if whole.contains(group) && (first.is_empty() || first.len() > 3 || groups.any(|g| g.len() != 3)) {
+        return None;
+    }
+
+    let mut normalized = whole.replace(group, "");
+    if let Some(fraction) = fraction {
+        normalized.push('.');
+        normalized.push_str(fraction);
+    }
+    Some(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(rows: &[ImportRow]) -> Vec<&FinancialRecord> {
+        rows.iter().map(|r| r.outcome.as_ref().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_parse_with_known_headers() {
+        let csv = "Name,Amount,Frequency,Type,Notes\n\
+                   Salary,\"10,000.00\",monthly,income,\n\
+                   Trash Service,$120.23,Quarterly,bill,curbside\n";
+
+        let rows = parse(csv.as_bytes(), &ColumnMapping::default()).unwrap();
+        let parsed = records(&rows);
+
+        assert_eq!(parsed.len(), 2);
+        assert_eq!((parsed[0].name.as_str(), parsed[0].amount), ("Salary", 10000.0));
+        assert_eq!(parsed[0].record_type, RecordType::Income);
+        assert_eq!(parsed[1].frequency, Frequency::Quarterly);
+        assert_eq!(parsed[1].record_type, RecordType::Expense);
+        assert_eq!(parsed[1].notes.as_deref(), Some("curbside"));
+    }
+
+    #[test]
+    fn test_parse_with_explicit_mapping_and_sign_inference() {
+        let csv = "Date;Details;Debit/Credit\n2025-01-03;NETFLIX.COM;(15.49)\n2025-01-04;ACME PAYROLL;2500\n";
+        let mapping = ColumnMapping {
+            payee: Some("details".into()),
+            amount: Some("3".into()),
+            delimiter: b';',
+            ..ColumnMapping::default()
+        };
+
+        let rows = parse(csv.as_bytes(), &mapping).unwrap();
+        let parsed = records(&rows);
+
+        assert_eq!(parsed[0].name, "NETFLIX.COM");
+        assert_eq!(parsed[0].payee.as_deref(), Some("NETFLIX.COM"));
+        assert_eq!((parsed[0].amount, parsed[0].record_type), (15.49, RecordType::Expense));
+        assert_eq!((parsed[1].amount, parsed[1].record_type), (2500.0, RecordType::Income));
+        assert_eq!(parsed[1].frequency, Frequency::Monthly);
+    }
+
+    #[test]
+    fn test_row_errors_are_reported_per_row() {
+        let csv = "name,amount,frequency\nGood,10,weekly\nBad Amount,ten,weekly\nBad Freq,10,fortnightly\n,10,weekly\n";
+
+        let rows = parse(csv.as_bytes(), &ColumnMapping::default()).unwrap();
+
+        assert!(rows[0].outcome.is_ok());
+        assert_eq!(rows[1].outcome, Err("invalid amount `ten`".to_string()));
+        assert_eq!(rows[2].outcome, Err("unknown frequency `fortnightly`".to_string()));
+        assert_eq!(rows[3].outcome, Err("missing name".to_string()));
+        assert_eq!(rows.iter().map(|r| r.line).collect::<Vec<_>>(), vec![2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_mapping_errors_fail_the_parse() {
+        assert!(parse("name,cost\n".as_bytes(), &ColumnMapping {
+            amount: Some("total".into()),
+            ..ColumnMapping::default()
+        }).is_err());
+        assert!(parse("name,notes\nRent,x\n".as_bytes(), &ColumnMapping::default()).is_err());
+    }
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(parse_amount("1,234.56"), Ok(1234.56));
+        assert_eq!(parse_amount("45.00-"), Ok(-45.0));
+        assert!(parse_amount("NaN").is_err());
+    }
+
+    #[test]
+    fn test_parse_amount_separators() {
+        assert_eq!(parse_amount("1.234,56"), Ok(1234.56));
+        assert_eq!(parse_amount("€ 1.234.567,8"), Ok(1234567.8));
+        assert_eq!(parse_amount("12,50"), Ok(12.5));
+        assert_eq!(parse_amount("1,234"), Ok(1234.0));
+        assert_eq!(parse_amount("1.234.567"), Ok(1234567.0));
+        assert_eq!(parse_amount("-1 234,56"), Ok(-1234.56));
+        // neither format
+        assert!(parse_amount("1,2345").is_err());
+        assert!(parse_amount("1.23,4.5").is_err());
+        assert!(parse_amount("12,34,56").is_err());
+    }
+}
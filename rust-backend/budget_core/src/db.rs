@@ -0,0 +1,167 @@
+use log::info;
+
+use rusqlite::{Connection, Result};
+use std::path::Path;
+
+use crate::encryption::{self, DbKey};
+use crate::record_repository;
+
+// compiled in, so the database can be created from any working directory
+const SCHEMA: &str = include_str!("../sql/schema.sql");
+
+// creates record_history and the triggers that keep it append-only
+const RECORD_HISTORY: &str = include_str!("../sql/migrations/004_record_history.sql");
+
+// Schema changes made after the initial schema.sql. Each entry is applied
+// once, in order, and the number applied is tracked in `PRAGMA user_version`.
+// Only ever append to this list.
+const MIGRATIONS: &[&str] = &[
+    include_str!("../sql/migrations/001_record_search.sql"),
+    include_str!("../sql/migrations/002_attachments.sql"),
+    include_str!("../sql/migrations/003_soft_delete.sql"),
+    RECORD_HISTORY,
+    include_str!("../sql/migrations/005_operation_journal.sql"),
+    include_str!("../sql/migrations/006_ledger_entries.sql"),
+    include_str!("../sql/migrations/007_ledger_account_kind.sql"),
+    include_str!("../sql/migrations/008_rules.sql"),
+    include_str!("../sql/migrations/009_record_starts_on.sql"),
+    include_str!("../sql/migrations/010_record_search_rowid.sql"),
+];
+
+// initialize the database: create the schema.sql tables and migrate
+pub fn init_db(path: &str) -> Result<Connection> {
+    init_db_with_key(path, None)
+}
+
+// init_db for a database that is (or is to be created) encrypted with `key`
+pub fn init_db_with_key(path: &str, key: Option<&DbKey>) -> Result<Connection> {
+    info!("Initializing Database...");
+    let conn = Connection::open(path)?;
+    match key {
+        Some(key) => encryption::apply_key(&conn, key)?,
+        // without a key SQLite only says "file is not a database"
+        None if encryption::is_encrypted(Path::new(path)).unwrap_or(false) => {
+            return Err(rusqlite::Error::SqliteFailure(
+                rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_NOTADB),
+                Some(format!("{} is encrypted, open it with its passphrase", path)),
+            ));
+        }
+        None => {}
+    }
+    // off by default in SQLite; attachments rely on ON DELETE CASCADE
+    conn.pragma_update(None, "foreign_keys", true)?;
+
+    conn.execute_batch(SCHEMA)?;
+    migrate(&conn)?;
+    record_repository::sync_search_index(&conn)?;
+
+    Ok(conn)
+}
+
+// the schema version migrate brings a database up to
+pub(crate) fn schema_version() -> usize {
+    MIGRATIONS.len()
+}
+
+// Delete everything in the database, for replacing it with an archive. The
+// append-only record_history is the one exception to its own rule: its
+// triggers are dropped for the delete and put back after.
+pub(crate) fn clear_all(conn: &Connection) -> Result<()> {
+    info!("Clearing the database");
+    conn.execute_batch(
+        "DROP TRIGGER IF EXISTS record_history_no_update;
+        DROP TRIGGER IF EXISTS record_history_no_delete;
+        DELETE FROM attachment;
+        DELETE FROM ledger_entry;
+        DELETE FROM rule;
+        DELETE FROM operation_journal;
+        DELETE FROM record_history;
+        DELETE FROM financial_record;",
+    )?;
+    conn.execute_batch(RECORD_HISTORY)
+}
+
+// bring an existing database up to the latest schema version
+pub fn migrate(conn: &Connection) -> Result<()> {
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    for (i, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+        info!("Applying migration {}", i + 1);
+        // run each migration and its version bump atomically
+        conn.execute_batch(&format!(
+            "BEGIN;\n{}\nPRAGMA user_version = {};\nCOMMIT;",
+            sql,
+            i + 1
+        ))?;
+    }
+
+    Ok(())
+}
+
+// Run `f` inside a SAVEPOINT: everything it does is kept if it returns Ok and
+// rolled back if it returns Err. Unlike a transaction, savepoints nest, so
+// this is safe to use from code that may already be inside one.
+pub fn with_savepoint<T, E: From<rusqlite::Error>>(
+    conn: &Connection,
+    f: impl FnOnce() -> std::result::Result<T, E>,
+) -> std::result::Result<T, E> {
+    conn.execute_batch("SAVEPOINT budget_sp")?;
+    match f() {
+        Ok(value) => {
+            conn.execute_batch("RELEASE budget_sp")?;
+            Ok(value)
+        }
+        Err(e) => {
+            conn.execute_batch("ROLLBACK TO budget_sp; RELEASE budget_sp")?;
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_init_db_creates_schema() {
+        // Use in-memory DB so we don't touch disk
+        let conn = init_db(":memory:").expect("Failed to init DB");
+
+        // Check that 'financial_record' table exists
+        let mut stmt = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type='table' AND name='financial_record'")
+            .expect("Failed to prepare table check");
+
+        let mut rows = stmt.query([]).expect("Failed to query sqlite_master");
+
+        assert!(rows.next().unwrap().is_some(), "Table 'financial_record' not found");
+    }
+
+    #[test]
+    fn test_migrate_is_idempotent() {
+        let conn = init_db(":memory:").expect("Failed to init DB");
+        migrate(&conn).expect("Second migrate failed");
+
+        let version: usize = conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+    }
+
+    #[test]
+    fn test_with_savepoint_rolls_back_on_error() {
+        let conn = init_db(":memory:").expect("Failed to init DB");
+        conn.execute_batch("CREATE TABLE t (x INTEGER)").unwrap();
+
+        let result: Result<()> = with_savepoint(&conn, || {
+            conn.execute("INSERT INTO t VALUES (1)", [])?;
+            // nested savepoints commit into the outer one
+            with_savepoint(&conn, || conn.execute("INSERT INTO t VALUES (2)", []))?;
+            Err(rusqlite::Error::InvalidQuery)
+        });
+        assert!(result.is_err());
+
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM t", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 0);
+    }
+}
@@ -0,0 +1,347 @@
+use log::info;
+
+use rusqlite::ffi;
+use rusqlite::{Connection, Error, ErrorCode, Result};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+// every plaintext SQLite file starts with this; an encrypted one starts with
+// random salt instead
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// The hash PBKDF2 derives the database key with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KdfAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl KdfAlgorithm {
+    fn as_pragma(&self) -> &'static str {
+        match self {
+            KdfAlgorithm::Sha1 => "PBKDF2_HMAC_SHA1",
+            KdfAlgorithm::Sha256 => "PBKDF2_HMAC_SHA256",
+            KdfAlgorithm::Sha512 => "PBKDF2_HMAC_SHA512",
+        }
+    }
+}
+
+impl FromStr for KdfAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sha1" => Ok(KdfAlgorithm::Sha1),
+            "sha256" => Ok(KdfAlgorithm::Sha256),
+            "sha512" => Ok(KdfAlgorithm::Sha512),
+            _ => Err(format!("unknown key derivation hash `{}` (sha1, sha256 or sha512)", s)),
+        }
+    }
+}
+
+/// How the database key is derived from the passphrase.
+///
+/// SQLCipher doesn't record these in the file: a database has to be opened
+/// with the settings it was encrypted with, or the passphrase is rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfSettings {
+    pub iterations: u32,
+    pub algorithm: KdfAlgorithm,
+}
+
+impl Default for KdfSettings {
+    /// SQLCipher 4's defaults
+    fn default() -> Self {
+        Self {
+            iterations: 256_000,
+            algorithm: KdfAlgorithm::Sha512,
+        }
+    }
+}
+
+/// What an encrypted database is opened with: a passphrase and the settings
+/// its key is derived with
+#[derive(Clone)]
+pub struct DbKey {
+    passphrase: String,
+    pub kdf: KdfSettings,
+}
+
+impl DbKey {
+    pub fn new(passphrase: impl Into<String>, kdf: KdfSettings) -> Self {
+        Self {
+            passphrase: passphrase.into(),
+            kdf,
+        }
+    }
+}
+
+// keep the passphrase out of logs and panics
+impl fmt::Debug for DbKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DbKey").field("passphrase", &"..").field("kdf", &self.kdf).finish()
+    }
+}
+
+/// Whether the file at `path` is an encrypted database. Files that don't
+/// exist yet, or are empty, are not.
+pub fn is_encrypted(path: &Path) -> io::Result<bool> {
+    let mut header = [0u8; 16];
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    match file.read_exact(&mut header) {
+        Ok(()) => Ok(&header != SQLITE_HEADER),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+// Key a freshly opened connection. This has to come before anything else
+// touches the database; SQLite's own checks then fail with "file is not a
+// database" on a wrong passphrase, which is reported as one here.
+pub(crate) fn apply_key(conn: &Connection, key: &DbKey) -> Result<()> {
+    // plain SQLite ignores the key pragma and would quietly write plaintext
+    let cipher: Option<String> = conn.query_row("PRAGMA cipher_version", [], |row| row.get(0)).ok();
+    if cipher.is_none() {
+        return Err(failure(
+            ffi::SQLITE_ERROR,
+            "this build can't open encrypted databases (build budget_core with the encryption feature)",
+        ));
+    }
+
+    conn.pragma_update(None, "key", &key.passphrase)?;
+    // a wrong passphrase is reported below; SQLCipher would also print its
+    // failed page checks to stderr. Keying sets up the logging, so this has
+    // to come after
+    conn.pragma_update(None, "cipher_log_level", "NONE")?;
+    conn.pragma_update(None, "cipher_kdf_algorithm", key.kdf.algorithm.as_pragma())?;
+    conn.pragma_update(None, "kdf_iter", key.kdf.iterations)?;
+
+    match conn.query_row("SELECT count(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0)) {
+        Ok(_) => Ok(()),
+        Err(e) if e.sqlite_error_code() == Some(ErrorCode::NotADatabase) => Err(failure(
+            ffi::SQLITE_NOTADB,
+            "wrong passphrase or key derivation settings, or the database isn't encrypted",
+        )),
+        Err(e) => Err(e),
+    }
+}
+
+/// Encrypts the plaintext database at `path` in place with `key`
+pub fn encrypt(path: &str, key: &DbKey) -> Result<()> {
+    change_key(path, None, Some(key))
+}
+
+/// Re-encrypts the database at `path` with a new passphrase and/or key
+/// derivation settings
+pub fn rekey(path: &str, current: &DbKey, new: &DbKey) -> Result<()> {
+    change_key(path, Some(current), Some(new))
+}
+
+// Rewrite the database at `path`, opened with `current` (None: plaintext),
+// under `new` (None: plaintext). The copy is made with sqlcipher_export next
+// to the database and checked before it replaces the original, so a failure
+// part way leaves the original untouched. Nothing else may have the database
+// open meanwhile.
+fn change_key(path: &str, current: Option<&DbKey>, new: Option<&DbKey>) -> Result<()> {
+    let rewritten = format!("{}.rekey", path);
+    remove_if_exists(&rewritten)?;
+
+    {
+        let conn = Connection::open(path)?;
+        if let Some(key) = current {
+            apply_key(&conn, key)?;
+        } else if is_encrypted(Path::new(path)).map_err(io_error)? {
+            return Err(failure(ffi::SQLITE_NOTADB, "the database is already encrypted"));
+        }
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        // fold the WAL into the database file, so that the copy has it all
+        // and the stale WAL can go once the copy has replaced the file
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+
+        let passphrase = new.map_or("", |key| key.passphrase.as_str());
+        conn.execute("ATTACH DATABASE ?1 AS rekeyed KEY ?2", [&rewritten, passphrase])?;
+        if let Some(key) = new {
+            conn.pragma_update(Some("rekeyed"), "cipher_kdf_algorithm", key.kdf.algorithm.as_pragma())?;
+            conn.pragma_update(Some("rekeyed"), "kdf_iter", key.kdf.iterations)?;
+        }
+        conn.query_row("SELECT sqlcipher_export('rekeyed')", [], |_| Ok(()))?;
+        conn.pragma_update(Some("rekeyed"), "user_version", version)?;
+        conn.execute_batch("DETACH DATABASE rekeyed")?;
+    }
+
+    let check = Connection::open(&rewritten)?;
+    if let Some(key) = new {
+        apply_key(&check, key)?;
+    }
+    let integrity: String = check.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+    drop(check);
+    if integrity != "ok" {
+        remove_if_exists(&rewritten)?;
+        return Err(failure(ffi::SQLITE_CORRUPT, &format!("the re-encrypted copy failed its integrity check: {}", integrity)));
+    }
+
+    fs::rename(&rewritten, path).map_err(io_error)?;
+    // the WAL and shared memory index belong to the old file
+    remove_if_exists(&format!("{}-wal", path))?;
+    remove_if_exists(&format!("{}-shm", path))?;
+    info!("Rewrote {} {}", path, if new.is_some() { "encrypted" } else { "as plaintext" });
+    Ok(())
+}
+
+fn remove_if_exists(path: &str) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(io_error(e)),
+        _ => Ok(()),
+    }
+}
+
+fn failure(code: i32, message: &str) -> Error {
+    Error::SqliteFailure(ffi::Error::new(code), Some(message.to_string()))
+}
+
+fn io_error(e: io::Error) -> Error {
+    failure(ffi::SQLITE_IOERR, &e.to_string())
+}
+
+#[cfg(all(test, feature = "encryption"))]
+mod tests {
+    use super::*;
+    use crate::models::{FinancialRecord, Frequency, RecordType};
+    use crate::pool::Pool;
+    use crate::service;
+
+    // a quick key: the default iteration count makes every open take a
+    // noticeable fraction of a second
+    fn key(passphrase: &str) -> DbKey {
+        DbKey::new(passphrase, KdfSettings { iterations: 1000, algorithm: KdfAlgorithm::Sha512 })
+    }
+
+    fn db_path(dir: &tempfile::TempDir) -> String {
+        dir.path().join("budget.db").to_str().unwrap().to_string()
+    }
+
+    fn add_rent(pool: &Pool) {
+        let rent = FinancialRecord::new("Rent", 1500.0, Frequency::Monthly, RecordType::Expense);
+        service::add_record(pool, &rent, "test").unwrap();
+    }
+
+    #[test]
+    fn test_encrypted_database_needs_its_passphrase() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = db_path(&dir);
+        add_rent(&Pool::open_with_key(&path, 2, Some(&key("hunter2"))).unwrap());
+
+        assert!(is_encrypted(Path::new(&path)).unwrap());
+        let contents = fs::read(&path).unwrap();
+        assert!(!contents.windows(4).any(|w| w == b"Rent"), "record name stored in plaintext");
+
+        assert!(Pool::open(&path, 0).is_err());
+        assert!(Pool::open_with_key(&path, 0, Some(&key("wrong"))).is_err());
+        let pool = Pool::open_with_key(&path, 2, Some(&key("hunter2"))).unwrap();
+        assert_eq!(service::get_all_records(&pool).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_kdf_settings_must_match() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = db_path(&dir);
+        add_rent(&Pool::open_with_key(&path, 0, Some(&key("hunter2"))).unwrap());
+
+        let mut other = key("hunter2");
+        other.kdf.iterations = 2000;
+        assert!(Pool::open_with_key(&path, 0, Some(&other)).is_err());
+        other.kdf = KdfSettings { iterations: 1000, algorithm: KdfAlgorithm::Sha256 };
+        assert!(Pool::open_with_key(&path, 0, Some(&other)).is_err());
+    }
+
+    #[test]
+    fn test_search_index_survives_encrypt() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = db_path(&dir);
+        let plaintext = Pool::open(&path, 0).unwrap();
+        let add = |name: &str| {
+            let record = FinancialRecord::new(name, 10.0, Frequency::Monthly, RecordType::Expense);
+            service::add_record(&plaintext, &record, "test").unwrap()
+        };
+        let (gym, mut water) = (add("Gym"), add("Water"));
+        add("Phone");
+        // a gap in the rowids, which the copy doesn't keep
+        let conn = plaintext.write().unwrap();
+        crate::record_repository::delete_record(&conn, &gym.id, "test").unwrap();
+        crate::record_repository::purge_record(&conn, &gym.id, "test").unwrap();
+        drop(conn);
+        drop(plaintext);
+
+        encrypt(&path, &key("hunter2")).unwrap();
+        let pool = Pool::open_with_key(&path, 0, Some(&key("hunter2"))).unwrap();
+        water.name = "Electricity".to_string();
+        service::update_record(&pool, &water, "test").unwrap();
+
+        let hits = |query: &str| -> Vec<String> {
+            service::search_records(&pool, query).unwrap().into_iter().map(|hit| hit.record.name).collect()
+        };
+        assert_eq!(hits("electricity"), ["Electricity"]);
+        assert!(hits("water").is_empty());
+        assert_eq!(hits("phone"), ["Phone"]);
+    }
+
+    #[test]
+    fn test_encrypt_migrates_a_plaintext_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = db_path(&dir);
+        // a WAL database, still open, with the record not yet checkpointed
+        let plaintext = Pool::open(&path, 2).unwrap();
+        add_rent(&plaintext);
+        let version: usize = plaintext.read().unwrap().query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+        drop(plaintext);
+        assert!(!is_encrypted(Path::new(&path)).unwrap());
+
+        encrypt(&path, &key("hunter2")).unwrap();
+
+        assert!(is_encrypted(Path::new(&path)).unwrap());
+        assert!(!Path::new(&format!("{}.rekey", path)).exists());
+        let pool = Pool::open_with_key(&path, 2, Some(&key("hunter2"))).unwrap();
+        let records = service::get_all_records(&pool).unwrap();
+        assert_eq!(records.len(), 1);
+        // the search index and history came along
+        assert_eq!(service::search_records(&pool, "ren").unwrap().len(), 1);
+        assert_eq!(service::get_record_history(&pool, &records[0].id).unwrap().len(), 1);
+        // and the schema version, so no migration runs twice
+        let after: usize = pool.read().unwrap().query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+        assert_eq!(after, version);
+
+        assert!(encrypt(&path, &key("again")).is_err());
+    }
+
+    #[test]
+    fn test_rekey_changes_passphrase_and_kdf() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = db_path(&dir);
+        add_rent(&Pool::open_with_key(&path, 0, Some(&key("hunter2"))).unwrap());
+
+        let new = DbKey::new("correct horse", KdfSettings { iterations: 4000, algorithm: KdfAlgorithm::Sha256 });
+        assert!(rekey(&path, &key("wrong"), &new).is_err());
+        rekey(&path, &key("hunter2"), &new).unwrap();
+
+        assert!(Pool::open_with_key(&path, 0, Some(&key("hunter2"))).is_err());
+        let pool = Pool::open_with_key(&path, 0, Some(&new)).unwrap();
+        assert_eq!(service::get_all_records(&pool).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_new_files_are_not_encrypted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = db_path(&dir);
+        assert!(!is_encrypted(Path::new(&path)).unwrap());
+        File::create(&path).unwrap();
+        assert!(!is_encrypted(Path::new(&path)).unwrap());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::models::{FinancialRecord, Summary};
+use crate::app_error::AppError;
+
+use std::io::Write;
+use std::str::FromStr;
+
+// Column order of record CSV exports. Don't reorder, only append: the names
+// match what csv_import recognises, so an export imports back as-is.
+pub const RECORD_CSV_HEADERS: [&str; 8] =
+    ["id", "name", "amount", "frequency", "record_type", "notes", "payee", "starts_on"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Json => "application/json",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            other => Err(format!("unknown export format `{}` (expected csv or json)", other)),
+        }
+    }
+}
+
+pub fn write_records(records: &[FinancialRecord], format: ExportFormat, out: impl Write) -> Result<(), AppError> {
+    match format {
+        ExportFormat::Csv => {
+            let mut csv = csv::Writer::from_writer(out);
+            csv.write_record(RECORD_CSV_HEADERS)?;
+            for r in records {
+                csv.write_record([
+                    r.id.to_string(),
+                    r.name.clone(),
+                    r.amount.to_string(),
+                    r.frequency.to_string(),
+                    r.record_type.to_string(),
+                    r.notes.clone().unwrap_or_default(),
+                    r.payee.clone().unwrap_or_default(),
+                    r.starts_on.map(|d| d.to_string()).unwrap_or_default(),
+                ])?;
+            }
+            csv.flush()?;
+        }
+        ExportFormat::Json => write_json(records, out)?,
+    }
+    Ok(())
+}
+
+// The summary as one row per record type plus a Net row
+pub fn write_summary(summary: &Summary, format: ExportFormat, out: impl Write) -> Result<(), AppError> {
+    match format {
+        ExportFormat::Csv => {
+            let mut csv = csv::Writer::from_writer(out);
+            csv.write_record(["record_type", "record_count", "monthly_total", "yearly_total"])?;
+            for line in &summary.lines {
+                csv.write_record([
+                    line.record_type.to_string(),
+                    line.record_count.to_string(),
+                    format!("{:.2}", line.monthly_total),
+                    format!("{:.2}", line.yearly_total),
+                ])?;
+            }
+            csv.write_record([
+                "Net".to_string(),
+                String::new(),
+                format!("{:.2}", summary.monthly_net),
+                format!("{:.2}", summary.monthly_net * 12.0),
+            ])?;
+            csv.flush()?;
+        }
+        ExportFormat::Json => write_json(summary, out)?,
+    }
+    Ok(())
+}
+
+fn write_json(value: &(impl serde::Serialize + ?Sized), mut out: impl Write) -> Result<(), AppError> {
+    serde_json::to_writer_pretty(&mut out, value).map_err(|e| AppError(format!("json error: {}", e)))?;
+    writeln!(out)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv_import::{self, ColumnMapping};
+    use crate::models::{Frequency, RecordType};
+
+    #[test]
+    fn test_csv_export_round_trips_through_import() {
+        let mut rent = FinancialRecord::new("Rent, \"downtown\"", 1500.1, Frequency::Monthly, RecordType::Expense);
+        rent.notes = Some("due on the 1st\nlate fee after 5th".into());
+        rent.payee = Some("ACME Properties".into());
+        let mut salary = FinancialRecord::new("Salary", 10000.0, Frequency::Yearly, RecordType::Income);
+        salary.starts_on = chrono::NaiveDate::from_ymd_opt(2025, 1, 31);
+        let loan = FinancialRecord::new("Car", 0.1 + 0.2, Frequency::Weekly, RecordType::Debt);
+        let records = vec![rent, salary, loan];
+
+        let mut out = Vec::new();
+        write_records(&records, ExportFormat::Csv, &mut out).unwrap();
+        let rows = csv_import::parse(out.as_slice(), &ColumnMapping::default()).unwrap();
+
+        let imported: Vec<FinancialRecord> = rows
+            .into_iter()
+            .zip(&records)
+            // imports always get fresh ids
+            .map(|(row, original)| FinancialRecord { id: original.id, ..row.outcome.unwrap() })
+            .collect();
+        assert_eq!(imported, records);
+    }
+
+    #[test]
+    fn test_json_export_is_an_array_of_records() {
+        let records = vec![FinancialRecord::new("Rent", 1500.0, Frequency::Monthly, RecordType::Expense)];
+
+        let mut out = Vec::new();
+        write_records(&records, ExportFormat::Json, &mut out).unwrap();
+
+        let parsed: Vec<FinancialRecord> = serde_json::from_slice(&out).unwrap();
+        assert_eq!(parsed, records);
+    }
+
+    #[test]
+    fn test_summary_csv() {
+        let records = vec![
+            FinancialRecord::new("Salary", 3000.0, Frequency::Monthly, RecordType::Income),
+            FinancialRecord::new("Rent", 1000.0, Frequency::Monthly, RecordType::Expense),
+        ];
+
+        let mut out = Vec::new();
+        write_summary(&Summary::from_records(&records), ExportFormat::Csv, &mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "record_type,record_count,monthly_total,yearly_total\n\
+             Income,1,3000.00,36000.00\n\
+             Expense,1,1000.00,12000.00\n\
+             Debt,0,0.00,0.00\n\
+             Net,,2000.00,24000.00\n"
+        );
+    }
+}
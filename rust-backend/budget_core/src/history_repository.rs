@@ -0,0 +1,164 @@
+use crate::models::{ChangeAction, FinancialRecord, HistoryEntry};
+
+use log::debug;
+use rusqlite::{params, Connection, Result, Row};
+use uuid::Uuid;
+
+const HISTORY_COLUMNS: &str = "id, record_id, action, actor, changed_at, before_json, after_json";
+
+fn to_json(record: Option<&FinancialRecord>) -> Result<Option<String>> {
+    record
+        .map(|r| serde_json::to_string(r).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e))))
+        .transpose()
+}
+
+fn from_json(idx: usize, json: Option<String>) -> Result<Option<serde_json::Value>> {
+    json.map(|j| {
+        serde_json::from_str(&j).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(e))
+        })
+    })
+    .transpose()
+}
+
+fn entry_from_row(row: &Row) -> Result<HistoryEntry> {
+    Ok(HistoryEntry {
+        id: row.get(0)?,
+        record_id: row.get(1)?,
+        action: row.get(2)?,
+        actor: row.get(3)?,
+        changed_at: row.get(4)?,
+        before: from_json(5, row.get(5)?)?,
+        after: from_json(6, row.get(6)?)?,
+    })
+}
+
+// Append a change to the history. Only record_repository should call this,
+// as part of the same savepoint as the change itself.
+pub(crate) fn record_change(
+    conn: &Connection,
+    record_id: &Uuid,
+    action: ChangeAction,
+    actor: &str,
+    before: Option<&FinancialRecord>,
+    after: Option<&FinancialRecord>,
+) -> Result<()> {
+    debug!("record_change(record_id={}, action={}, actor={})", record_id, action, actor);
+    conn.execute(
+        "INSERT INTO record_history (record_id, action, actor, before_json, after_json)
+        VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![record_id, action, actor, to_json(before)?, to_json(after)?],
+    )?;
+    Ok(())
+}
+
+// every change to one record, oldest first
+pub fn get_history_for_record(conn: &Connection, record_id: &Uuid) -> Result<Vec<HistoryEntry>> {
+    debug!("get_history_for_record(record_id={})", record_id);
+    conn.prepare(&format!(
+        "SELECT {} FROM record_history WHERE record_id = ?1 ORDER BY id",
+        HISTORY_COLUMNS
+    ))?
+    .query_map(params![record_id], entry_from_row)?
+    .collect()
+}
+
+// When the record was first created, as YYYY-MM-DD: the day of its earliest
+// change, which for records older than the history is their first update.
+// None if it has no history at all.
+pub fn get_created_on(conn: &Connection, record_id: &Uuid) -> Result<Option<String>> {
+    debug!("get_created_on(record_id={})", record_id);
+    conn.query_row(
+        "SELECT substr(MIN(changed_at), 1, 10) FROM record_history WHERE record_id = ?1",
+        params![record_id],
+        |row| row.get(0),
+    )
+}
+
+// Most recent changes across all records, newest first. Pass the smallest id
+// of the previous page as `before_id` to page further back.
+pub fn get_activity(conn: &Connection, limit: usize, before_id: Option<i64>) -> Result<Vec<HistoryEntry>> {
+    debug!("get_activity(limit={}, before_id={:?})", limit, before_id);
+    conn.prepare(&format!(
+        "SELECT {} FROM record_history
+         WHERE ?1 IS NULL OR id < ?1
+         ORDER BY id DESC
+         LIMIT ?2",
+        HISTORY_COLUMNS
+    ))?
+    .query_map(params![before_id, limit as i64], entry_from_row)?
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Frequency, RecordType};
+    use crate::record_repository;
+
+    fn setup_conn() -> Connection {
+        crate::db::init_db(":memory:").expect("failed to init memory db")
+    }
+
+    #[test]
+    fn test_mutations_are_logged_with_snapshots() {
+        let conn = setup_conn();
+        let mut record = FinancialRecord::new("Rent", 1500.0, Frequency::Monthly, RecordType::Expense);
+        record_repository::insert_record(&conn, &record, "alice").unwrap();
+        record.amount = 1600.0;
+        record_repository::update_record(&conn, &record, "bob").unwrap();
+        record_repository::delete_record(&conn, &record.id, "bob").unwrap();
+
+        let history = get_history_for_record(&conn, &record.id).unwrap();
+        let actions: Vec<_> = history.iter().map(|h| (h.action, h.actor.as_str())).collect();
+        assert_eq!(actions, vec![
+            (ChangeAction::Insert, "alice"),
+            (ChangeAction::Update, "bob"),
+            (ChangeAction::Delete, "bob"),
+        ]);
+
+        let update = &history[1];
+        assert_eq!(update.before.as_ref().unwrap()["amount"], 1500.0);
+        assert_eq!(update.after.as_ref().unwrap()["amount"], 1600.0);
+        assert!(history[0].before.is_none());
+        assert!(history[2].after.is_none());
+    }
+
+    #[test]
+    fn test_noop_mutations_are_not_logged() {
+        let conn = setup_conn();
+        let record = FinancialRecord::new("Ghost", 1.0, Frequency::Monthly, RecordType::Expense);
+
+        record_repository::update_record(&conn, &record, "alice").unwrap();
+        record_repository::delete_record(&conn, &record.id, "alice").unwrap();
+
+        assert!(get_history_for_record(&conn, &record.id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_activity_pages_newest_first() {
+        let conn = setup_conn();
+        for name in ["a", "b", "c"] {
+            let record = FinancialRecord::new(name, 1.0, Frequency::Monthly, RecordType::Expense);
+            record_repository::insert_record(&conn, &record, "alice").unwrap();
+        }
+
+        let first = get_activity(&conn, 2, None).unwrap();
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[0].after.as_ref().unwrap()["name"], "c");
+
+        let rest = get_activity(&conn, 2, Some(first[1].id)).unwrap();
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].after.as_ref().unwrap()["name"], "a");
+    }
+
+    #[test]
+    fn test_history_is_append_only() {
+        let conn = setup_conn();
+        let record = FinancialRecord::new("Rent", 1500.0, Frequency::Monthly, RecordType::Expense);
+        record_repository::insert_record(&conn, &record, "alice").unwrap();
+
+        assert!(conn.execute("UPDATE record_history SET actor = 'mallory'", []).is_err());
+        assert!(conn.execute("DELETE FROM record_history", []).is_err());
+    }
+}
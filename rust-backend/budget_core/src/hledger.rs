@@ -0,0 +1,346 @@
+use crate::models::{AccountKind, FinancialRecord, Frequency, ImportRow, LedgerEntry, RecordType};
+use crate::csv_import::parse_amount;
+use crate::app_error::AppError;
+
+use std::collections::HashMap;
+use std::io::Write;
+
+// where budgeted money comes from / goes to in the periodic transactions
+const BUDGET_ACCOUNT: &str = "assets:checking";
+const COMMODITY: &str = "$";
+
+fn top_account(record_type: RecordType) -> &'static str {
+    match record_type {
+        RecordType::Income => "income",
+        RecordType::Expense => "expenses",
+        RecordType::Debt => "liabilities",
+    }
+}
+
+fn period(frequency: Frequency) -> &'static str {
+    match frequency {
+        Frequency::Daily => "daily",
+        Frequency::Weekly => "weekly",
+        Frequency::Monthly => "monthly",
+        Frequency::Quarterly => "quarterly",
+        Frequency::Yearly => "yearly",
+    }
+}
+
+// Account names can't contain two spaces in a row (that ends the name) and
+// ':' separates sub-accounts
+fn account_segment(name: &str) -> String {
+    let cleaned = one_line(name).replace(':', "-");
+    cleaned.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn one_line(text: &str) -> String {
+    text.lines().map(str::trim).filter(|l| !l.is_empty()).collect::<Vec<_>>().join(" ")
+}
+
+fn record_account(record: &FinancialRecord) -> String {
+    format!("{}:{}", top_account(record.record_type), account_segment(&record.name))
+}
+
+fn money(amount: f64) -> String {
+    // avoid "$-0.00"
+    let amount = if amount.abs() < 0.005 { 0.0 } else { amount };
+    format!("{}{:.2}", COMMODITY, amount)
+}
+
+/// Write records as periodic transactions (`~ monthly  Rent`) followed by the
+/// ledger entries as regular transactions, in ledger/hledger journal syntax.
+///
+/// Income is posted from `income:<name>`, expenses to `expenses:<name>` and
+/// debt payments to `liabilities:<name>`, all against assets:checking. An
+/// actual transaction is posted to its record's account when it's linked to
+/// one, else to its rule category.
+pub fn write(records: &[FinancialRecord], entries: &[LedgerEntry], mut out: impl Write) -> Result<(), AppError> {
+    writeln!(out, "; Budget exported from Overkill budget app")?;
+    if !records.is_empty() {
+        writeln!(out, "\n; budgeted, see `hledger bal --budget`")?;
+    }
+    for record in records {
+        writeln!(out, "\n~ {}  {}", period(record.frequency), one_line(&record.name))?;
+        if let Some(payee) = &record.payee {
+            writeln!(out, "    ; payee: {}", one_line(payee))?;
+        }
+        for line in record.notes.iter().flat_map(|n| n.lines()).filter(|l| !l.trim().is_empty()) {
+            writeln!(out, "    ; {}", line.trim())?;
+        }
+        let amount = match record.record_type {
+            RecordType::Income => -record.amount,
+            RecordType::Expense | RecordType::Debt => record.amount,
+        };
+        writeln!(out, "    {}  {}", record_account(record), money(amount))?;
+        writeln!(out, "    {}", BUDGET_ACCOUNT)?;
+    }
+
+    if !entries.is_empty() {
+        writeln!(out, "\n; actual transactions")?;
+    }
+    let accounts: HashMap<_, _> = records.iter().map(|r| (r.id, record_account(r))).collect();
+    let mut sorted: Vec<&LedgerEntry> = entries.iter().collect();
+    sorted.sort_by_key(|e| e.posted_on);
+    for entry in sorted {
+        let counter = match entry.account_kind {
+            AccountKind::CreditCard => format!("liabilities:{}", account_segment(&entry.account)),
+            AccountKind::Bank | AccountKind::Cash => format!("assets:{}", account_segment(&entry.account)),
+        };
+        let category = entry
+            .record_id
+            .and_then(|id| accounts.get(&id).cloned())
+            .unwrap_or_else(|| {
+                let record_type = entry.record_type.unwrap_or(if entry.amount > 0.0 { RecordType::Income } else { RecordType::Expense });
+                let category = entry.category.as_deref().map(|c| c.split(':').map(account_segment).collect::<Vec<_>>().join(":"));
+                format!("{}:{}", top_account(record_type), category.unwrap_or_else(|| "uncategorized".to_string()))
+            });
+
+        write!(out, "\n{} {}", entry.posted_on, one_line(entry.display_name()))?;
+        let mut tags = Vec::new();
+        if let Some(memo) = &entry.memo {
+            tags.push(one_line(memo));
+        }
+        if let Some(fitid) = &entry.fitid {
+            tags.push(format!("fitid: {}", fitid));
+        }
+        if !entry.tags.is_empty() {
+            tags.extend(entry.tags.iter().map(|t| format!("{}:", account_segment(t).replace([' ', ','], "-"))));
+        }
+        if tags.is_empty() {
+            writeln!(out)?;
+        } else {
+            writeln!(out, "  ; {}", tags.join(", "))?;
+        }
+        writeln!(out, "    {}  {}", category, money(-entry.amount))?;
+        writeln!(out, "    {}  {}", counter, money(entry.amount))?;
+    }
+    Ok(())
+}
+
+// A periodic transaction being read: where it starts and its lines so far
+struct Periodic {
+    line: u64,
+    period: String,
+    description: String,
+    comments: Vec<String>,
+    postings: Vec<(String, Option<String>)>,
+}
+
+/// Read the periodic transactions (`~ PERIOD  DESCRIPTION` plus postings) of
+/// a ledger/hledger journal back into records. Everything else in the
+/// journal (regular transactions, directives) is ignored.
+///
+/// The posting to an income, expenses or liabilities account gives the
+/// record's type and amount; the description (or that account's last part)
+/// its name. `; payee: X` comments set the payee, other comments the notes.
+pub fn parse(input: &str) -> Vec<ImportRow> {
+    let mut rows = Vec::new();
+    let mut current: Option<Periodic> = None;
+
+    for (number, raw) in input.lines().enumerate() {
+        let number = number as u64 + 1;
+        let line = raw.trim_end();
+        let indented = line.starts_with([' ', '\t']);
+        let content = line.trim_start();
+
+        if indented && !content.is_empty() {
+            if let Some(periodic) = current.as_mut() {
+                match content.strip_prefix([';', '#', '*']) {
+                    Some(comment) => periodic.comments.push(comment.trim().to_string()),
+                    None => periodic.postings.push(split_posting(content)),
+                }
+            }
+            continue;
+        }
+
+        // any unindented line ends the transaction being read
+        if let Some(periodic) = current.take() {
+            rows.push(ImportRow { line: periodic.line, outcome: to_record(&periodic) });
+        }
+        if let Some(header) = content.strip_prefix('~') {
+            let (period, description) = split_two_spaces(header.trim());
+            current = Some(Periodic {
+                line: number,
+                period: period.to_string(),
+                description: description.unwrap_or_default().to_string(),
+                comments: Vec::new(),
+                postings: Vec::new(),
+            });
+        }
+    }
+    if let Some(periodic) = current {
+        rows.push(ImportRow { line: periodic.line, outcome: to_record(&periodic) });
+    }
+    rows
+}
+
+// "account  amount  ; comment": fields are separated by two spaces or a tab
+fn split_two_spaces(text: &str) -> (&str, Option<&str>) {
+    let split = [text.find("  "), text.find('\t')].into_iter().flatten().min();
+    match split {
+        Some(at) => (text[..at].trim(), Some(text[at..].trim()).filter(|rest| !rest.is_empty())),
+        None => (text.trim(), None),
+    }
+}
+
+fn split_posting(content: &str) -> (String, Option<String>) {
+    let content = content.split(';').next().unwrap_or_default();
+    let (account, amount) = split_two_spaces(content);
+    // (virtual) and [balanced virtual] postings count like real ones here
+    let account = account.trim_matches(|c| c == '(' || c == ')' || c == '[' || c == ']');
+    (account.to_string(), amount.map(str::to_string))
+}
+
+fn parse_money(raw: &str) -> Result<f64, String> {
+    // keep the number, drop the commodity ("$", "USD", "EUR ", ...) and any
+    // "@ price" / "= balance assertion" after it
+    let raw = raw.split(['@', '=']).next().unwrap_or_default();
+    // parse_amount tells "1,234.56" from "1.234,56 EUR"
+    let number: String = raw.chars().filter(|c| c.is_ascii_digit() || matches!(c, '-' | '.' | ',' | '(' | ')')).collect();
+    parse_amount(&number).map_err(|_| format!("invalid amount `{}`", raw.trim()))
+}
+
+fn record_type_of(account: &str) -> Option<RecordType> {
+    match account.split(':').next()?.trim().to_lowercase().as_str() {
+        "income" | "revenue" | "revenues" => Some(RecordType::Income),
+        "expenses" | "expense" => Some(RecordType::Expense),
+        "liabilities" | "liability" => Some(RecordType::Debt),
+        _ => None,
+    }
+}
+
+fn to_record(periodic: &Periodic) -> Result<FinancialRecord, String> {
+    // "monthly from 2025-01", "every 3 months in 2025", ...
+    let period = periodic.period.to_lowercase();
+    let period = period
+        .split([' ', '\t'])
+        .take_while(|word| !matches!(*word, "from" | "in" | "to" | "since" | "until"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let frequency = match period.as_str() {
+        "every 3 months" | "every quarter" => Some(Frequency::Quarterly),
+        "every 12 months" | "every year" => Some(Frequency::Yearly),
+        "every 7 days" => Some(Frequency::Weekly),
+        other => Frequency::parse_lenient(other),
+    }
+    .ok_or_else(|| format!("unsupported period `{}`", periodic.period))?;
+
+    let (account, amount) = periodic
+        .postings
+        .iter()
+        .find(|(account, _)| record_type_of(account).is_some())
+        .ok_or_else(|| "no income, expenses or liabilities posting".to_string())?;
+    let record_type = record_type_of(account).expect("found by record_type_of");
+
+    let amount = match amount {
+        Some(amount) => parse_money(amount)?,
+        // the amount was left out to be balanced by the others
+        None => -periodic
+            .postings
+            .iter()
+            .filter_map(|(_, amount)| amount.as_deref())
+            .map(parse_money)
+            .sum::<Result<f64, String>>()?,
+    };
+    if amount == 0.0 {
+        return Err("amount is zero".to_string());
+    }
+
+    let name = Some(periodic.description.trim())
+        .filter(|d| !d.is_empty())
+        .or_else(|| account.rsplit(':').next())
+        .unwrap_or_default();
+    let mut record = FinancialRecord::new(name, amount.abs(), frequency, record_type);
+
+    let mut notes = Vec::new();
+    for comment in &periodic.comments {
+        match comment.strip_prefix("payee:") {
+            Some(payee) => record.payee = Some(payee.trim().to_string()),
+            None => notes.push(comment.clone()),
+        }
+    }
+    if !notes.is_empty() {
+        record.notes = Some(notes.join("\n"));
+    }
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_write_and_read_back() {
+        let mut rent = FinancialRecord::new("Rent: downtown", 1500.0, Frequency::Monthly, RecordType::Expense);
+        rent.payee = Some("ACME Properties".to_string());
+        rent.notes = Some("due on the 1st\nlate fee after 5th".to_string());
+        let salary = FinancialRecord::new("Salary", 48000.0, Frequency::Yearly, RecordType::Income);
+        let car = FinancialRecord::new("Car  loan", 320.0, Frequency::Quarterly, RecordType::Debt);
+        let records = vec![rent, salary, car];
+        let mut paid = LedgerEntry::new("Checking", NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(), -1500.0, "ACME PROPERTIES");
+        paid.record_id = Some(records[0].id);
+        paid.fitid = Some("42".to_string());
+
+        let mut out = Vec::new();
+        write(&records, &[paid], &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("~ monthly  Rent: downtown\n    ; payee: ACME Properties\n    ; due on the 1st\n    ; late fee after 5th\n    expenses:Rent- downtown  $1500.00\n    assets:checking\n"), "{}", text);
+        assert!(text.contains("~ yearly  Salary\n    income:Salary  $-48000.00\n"), "{}", text);
+        assert!(text.contains("2025-01-01 ACME PROPERTIES  ; fitid: 42\n    expenses:Rent- downtown  $1500.00\n    assets:Checking  $-1500.00\n"), "{}", text);
+
+        let back: Vec<FinancialRecord> = parse(&text).into_iter().map(|r| r.outcome.unwrap()).collect();
+        assert_eq!(back.len(), 3);
+        for (original, read) in records.iter().zip(&back) {
+            assert_eq!(
+                (&original.name, original.amount, original.frequency, original.record_type, &original.payee, &original.notes),
+                (&read.name, read.amount, read.frequency, read.record_type, &read.payee, &read.notes)
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_hand_written_journal() {
+        let journal = "\
+; my budget
+account expenses:Food
+
+~ every 3 months from 2025-01  Water bill
+    expenses:Utilities:Water    45,00 EUR
+    assets:bank
+
+~ weekly
+    assets:bank                    $-120.00
+    expenses:Groceries
+
+2025-01-03 Groceries
+    expenses:Groceries  $80
+    assets:bank
+
+~ biweekly  Paycheck
+    income:Job  $-2000
+    assets:bank
+";
+        let rows = parse(journal);
+        assert_eq!(rows.len(), 3);
+        let water = rows[0].outcome.as_ref().unwrap();
+        assert_eq!((water.name.as_str(), water.amount, water.frequency), ("Water bill", 45.0, Frequency::Quarterly));
+        // amount inferred from the balancing posting, name from the account
+        let groceries = rows[1].outcome.as_ref().unwrap();
+        assert_eq!((groceries.name.as_str(), groceries.amount, groceries.record_type), ("Groceries", 120.0, RecordType::Expense));
+        assert_eq!(rows[2].line, 16);
+        assert!(rows[2].outcome.as_ref().unwrap_err().contains("biweekly"));
+    }
+
+    #[test]
+    fn test_parse_european_amounts() {
+        let journal = |amount: &str| format!("~ monthly  Rent\n    expenses:Rent    {}\n    assets:bank\n", amount);
+        let amount = |amount: &str| parse(&journal(amount))[0].outcome.clone().map(|r| r.amount);
+
+        assert_eq!(amount("1.234,56 EUR"), Ok(1234.56));
+        assert_eq!(amount("EUR 1.234,56"), Ok(1234.56));
+        assert_eq!(amount("$1,234.56"), Ok(1234.56));
+        assert!(amount("1.234,5,6 EUR").is_err());
+    }
+}
@@ -0,0 +1,220 @@
+use crate::models::{FinancialRecord, Frequency, RecordType};
+use crate::app_error::AppError;
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use std::io::Write;
+
+const PRODID: &str = "-//Overkill//Budget//EN";
+const CALENDAR_NAME: &str = "Budget";
+// content lines longer than this (in octets, without the CRLF) must be folded
+const MAX_LINE_OCTETS: usize = 75;
+
+// Escapes a TEXT value (RFC 5545 3.3.11)
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Writes one content line, folded into 75 octet chunks (continuation lines
+// start with a space) without splitting a UTF-8 character
+fn write_line(out: &mut impl Write, line: &str) -> Result<(), AppError> {
+    let mut rest = line;
+    let mut limit = MAX_LINE_OCTETS;
+    loop {
+        if rest.len() <= limit {
+            write!(out, "{}\r\n", rest)?;
+            return Ok(());
+        }
+        let mut split = limit;
+        while !rest.is_char_boundary(split) {
+            split -= 1;
+        }
+        write!(out, "{}\r\n ", &rest[..split])?;
+        rest = &rest[split..];
+        // the leading space counts towards the next line
+        limit = MAX_LINE_OCTETS - 1;
+    }
+}
+
+// BYMONTHDAY part for a start date late in the month. Recurrences on a day
+// the month doesn't have are skipped by calendars, so "the 30th" becomes
+// "the 30th, or the last day of shorter months". Only the 31st is the last
+// day of every month; a start on Feb 28 stays on the 28th, and one on a leap
+// day is the 29th in months that have one.
+fn month_day(date: NaiveDate) -> Option<String> {
+    match date.day() {
+        31 => Some(";BYMONTHDAY=-1".to_string()),
+        day if day > 28 => {
+            let days: Vec<String> = (28..=day).map(|d| d.to_string()).collect();
+            Some(format!(";BYMONTHDAY={};BYSETPOS=-1", days.join(",")))
+        }
+        _ => None,
+    }
+}
+
+fn rrule(frequency: Frequency, start: NaiveDate) -> String {
+    match frequency {
+        Frequency::Daily => "FREQ=DAILY".to_string(),
+        Frequency::Weekly => "FREQ=WEEKLY".to_string(),
+        Frequency::Monthly => format!("FREQ=MONTHLY{}", month_day(start).unwrap_or_default()),
+        Frequency::Quarterly => format!("FREQ=MONTHLY;INTERVAL=3{}", month_day(start).unwrap_or_default()),
+        // every year's February ends on the 28th or a leap day
+        Frequency::Yearly if (start.month(), start.day()) == (2, 29) => {
+            "FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=-1".to_string()
+        }
+        Frequency::Yearly => match month_day(start) {
+            Some(day) => format!("FREQ=YEARLY;BYMONTH={}{}", start.month(), day),
+            None => "FREQ=YEARLY".to_string(),
+        },
+    }
+}
+
+fn summary(record: &FinancialRecord) -> String {
+    let amount = format!("${:.2}", record.amount);
+    match record.record_type {
+        RecordType::Income => format!("Payday: {} ({})", record.name, amount),
+        RecordType::Expense => format!("{} due ({})", record.name, amount),
+        RecordType::Debt => format!("{} payment due ({})", record.name, amount),
+    }
+}
+
+fn description(record: &FinancialRecord) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(payee) = record.payee.as_deref().filter(|p| !p.trim().is_empty()) {
+        parts.push(format!("Payee: {}", payee.trim()));
+    }
+    if let Some(notes) = record.notes.as_deref().filter(|n| !n.trim().is_empty()) {
+        parts.push(notes.trim().to_string());
+    }
+    (!parts.is_empty()).then(|| parts.join("\n"))
+}
+
+/// Write an RFC 5545 calendar with one recurring all-day event per record,
+/// starting on the date paired with it and repeating at its frequency. Each
+/// event carries a reminder the day before.
+///
+/// `stamp` is used as DTSTAMP for every event, pass the time of the export.
+pub fn write(schedule: &[(FinancialRecord, NaiveDate)], stamp: DateTime<Utc>, mut out: impl Write) -> Result<(), AppError> {
+    let out = &mut out;
+    let dtstamp = stamp.format("%Y%m%dT%H%M%SZ").to_string();
+    write_line(out, "BEGIN:VCALENDAR")?;
+    write_line(out, "VERSION:2.0")?;
+    write_line(out, &format!("PRODID:{}", PRODID))?;
+    write_line(out, "CALSCALE:GREGORIAN")?;
+    write_line(out, "METHOD:PUBLISH")?;
+    write_line(out, &format!("X-WR-CALNAME:{}", CALENDAR_NAME))?;
+    for (record, start) in schedule {
+        let summary = escape_text(&summary(record));
+        write_line(out, "BEGIN:VEVENT")?;
+        write_line(out, &format!("UID:{}@budget", record.id))?;
+        write_line(out, &format!("DTSTAMP:{}", dtstamp))?;
+        write_line(out, &format!("DTSTART;VALUE=DATE:{}", start.format("%Y%m%d")))?;
+        write_line(out, &format!("RRULE:{}", rrule(record.frequency, *start)))?;
+        write_line(out, &format!("SUMMARY:{}", summary))?;
+        if let Some(description) = description(record) {
+            write_line(out, &format!("DESCRIPTION:{}", escape_text(&description)))?;
+        }
+        write_line(out, "TRANSP:TRANSPARENT")?;
+        write_line(out, "BEGIN:VALARM")?;
+        write_line(out, "ACTION:DISPLAY")?;
+        write_line(out, "TRIGGER:-P1D")?;
+        write_line(out, &format!("DESCRIPTION:{}", summary))?;
+        write_line(out, "END:VALARM")?;
+        write_line(out, "END:VEVENT")?;
+    }
+    write_line(out, "END:VCALENDAR")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_rrule_from_frequency() {
+        assert_eq!(rrule(Frequency::Weekly, date(2025, 3, 7)), "FREQ=WEEKLY");
+        assert_eq!(rrule(Frequency::Monthly, date(2025, 3, 1)), "FREQ=MONTHLY");
+        assert_eq!(rrule(Frequency::Quarterly, date(2025, 3, 15)), "FREQ=MONTHLY;INTERVAL=3");
+        assert_eq!(rrule(Frequency::Monthly, date(2025, 1, 31)), "FREQ=MONTHLY;BYMONTHDAY=-1");
+        assert_eq!(
+            rrule(Frequency::Monthly, date(2025, 1, 30)),
+            "FREQ=MONTHLY;BYMONTHDAY=28,29,30;BYSETPOS=-1"
+        );
+        assert_eq!(rrule(Frequency::Yearly, date(2024, 2, 29)), "FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=-1");
+    }
+
+    #[test]
+    fn test_rrule_from_the_end_of_a_short_month() {
+        // the 28th every month, not the last day
+        assert_eq!(rrule(Frequency::Monthly, date(2025, 2, 28)), "FREQ=MONTHLY");
+        assert_eq!(
+            rrule(Frequency::Monthly, date(2025, 4, 30)),
+            "FREQ=MONTHLY;BYMONTHDAY=28,29,30;BYSETPOS=-1"
+        );
+        assert_eq!(
+            rrule(Frequency::Quarterly, date(2025, 6, 29)),
+            "FREQ=MONTHLY;INTERVAL=3;BYMONTHDAY=28,29;BYSETPOS=-1"
+        );
+        // a leap day start is the 29th (or the 28th in February), not the
+        // 30th or 31st of longer months
+        assert_eq!(
+            rrule(Frequency::Monthly, date(2028, 2, 29)),
+            "FREQ=MONTHLY;BYMONTHDAY=28,29;BYSETPOS=-1"
+        );
+        assert_eq!(
+            rrule(Frequency::Quarterly, date(2028, 2, 29)),
+            "FREQ=MONTHLY;INTERVAL=3;BYMONTHDAY=28,29;BYSETPOS=-1"
+        );
+    }
+
+    #[test]
+    fn test_write_calendar() {
+        let mut rent = FinancialRecord::new("Rent", 1500.0, Frequency::Monthly, RecordType::Expense);
+        rent.notes = Some("Landlord; pay by transfer, not cheque\nRef 12".to_string());
+        let salary = FinancialRecord::new("Salary", 2500.0, Frequency::Weekly, RecordType::Income);
+        let schedule = vec![(rent.clone(), date(2025, 1, 1)), (salary, date(2025, 1, 3))];
+        let stamp = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+
+        let mut out = Vec::new();
+        write(&schedule, stamp, &mut out).unwrap();
+        let ics = String::from_utf8(out).unwrap();
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 2);
+        assert!(ics.contains(&format!("UID:{}@budget\r\n", rent.id)));
+        assert!(ics.contains("DTSTAMP:20250601T120000Z\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20250101\r\nRRULE:FREQ=MONTHLY\r\nSUMMARY:Rent due ($1500.00)\r\n"));
+        assert!(ics.contains("DESCRIPTION:Landlord\\; pay by transfer\\, not cheque\\nRef 12\r\n"));
+        assert!(ics.contains("SUMMARY:Payday: Salary ($2500.00)\r\n"));
+        assert!(ics.contains("TRIGGER:-P1D\r\n"));
+    }
+
+    #[test]
+    fn test_long_lines_are_folded() {
+        let line = format!("SUMMARY:{}", "é".repeat(60));
+        let mut out = Vec::new();
+        write_line(&mut out, &line).unwrap();
+        let text = String::from_utf8(out).unwrap();
+
+        for folded in text.split("\r\n").filter(|l| !l.is_empty()) {
+            assert!(folded.len() <= MAX_LINE_OCTETS);
+        }
+        assert_eq!(text.replace("\r\n ", "").trim_end(), line);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::models::{ChangeAction, FinancialRecord, JournalEntry};
+
+use log::debug;
+use rusqlite::{params, Connection, Result, Row};
+use uuid::Uuid;
+
+const JOURNAL_COLUMNS: &str = "id, record_id, action, actor, created_at, before_json, after_json, undone";
+
+// how many operations are kept for undo
+pub(crate) const JOURNAL_LIMIT: i64 = 100;
+
+fn to_json(record: Option<&FinancialRecord>) -> Result<Option<String>> {
+    record
+        .map(|r| serde_json::to_string(r).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e))))
+        .transpose()
+}
+
+fn from_json(idx: usize, json: Option<String>) -> Result<Option<FinancialRecord>> {
+    json.map(|j| {
+        serde_json::from_str(&j).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(e))
+        })
+    })
+    .transpose()
+}
+
+fn entry_from_row(row: &Row) -> Result<JournalEntry> {
+    Ok(JournalEntry {
+        id: row.get(0)?,
+        record_id: row.get(1)?,
+        action: row.get(2)?,
+        actor: row.get(3)?,
+        created_at: row.get(4)?,
+        before: from_json(5, row.get(5)?)?,
+        after: from_json(6, row.get(6)?)?,
+        undone: row.get(7)?,
+    })
+}
+
+// Push a new operation on the undo stack. This drops the redo stack, and the
+// oldest entries once there are more than JOURNAL_LIMIT.
+pub fn push(
+    conn: &Connection,
+    record_id: &Uuid,
+    action: ChangeAction,
+    actor: &str,
+    before: Option<&FinancialRecord>,
+    after: Option<&FinancialRecord>,
+) -> Result<()> {
+    debug!("journal push(record_id={}, action={})", record_id, action);
+    conn.execute("DELETE FROM operation_journal WHERE undone = 1", [])?;
+    conn.execute(
+        "INSERT INTO operation_journal (record_id, action, actor, before_json, after_json)
+        VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![record_id, action, actor, to_json(before)?, to_json(after)?],
+    )?;
+    conn.execute(
+        "DELETE FROM operation_journal WHERE id <= last_insert_rowid() - ?1",
+        params![JOURNAL_LIMIT],
+    )?;
+    Ok(())
+}
+
+// top `n` of the undo stack, most recent first
+pub fn get_undoable(conn: &Connection, n: usize) -> Result<Vec<JournalEntry>> {
+    conn.prepare(&format!(
+        "SELECT {} FROM operation_journal WHERE undone = 0 ORDER BY id DESC LIMIT ?1",
+        JOURNAL_COLUMNS
+    ))?
+    .query_map(params![n as i64], entry_from_row)?
+    .collect()
+}
+
+// top `n` of the redo stack, most recently undone first
+pub fn get_redoable(conn: &Connection, n: usize) -> Result<Vec<JournalEntry>> {
+    conn.prepare(&format!(
+        "SELECT {} FROM operation_journal WHERE undone = 1 ORDER BY id ASC LIMIT ?1",
+        JOURNAL_COLUMNS
+    ))?
+    .query_map(params![n as i64], entry_from_row)?
+    .collect()
+}
+
+pub fn set_undone(conn: &Connection, id: i64, undone: bool) -> Result<()> {
+    debug!("journal set_undone(id={}, undone={})", id, undone);
+    conn.execute(
+        "UPDATE operation_journal SET undone = ?1 WHERE id = ?2",
+        params![undone, id],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Frequency, RecordType};
+
+    fn setup_conn() -> Connection {
+        crate::db::init_db(":memory:").expect("failed to init memory db")
+    }
+
+    #[test]
+    fn test_push_drops_redo_stack() {
+        let conn = setup_conn();
+        let a = FinancialRecord::new("a", 1.0, Frequency::Monthly, RecordType::Expense);
+        let b = FinancialRecord::new("b", 1.0, Frequency::Monthly, RecordType::Expense);
+        push(&conn, &a.id, ChangeAction::Insert, "test", None, Some(&a)).unwrap();
+        push(&conn, &b.id, ChangeAction::Insert, "test", None, Some(&b)).unwrap();
+
+        let top = get_undoable(&conn, 1).unwrap();
+        assert_eq!(top[0].after.as_ref(), Some(&b));
+        set_undone(&conn, top[0].id, true).unwrap();
+        assert_eq!(get_redoable(&conn, 10).unwrap().len(), 1);
+
+        push(&conn, &a.id, ChangeAction::Delete, "test", Some(&a), None).unwrap();
+        assert!(get_redoable(&conn, 10).unwrap().is_empty());
+        assert_eq!(get_undoable(&conn, 10).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_journal_is_bounded() {
+        let conn = setup_conn();
+        let a = FinancialRecord::new("a", 1.0, Frequency::Monthly, RecordType::Expense);
+        for _ in 0..JOURNAL_LIMIT + 5 {
+            push(&conn, &a.id, ChangeAction::Update, "test", Some(&a), Some(&a)).unwrap();
+        }
+
+        assert_eq!(get_undoable(&conn, 1000).unwrap().len() as i64, JOURNAL_LIMIT);
+    }
+}
@@ -0,0 +1,194 @@
+use crate::models::LedgerEntry;
+
+use chrono::NaiveDate;
+use log::debug;
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+use uuid::Uuid;
+
+const LEDGER_COLUMNS: &str = "id, account, account_kind, fitid, posted_on, amount, payee, memo, record_id,
+    name, record_type, category, tags";
+
+fn ledger_entry_from_row(row: &Row) -> Result<LedgerEntry> {
+    Ok(LedgerEntry {
+        id: row.get(0)?,
+        account: row.get(1)?,
+        account_kind: row.get(2)?,
+        fitid: row.get(3)?,
+        posted_on: row.get(4)?,
+        amount: row.get(5)?,
+        payee: row.get(6)?,
+        memo: row.get(7)?,
+        record_id: row.get(8)?,
+        name: row.get(9)?,
+        record_type: row.get(10)?,
+        category: row.get(11)?,
+        tags: tags_from_row(row, 12)?,
+    })
+}
+
+// tags are stored as a JSON array of strings
+pub(crate) fn tags_to_json(tags: &[String]) -> String {
+    serde_json::to_string(tags).expect("a list of strings always serializes")
+}
+
+pub(crate) fn tags_from_row(row: &Row, index: usize) -> Result<Vec<String>> {
+    let json: String = row.get(index)?;
+    serde_json::from_str(&json).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(e))
+    })
+}
+
+// Inserts the entry unless one with the same account and FITID exists.
+// Returns whether it was inserted.
+pub fn insert_entry(conn: &Connection, entry: &LedgerEntry) -> Result<bool> {
+    debug!("insert_entry(account={}, fitid={:?})", entry.account, entry.fitid);
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO ledger_entry (id, account, account_kind, fitid, posted_on, amount, payee, memo, record_id,
+            name, record_type, category, tags)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        params![
+            &entry.id,
+            &entry.account,
+            &entry.account_kind,
+            &entry.fitid,
+            &entry.posted_on,
+            &entry.amount,
+            &entry.payee,
+            &entry.memo,
+            &entry.record_id,
+            &entry.name,
+            &entry.record_type,
+            &entry.category,
+            tags_to_json(&entry.tags)
+        ],
+    )?;
+    Ok(inserted > 0)
+}
+
+// Entries without a FITID can't be recognised as duplicates, never reports them.
+pub fn exists(conn: &Connection, account: &str, fitid: Option<&str>) -> Result<bool> {
+    let Some(fitid) = fitid else {
+        return Ok(false);
+    };
+    conn.query_row(
+        "SELECT 1 FROM ledger_entry WHERE account = ?1 AND fitid = ?2",
+        params![account, fitid],
+        |_| Ok(()),
+    )
+    .optional()
+    .map(|found| found.is_some())
+}
+
+pub fn get_entry_by_id(conn: &Connection, id: &Uuid) -> Result<LedgerEntry> {
+    debug!("get_entry_by_id(id={})", id);
+    conn.query_row(
+        &format!("SELECT {} FROM ledger_entry WHERE id = ?1", LEDGER_COLUMNS),
+        params![id],
+        ledger_entry_from_row,
+    )
+}
+
+// newest first
+pub fn get_entries(conn: &Connection, limit: usize) -> Result<Vec<LedgerEntry>> {
+    debug!("get_entries(limit={})", limit);
+    conn.prepare(&format!(
+        "SELECT {} FROM ledger_entry ORDER BY posted_on DESC, payee, id LIMIT ?1",
+        LEDGER_COLUMNS
+    ))?
+    .query_map(params![limit as i64], ledger_entry_from_row)?
+    .collect()
+}
+
+// oldest first, for exports
+pub fn get_all_entries(conn: &Connection) -> Result<Vec<LedgerEntry>> {
+    debug!("get_all_entries()");
+    conn.prepare(&format!(
+        "SELECT {} FROM ledger_entry ORDER BY posted_on, account, id",
+        LEDGER_COLUMNS
+    ))?
+    .query_map([], ledger_entry_from_row)?
+    .collect()
+}
+
+// date of the most recent entry linked to a record, if any
+pub fn get_last_posted_on(conn: &Connection, record_id: &Uuid) -> Result<Option<NaiveDate>> {
+    debug!("get_last_posted_on(record_id={})", record_id);
+    conn.query_row(
+        "SELECT MAX(posted_on) FROM ledger_entry WHERE record_id = ?1",
+        params![record_id],
+        |row| row.get(0),
+    )
+}
+
+// Saves what the rules made of an entry (name, record type, category, tags)
+pub fn update_classification(conn: &Connection, entry: &LedgerEntry) -> Result<bool> {
+    debug!("update_classification(id={})", entry.id);
+    let updated = conn.execute(
+        "UPDATE ledger_entry SET name = ?2, record_type = ?3, category = ?4, tags = ?5 WHERE id = ?1",
+        params![&entry.id, &entry.name, &entry.record_type, &entry.category, tags_to_json(&entry.tags)],
+    )?;
+    Ok(updated > 0)
+}
+
+// Links (or with None unlinks) an entry to a budget record. Returns false if
+// there is no such entry.
+pub fn set_record_id(conn: &Connection, id: &Uuid, record_id: Option<&Uuid>) -> Result<bool> {
+    debug!("set_record_id(id={}, record_id={:?})", id, record_id);
+    let updated = conn.execute(
+        "UPDATE ledger_entry SET record_id = ?2 WHERE id = ?1",
+        params![id, record_id],
+    )?;
+    Ok(updated > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+    use chrono::NaiveDate;
+
+    fn entry(fitid: Option<&str>) -> LedgerEntry {
+        LedgerEntry {
+            fitid: fitid.map(str::to_string),
+            ..LedgerEntry::new("000123", NaiveDate::from_ymd_opt(2025, 1, 3).unwrap(), -15.49, "NETFLIX.COM")
+        }
+    }
+
+    #[test]
+    fn test_insert_skips_duplicate_fitid() {
+        let conn = init_db(":memory:").unwrap();
+
+        assert!(insert_entry(&conn, &entry(Some("A1"))).unwrap());
+        assert!(!insert_entry(&conn, &entry(Some("A1"))).unwrap());
+        assert!(insert_entry(&conn, &entry(Some("A2"))).unwrap());
+        // no FITID, nothing to deduplicate on
+        assert!(insert_entry(&conn, &entry(None)).unwrap());
+        assert!(insert_entry(&conn, &entry(None)).unwrap());
+
+        assert_eq!(get_entries(&conn, 10).unwrap().len(), 4);
+        assert!(exists(&conn, "000123", Some("A1")).unwrap());
+        assert!(!exists(&conn, "999", Some("A1")).unwrap());
+        assert!(!exists(&conn, "000123", None).unwrap());
+    }
+
+    #[test]
+    fn test_round_trip_and_link() {
+        let conn = init_db(":memory:").unwrap();
+        let record = crate::models::FinancialRecord::new(
+            "Netflix".to_string(),
+            15.49,
+            crate::models::Frequency::Monthly,
+            crate::models::RecordType::Expense,
+        );
+        crate::record_repository::insert_record(&conn, &record, "test").unwrap();
+        let mut stored = entry(Some("A1"));
+        stored.memo = Some("Streaming".to_string());
+        stored.tags = vec!["tv".to_string(), "subscription".to_string()];
+        insert_entry(&conn, &stored).unwrap();
+
+        assert_eq!(get_entry_by_id(&conn, &stored.id).unwrap(), stored);
+        assert!(set_record_id(&conn, &stored.id, Some(&record.id)).unwrap());
+        assert_eq!(get_entry_by_id(&conn, &stored.id).unwrap().record_id, Some(record.id));
+        assert!(!set_record_id(&conn, &Uuid::new_v4(), None).unwrap());
+    }
+}
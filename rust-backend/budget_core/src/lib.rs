@@ -0,0 +1,93 @@
+//! The budget itself: records and ledger transactions, the SQLite storage
+//! behind them and the services that read and change them, plus the file
+//! formats they are imported from and exported to.
+//!
+//! The server, the CLI and the Tauri app are thin shells around this crate.
+//! They open a database as a [`pool::Pool`] and go through [`service`] for
+//! everything else:
+//!
+//! ```no_run
+//! use budget_core::models::{FinancialRecord, Frequency, RecordType};
+//! use budget_core::pool::Pool;
+//! use budget_core::service;
+//!
+//! let db = Pool::open("budget.db", 4).unwrap();
+//! let rent = FinancialRecord::new("Rent", 1500.0, Frequency::Monthly, RecordType::Expense);
+//! service::add_record(&db, &rent, "example").unwrap();
+//! println!("{:?}", service::get_summary(&db).unwrap());
+//! ```
+
+/// Records, ledger transactions, rules and the reports built from them
+pub mod models;
+/// The shared database handle
+pub mod types;
+/// The error services return when a plain `rusqlite::Error` won't do
+pub mod app_error;
+
+/// Opening the database and migrating it to the current schema
+pub mod db;
+/// The connections the apps share: one writer and a few readers
+pub mod pool;
+/// Encrypting the database at rest with SQLCipher
+pub mod encryption;
+/// Snapshots of the database, their rotation and restoring from them
+pub mod backup;
+/// Whole-database archives, for moving a budget between machines
+pub mod archive;
+/// Budget records, their search index and the trash
+pub mod record_repository;
+/// Per-record change history
+pub mod history_repository;
+/// The undo/redo journal
+pub mod journal_repository;
+/// Attachment metadata
+pub mod attachment_repository;
+/// Attachment contents, stored on disk by hash
+pub mod attachment_store;
+/// Transactions imported from bank statements and journals
+pub mod ledger_repository;
+/// Categorization rules for ledger transactions
+pub mod rule_repository;
+
+/// The storage interface the record services are written against
+pub mod record_store;
+/// A record store that lives in memory, for tests
+pub mod memory_store;
+/// Record storage in PostgreSQL, for a server several people share
+#[cfg(feature = "postgres")]
+pub mod pg_store;
+
+/// Everything the front ends do, on top of the repositories: validation,
+/// history, undo/redo and imports that are all or nothing
+pub mod service;
+
+/// When a record falls due
+pub mod schedule;
+/// Month-by-month totals and breakdowns for charts and reports
+pub mod charts;
+/// Standalone SVG charts
+pub mod svg_chart;
+
+/// CSV records import
+pub mod csv_import;
+/// CSV and JSON records export
+pub mod export;
+/// OFX/QFX statement import
+pub mod ofx_import;
+/// QIF import and export
+pub mod qif;
+/// ledger/hledger journal import and export
+pub mod hledger;
+/// iCalendar feed of upcoming bills and paydays
+pub mod icalendar;
+/// XLSX/ODS workbook export
+pub mod workbook;
+/// Printable PDF budget report
+pub mod pdf_report;
+
+mod record_matcher;
+mod recurring_detector;
+mod rule_engine;
+mod xlsx;
+mod ods;
+mod pdf;
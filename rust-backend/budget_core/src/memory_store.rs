@@ -0,0 +1,405 @@
+use crate::models::{ChangeAction, FinancialRecord, Frequency, HistoryEntry, JournalEntry, RecordType, SearchHit, TrashedRecord};
+use crate::models::search_hit::{MATCH_START, MATCH_END};
+use crate::journal_repository::JOURNAL_LIMIT;
+use crate::record_store::RecordStore;
+
+use chrono::{Duration, Utc};
+use log::debug;
+use rusqlite::Result;
+use std::cell::RefCell;
+use uuid::Uuid;
+
+// same formats as the SQLite store: deleted_at is to the second, history and
+// journal timestamps to the millisecond
+const DELETED_AT_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+const CHANGED_AT_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.3fZ";
+
+// search weights per field, as in the SQLite store's bm25()
+const NAME_WEIGHT: f64 = 10.0;
+const PAYEE_WEIGHT: f64 = 5.0;
+const NOTES_WEIGHT: f64 = 2.0;
+
+/// A [`RecordStore`] that keeps everything in memory, for tests and
+/// throwaway sessions. It starts out empty and is gone when dropped.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    data: RefCell<Data>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Data {
+    /// live and trashed records, in the order they were added
+    records: Vec<StoredRecord>,
+    history: Vec<HistoryEntry>,
+    journal: Vec<JournalEntry>,
+    /// last id handed out to a history / journal entry
+    history_id: i64,
+    journal_id: i64,
+}
+
+#[derive(Debug, Clone)]
+struct StoredRecord {
+    record: FinancialRecord,
+    deleted_at: Option<String>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn find(&self, id: &Uuid, trashed: bool) -> Result<FinancialRecord> {
+        self.data
+            .borrow()
+            .records
+            .iter()
+            .find(|r| r.record.id == *id && r.deleted_at.is_some() == trashed)
+            .map(|r| r.record.clone())
+            .ok_or(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    // applies `change` to the live (or trashed) copy of a record, returns
+    // false if there is none
+    fn modify(&self, id: &Uuid, trashed: bool, change: impl FnOnce(&mut StoredRecord)) -> bool {
+        let mut data = self.data.borrow_mut();
+        match data.records.iter_mut().find(|r| r.record.id == *id && r.deleted_at.is_some() == trashed) {
+            Some(stored) => {
+                change(stored);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn record_change(
+        &self,
+        record_id: &Uuid,
+        action: ChangeAction,
+        actor: &str,
+        before: Option<&FinancialRecord>,
+        after: Option<&FinancialRecord>,
+    ) -> Result<()> {
+        let mut data = self.data.borrow_mut();
+        data.history_id += 1;
+        let entry = HistoryEntry {
+            id: data.history_id,
+            record_id: *record_id,
+            action,
+            actor: actor.to_string(),
+            changed_at: now(CHANGED_AT_FORMAT),
+            before: before.map(to_value).transpose()?,
+            after: after.map(to_value).transpose()?,
+        };
+        data.history.push(entry);
+        Ok(())
+    }
+}
+
+fn now(format: &str) -> String {
+    Utc::now().format(format).to_string()
+}
+
+fn to_value(record: &FinancialRecord) -> Result<serde_json::Value> {
+    serde_json::to_value(record).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+impl RecordStore for MemoryStore {
+    fn atomically<T, E: From<rusqlite::Error>>(
+        &self,
+        f: impl FnOnce() -> std::result::Result<T, E>,
+    ) -> std::result::Result<T, E> {
+        let saved = self.data.borrow().clone();
+        let result = f();
+        if result.is_err() {
+            *self.data.borrow_mut() = saved;
+        }
+        result
+    }
+
+    fn insert_record(&self, record: &FinancialRecord, actor: &str) -> Result<()> {
+        debug!("insert_record({})", record);
+        if self.data.borrow().records.iter().any(|r| r.record.id == record.id) {
+            // what the primary key does for the SQLite store
+            return Err(rusqlite::Error::InvalidParameterName(format!("duplicate record id {}", record.id)));
+        }
+        self.data.borrow_mut().records.push(StoredRecord { record: record.clone(), deleted_at: None });
+        self.record_change(&record.id, ChangeAction::Insert, actor, None, Some(record))
+    }
+
+    fn update_record(&self, record: &FinancialRecord, actor: &str) -> Result<()> {
+        debug!("update_record({})", record);
+        let before = match self.find(&record.id, false) {
+            Ok(before) => before,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        self.modify(&record.id, false, |stored| stored.record = record.clone());
+        self.record_change(&record.id, ChangeAction::Update, actor, Some(&before), Some(record))
+    }
+
+    fn delete_record(&self, id: &Uuid, actor: &str) -> Result<bool> {
+        debug!("delete_record(id={})", id);
+        let before = match self.find(id, false) {
+            Ok(record) => record,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        self.modify(id, false, |stored| stored.deleted_at = Some(now(DELETED_AT_FORMAT)));
+        self.record_change(id, ChangeAction::Delete, actor, Some(&before), None)?;
+        Ok(true)
+    }
+
+    fn restore_record(&self, id: &Uuid, actor: &str) -> Result<bool> {
+        debug!("restore_record(id={})", id);
+        if !self.modify(id, true, |stored| stored.deleted_at = None) {
+            return Ok(false);
+        }
+        let after = self.find(id, false)?;
+        self.record_change(id, ChangeAction::Restore, actor, None, Some(&after))?;
+        Ok(true)
+    }
+
+    fn purge_record(&self, id: &Uuid, actor: &str) -> Result<bool> {
+        debug!("purge_record(id={})", id);
+        let before = match self.find(id, true) {
+            Ok(record) => record,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        self.data.borrow_mut().records.retain(|r| r.record.id != *id);
+        self.record_change(id, ChangeAction::Purge, actor, Some(&before), None)?;
+        Ok(true)
+    }
+
+    fn get_record_by_id(&self, id: &Uuid) -> Result<FinancialRecord> {
+        self.find(id, false)
+    }
+
+    fn get_trashed_record(&self, id: &Uuid) -> Result<FinancialRecord> {
+        self.find(id, true)
+    }
+
+    fn get_records(&self) -> Result<Vec<FinancialRecord>> {
+        Ok(self.data.borrow().records.iter().filter(|r| r.deleted_at.is_none()).map(|r| r.record.clone()).collect())
+    }
+
+    fn get_records_filtered(
+        &self,
+        record_type: Option<RecordType>,
+        frequency: Option<Frequency>,
+    ) -> Result<Vec<FinancialRecord>> {
+        let mut records: Vec<FinancialRecord> = self
+            .get_records()?
+            .into_iter()
+            .filter(|r| record_type.is_none_or(|t| r.record_type == t))
+            .filter(|r| frequency.is_none_or(|f| r.frequency == f))
+            .collect();
+        // the order the SQLite store sorts in: type by name, then name and id
+        records.sort_by(|a, b| {
+            (a.record_type.to_string(), &a.name, a.id).cmp(&(b.record_type.to_string(), &b.name, b.id))
+        });
+        Ok(records)
+    }
+
+    fn get_trashed_records(&self) -> Result<Vec<TrashedRecord>> {
+        let mut trashed: Vec<TrashedRecord> = self
+            .data
+            .borrow()
+            .records
+            .iter()
+            .filter_map(|r| {
+                r.deleted_at.as_ref().map(|deleted_at| TrashedRecord {
+                    record: r.record.clone(),
+                    deleted_at: deleted_at.clone(),
+                })
+            })
+            .collect();
+        // stable, so records trashed in the same second keep their order
+        trashed.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+        Ok(trashed)
+    }
+
+    fn get_expired_trash(&self, retention_days: u32) -> Result<Vec<Uuid>> {
+        let cutoff = (Utc::now() - Duration::days(retention_days.into())).format(DELETED_AT_FORMAT).to_string();
+        Ok(self
+            .data
+            .borrow()
+            .records
+            .iter()
+            .filter(|r| r.deleted_at.as_ref().is_some_and(|at| *at <= cutoff))
+            .map(|r| r.record.id)
+            .collect())
+    }
+
+    // A word-prefix match on the fields the SQLite store indexes. Every
+    // term has to be found somewhere; hits are ranked by where they matched.
+    fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        debug!("search(query={:?}, limit={})", query, limit);
+        let terms: Vec<String> = words(query).map(|(_, word)| word.to_lowercase()).collect();
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut hits: Vec<SearchHit> = self
+            .get_records()?
+            .into_iter()
+            .filter_map(|record| {
+                let fields = [
+                    (record.name.as_str(), NAME_WEIGHT),
+                    (record.payee.as_deref().unwrap_or(""), PAYEE_WEIGHT),
+                    (record.notes.as_deref().unwrap_or(""), NOTES_WEIGHT),
+                ];
+                let found = |text: &str, term: &String| words(text).any(|(_, w)| w.to_lowercase().starts_with(term.as_str()));
+                if !terms.iter().all(|term| fields.iter().any(|(text, _)| found(text, term))) {
+                    return None;
+                }
+
+                let score: f64 = fields
+                    .iter()
+                    .map(|(text, weight)| weight * terms.iter().filter(|term| found(text, term)).count() as f64)
+                    .sum();
+                // the best weighted field that matched, as the snippet
+                let snippet = fields
+                    .iter()
+                    .find(|(text, _)| terms.iter().any(|term| found(text, term)))
+                    .map(|(text, _)| highlight(text, &terms))
+                    .unwrap_or_default();
+                Some(SearchHit {
+                    name_highlight: highlight(&record.name, &terms),
+                    snippet,
+                    // lower is better, as with bm25
+                    rank: -score,
+                    record,
+                })
+            })
+            .collect();
+        hits.sort_by(|a, b| a.rank.total_cmp(&b.rank));
+        hits.truncate(limit);
+        Ok(hits)
+    }
+
+    fn get_history_for_record(&self, record_id: &Uuid) -> Result<Vec<HistoryEntry>> {
+        Ok(self.data.borrow().history.iter().filter(|h| h.record_id == *record_id).cloned().collect())
+    }
+
+    fn get_activity(&self, limit: usize, before_id: Option<i64>) -> Result<Vec<HistoryEntry>> {
+        Ok(self
+            .data
+            .borrow()
+            .history
+            .iter()
+            .rev()
+            .filter(|h| before_id.is_none_or(|before| h.id < before))
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    fn push_journal(
+        &self,
+        record_id: &Uuid,
+        action: ChangeAction,
+        actor: &str,
+        before: Option<&FinancialRecord>,
+        after: Option<&FinancialRecord>,
+    ) -> Result<()> {
+        debug!("journal push(record_id={}, action={})", record_id, action);
+        let mut data = self.data.borrow_mut();
+        data.journal.retain(|e| !e.undone);
+        data.journal_id += 1;
+        let entry = JournalEntry {
+            id: data.journal_id,
+            record_id: *record_id,
+            action,
+            actor: actor.to_string(),
+            created_at: now(CHANGED_AT_FORMAT),
+            before: before.cloned(),
+            after: after.cloned(),
+            undone: false,
+        };
+        data.journal.push(entry);
+        let oldest_kept = data.journal_id - JOURNAL_LIMIT;
+        data.journal.retain(|e| e.id > oldest_kept);
+        Ok(())
+    }
+
+    fn get_undoable(&self, n: usize) -> Result<Vec<JournalEntry>> {
+        Ok(self.data.borrow().journal.iter().rev().filter(|e| !e.undone).take(n).cloned().collect())
+    }
+
+    fn get_redoable(&self, n: usize) -> Result<Vec<JournalEntry>> {
+        Ok(self.data.borrow().journal.iter().filter(|e| e.undone).take(n).cloned().collect())
+    }
+
+    fn set_undone(&self, id: i64, undone: bool) -> Result<()> {
+        debug!("journal set_undone(id={}, undone={})", id, undone);
+        if let Some(entry) = self.data.borrow_mut().journal.iter_mut().find(|e| e.id == id) {
+            entry.undone = undone;
+        }
+        Ok(())
+    }
+}
+
+// the words of `text` with their byte offsets, split the way the search
+// index tokenizes: runs of letters and digits
+fn words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(move |word| (word.as_ptr() as usize - text.as_ptr() as usize, word))
+}
+
+// `text` with every word matching one of `terms` wrapped in MATCH_START /
+// MATCH_END
+fn highlight(text: &str, terms: &[String]) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut copied = 0;
+    for (start, word) in words(text) {
+        let lower = word.to_lowercase();
+        if terms.iter().any(|term| lower.starts_with(term.as_str())) {
+            out.push_str(&text[copied..start]);
+            out.push_str(MATCH_START);
+            out.push_str(word);
+            out.push_str(MATCH_END);
+            copied = start + word.len();
+        }
+    }
+    out.push_str(&text[copied..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_matches_word_prefixes_and_highlights() {
+        let store = MemoryStore::new();
+        let mut gym = FinancialRecord::new("Gym", 30.0, Frequency::Monthly, RecordType::Expense);
+        gym.notes = Some("Climbing, unlimited".to_string());
+        let netflix = FinancialRecord::new("Netflix", 15.49, Frequency::Monthly, RecordType::Expense);
+        store.insert_record(&gym, "test").unwrap();
+        store.insert_record(&netflix, "test").unwrap();
+
+        let hits = store.search("unlim", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].snippet, format!("Climbing, {}unlimited{}", MATCH_START, MATCH_END));
+        assert_eq!(store.search("NETF", 10).unwrap()[0].name_highlight, format!("{}Netflix{}", MATCH_START, MATCH_END));
+        assert!(store.search("gym netflix", 10).unwrap().is_empty());
+        assert!(store.search(" * ", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_atomically_undoes_everything_on_error() {
+        let store = MemoryStore::new();
+        let rent = FinancialRecord::new("Rent", 1500.0, Frequency::Monthly, RecordType::Expense);
+        let result: Result<()> = store.atomically(|| {
+            store.insert_record(&rent, "test")?;
+            store.atomically(|| store.push_journal(&rent.id, ChangeAction::Insert, "test", None, Some(&rent)))?;
+            Err(rusqlite::Error::InvalidQuery)
+        });
+        assert!(result.is_err());
+        assert!(store.get_records().unwrap().is_empty());
+        assert!(store.get_activity(10, None).unwrap().is_empty());
+        assert!(store.get_undoable(10).unwrap().is_empty());
+    }
+}
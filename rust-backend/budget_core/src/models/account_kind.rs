@@ -0,0 +1,49 @@
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use rusqlite::types::{ToSql, ToSqlOutput, ValueRef, FromSql, FromSqlResult, FromSqlError};
+
+// What sort of account a ledger entry was posted to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AccountKind {
+    Bank,
+    CreditCard,
+    Cash,
+}
+
+impl std::str::FromStr for AccountKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Bank" => Ok(AccountKind::Bank),
+            "CreditCard" => Ok(AccountKind::CreditCard),
+            "Cash" => Ok(AccountKind::Cash),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for AccountKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            AccountKind::Bank => "Bank",
+            AccountKind::CreditCard => "CreditCard",
+            AccountKind::Cash => "Cash",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl ToSql for AccountKind {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
+
+impl FromSql for AccountKind {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let s = value.as_str()?;
+        s.parse::<AccountKind>()
+            .map_err(|_| FromSqlError::Other("invalid account_kind".into()))
+    }
+}
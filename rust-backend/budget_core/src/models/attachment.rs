@@ -0,0 +1,19 @@
+use uuid::Uuid;
+use ::serde::{Serialize, Deserialize};
+
+/// ——————————————————————————————————————————————
+/// Attachment: a file (receipt, statement, ...) on a record
+/// ——————————————————————————————————————————————
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: Uuid,
+    pub record_id: Uuid,
+
+    pub file_name: String,
+    pub content_type: String,
+    pub size: i64,
+
+    /// hex sha256 of the content, also its key in the AttachmentStore
+    pub sha256: String,
+    pub created_at: String,
+}
@@ -0,0 +1,56 @@
+
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use rusqlite::types::{ToSql, ToSqlOutput, ValueRef, FromSql, FromSqlResult, FromSqlError};
+
+// What a record_history entry did to its record
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChangeAction {
+    Insert,
+    Update,
+    Delete,
+    Restore,
+    Purge,
+}
+
+impl std::str::FromStr for ChangeAction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Insert" => Ok(ChangeAction::Insert),
+            "Update" => Ok(ChangeAction::Update),
+            "Delete" => Ok(ChangeAction::Delete),
+            "Restore" => Ok(ChangeAction::Restore),
+            "Purge" => Ok(ChangeAction::Purge),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for ChangeAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ChangeAction::Insert => "Insert",
+            ChangeAction::Update => "Update",
+            ChangeAction::Delete => "Delete",
+            ChangeAction::Restore => "Restore",
+            ChangeAction::Purge => "Purge",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl ToSql for ChangeAction {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
+
+impl FromSql for ChangeAction {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let s = value.as_str()?;
+        s.parse::<ChangeAction>()
+            .map_err(|_| FromSqlError::Other("invalid change action".into()))
+    }
+}
@@ -1,4 +1,5 @@
 use std::fmt;
+use chrono::NaiveDate;
 use uuid::Uuid;
 use ::serde::{Serialize, Deserialize};
 use super::frequency::Frequency;
@@ -16,6 +17,14 @@ pub struct FinancialRecord {
 
     pub frequency: Frequency,
     pub record_type: RecordType,
+
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub payee: Option<String>,
+    /// first due date, the schedule repeats from here at `frequency`
+    #[serde(default)]
+    pub starts_on: Option<NaiveDate>,
 }
 
 impl FinancialRecord {
@@ -31,8 +40,16 @@ impl FinancialRecord {
             amount,
             frequency,
             record_type,
+            notes: None,
+            payee: None,
+            starts_on: None,
         }
     }
+
+    // the amount normalized to an average month
+    pub fn monthly_amount(&self) -> f64 {
+        self.amount * self.frequency.occurrences_per_year() / 12.0
+    }
 }
 
 impl fmt::Display for FinancialRecord {
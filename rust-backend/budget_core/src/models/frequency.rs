@@ -0,0 +1,131 @@
+
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use rusqlite::types::{ToSql, ToSqlOutput, ValueRef, FromSql, FromSqlResult, FromSqlError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl std::str::FromStr for Frequency {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Daily" => Ok(Frequency::Daily),
+            "Weekly" => Ok(Frequency::Weekly),
+            "Monthly" => Ok(Frequency::Monthly),
+            "Quarterly" => Ok(Frequency::Quarterly),
+            "Yearly" => Ok(Frequency::Yearly),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Frequency {
+    pub const ALL: [Frequency; 5] = [
+        Frequency::Daily,
+        Frequency::Weekly,
+        Frequency::Monthly,
+        Frequency::Quarterly,
+        Frequency::Yearly,
+    ];
+
+    // how many times this frequency occurs in a year
+    pub fn occurrences_per_year(&self) -> f64 {
+        match self {
+            Frequency::Daily => 365.0,
+            Frequency::Weekly => 52.0,
+            Frequency::Monthly => 12.0,
+            Frequency::Quarterly => 4.0,
+            Frequency::Yearly => 1.0,
+        }
+    }
+
+    // Forgiving parse for imported data: case-insensitive and accepts the
+    // usual spellings ("month", "annually", "wk", "per year", ...)
+    pub fn parse_lenient(s: &str) -> Option<Self> {
+        let s = s.trim().to_lowercase();
+        let s = s
+            .trim_start_matches("every ")
+            .trim_start_matches("per ")
+            .trim_start_matches("once a ");
+        match s {
+            "daily" | "day" | "d" => Some(Frequency::Daily),
+            "weekly" | "week" | "wk" | "w" => Some(Frequency::Weekly),
+            "monthly" | "month" | "mo" | "mth" | "m" | "montly" => Some(Frequency::Monthly),
+            "quarterly" | "quarter" | "qtr" | "q" | "3 months" => Some(Frequency::Quarterly),
+            "yearly" | "year" | "yr" | "y" | "annual" | "annually" | "12 months" => Some(Frequency::Yearly),
+            _ => None,
+        }
+    }
+}
+
+// Implement Display (gives you .to_string())
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Frequency::Daily => "Daily",
+            Frequency::Weekly => "Weekly",
+            Frequency::Monthly => "Monthly",
+            Frequency::Quarterly => "Quarterly",
+            Frequency::Yearly => "Yearly",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl ToSql for Frequency {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
+
+impl FromSql for Frequency {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        // expect a TEXT column
+        let s = value.as_str()?;             // if it's not TEXT, this errors
+        s.parse::<Frequency>()               // use your FromStr
+            .map_err(|_| FromSqlError::Other("invalid frequency".into()))
+    }
+}
+
+// In PostgreSQL it is the `frequency` enum type, whose labels are the variant names
+#[cfg(feature = "postgres")]
+impl postgres_types::ToSql for Frequency {
+    fn to_sql(
+        &self,
+        _ty: &postgres_types::Type,
+        out: &mut bytes::BytesMut,
+    ) -> Result<postgres_types::IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        out.extend_from_slice(self.to_string().as_bytes());
+        Ok(postgres_types::IsNull::No)
+    }
+
+    fn accepts(ty: &postgres_types::Type) -> bool {
+        ty.name() == "frequency"
+    }
+
+    postgres_types::to_sql_checked!();
+}
+
+#[cfg(feature = "postgres")]
+impl<'a> postgres_types::FromSql<'a> for Frequency {
+    fn from_sql(
+        _ty: &postgres_types::Type,
+        raw: &'a [u8],
+    ) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        std::str::from_utf8(raw)?
+            .parse::<Frequency>()
+            .map_err(|_| "invalid frequency".into())
+    }
+
+    fn accepts(ty: &postgres_types::Type) -> bool {
+        ty.name() == "frequency"
+    }
+}
@@ -0,0 +1,21 @@
+use uuid::Uuid;
+use ::serde::Serialize;
+use super::change_action::ChangeAction;
+
+/// ——————————————————————————————————————————————
+/// History Entry: one change to a record, as logged in record_history
+/// ——————————————————————————————————————————————
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistoryEntry {
+    pub id: i64,
+    pub record_id: Uuid,
+
+    pub action: ChangeAction,
+    pub actor: String,
+    pub changed_at: String,
+
+    /// the record as JSON before / after the change. Kept as raw JSON so old
+    /// entries stay readable as FinancialRecord grows new fields.
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+}
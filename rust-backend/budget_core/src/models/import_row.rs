@@ -0,0 +1,30 @@
+use ::serde::Serialize;
+use super::financial_record::FinancialRecord;
+
+/// ——————————————————————————————————————————————
+/// Import Row: one parsed row/entry of an imported file
+/// ——————————————————————————————————————————————
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImportRow {
+    /// line (or entry) number in the source file, for error messages
+    pub line: u64,
+    /// the record the row maps to, or why it couldn't be mapped
+    pub outcome: Result<FinancialRecord, String>,
+}
+
+/// ——————————————————————————————————————————————
+/// Import Report: what an import did (or would do, for a dry run)
+/// ——————————————————————————————————————————————
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImportReport {
+    pub rows: Vec<ImportRow>,
+    /// number of records actually inserted
+    pub imported: usize,
+    pub dry_run: bool,
+}
+
+impl ImportReport {
+    pub fn error_count(&self) -> usize {
+        self.rows.iter().filter(|r| r.outcome.is_err()).count()
+    }
+}
@@ -0,0 +1,24 @@
+use uuid::Uuid;
+use ::serde::Serialize;
+use super::change_action::ChangeAction;
+use super::financial_record::FinancialRecord;
+
+/// ——————————————————————————————————————————————
+/// Journal Entry: an undoable operation made through the service layer
+/// ——————————————————————————————————————————————
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JournalEntry {
+    pub id: i64,
+    pub record_id: Uuid,
+
+    /// Insert, Update or Delete
+    pub action: ChangeAction,
+    pub actor: String,
+    pub created_at: String,
+
+    pub before: Option<FinancialRecord>,
+    pub after: Option<FinancialRecord>,
+
+    /// true while the operation sits on the redo stack
+    pub undone: bool,
+}
@@ -0,0 +1,87 @@
+use super::{AccountKind, RecordType};
+use chrono::NaiveDate;
+use uuid::Uuid;
+use ::serde::{Serialize, Deserialize};
+
+/// ——————————————————————————————————————————————
+/// Ledger Entry: an actual transaction from a bank or card statement
+/// ——————————————————————————————————————————————
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub id: Uuid,
+
+    /// account the transaction was posted to (e.g. the OFX ACCTID)
+    pub account: String,
+    pub account_kind: AccountKind,
+    /// the bank's unique id for the transaction, used to skip duplicates
+    pub fitid: Option<String>,
+
+    pub posted_on: NaiveDate,
+    /// signed: negative is money leaving the account
+    pub amount: f64,
+    pub payee: String,
+    pub memo: Option<String>,
+
+    /// budget record this transaction belongs to, if recognised
+    pub record_id: Option<Uuid>,
+
+    // set by rules
+    /// clean display name, e.g. "Coffee" for "SQ *COFFEE 1234"
+    pub name: Option<String>,
+    pub record_type: Option<RecordType>,
+    pub category: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl LedgerEntry {
+    // the rule given name if there is one, else the raw payee
+    pub fn display_name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.payee)
+    }
+
+    pub fn new(account: impl Into<String>, posted_on: NaiveDate, amount: f64, payee: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            account: account.into(),
+            account_kind: AccountKind::Bank,
+            fitid: None,
+            posted_on,
+            amount,
+            payee: payee.into(),
+            memo: None,
+            record_id: None,
+            name: None,
+            record_type: None,
+            category: None,
+            tags: Vec::new(),
+        }
+    }
+}
+
+/// ——————————————————————————————————————————————
+/// Ledger Import Row: what happened to one imported transaction
+/// ——————————————————————————————————————————————
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LedgerImportRow {
+    pub entry: LedgerEntry,
+    /// already imported before (same account and FITID), skipped
+    pub duplicate: bool,
+    /// best matching budget record and its match score (0..1)
+    pub suggestion: Option<RecordMatch>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RecordMatch {
+    pub record_id: Uuid,
+    pub record_name: String,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LedgerImportReport {
+    pub rows: Vec<LedgerImportRow>,
+    pub imported: usize,
+    pub duplicates: usize,
+    pub dry_run: bool,
+}
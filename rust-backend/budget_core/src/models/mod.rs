@@ -0,0 +1,32 @@
+pub mod financial_record;
+pub mod record_type;
+pub mod frequency;
+pub mod search_hit;
+pub mod attachment;
+pub mod trashed_record;
+pub mod change_action;
+pub mod history_entry;
+pub mod journal_entry;
+pub mod import_row;
+pub mod summary;
+pub mod ledger_entry;
+pub mod account_kind;
+pub mod recurring_suggestion;
+pub mod rule;
+
+// Re-export for easier imports elsewhere:
+pub use financial_record::FinancialRecord;
+pub use record_type::RecordType;
+pub use frequency::Frequency;
+pub use search_hit::SearchHit;
+pub use attachment::Attachment;
+pub use trashed_record::TrashedRecord;
+pub use change_action::ChangeAction;
+pub use history_entry::HistoryEntry;
+pub use journal_entry::JournalEntry;
+pub use import_row::{ImportRow, ImportReport};
+pub use summary::Summary;
+pub use ledger_entry::{LedgerEntry, LedgerImportRow, LedgerImportReport, RecordMatch};
+pub use account_kind::AccountKind;
+pub use recurring_suggestion::RecurringSuggestion;
+pub use rule::{Rule, RuleTestResult};
@@ -0,0 +1,99 @@
+
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use rusqlite::types::{ToSql, ToSqlOutput, ValueRef, FromSql, FromSqlResult, FromSqlError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RecordType {
+    Income,
+    Expense,
+    Debt,
+}
+
+impl std::str::FromStr for RecordType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Income" => Ok(RecordType::Income),
+            "Expense" => Ok(RecordType::Expense),
+            "Debt" => Ok(RecordType::Debt),
+            _ => Err(()),
+        }
+    }
+}
+
+impl RecordType {
+    pub const ALL: [RecordType; 3] = [RecordType::Income, RecordType::Expense, RecordType::Debt];
+
+    // Forgiving parse for imported data: case-insensitive and accepts the
+    // words banks and spreadsheets tend to use ("credit", "bill", "loan", ...)
+    pub fn parse_lenient(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "income" | "in" | "credit" | "cr" | "deposit" | "salary" | "paycheck" => Some(RecordType::Income),
+            "expense" | "expenses" | "out" | "debit" | "dr" | "bill" | "payment" => Some(RecordType::Expense),
+            "debt" | "loan" | "liability" | "credit card" | "mortgage" => Some(RecordType::Debt),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RecordType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            RecordType::Income => "Income",
+            RecordType::Expense => "Expense",
+            RecordType::Debt => "Debt",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl ToSql for RecordType {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
+
+impl FromSql for RecordType {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let s = value.as_str()?;
+        s.parse::<RecordType>()
+            .map_err(|_| FromSqlError::Other("invalid record_type".into()))
+    }
+}
+
+// In PostgreSQL it is the `record_type` enum type, whose labels are the variant names
+#[cfg(feature = "postgres")]
+impl postgres_types::ToSql for RecordType {
+    fn to_sql(
+        &self,
+        _ty: &postgres_types::Type,
+        out: &mut bytes::BytesMut,
+    ) -> Result<postgres_types::IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        out.extend_from_slice(self.to_string().as_bytes());
+        Ok(postgres_types::IsNull::No)
+    }
+
+    fn accepts(ty: &postgres_types::Type) -> bool {
+        ty.name() == "record_type"
+    }
+
+    postgres_types::to_sql_checked!();
+}
+
+#[cfg(feature = "postgres")]
+impl<'a> postgres_types::FromSql<'a> for RecordType {
+    fn from_sql(
+        _ty: &postgres_types::Type,
+        raw: &'a [u8],
+    ) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        std::str::from_utf8(raw)?
+            .parse::<RecordType>()
+            .map_err(|_| "invalid record_type".into())
+    }
+
+    fn accepts(ty: &postgres_types::Type) -> bool {
+        ty.name() == "record_type"
+    }
+}
@@ -0,0 +1,26 @@
+use ::serde::Serialize;
+use chrono::NaiveDate;
+use uuid::Uuid;
+use super::financial_record::FinancialRecord;
+
+/// ——————————————————————————————————————————————
+/// Recurring Suggestion: a record proposed from repeating ledger transactions
+/// ——————————————————————————————————————————————
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RecurringSuggestion {
+    /// normalized payee the transactions were grouped by, identifies the suggestion
+    pub key: String,
+    /// the record that would be created
+    pub record: FinancialRecord,
+
+    pub occurrences: usize,
+    pub first_seen: NaiveDate,
+    pub last_seen: NaiveDate,
+    /// last_seen plus the typical interval
+    pub next_expected: NaiveDate,
+    /// 0..1, how regular the timing and amounts are
+    pub confidence: f64,
+
+    /// the ledger entries that get linked to the record if it's created
+    pub entry_ids: Vec<Uuid>,
+}
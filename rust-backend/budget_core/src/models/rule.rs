@@ -0,0 +1,76 @@
+use uuid::Uuid;
+use ::serde::{Serialize, Deserialize};
+use super::record_type::RecordType;
+use super::ledger_entry::LedgerEntry;
+
+/// ——————————————————————————————————————————————
+/// Rule: cleans up and categorizes imported transactions
+/// ——————————————————————————————————————————————
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    pub id: Uuid,
+    /// label for the rule itself
+    pub name: String,
+    /// rules run in ascending position, later ones override earlier ones
+    pub position: i64,
+    pub enabled: bool,
+
+    // Conditions. All the ones given must match; a rule without any matches nothing.
+    /// case-insensitive substring of the payee
+    pub payee_contains: Option<String>,
+    pub payee_regex: Option<String>,
+    /// bounds on the absolute amount, inclusive
+    pub min_amount: Option<f64>,
+    pub max_amount: Option<f64>,
+    pub account: Option<String>,
+
+    // Actions
+    pub set_name: Option<String>,
+    pub set_record_type: Option<RecordType>,
+    pub set_category: Option<String>,
+    #[serde(default)]
+    pub add_tags: Vec<String>,
+}
+
+impl Rule {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            position: 0,
+            enabled: true,
+            payee_contains: None,
+            payee_regex: None,
+            min_amount: None,
+            max_amount: None,
+            account: None,
+            set_name: None,
+            set_record_type: None,
+            set_category: None,
+            add_tags: Vec::new(),
+        }
+    }
+
+    pub fn has_conditions(&self) -> bool {
+        self.payee_contains.is_some()
+            || self.payee_regex.is_some()
+            || self.min_amount.is_some()
+            || self.max_amount.is_some()
+            || self.account.is_some()
+    }
+
+    pub fn has_actions(&self) -> bool {
+        self.set_name.is_some() || self.set_record_type.is_some() || self.set_category.is_some() || !self.add_tags.is_empty()
+    }
+}
+
+/// ——————————————————————————————————————————————
+/// Rule Test Result: what a rule would do to existing transactions
+/// ——————————————————————————————————————————————
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RuleTestResult {
+    /// how many transactions the rule matches
+    pub matched: usize,
+    /// the first few of them as they are and as the rule would leave them
+    pub examples: Vec<(LedgerEntry, LedgerEntry)>,
+}
@@ -0,0 +1,23 @@
+use ::serde::Serialize;
+use super::financial_record::FinancialRecord;
+
+/// Marks the start of a matched term in `SearchHit` highlights and snippets
+pub const MATCH_START: &str = "\u{2}";
+/// Marks the end of a matched term in `SearchHit` highlights and snippets
+pub const MATCH_END: &str = "\u{3}";
+
+/// ——————————————————————————————————————————————
+/// Search Hit: a record matched by full-text search
+/// ——————————————————————————————————————————————
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchHit {
+    pub record: FinancialRecord,
+
+    /// record name with matched terms wrapped in MATCH_START / MATCH_END
+    pub name_highlight: String,
+    /// best matching fragment of name, notes or payee, marked up the same way
+    pub snippet: String,
+
+    /// bm25 score, lower is a better match
+    pub rank: f64,
+}
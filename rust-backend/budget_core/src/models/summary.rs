@@ -0,0 +1,81 @@
+use ::serde::Serialize;
+use super::financial_record::FinancialRecord;
+use super::record_type::RecordType;
+
+/// ——————————————————————————————————————————————
+/// Summary: every record normalized to a monthly amount, totalled per type
+/// ——————————————————————————————————————————————
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Summary {
+    /// one line per RecordType, always in Income, Expense, Debt order
+    pub lines: Vec<SummaryLine>,
+
+    pub monthly_income: f64,
+    pub monthly_expenses: f64,
+    pub monthly_debt: f64,
+    /// income minus expenses and debt payments
+    pub monthly_net: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SummaryLine {
+    pub record_type: RecordType,
+    pub record_count: usize,
+    pub monthly_total: f64,
+    pub yearly_total: f64,
+}
+
+impl Summary {
+    pub fn from_records(records: &[FinancialRecord]) -> Self {
+        let lines: Vec<SummaryLine> = [RecordType::Income, RecordType::Expense, RecordType::Debt]
+            .into_iter()
+            .map(|record_type| {
+                let of_type = records.iter().filter(|r| r.record_type == record_type);
+                // fold from +0.0: an empty f64 sum() is -0.0, which prints as "-0.00"
+                let monthly_total = of_type.clone().fold(0.0, |total, r| total + r.monthly_amount());
+                SummaryLine {
+                    record_type,
+                    record_count: of_type.count(),
+                    monthly_total,
+                    yearly_total: monthly_total * 12.0,
+                }
+            })
+            .collect();
+
+        let total = |t: RecordType| lines.iter().find(|l| l.record_type == t).map(|l| l.monthly_total).unwrap_or(0.0);
+        let (income, expenses, debt) = (total(RecordType::Income), total(RecordType::Expense), total(RecordType::Debt));
+
+        Self {
+            lines,
+            monthly_income: income,
+            monthly_expenses: expenses,
+            monthly_debt: debt,
+            monthly_net: income - expenses - debt,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Frequency;
+
+    #[test]
+    fn test_summary_normalizes_to_monthly() {
+        let records = vec![
+            FinancialRecord::new("Salary", 2000.0, Frequency::Weekly, RecordType::Income),
+            FinancialRecord::new("Rent", 1500.0, Frequency::Monthly, RecordType::Expense),
+            FinancialRecord::new("Insurance", 600.0, Frequency::Quarterly, RecordType::Expense),
+            FinancialRecord::new("Car Loan", 3600.0, Frequency::Yearly, RecordType::Debt),
+        ];
+
+        let summary = Summary::from_records(&records);
+
+        assert!((summary.monthly_income - 2000.0 * 52.0 / 12.0).abs() < 1e-9);
+        assert_eq!(summary.monthly_expenses, 1700.0);
+        assert_eq!(summary.monthly_debt, 300.0);
+        assert!((summary.monthly_net - (summary.monthly_income - 2000.0)).abs() < 1e-9);
+        assert_eq!(summary.lines[1].record_count, 2);
+        assert_eq!(summary.lines[1].yearly_total, 1700.0 * 12.0);
+    }
+}
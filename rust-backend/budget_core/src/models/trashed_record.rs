@@ -0,0 +1,11 @@
+use ::serde::Serialize;
+use super::financial_record::FinancialRecord;
+
+/// ——————————————————————————————————————————————
+/// Trashed Record: a soft deleted record and when it was deleted
+/// ——————————————————————————————————————————————
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TrashedRecord {
+    pub record: FinancialRecord,
+    pub deleted_at: String,
+}
@@ -0,0 +1,252 @@
+use crate::workbook::{escape_xml, Cell, Sheet, Style, Value, Workbook};
+use crate::app_error::AppError;
+
+use regex::{Captures, Regex};
+use std::io::{Cursor, Write};
+use std::sync::LazyLock;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const MIME_TYPE: &str = "application/vnd.oasis.opendocument.spreadsheet";
+const CURRENCY: &str = "USD";
+// roughly one character of the default font
+const INCHES_PER_CHAR: f64 = 0.09;
+
+// an A1 reference or range, optionally on another sheet: B2, $B$2:$B$9,
+// Records!B2:B9
+static REFERENCE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:([A-Za-z_][A-Za-z0-9_]*|'[^']+')!)?(\$?[A-Z]{1,3}\$?[0-9]+)(?::(\$?[A-Z]{1,3}\$?[0-9]+))?")
+        .expect("valid regex")
+});
+
+const AUTOMATIC_STYLES: &str = concat!(
+    "<number:currency-style style:name=\"N_CURRENCY\">",
+    "<number:currency-symbol>$</number:currency-symbol>",
+    "<number:number number:decimal-places=\"2\" number:min-decimal-places=\"2\" number:min-integer-digits=\"1\" number:grouping=\"true\"/>",
+    "</number:currency-style>",
+    "<number:percentage-style style:name=\"N_PERCENT\">",
+    "<number:number number:decimal-places=\"2\" number:min-decimal-places=\"2\" number:min-integer-digits=\"1\"/>",
+    "<number:text>%</number:text>",
+    "</number:percentage-style>",
+    "<number:date-style style:name=\"N_DATE\">",
+    "<number:year number:style=\"long\"/><number:text>-</number:text>",
+    "<number:month number:style=\"long\"/><number:text>-</number:text>",
+    "<number:day number:style=\"long\"/>",
+    "</number:date-style>",
+    "<style:style style:name=\"header\" style:family=\"table-cell\"><style:text-properties fo:font-weight=\"bold\"/></style:style>",
+    "<style:style style:name=\"currency\" style:family=\"table-cell\" style:data-style-name=\"N_CURRENCY\"/>",
+    "<style:style style:name=\"currency-total\" style:family=\"table-cell\" style:data-style-name=\"N_CURRENCY\">",
+    "<style:text-properties fo:font-weight=\"bold\"/></style:style>",
+    "<style:style style:name=\"percent\" style:family=\"table-cell\" style:data-style-name=\"N_PERCENT\"/>",
+    "<style:style style:name=\"date\" style:family=\"table-cell\" style:data-style-name=\"N_DATE\"/>",
+);
+
+fn style_name(style: Style) -> Option<&'static str> {
+    match style {
+        Style::Default => None,
+        Style::Header => Some("header"),
+        Style::Currency => Some("currency"),
+        Style::CurrencyTotal => Some("currency-total"),
+        Style::Percent => Some("percent"),
+        Style::Date => Some("date"),
+    }
+}
+
+fn reference(sheet: Option<&str>, cell: &str) -> String {
+    match sheet {
+        Some(sheet) => format!("${}.{}", sheet, cell),
+        None => format!(".{}", cell),
+    }
+}
+
+// Translates an A1 style formula to OpenFormula: references go in brackets
+// (`[.B2:.B9]`, `[$Records.B2]`) and arguments are separated by `;`. String
+// literals are left alone.
+fn to_openformula(formula: &str) -> String {
+    formula
+        .split('"')
+        .enumerate()
+        .map(|(i, part)| {
+            // odd parts are inside quotes
+            if i % 2 == 1 {
+                return part.to_string();
+            }
+            REFERENCE
+                .replace_all(part, |caps: &Captures| {
+                    let sheet = caps.get(1).map(|m| m.as_str());
+                    match caps.get(3) {
+                        Some(end) => format!("[{}:{}]", reference(sheet, &caps[2]), reference(None, end.as_str())),
+                        None => format!("[{}]", reference(sheet, &caps[2])),
+                    }
+                })
+                .replace(',', ";")
+        })
+        .collect::<Vec<_>>()
+        .join("\"")
+}
+
+// office:value-type and value attributes for a number shown in `style`
+fn number_attributes(value: f64, style: Style) -> String {
+    match style {
+        Style::Currency | Style::CurrencyTotal => format!(
+            "office:value-type=\"currency\" office:currency=\"{}\" office:value=\"{}\"",
+            CURRENCY, value
+        ),
+        Style::Percent => format!("office:value-type=\"percentage\" office:value=\"{}\"", value),
+        _ => format!("office:value-type=\"float\" office:value=\"{}\"", value),
+    }
+}
+
+fn paragraph(text: &str) -> String {
+    let lines: Vec<String> = text.lines().map(escape_xml).collect();
+    format!("<text:p>{}</text:p>", lines.join("<text:line-break/>"))
+}
+
+fn cell_xml(cell: &Cell) -> String {
+    let style = style_name(cell.style)
+        .map(|name| format!(" table:style-name=\"{}\"", name))
+        .unwrap_or_default();
+    match &cell.value {
+        Value::Empty => format!("<table:table-cell{}/>", style),
+        Value::Text(text) if text.is_empty() => format!("<table:table-cell{}/>", style),
+        Value::Text(text) => format!(
+            "<table:table-cell{} office:value-type=\"string\">{}</table:table-cell>",
+            style,
+            paragraph(text)
+        ),
+        Value::Number(value) => format!(
+            "<table:table-cell{} {}/>",
+            style,
+            number_attributes(*value, cell.style)
+        ),
+        Value::Date(date) => format!(
+            "<table:table-cell{} office:value-type=\"date\" office:date-value=\"{}\"><text:p>{}</text:p></table:table-cell>",
+            style, date, date
+        ),
+        Value::Formula { formula, value } => format!(
+            "<table:table-cell{} table:formula=\"of:={}\" {}/>",
+            style,
+            escape_xml(&to_openformula(formula)),
+            number_attributes(*value, cell.style)
+        ),
+    }
+}
+
+fn table_xml(sheet: &Sheet, index: usize) -> String {
+    let mut xml = format!("<table:table table:name=\"{}\">", escape_xml(&sheet.name));
+    for col in 0..sheet.widths.len() {
+        xml.push_str(&format!("<table:table-column table:style-name=\"co{}-{}\"/>", index, col));
+    }
+    for cells in &sheet.rows {
+        xml.push_str("<table:table-row>");
+        if cells.is_empty() {
+            xml.push_str("<table:table-cell/>");
+        }
+        for cell in cells {
+            xml.push_str(&cell_xml(cell));
+        }
+        xml.push_str("</table:table-row>");
+    }
+    xml.push_str("</table:table>");
+    xml
+}
+
+fn content_xml(workbook: &Workbook) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <office:document-content \
+         xmlns:office=\"urn:oasis:names:tc:opendocument:xmlns:office:1.0\" \
+         xmlns:style=\"urn:oasis:names:tc:opendocument:xmlns:style:1.0\" \
+         xmlns:text=\"urn:oasis:names:tc:opendocument:xmlns:text:1.0\" \
+         xmlns:table=\"urn:oasis:names:tc:opendocument:xmlns:table:1.0\" \
+         xmlns:fo=\"urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0\" \
+         xmlns:number=\"urn:oasis:names:tc:opendocument:xmlns:datastyle:1.0\" \
+         xmlns:of=\"urn:oasis:names:tc:opendocument:xmlns:of:1.2\" \
+         office:version=\"1.2\"><office:automatic-styles>",
+    );
+    xml.push_str(AUTOMATIC_STYLES);
+    for (index, sheet) in workbook.sheets.iter().enumerate() {
+        for (col, width) in sheet.widths.iter().enumerate() {
+            xml.push_str(&format!(
+                "<style:style style:name=\"co{}-{}\" style:family=\"table-column\">\
+                 <style:table-column-properties style:column-width=\"{:.2}in\"/></style:style>",
+                index,
+                col,
+                width * INCHES_PER_CHAR
+            ));
+        }
+    }
+    xml.push_str("</office:automatic-styles><office:body><office:spreadsheet>");
+    for (index, sheet) in workbook.sheets.iter().enumerate() {
+        xml.push_str(&table_xml(sheet, index));
+    }
+    xml.push_str("</office:spreadsheet></office:body></office:document-content>");
+    xml
+}
+
+fn manifest_xml() -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <manifest:manifest xmlns:manifest=\"urn:oasis:names:tc:opendocument:xmlns:manifest:1.0\" manifest:version=\"1.2\">\
+         <manifest:file-entry manifest:full-path=\"/\" manifest:version=\"1.2\" manifest:media-type=\"{}\"/>\
+         <manifest:file-entry manifest:full-path=\"content.xml\" manifest:media-type=\"text/xml\"/>\
+         </manifest:manifest>",
+        MIME_TYPE
+    )
+}
+
+/// Write the workbook as an OpenDocument (.ods) spreadsheet
+pub fn write(workbook: &Workbook, mut out: impl Write) -> Result<(), AppError> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    // the mimetype has to be the first entry, uncompressed, so the file type
+    // can be sniffed from a fixed offset
+    zip.start_file("mimetype", SimpleFileOptions::default().compression_method(CompressionMethod::Stored))?;
+    zip.write_all(MIME_TYPE.as_bytes())?;
+
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file("META-INF/manifest.xml", options)?;
+    zip.write_all(manifest_xml().as_bytes())?;
+    zip.start_file("content.xml", options)?;
+    zip.write_all(content_xml(workbook).as_bytes())?;
+    out.write_all(&zip.finish()?.into_inner())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{FinancialRecord, Frequency, RecordType};
+    use crate::workbook;
+    use chrono::NaiveDate;
+    use std::io::Read;
+
+    #[test]
+    fn test_openformula_translation() {
+        assert_eq!(to_openformula("SUM(C2:C9)"), "SUM([.C2:.C9])");
+        assert_eq!(to_openformula("IF(C2=0,0,C5/C2)"), "IF([.C2]=0;0;[.C5]/[.C2])");
+        assert_eq!(
+            to_openformula("SUMIF(Records!$B$2:$B$4,\"A,B2\",Records!$E$2:$E$4)"),
+            "SUMIF([$Records.$B$2:.$B$4];\"A,B2\";[$Records.$E$2:.$E$4])"
+        );
+    }
+
+    #[test]
+    fn test_write_ods_package() {
+        let records = vec![FinancialRecord::new("Salary", 2000.0, Frequency::Monthly, RecordType::Income)];
+        let book = workbook::build(&records, NaiveDate::from_ymd_opt(2025, 1, 15).unwrap());
+
+        let mut out = Vec::new();
+        write(&book, &mut out).unwrap();
+        // "PK" header, then the stored mimetype as the first file
+        assert_eq!(&out[30..38], b"mimetype");
+        assert_eq!(&out[38..38 + MIME_TYPE.len()], MIME_TYPE.as_bytes());
+
+        let mut zip = zip::ZipArchive::new(Cursor::new(out)).unwrap();
+        let mut content = String::new();
+        zip.by_name("content.xml").unwrap().read_to_string(&mut content).unwrap();
+        assert!(content.contains("<table:table table:name=\"Projection\">"));
+        assert!(content.contains(
+            "table:formula=\"of:=[.D2]*12/12\" office:value-type=\"currency\" office:currency=\"USD\" office:value=\"2000\""
+        ));
+    }
+}
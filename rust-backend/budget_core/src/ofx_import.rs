@@ -0,0 +1,313 @@
+use crate::models::{AccountKind, LedgerEntry};
+use crate::app_error::AppError;
+
+use chrono::NaiveDate;
+use log::debug;
+
+// One element of an OFX document. Leaf elements carry text, aggregates
+// carry children.
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    text: Option<String>,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    fn text_of(&self, name: &str) -> Option<&str> {
+        self.child(name).and_then(|c| c.text.as_deref())
+    }
+
+    // depth-first search for every element called `name`
+    fn find_all<'a>(&'a self, name: &str, found: &mut Vec<&'a Element>) {
+        for child in &self.children {
+            if child.name == name {
+                found.push(child);
+            } else {
+                child.find_all(name, found);
+            }
+        }
+    }
+}
+
+/// Parse an OFX/QFX statement into ledger entries, one per STMTTRN.
+///
+/// Handles both OFX 1.x (SGML: header lines, leaf tags without closing tags)
+/// and OFX 2.x (XML). Bank and credit card statements are supported; the
+/// account is taken from the statement's BANKACCTFROM/CCACCTFROM.
+pub fn parse(input: &str) -> Result<Vec<LedgerEntry>, AppError> {
+    let start = input
+        .find("<OFX>")
+        .or_else(|| input.find("<ofx>"))
+        .ok_or_else(|| AppError("not an OFX file: no <OFX> element".to_string()))?;
+    let root = build_tree(&input[start..])?;
+
+    let mut statements = Vec::new();
+    root.find_all("STMTRS", &mut statements);
+    root.find_all("CCSTMTRS", &mut statements);
+    if statements.is_empty() {
+        return Err(AppError("OFX file contains no bank or credit card statement".to_string()));
+    }
+
+    let mut entries = Vec::new();
+    for statement in statements {
+        let (account_from, account_kind) = match statement.child("BANKACCTFROM") {
+            Some(bank) => (Some(bank), AccountKind::Bank),
+            None => (statement.child("CCACCTFROM"), AccountKind::CreditCard),
+        };
+        let account = account_from
+            .and_then(|a| a.text_of("ACCTID"))
+            .unwrap_or("unknown")
+            .to_string();
+
+        let mut transactions = Vec::new();
+        statement.find_all("STMTTRN", &mut transactions);
+        debug!("OFX statement for account {} has {} transactions", account, transactions.len());
+
+        for trn in transactions {
+            entries.push(LedgerEntry { account_kind, ..parse_transaction(trn, &account)? });
+        }
+    }
+    Ok(entries)
+}
+
+fn parse_transaction(trn: &Element, account: &str) -> Result<LedgerEntry, AppError> {
+    let fitid = trn.text_of("FITID").map(str::to_string);
+    let context = || format!("transaction {}", fitid.as_deref().unwrap_or("without FITID"));
+
+    let posted = trn.text_of("DTPOSTED").ok_or_else(|| AppError(format!("{}: missing DTPOSTED", context())))?;
+    let posted_on = parse_date(posted).ok_or_else(|| AppError(format!("{}: invalid DTPOSTED `{}`", context(), posted)))?;
+
+    let raw_amount = trn.text_of("TRNAMT").ok_or_else(|| AppError(format!("{}: missing TRNAMT", context())))?;
+    let amount = parse_amount(raw_amount)
+        .ok_or_else(|| AppError(format!("{}: invalid TRNAMT `{}`", context(), raw_amount)))?;
+
+    // NAME is the usual payee field, some banks only fill the PAYEE aggregate
+    // or put everything in MEMO
+    let memo = trn.text_of("MEMO").map(str::to_string);
+    let payee = trn
+        .text_of("NAME")
+        .or_else(|| trn.child("PAYEE").and_then(|p| p.text_of("NAME")))
+        .map(str::to_string)
+        .or_else(|| memo.clone())
+        .unwrap_or_else(|| "Unknown payee".to_string());
+
+    Ok(LedgerEntry {
+        fitid,
+        memo: memo.filter(|m| *m != payee),
+        ..LedgerEntry::new(account, posted_on, amount, payee)
+    })
+}
+
+// OFX dates are YYYYMMDD optionally followed by HHMMSS[.XXX][[offset:TZ]];
+// only the date part matters here
+fn parse_date(raw: &str) -> Option<NaiveDate> {
+    let digits = raw.get(..8)?;
+    NaiveDate::parse_from_str(digits, "%Y%m%d").ok()
+}
+
+fn parse_amount(raw: &str) -> Option<f64> {
+    let raw = raw.trim();
+    // some European banks use a decimal comma
+    let normalized = if raw.contains(',') && !raw.contains('.') {
+        raw.replace(',', ".")
+    } else {
+        raw.replace(',', "")
+    };
+    normalized.parse::<f64>().ok().filter(|a| a.is_finite())
+}
+
+enum Token {
+    Open(String),
+    Close(String),
+    Text(String),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, AppError> {
+    let mut tokens = Vec::new();
+    let mut rest = input;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('<') {
+            let end = after
+                .find('>')
+                .ok_or_else(|| AppError("malformed OFX: unterminated tag".to_string()))?;
+            let tag = after[..end].trim();
+            rest = &after[end + 1..];
+            // skip <?xml ...?>, <?OFX ...?> and <!-- comments -->
+            if tag.starts_with('?') || tag.starts_with('!') {
+                continue;
+            }
+            if let Some(name) = tag.strip_prefix('/') {
+                tokens.push(Token::Close(name.trim().to_uppercase()));
+                continue;
+            }
+            let empty = tag.ends_with('/');
+            let name = tag.trim_end_matches('/').split_whitespace().next().unwrap_or("").to_uppercase();
+            tokens.push(Token::Open(name.clone()));
+            // an empty element, <MEMO/>, closes itself
+            if empty {
+                tokens.push(Token::Close(name));
+            }
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            let text = decode_entities(rest[..end].trim());
+            if !text.is_empty() {
+                tokens.push(Token::Text(text));
+            }
+            rest = &rest[end..];
+        }
+    }
+    Ok(tokens)
+}
+
+// In SGML OFX leaf elements are never closed, so an element that got text is
+// closed implicitly by whatever tag comes next. A close tag closes everything
+// opened since its matching open tag.
+fn build_tree(input: &str) -> Result<Element, AppError> {
+    let mut stack = vec![Element { name: "#document".to_string(), ..Element::default() }];
+
+    fn pop_into_parent(stack: &mut Vec<Element>) {
+        let done = stack.pop().expect("stack never pops the document");
+        stack.last_mut().expect("document stays on the stack").children.push(done);
+    }
+
+    for token in tokenize(input)? {
+        let open_leaf = stack.len() > 1 && stack.last().is_some_and(|e| e.text.is_some());
+        match token {
+            Token::Text(text) => {
+                if stack.len() > 1 {
+                    stack.last_mut().expect("checked above").text = Some(text);
+                }
+            }
+            Token::Open(name) => {
+                if open_leaf {
+                    pop_into_parent(&mut stack);
+                }
+                stack.push(Element { name, ..Element::default() });
+            }
+            Token::Close(name) => {
+                if !stack.iter().skip(1).any(|e| e.name == name) {
+                    // stray close tag, nothing to close
+                    continue;
+                }
+                while stack.len() > 1 {
+                    let closing = stack.last().is_some_and(|e| e.name == name);
+                    pop_into_parent(&mut stack);
+                    if closing {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    while stack.len() > 1 {
+        pop_into_parent(&mut stack);
+    }
+    Ok(stack.pop().expect("document"))
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OFX_SGML: &str = "OFXHEADER:100\r\nDATA:OFXSGML\r\nVERSION:102\r\n\r\n\
+<OFX>
+<SIGNONMSGSRSV1><SONRS><STATUS><CODE>0<SEVERITY>INFO</STATUS><DTSERVER>20250131</SONRS></SIGNONMSGSRSV1>
+<BANKMSGSRSV1><STMTTRNRS><TRNUID>1<STMTRS><CURDEF>USD
+<BANKACCTFROM><BANKID>121000248<ACCTID>000123456<ACCTTYPE>CHECKING</BANKACCTFROM>
+<BANKTRANLIST><DTSTART>20250101<DTEND>20250131
+<STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20250103120000[-5:EST]<TRNAMT>-15.49<FITID>2025010301<NAME>NETFLIX.COM<MEMO>Streaming</STMTTRN>
+<STMTTRN><TRNTYPE>CREDIT<DTPOSTED>20250115<TRNAMT>2500.00<FITID>2025011501<NAME>ACME CORP PAYROLL</STMTTRN>
+<STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20250120<TRNAMT>-42,10<FITID>2025012001<MEMO>AT&amp;T WIRELESS</STMTTRN>
+</BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1>
+</OFX>";
+
+    const OFX_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<?OFX OFXHEADER="200" VERSION="220" SECURITY="NONE" OLDFILEUID="NONE" NEWFILEUID="NONE"?>
+<OFX>
+  <CREDITCARDMSGSRSV1>
+    <CCSTMTTRNRS>
+      <TRNUID>1</TRNUID>
+      <CCSTMTRS>
+        <CURDEF>USD</CURDEF>
+        <CCACCTFROM><ACCTID>4111-XXXX</ACCTID></CCACCTFROM>
+        <BANKTRANLIST>
+          <STMTTRN>
+            <TRNTYPE>DEBIT</TRNTYPE>
+            <DTPOSTED>20250205</DTPOSTED>
+            <TRNAMT>-60.00</TRNAMT>
+            <FITID>CC-1</FITID>
+            <NAME>CITY GYM</NAME>
+            <MEMO/>
+          </STMTTRN>
+        </BANKTRANLIST>
+      </CCSTMTRS>
+    </CCSTMTTRNRS>
+  </CREDITCARDMSGSRSV1>
+</OFX>"#;
+
+    #[test]
+    fn test_parse_sgml_bank_statement() {
+        let entries = parse(OFX_SGML).unwrap();
+
+        assert_eq!(entries.len(), 3);
+        let netflix = &entries[0];
+        assert_eq!(netflix.account, "000123456");
+        assert_eq!(netflix.fitid.as_deref(), Some("2025010301"));
+        assert_eq!(netflix.posted_on, NaiveDate::from_ymd_opt(2025, 1, 3).unwrap());
+        assert_eq!((netflix.amount, netflix.payee.as_str()), (-15.49, "NETFLIX.COM"));
+        assert_eq!(netflix.memo.as_deref(), Some("Streaming"));
+        assert_eq!(entries[1].amount, 2500.0);
+        // no NAME: falls back to MEMO, decimal comma understood
+        assert_eq!((entries[2].payee.as_str(), entries[2].amount), ("AT&T WIRELESS", -42.10));
+        assert_eq!(entries[2].memo, None);
+    }
+
+    #[test]
+    fn test_parse_xml_credit_card_statement() {
+        let entries = parse(OFX_XML).unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].account, "4111-XXXX");
+        assert_eq!(entries[0].account_kind, AccountKind::CreditCard);
+        assert_eq!((entries[0].payee.as_str(), entries[0].amount), ("CITY GYM", -60.0));
+        assert_eq!(entries[0].fitid.as_deref(), Some("CC-1"));
+    }
+
+    #[test]
+    fn test_empty_element_doesnt_swallow_its_siblings() {
+        let xml = OFX_XML.replace("<MEMO/>", "").replace("<TRNTYPE>DEBIT</TRNTYPE>", "<TRNTYPE>DEBIT</TRNTYPE><MEMO/>");
+        let entries = parse(&xml).unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].posted_on, NaiveDate::from_ymd_opt(2025, 2, 5).unwrap());
+        assert_eq!((entries[0].payee.as_str(), entries[0].amount), ("CITY GYM", -60.0));
+        assert_eq!(entries[0].memo, None);
+    }
+
+    #[test]
+    fn test_rejects_non_ofx() {
+        assert!(parse("Date,Amount\n2025-01-01,3").is_err());
+        assert!(parse("<OFX><SIGNONMSGSRSV1></SIGNONMSGSRSV1></OFX>").is_err());
+    }
+
+    #[test]
+    fn test_reports_bad_transactions() {
+        let bad = OFX_SGML.replace("<DTPOSTED>20250115", "<DTPOSTED>2025-01-15");
+        let err = parse(&bad).unwrap_err();
+        assert!(err.0.contains("2025011501"), "{}", err);
+    }
+}
@@ -0,0 +1,247 @@
+use crate::app_error::AppError;
+
+use std::io::Write;
+
+// US Letter, in points
+pub const PAGE_WIDTH: f64 = 612.0;
+pub const PAGE_HEIGHT: f64 = 792.0;
+
+// Advance widths of Helvetica and Helvetica-Bold for ' ' to '~' in 1/1000
+// of the font size, from the standard 14 font metrics. Those fonts are
+// built into every PDF reader, so nothing needs to be embedded.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556, 556, 556, 556,
+    556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667,
+    556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556, 333, 556,
+    556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722,
+    500, 500, 500, 334, 260, 334, 584,
+];
+const HELVETICA_BOLD_WIDTHS: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556, 556, 556, 556,
+    556, 556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722,
+    611, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556, 333, 556,
+    611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778,
+    556, 556, 500, 389, 280, 389, 584,
+];
+// for everything outside ASCII
+const DEFAULT_WIDTH: u16 = 556;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Font {
+    Regular,
+    Bold,
+}
+
+impl Font {
+    fn resource(&self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color(pub f64, pub f64, pub f64);
+
+impl Color {
+    pub const BLACK: Color = Color(0.0, 0.0, 0.0);
+}
+
+pub fn text_width(text: &str, font: Font, size: f64) -> f64 {
+    let widths = match font {
+        Font::Regular => &HELVETICA_WIDTHS,
+        Font::Bold => &HELVETICA_BOLD_WIDTHS,
+    };
+    let units: u32 = text
+        .chars()
+        .map(|c| match c {
+            ' '..='~' => u32::from(widths[c as usize - 32]),
+            _ => u32::from(DEFAULT_WIDTH),
+        })
+        .sum();
+    f64::from(units) * size / 1000.0
+}
+
+// Encodes text as WinAnsi (the fonts' encoding) inside a PDF string literal.
+// Latin-1 maps straight across, a few common typographic characters are
+// mapped by hand and anything else becomes '?'.
+fn string_literal(text: &str) -> Vec<u8> {
+    let mut bytes = vec![b'('];
+    for c in text.chars() {
+        let byte = match c {
+            '(' | ')' | '\\' => {
+                bytes.push(b'\\');
+                c as u8
+            }
+            ' '..='~' => c as u8,
+            '\u{a0}'..='\u{ff}' => c as u32 as u8,
+            '€' => 0x80,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            '…' => 0x85,
+            '\t' | '\n' | '\r' => b' ',
+            _ => b'?',
+        };
+        bytes.push(byte);
+    }
+    bytes.push(b')');
+    bytes
+}
+
+// Numbers in content streams: short, and never in exponent notation
+fn num(value: f64) -> String {
+    let s = format!("{:.2}", value);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" { "0".to_string() } else { s.to_string() }
+}
+
+/// One page's content stream. Coordinates are in points from the bottom left
+/// corner, text positions are the left end of the baseline.
+#[derive(Debug, Default, Clone)]
+pub struct Page {
+    content: Vec<u8>,
+}
+
+impl Page {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn op(&mut self, op: &str) {
+        self.content.extend_from_slice(op.as_bytes());
+        self.content.push(b'\n');
+    }
+
+    pub fn text(&mut self, x: f64, y: f64, text: &str, font: Font, size: f64, color: Color) {
+        self.op(&format!(
+            "BT /{} {} Tf {} {} {} rg {} {} Td",
+            font.resource(), num(size), num(color.0), num(color.1), num(color.2), num(x), num(y)
+        ));
+        self.content.extend_from_slice(&string_literal(text));
+        self.op(" Tj ET");
+    }
+
+    // text ending at `x`, for numbers in columns
+    pub fn text_right(&mut self, x: f64, y: f64, text: &str, font: Font, size: f64, color: Color) {
+        self.text(x - text_width(text, font, size), y, text, font, size, color);
+    }
+
+    pub fn text_centered(&mut self, x: f64, y: f64, text: &str, font: Font, size: f64, color: Color) {
+        self.text(x - text_width(text, font, size) / 2.0, y, text, font, size, color);
+    }
+
+    pub fn fill_rect(&mut self, x: f64, y: f64, width: f64, height: f64, color: Color) {
+        self.op(&format!(
+            "{} {} {} rg {} {} {} {} re f",
+            num(color.0), num(color.1), num(color.2), num(x), num(y), num(width), num(height)
+        ));
+    }
+
+    pub fn line(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, width: f64, color: Color) {
+        self.op(&format!(
+            "{} {} {} RG {} w {} {} m {} {} l S",
+            num(color.0), num(color.1), num(color.2), num(width), num(x1), num(y1), num(x2), num(y2)
+        ));
+    }
+}
+
+/// Write `pages` as a PDF 1.4 document
+pub fn write(pages: &[Page], title: &str, mut out: impl Write) -> Result<(), AppError> {
+    // objects: 1 catalog, 2 page tree, 3 and 4 fonts, 5 info, then a page
+    // object and its content stream for every page
+    let first_page = 6;
+    let mut objects: Vec<Vec<u8>> = Vec::new();
+    let kids: Vec<String> = (0..pages.len()).map(|i| format!("{} 0 R", first_page + 2 * i)).collect();
+    objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+    objects.push(format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len()).into_bytes());
+    for font in ["Helvetica", "Helvetica-Bold"] {
+        objects.push(
+            format!("<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>", font).into_bytes(),
+        );
+    }
+    let mut info = b"<< /Producer (Overkill budget app) /Title ".to_vec();
+    info.extend_from_slice(&string_literal(title));
+    info.extend_from_slice(b" >>");
+    objects.push(info);
+    for (i, page) in pages.iter().enumerate() {
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                num(PAGE_WIDTH),
+                num(PAGE_HEIGHT),
+                first_page + 2 * i + 1
+            )
+            .into_bytes(),
+        );
+        let mut stream = format!("<< /Length {} >>\nstream\n", page.content.len()).into_bytes();
+        stream.extend_from_slice(&page.content);
+        stream.extend_from_slice(b"\nendstream");
+        objects.push(stream);
+    }
+
+    let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+    let xref = pdf.len();
+    pdf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R /Info 5 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .as_bytes(),
+    );
+    out.write_all(&pdf)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_width_and_encoding() {
+        assert_eq!(text_width("$1,000.00", Font::Regular, 10.0), 44.48);
+        assert_eq!(string_literal("Café (1) – a\\b"), b"(Caf\xe9 \\(1\\) \x96 a\\\\b)".to_vec());
+        assert_eq!(num(12.0), "12");
+        assert_eq!(num(-0.001), "0");
+        assert_eq!(num(12.345), "12.35");
+    }
+
+    #[test]
+    fn test_write_has_valid_xref() {
+        let mut page = Page::new();
+        page.text(50.0, 700.0, "Hello", Font::Bold, 12.0, Color::BLACK);
+        let mut out = Vec::new();
+        write(&[page.clone(), page], "Test", &mut out).unwrap();
+        assert!(out.starts_with(b"%PDF-1.4"));
+        assert!(out.windows(8).any(|w| w == b"/Count 2"));
+        // every xref entry points at its object
+        let xref = out.windows(6).position(|w| w == b"\nxref\n").unwrap() + 1;
+        let tail = String::from_utf8(out[xref..].to_vec()).unwrap();
+        let entries: Vec<&str> = tail.lines().skip(3).take_while(|l| l.ends_with(" n ")).collect();
+        assert_eq!(entries.len(), 9);
+        for (i, entry) in entries.iter().enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(out[offset..].starts_with(format!("{} 0 obj", i + 1).as_bytes()));
+        }
+        let startxref: usize = tail.lines().rev().nth(1).unwrap().parse().unwrap();
+        assert_eq!(startxref, xref);
+    }
+}
@@ -0,0 +1,445 @@
+use crate::models::{FinancialRecord, RecordType, Summary};
+use crate::pdf::{self, text_width, Color, Font, Page, PAGE_HEIGHT, PAGE_WIDTH};
+use crate::charts::{self, axis_step, money, short_money};
+use crate::schedule;
+use crate::app_error::AppError;
+
+use chrono::{Datelike, Months, NaiveDate};
+use std::io::Write;
+
+// longest period a single report covers
+pub const MAX_MONTHS: u32 = 24;
+
+const MARGIN: f64 = 54.0;
+const CONTENT_WIDTH: f64 = PAGE_WIDTH - 2.0 * MARGIN;
+const TOP: f64 = PAGE_HEIGHT - MARGIN;
+// leaves room for the footer
+const BOTTOM: f64 = MARGIN + 24.0;
+const ROW_HEIGHT: f64 = 15.0;
+const TEXT_SIZE: f64 = 9.5;
+const CHART_HEIGHT: f64 = 170.0;
+
+const GRAY: Color = Color(0.45, 0.45, 0.45);
+const RULE: Color = Color(0.8, 0.8, 0.8);
+const INCOME: Color = Color(0.22, 0.6, 0.35);
+const OUTGOING: Color = Color(0.8, 0.3, 0.25);
+
+const RECORD_TYPES: [RecordType; 3] = [RecordType::Income, RecordType::Expense, RecordType::Debt];
+
+/// The whole months a report covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportPeriod {
+    /// 1st of the first month
+    pub first_month: NaiveDate,
+    pub months: u32,
+}
+
+impl ReportPeriod {
+    /// From `from` to `to` inclusive, both `YYYY-MM` (or a full date within
+    /// the month). Either defaults to the other, both to `today`'s month.
+    pub fn parse(from: Option<&str>, to: Option<&str>, today: NaiveDate) -> Result<Self, String> {
+        let parse_month = |value: Option<&str>| -> Result<Option<NaiveDate>, String> {
+            let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) else {
+                return Ok(None);
+            };
+            NaiveDate::parse_from_str(&format!("{}-01", value), "%Y-%m-%d")
+                .or_else(|_| NaiveDate::parse_from_str(value, "%Y-%m-%d"))
+                .map(|date| Some(date.with_day(1).expect("every month has a 1st")))
+                .map_err(|_| format!("invalid month `{}` (expected YYYY-MM)", value))
+        };
+        let (from, to) = match (parse_month(from)?, parse_month(to)?) {
+            (Some(from), Some(to)) => (from, to),
+            (Some(month), None) | (None, Some(month)) => (month, month),
+            (None, None) => {
+                let month = today.with_day(1).expect("every month has a 1st");
+                (month, month)
+            }
+        };
+        if to < from {
+            return Err("the period ends before it starts".to_string());
+        }
+        let months = (to.year() - from.year()) as u32 * 12 + to.month() - from.month() + 1;
+        if months > MAX_MONTHS {
+            return Err(format!("a report covers at most {} months", MAX_MONTHS));
+        }
+        Ok(Self { first_month: from, months })
+    }
+
+    pub fn month_starts(&self) -> impl Iterator<Item = NaiveDate> + '_ {
+        (0..self.months).map(|i| self.first_month + Months::new(i))
+    }
+
+    pub fn label(&self) -> String {
+        let last = self.first_month + Months::new(self.months - 1);
+        if self.months == 1 {
+            self.first_month.format("%B %Y").to_string()
+        } else {
+            format!("{} – {}", self.first_month.format("%b %Y"), last.format("%b %Y"))
+        }
+    }
+
+    // what a record is due over the whole period
+    fn amount(&self, record: &FinancialRecord) -> f64 {
+        self.month_starts().map(|month| schedule::amount_in_month(record, month)).sum()
+    }
+}
+
+fn percent(ratio: f64) -> String {
+    format!("{:.1}%", ratio * 100.0)
+}
+
+// Cuts `text` down to `width`, ending in an ellipsis when it had to
+fn fit(text: &str, width: f64, font: Font) -> String {
+    if text_width(text, font, TEXT_SIZE) <= width {
+        return text.to_string();
+    }
+    let mut fitted: String = text.to_string();
+    while !fitted.is_empty() && text_width(&format!("{}…", fitted), font, TEXT_SIZE) > width {
+        fitted.pop();
+    }
+    format!("{}…", fitted.trim_end())
+}
+
+#[derive(Clone, Copy)]
+enum Align {
+    Left,
+    Right,
+}
+
+struct Column {
+    title: &'static str,
+    width: f64,
+    align: Align,
+}
+
+const fn column(title: &'static str, width: f64, align: Align) -> Column {
+    Column { title, width, align }
+}
+
+// Pages being laid out top to bottom, `y` is where the next line goes
+struct Layout {
+    pages: Vec<Page>,
+    y: f64,
+}
+
+impl Layout {
+    fn new() -> Self {
+        Self { pages: vec![Page::new()], y: TOP }
+    }
+
+    fn page(&mut self) -> &mut Page {
+        self.pages.last_mut().expect("a layout always has a page")
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(Page::new());
+        self.y = TOP;
+    }
+
+    // starts a new page unless `height` more fits on this one
+    fn ensure(&mut self, height: f64) {
+        if self.y - height < BOTTOM {
+            self.new_page();
+        }
+    }
+
+    fn heading(&mut self, text: &str) {
+        // keep a heading together with the start of what follows it
+        self.ensure(28.0 + 3.0 * ROW_HEIGHT);
+        self.y -= 18.0;
+        let y = self.y;
+        self.page().text(MARGIN, y, text, Font::Bold, 13.0, Color::BLACK);
+        self.y -= 10.0;
+    }
+
+    fn note(&mut self, text: &str) {
+        self.ensure(ROW_HEIGHT);
+        self.y -= ROW_HEIGHT;
+        let y = self.y;
+        self.page().text(MARGIN, y, text, Font::Regular, TEXT_SIZE, GRAY);
+    }
+
+    fn row(&mut self, columns: &[Column], cells: &[String], font: Font, color: Color) {
+        self.y -= ROW_HEIGHT;
+        let y = self.y;
+        let mut x = MARGIN;
+        for (column, cell) in columns.iter().zip(cells) {
+            if cell.is_empty() {
+                x += column.width;
+                continue;
+            }
+            // a little padding between columns
+            let text = fit(cell, column.width - 6.0, font);
+            match column.align {
+                Align::Left => self.page().text(x, y, &text, font, TEXT_SIZE, color),
+                Align::Right => self.page().text_right(x + column.width, y, &text, font, TEXT_SIZE, color),
+            }
+            x += column.width;
+        }
+    }
+
+    fn rule(&mut self, width: f64) {
+        let y = self.y - 4.0;
+        self.page().line(MARGIN, y, MARGIN + CONTENT_WIDTH, y, width, RULE);
+    }
+
+    // A table whose header repeats on every page it runs onto. With
+    // `total` the last row is a bold total under a rule.
+    fn table(&mut self, columns: &[Column], rows: &[Vec<String>], total: bool) {
+        let titles: Vec<String> = columns.iter().map(|c| c.title.to_string()).collect();
+        let header = |layout: &mut Layout| {
+            layout.row(columns, &titles, Font::Bold, GRAY);
+            layout.rule(0.8);
+        };
+        self.ensure(2.0 * ROW_HEIGHT);
+        header(self);
+        for (i, cells) in rows.iter().enumerate() {
+            if self.y - ROW_HEIGHT < BOTTOM {
+                self.new_page();
+                header(self);
+            }
+            if total && i == rows.len() - 1 {
+                self.rule(0.5);
+                self.row(columns, cells, Font::Bold, Color::BLACK);
+            } else {
+                self.row(columns, cells, Font::Regular, Color::BLACK);
+            }
+        }
+        self.y -= 6.0;
+    }
+}
+
+fn summary_section(layout: &mut Layout, records: &[FinancialRecord], period: &ReportPeriod) {
+    let summary = Summary::from_records(records);
+    let in_period = |t: RecordType| {
+        records.iter().filter(|r| r.record_type == t).map(|r| period.amount(r)).sum::<f64>()
+    };
+    let columns = [
+        column("Type", 180.0, Align::Left),
+        column("Records", 70.0, Align::Right),
+        column("Monthly", 120.0, Align::Right),
+        column("In period", 134.0, Align::Right),
+    ];
+    let mut rows: Vec<Vec<String>> = summary
+        .lines
+        .iter()
+        .map(|line| {
+            vec![
+                line.record_type.to_string(),
+                line.record_count.to_string(),
+                money(line.monthly_total),
+                money(in_period(line.record_type)),
+            ]
+        })
+        .collect();
+    let net = in_period(RecordType::Income) - in_period(RecordType::Expense) - in_period(RecordType::Debt);
+    rows.push(vec!["Net".to_string(), String::new(), money(summary.monthly_net), money(net)]);
+
+    layout.heading("Summary");
+    layout.table(&columns, &rows, true);
+    if summary.monthly_income > 0.0 {
+        layout.note(&format!(
+            "Savings rate {}, monthly amounts are every record normalized to an average month.",
+            percent(summary.monthly_net / summary.monthly_income)
+        ));
+    }
+}
+
+// Income next to expenses plus debt payments for every month of the period
+fn chart_section(layout: &mut Layout, records: &[FinancialRecord], period: &ReportPeriod) {
+    let months = charts::month_totals(records, period.first_month, period.months);
+
+    layout.heading("Income vs. expenses");
+    layout.ensure(CHART_HEIGHT + 40.0);
+    let axis_width = 56.0;
+    let left = MARGIN + axis_width;
+    let width = CONTENT_WIDTH - axis_width;
+    let bottom = layout.y - CHART_HEIGHT - 10.0;
+    let max = months.iter().map(|m| m.income.max(m.outgoing())).fold(0.0, f64::max);
+    let step = axis_step(max);
+    let top_value = (max / step).ceil().max(1.0) * step;
+    let scale = CHART_HEIGHT / top_value;
+
+    let page = layout.page();
+    let mut value = 0.0;
+    while value <= top_value + step / 2.0 {
+        let y = bottom + value * scale;
+        page.line(left, y, left + width, y, if value == 0.0 { 0.8 } else { 0.3 }, RULE);
+        page.text_right(left - 6.0, y - 3.0, &short_money(value), Font::Regular, 7.5, GRAY);
+        value += step;
+    }
+
+    let slot = width / months.len() as f64;
+    let bar = (slot * 0.32).min(22.0);
+    for (i, totals) in months.iter().enumerate() {
+        let center = left + slot * (i as f64 + 0.5);
+        page.fill_rect(center - bar, bottom, bar, totals.income * scale, INCOME);
+        page.fill_rect(center, bottom, bar, totals.outgoing() * scale, OUTGOING);
+        page.text_centered(center, bottom - 12.0, &totals.month.format("%b").to_string(), Font::Regular, 7.5, GRAY);
+    }
+
+    let legend_y = bottom - 28.0;
+    page.fill_rect(left, legend_y, 8.0, 8.0, INCOME);
+    page.text(left + 12.0, legend_y + 1.0, "Income", Font::Regular, 8.0, GRAY);
+    page.fill_rect(left + 70.0, legend_y, 8.0, 8.0, OUTGOING);
+    page.text(left + 82.0, legend_y + 1.0, "Expenses and debt payments", Font::Regular, 8.0, GRAY);
+    layout.y = legend_y - 8.0;
+}
+
+fn listing_section(layout: &mut Layout, records: &[FinancialRecord], record_type: RecordType, period: &ReportPeriod) {
+    let mut of_type: Vec<(&FinancialRecord, f64)> = records
+        .iter()
+        .filter(|r| r.record_type == record_type)
+        .map(|r| (r, period.amount(r)))
+        .collect();
+    of_type.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.name.cmp(&b.0.name)));
+
+    layout.heading(&format!("{} ({})", record_type, of_type.len()));
+    if of_type.is_empty() {
+        layout.note(&format!("No {} records.", record_type.to_string().to_lowercase()));
+        return;
+    }
+    let columns = [
+        column("Name", 190.0, Align::Left),
+        column("Frequency", 74.0, Align::Left),
+        column("Amount", 80.0, Align::Right),
+        column("Monthly", 80.0, Align::Right),
+        column("In period", 80.0, Align::Right),
+    ];
+    let mut rows: Vec<Vec<String>> = of_type
+        .iter()
+        .map(|(r, amount)| {
+            vec![r.name.clone(), r.frequency.to_string(), money(r.amount), money(r.monthly_amount()), money(*amount)]
+        })
+        .collect();
+    rows.push(vec![
+        "Total".to_string(),
+        String::new(),
+        String::new(),
+        money(of_type.iter().map(|(r, _)| r.monthly_amount()).sum()),
+        money(of_type.iter().map(|(_, amount)| amount).sum()),
+    ]);
+    layout.table(&columns, &rows, true);
+}
+
+fn debt_section(layout: &mut Layout, records: &[FinancialRecord], period: &ReportPeriod) {
+    let summary = Summary::from_records(records);
+    let debts: Vec<&FinancialRecord> = records.iter().filter(|r| r.record_type == RecordType::Debt).collect();
+
+    layout.heading("Debt overview");
+    if debts.is_empty() {
+        layout.note("No debt payments, nothing to pay down.");
+        return;
+    }
+    let share = |monthly: f64| {
+        if summary.monthly_income > 0.0 { percent(monthly / summary.monthly_income) } else { "–".to_string() }
+    };
+    let columns = [
+        column("Debt", 190.0, Align::Left),
+        column("Payment", 84.0, Align::Right),
+        column("Monthly", 80.0, Align::Right),
+        column("In period", 80.0, Align::Right),
+        column("Of income", 70.0, Align::Right),
+    ];
+    let mut rows: Vec<Vec<String>> = debts
+        .iter()
+        .map(|r| {
+            vec![
+                r.name.clone(),
+                format!("{} {}", money(r.amount), r.frequency.to_string().to_lowercase()),
+                money(r.monthly_amount()),
+                money(period.amount(r)),
+                share(r.monthly_amount()),
+            ]
+        })
+        .collect();
+    rows.push(vec![
+        "Total".to_string(),
+        String::new(),
+        money(summary.monthly_debt),
+        money(debts.iter().map(|r| period.amount(r)).sum()),
+        share(summary.monthly_debt),
+    ]);
+    layout.table(&columns, &rows, true);
+    if summary.monthly_income > 0.0 {
+        layout.note(&format!(
+            "Debt-to-income ratio {}: debt payments take that share of monthly income.",
+            percent(summary.monthly_debt / summary.monthly_income)
+        ));
+    }
+}
+
+/// Write the budget report for `period` as a PDF: a summary table, the
+/// income vs. expenses chart, a listing per record type and a debt overview.
+pub fn write(records: &[FinancialRecord], period: &ReportPeriod, generated_on: NaiveDate, out: impl Write) -> Result<(), AppError> {
+    let mut layout = Layout::new();
+    let title_y = layout.y - 20.0;
+    let page = layout.page();
+    page.text(MARGIN, title_y, "Budget report", Font::Bold, 22.0, Color::BLACK);
+    page.text(MARGIN, title_y - 20.0, &period.label(), Font::Regular, 12.0, Color::BLACK);
+    page.text_right(
+        MARGIN + CONTENT_WIDTH,
+        title_y - 20.0,
+        &format!("Generated {}", generated_on),
+        Font::Regular,
+        8.5,
+        GRAY,
+    );
+    layout.y = title_y - 30.0;
+
+    summary_section(&mut layout, records, period);
+    chart_section(&mut layout, records, period);
+    for record_type in RECORD_TYPES {
+        listing_section(&mut layout, records, record_type, period);
+    }
+    debt_section(&mut layout, records, period);
+
+    let count = layout.pages.len();
+    let footer = format!("Budget report, {}", period.label());
+    for (i, page) in layout.pages.iter_mut().enumerate() {
+        let y = MARGIN - 4.0;
+        page.line(MARGIN, y + 12.0, MARGIN + CONTENT_WIDTH, y + 12.0, 0.5, RULE);
+        page.text(MARGIN, y, &footer, Font::Regular, 8.0, GRAY);
+        page.text_right(MARGIN + CONTENT_WIDTH, y, &format!("Page {} of {}", i + 1, count), Font::Regular, 8.0, GRAY);
+    }
+    pdf::write(&layout.pages, &footer, out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Frequency;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_parse_period() {
+        let today = date(2025, 5, 17);
+        assert_eq!(ReportPeriod::parse(None, None, today), Ok(ReportPeriod { first_month: date(2025, 5, 1), months: 1 }));
+        let quarter = ReportPeriod::parse(Some("2024-11"), Some("2025-01-31"), today).unwrap();
+        assert_eq!(quarter, ReportPeriod { first_month: date(2024, 11, 1), months: 3 });
+        assert_eq!(quarter.label(), "Nov 2024 – Jan 2025");
+        assert!(ReportPeriod::parse(Some("2025-03"), Some("2025-01"), today).is_err());
+        assert!(ReportPeriod::parse(Some("2020-01"), Some("2025-01"), today).is_err());
+        assert!(ReportPeriod::parse(Some("March"), None, today).is_err());
+    }
+
+    #[test]
+    fn test_long_listing_breaks_pages() {
+        let mut records: Vec<FinancialRecord> = (0..80)
+            .map(|i| FinancialRecord::new(format!("Expense {}", i), 10.0, Frequency::Monthly, RecordType::Expense))
+            .collect();
+        records.push(FinancialRecord::new("Salary", 4000.0, Frequency::Monthly, RecordType::Income));
+        let period = ReportPeriod::parse(Some("2025-01"), Some("2025-03"), date(2025, 1, 1)).unwrap();
+
+        let mut out = Vec::new();
+        write(&records, &period, date(2025, 4, 1), &mut out).unwrap();
+        let pdf = String::from_utf8_lossy(&out);
+        assert!(pdf.contains("/Count 3"));
+        assert!(pdf.contains("(Page 3 of 3)"));
+        assert!(pdf.contains("(Expense 79)"));
+        assert!(pdf.contains("($2,400.00)"));
+    }
+}
@@ -0,0 +1,269 @@
+use crate::db::init_db_with_key;
+use crate::encryption::{self, DbKey};
+
+use log::{info, warn};
+use rusqlite::{Connection, OpenFlags, Result};
+use std::ops::Deref;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+// how long a connection waits on a lock held by another process (the CLI
+// importing while the server runs, say) before giving up with SQLITE_BUSY
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The connections to one SQLite database, shared by every request.
+///
+/// There is a single writer, as SQLite only ever allows one, and a few
+/// read-only connections. The database runs in WAL mode, so readers don't
+/// wait for the writer or for each other. Cloning a pool is cheap and the
+/// clones share the connections.
+///
+/// A panic while a connection is checked out doesn't take the pool down with
+/// it: the writer is rolled back to its last commit and handed out again.
+#[derive(Clone)]
+pub struct Pool {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    writer: Mutex<Connection>,
+    /// idle readers; empty while they are all checked out
+    readers: Mutex<Vec<Connection>>,
+    reader_returned: Condvar,
+    /// false for in-memory databases, which can't be shared between
+    /// connections, so reads go through the writer
+    has_readers: bool,
+}
+
+impl Pool {
+    /// Opens the database at `path` (creating and migrating it if needed)
+    /// with one writer and `readers` read-only connections.
+    pub fn open(path: &str, readers: usize) -> Result<Pool> {
+        Pool::open_with_key(path, readers, None)
+    }
+
+    /// [`Pool::open`] for a database encrypted with `key`, which every
+    /// connection is opened with. A new database is created encrypted.
+    pub fn open_with_key(path: &str, readers: usize, key: Option<&DbKey>) -> Result<Pool> {
+        let writer = init_db_with_key(path, key)?;
+        writer.busy_timeout(BUSY_TIMEOUT)?;
+
+        let in_memory = path == ":memory:" || path.is_empty();
+        let readers = if in_memory { 0 } else { readers };
+        if !in_memory {
+            let mode: String = writer.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get(0))?;
+            if !mode.eq_ignore_ascii_case("wal") {
+                warn!("Database {} is in {} mode, readers may wait for writes", path, mode);
+            }
+        }
+
+        let readers = (0..readers).map(|_| open_reader(path, key)).collect::<Result<Vec<_>>>()?;
+        info!("Opened {} with {} reader connections", path, readers.len());
+        Ok(Pool {
+            inner: Arc::new(Inner {
+                writer: Mutex::new(writer),
+                has_readers: !readers.is_empty(),
+                readers: Mutex::new(readers),
+                reader_returned: Condvar::new(),
+            }),
+        })
+    }
+
+    /// A connection for reading, waiting for one to come free if they are all
+    /// in use. Writes through it fail.
+    pub fn read(&self) -> Result<PooledConnection<'_>> {
+        if !self.inner.has_readers {
+            return self.write().map(PooledConnection::Writer);
+        }
+
+        let mut idle = self.inner.readers.lock().unwrap_or_else(PoisonError::into_inner);
+        loop {
+            if let Some(conn) = idle.pop() {
+                return Ok(PooledConnection::Reader(Reader { pool: &self.inner, conn: Some(conn) }));
+            }
+            idle = self.inner.reader_returned.wait(idle).unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// The writer, once nobody else is using it
+    pub fn write(&self) -> Result<MutexGuard<'_, Connection>> {
+        match self.inner.writer.lock() {
+            Ok(conn) => Ok(conn),
+            Err(poisoned) => {
+                // whoever panicked may have left a savepoint open; drop it
+                // so its half-made changes are never committed
+                let conn = poisoned.into_inner();
+                if !conn.is_autocommit() {
+                    warn!("Rolling back the writer after a panic");
+                    conn.execute_batch("ROLLBACK")?;
+                }
+                self.inner.writer.clear_poison();
+                Ok(conn)
+            }
+        }
+    }
+}
+
+fn open_reader(path: &str, key: Option<&DbKey>) -> Result<Connection> {
+    let conn = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI,
+    )?;
+    if let Some(key) = key {
+        encryption::apply_key(&conn, key)?;
+    }
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    Ok(conn)
+}
+
+/// A connection checked out of a [`Pool`]
+pub enum PooledConnection<'a> {
+    Reader(Reader<'a>),
+    /// for pools without readers
+    Writer(MutexGuard<'a, Connection>),
+}
+
+/// A read-only connection, which goes back to the pool when dropped
+pub struct Reader<'a> {
+    pool: &'a Inner,
+    conn: Option<Connection>,
+}
+
+impl Deref for PooledConnection<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        match self {
+            PooledConnection::Reader(reader) => reader.conn.as_ref().expect("a reader holds its connection until dropped"),
+            PooledConnection::Writer(conn) => conn,
+        }
+    }
+}
+
+impl Drop for Reader<'_> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool.readers.lock().unwrap_or_else(PoisonError::into_inner).push(conn);
+            self.pool.reader_returned.notify_one();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+
+    fn file_pool(dir: &tempfile::TempDir, readers: usize) -> Pool {
+        Pool::open(dir.path().join("budget.db").to_str().unwrap(), readers).unwrap()
+    }
+
+    fn count(conn: &Connection) -> i64 {
+        conn.query_row("SELECT COUNT(*) FROM financial_record", [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn test_readers_run_alongside_the_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = file_pool(&dir, 2);
+
+        let writer = pool.write().unwrap();
+        writer.execute_batch("BEGIN").unwrap();
+        writer
+            .execute("INSERT INTO financial_record (id, name, amount, frequency, record_type) VALUES (x'01', 'Rent', 1500, 'Monthly', 'Expense')", [])
+            .unwrap();
+
+        // both readers at once, while the write is in progress: they see the
+        // last commit
+        let (a, b) = (pool.read().unwrap(), pool.read().unwrap());
+        assert_eq!((count(&a), count(&b)), (0, 0));
+        assert!(a.execute("DELETE FROM financial_record", []).is_err());
+        drop((a, b));
+
+        writer.execute_batch("COMMIT").unwrap();
+        assert_eq!(count(&pool.read().unwrap()), 1);
+    }
+
+    #[test]
+    fn test_in_memory_pool_reads_through_the_writer() {
+        let pool = Pool::open(":memory:", 4).unwrap();
+        pool.write()
+            .unwrap()
+            .execute("INSERT INTO financial_record (id, name, amount, frequency, record_type) VALUES (x'01', 'Rent', 1500, 'Monthly', 'Expense')", [])
+            .unwrap();
+        assert_eq!(count(&pool.read().unwrap()), 1);
+    }
+
+    #[test]
+    fn test_panic_while_writing_rolls_back_and_keeps_the_pool() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = file_pool(&dir, 1);
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let writer = pool.write().unwrap();
+            writer.execute_batch("SAVEPOINT budget_sp").unwrap();
+            writer
+                .execute("INSERT INTO financial_record (id, name, amount, frequency, record_type) VALUES (x'01', 'Rent', 1500, 'Monthly', 'Expense')", [])
+                .unwrap();
+            panic!("handler bug");
+        }));
+        assert!(result.is_err());
+
+        let writer = pool.write().unwrap();
+        assert!(writer.is_autocommit());
+        assert_eq!(count(&writer), 0);
+    }
+
+    #[test]
+    fn test_open_read_transaction_doesnt_block_others() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = file_pool(&dir, 2);
+        let insert = |id: u8| {
+            pool.write()
+                .unwrap()
+                .execute(
+                    "INSERT INTO financial_record (id, name, amount, frequency, record_type)
+                    VALUES (?1, 'Rent', 1500, 'Monthly', 'Expense')",
+                    [vec![id]],
+                )
+                .unwrap()
+        };
+        insert(1);
+
+        // a long report: one reader stays inside a read transaction
+        let report = pool.read().unwrap();
+        report.execute_batch("BEGIN").unwrap();
+        assert_eq!(count(&report), 1);
+
+        // under WAL the writer still commits and the other reader sees it,
+        // while the open transaction keeps reading its snapshot
+        insert(2);
+        assert_eq!(count(&pool.read().unwrap()), 2);
+        assert_eq!(count(&report), 1);
+
+        report.execute_batch("COMMIT").unwrap();
+        assert_eq!(count(&report), 2);
+    }
+
+    #[test]
+    fn test_readers_are_held_at_once() {
+        const READERS: usize = 4;
+        let dir = tempfile::tempdir().unwrap();
+        let pool = file_pool(&dir, READERS);
+        let all_holding = std::sync::Barrier::new(READERS);
+
+        // every thread keeps its reader, mid-transaction, until all of them
+        // have one: this never finishes if reads are handed out one at a time
+        std::thread::scope(|scope| {
+            for _ in 0..READERS {
+                scope.spawn(|| {
+                    let conn = pool.read().unwrap();
+                    conn.execute_batch("BEGIN").unwrap();
+                    assert_eq!(count(&conn), 0);
+                    all_holding.wait();
+                    conn.execute_batch("COMMIT").unwrap();
+                });
+            }
+        });
+    }
+}
@@ -0,0 +1,470 @@
+use crate::models::{AccountKind, FinancialRecord, Frequency, ImportRow, LedgerEntry, RecordType};
+use crate::csv_import::parse_amount;
+use crate::app_error::AppError;
+
+use chrono::NaiveDate;
+use log::debug;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Write;
+
+// Category prefix the writer puts on Debt records, QIF itself only knows
+// income and expense categories
+const DEBT_CATEGORY: &str = "Debt";
+
+#[derive(Clone, Debug)]
+pub struct QifOptions {
+    /// account for transactions that aren't preceded by an !Account block
+    pub account: String,
+    /// dates are DD/MM/YYYY rather than the usual (US) MM/DD/YYYY
+    pub day_first: bool,
+}
+
+impl Default for QifOptions {
+    fn default() -> Self {
+        Self {
+            account: "QIF".to_string(),
+            day_first: false,
+        }
+    }
+}
+
+// What a QIF file holds for the budget: categories with a budget amount
+// become records, bank/card/cash transactions become ledger entries.
+#[derive(Debug, Default)]
+pub struct QifImport {
+    pub records: Vec<ImportRow>,
+    pub transactions: Vec<LedgerEntry>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Section {
+    Transactions(AccountKind),
+    Categories,
+    Account,
+    // investment, memorized, class, ... sections aren't imported
+    Unsupported,
+}
+
+fn section_for(header: &str) -> Option<Section> {
+    let header = header.trim().to_lowercase();
+    if header == "!account" {
+        return Some(Section::Account);
+    }
+    let section = match header.strip_prefix("!type:")?.trim() {
+        "bank" => Section::Transactions(AccountKind::Bank),
+        "ccard" => Section::Transactions(AccountKind::CreditCard),
+        "cash" => Section::Transactions(AccountKind::Cash),
+        "cat" => Section::Categories,
+        _ => Section::Unsupported,
+    };
+    Some(section)
+}
+
+// One ^-terminated item: its field lines keyed by their code letter, in order
+struct Item {
+    line: u64,
+    fields: Vec<(char, String)>,
+}
+
+impl Item {
+    fn get(&self, code: char) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(c, value)| *c == code && !value.is_empty())
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn has(&self, code: char) -> bool {
+        self.fields.iter().any(|(c, _)| *c == code)
+    }
+}
+
+/// Parse a QIF file into budget records and ledger transactions.
+///
+/// Category (`!Type:Cat`) items with a budget amount map to Monthly records,
+/// income categories to Income and the rest to Expense (or Debt when filed
+/// under a "Debt" or "Loan" parent). Bank, CCard and Cash transactions map to
+/// ledger entries of the current `!Account`; QIF has no transaction ids, so
+/// one is derived from each transaction's contents to recognise re-imports.
+pub fn parse(input: &str, options: &QifOptions) -> Result<QifImport, AppError> {
+    let mut import = QifImport::default();
+    let mut section = None;
+    let mut account = options.account.clone();
+    // between !Option:AutoSwitch and !Clear:AutoSwitch, !Account items are
+    // a list of accounts rather than a switch to one
+    let mut account_list = false;
+    // occurrences of each transaction so far, so identical ones get distinct ids
+    let mut seen: HashMap<String, u32> = HashMap::new();
+    let mut item = Item { line: 1, fields: Vec::new() };
+
+    for (number, line) in input.lines().enumerate() {
+        let number = number as u64 + 1;
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if line.starts_with('!') {
+            match line.trim().to_lowercase().as_str() {
+                "!option:autoswitch" => account_list = true,
+                "!clear:autoswitch" => account_list = false,
+                _ => {
+                    section = section_for(line);
+                    if section.is_none() || section == Some(Section::Unsupported) {
+                        debug!("Skipping QIF section `{}` at line {}", line.trim(), number);
+                    }
+                }
+            }
+            item = Item { line: number + 1, fields: Vec::new() };
+            continue;
+        }
+
+        if line.starts_with('^') {
+            match section {
+                Some(Section::Account) if !account_list => {
+                    if let Some(name) = item.get('N') {
+                        account = name.to_string();
+                    }
+                }
+                Some(Section::Categories) => {
+                    if let Some(row) = category_row(&item) {
+                        import.records.push(row);
+                    }
+                }
+                Some(Section::Transactions(kind)) => {
+                    let mut entry = transaction(&item, &account, kind, options)?;
+                    let key = fingerprint(&entry);
+                    let occurrence = seen.entry(key.clone()).or_insert(0);
+                    *occurrence += 1;
+                    entry.fitid = Some(format!("qif-{}", &format!("{:x}", Sha256::digest(format!("{}|{}", key, occurrence)))[..32]));
+                    import.transactions.push(entry);
+                }
+                _ => {}
+            }
+            item = Item { line: number + 1, fields: Vec::new() };
+            continue;
+        }
+
+        let mut chars = line.chars();
+        let code = chars.next().expect("line is not empty").to_ascii_uppercase();
+        item.fields.push((code, chars.as_str().trim().to_string()));
+    }
+
+    Ok(import)
+}
+
+// Categories without a budget amount are just labels, not records
+fn category_row(item: &Item) -> Option<ImportRow> {
+    // Quicken writes one B line per month, use their average
+    let budgets = item
+        .fields
+        .iter()
+        .filter(|(c, value)| *c == 'B' && !value.is_empty())
+        .map(|(_, value)| parse_amount(value))
+        .collect::<Result<Vec<f64>, String>>();
+
+    let outcome = match budgets {
+        Ok(budgets) if budgets.is_empty() => return None,
+        Ok(budgets) => {
+            let full_name = item.get('N').unwrap_or_default();
+            let record_type = if item.has('I') {
+                RecordType::Income
+            } else if full_name.split(':').any(|part| RecordType::parse_lenient(part) == Some(RecordType::Debt)) {
+                RecordType::Debt
+            } else {
+                RecordType::Expense
+            };
+            let amount = (budgets.iter().sum::<f64>() / budgets.len() as f64).abs();
+            // subcategories are "Parent:Child", the record is the child
+            let name = full_name.rsplit(':').next().unwrap_or_default().trim();
+            if name.is_empty() {
+                Err("category has no name".to_string())
+            } else if amount == 0.0 {
+                Err(format!("category `{}` has a zero budget", name))
+            } else {
+                let mut record = FinancialRecord::new(name, amount, Frequency::Monthly, record_type);
+                record.notes = item.get('D').map(str::to_string);
+                Ok(record)
+            }
+        }
+        Err(e) => Err(e),
+    };
+    Some(ImportRow { line: item.line, outcome })
+}
+
+fn transaction(item: &Item, account: &str, kind: AccountKind, options: &QifOptions) -> Result<LedgerEntry, AppError> {
+    let at = |message: String| AppError(format!("line {}: {}", item.line, message));
+
+    let raw_date = item.get('D').ok_or_else(|| at("transaction has no date".to_string()))?;
+    let posted_on = parse_date(raw_date, options.day_first).ok_or_else(|| at(format!("invalid date `{}`", raw_date)))?;
+    let raw_amount = item
+        .get('T')
+        .or_else(|| item.get('U'))
+        .ok_or_else(|| at("transaction has no amount".to_string()))?;
+    let amount = parse_amount(raw_amount).map_err(at)?;
+
+    let memo = item.get('M').map(str::to_string);
+    let payee = item
+        .get('P')
+        .or(memo.as_deref())
+        .or_else(|| item.get('L'))
+        .unwrap_or("Unknown payee")
+        .to_string();
+
+    Ok(LedgerEntry {
+        account_kind: kind,
+        memo: memo.filter(|m| *m != payee),
+        ..LedgerEntry::new(account, posted_on, amount, payee)
+    })
+}
+
+fn fingerprint(entry: &LedgerEntry) -> String {
+    format!(
+        "{}|{}|{:.2}|{}|{}",
+        entry.account, entry.posted_on, entry.amount, entry.payee, entry.memo.as_deref().unwrap_or_default()
+    )
+}
+
+// QIF dates come as 1/3/2025, 01/03/25, 1/ 3'25 (Quicken: ' means 20xx),
+// 01-03-2025, 01.03.2025 or 2025-01-03
+fn parse_date(raw: &str, day_first: bool) -> Option<NaiveDate> {
+    let compact: String = raw.chars().filter(|c| !c.is_whitespace()).collect();
+    let apostrophe = compact.contains('\'');
+    let parts: Vec<i32> = compact
+        .split(['/', '-', '.', '\''])
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+    let [a, b, c] = parts[..] else {
+        return None;
+    };
+
+    let (year, month, day) = if a > 999 {
+        (a, b, c)
+    } else {
+        let year = match c {
+            y if y > 99 => y,
+            y if apostrophe || y < 70 => 2000 + y,
+            y => 1900 + y,
+        };
+        if day_first { (year, b, a) } else { (year, a, b) }
+    };
+    NaiveDate::from_ymd_opt(year, u32::try_from(month).ok()?, u32::try_from(day).ok()?)
+}
+
+/// Write records as a QIF budget (`!Type:Cat` categories with their monthly
+/// amount) followed by the ledger entries, one `!Account` and transaction
+/// section per account. Entries linked to a record are filed under its
+/// category.
+pub fn write(records: &[FinancialRecord], entries: &[LedgerEntry], mut out: impl Write) -> Result<(), AppError> {
+    if !records.is_empty() {
+        writeln!(out, "!Type:Cat")?;
+        for record in records {
+            writeln!(out, "N{}", category_name(record))?;
+            if let Some(notes) = &record.notes {
+                writeln!(out, "D{}", one_line(notes))?;
+            }
+            writeln!(out, "{}", if record.record_type == RecordType::Income { "I" } else { "E" })?;
+            writeln!(out, "B{:.2}", record.monthly_amount())?;
+            writeln!(out, "^")?;
+        }
+    }
+
+    let categories: HashMap<_, _> = records.iter().map(|r| (r.id, category_name(r))).collect();
+    let mut accounts: Vec<(&str, AccountKind)> = Vec::new();
+    for entry in entries {
+        if !accounts.contains(&(entry.account.as_str(), entry.account_kind)) {
+            accounts.push((entry.account.as_str(), entry.account_kind));
+        }
+    }
+
+    for (account, kind) in accounts {
+        let code = match kind {
+            AccountKind::Bank => "Bank",
+            AccountKind::CreditCard => "CCard",
+            AccountKind::Cash => "Cash",
+        };
+        writeln!(out, "!Account\nN{}\nT{}\n^\n!Type:{}", one_line(account), code, code)?;
+
+        let mut transactions: Vec<&LedgerEntry> = entries
+            .iter()
+            .filter(|e| e.account == account && e.account_kind == kind)
+            .collect();
+        transactions.sort_by_key(|e| e.posted_on);
+        for entry in transactions {
+            writeln!(out, "D{}", entry.posted_on.format("%m/%d/%Y"))?;
+            writeln!(out, "T{:.2}", entry.amount)?;
+            writeln!(out, "P{}", one_line(&entry.payee))?;
+            if let Some(memo) = &entry.memo {
+                writeln!(out, "M{}", one_line(memo))?;
+            }
+            if let Some(category) = entry.record_id.and_then(|id| categories.get(&id)) {
+                writeln!(out, "L{}", category)?;
+            }
+            writeln!(out, "^")?;
+        }
+    }
+    Ok(())
+}
+
+// ':' separates subcategories and '/' a class in QIF category fields
+fn category_name(record: &FinancialRecord) -> String {
+    let name = one_line(&record.name).replace([':', '/'], "-");
+    match record.record_type {
+        RecordType::Debt => format!("{}:{}", DEBT_CATEGORY, name),
+        _ => name,
+    }
+}
+
+// every QIF field is a single line
+fn one_line(text: &str) -> String {
+    text.lines().map(str::trim).filter(|l| !l.is_empty()).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QIF: &str = "!Option:AutoSwitch
+!Account
+NSavings
+TBank
+^
+!Clear:AutoSwitch
+!Type:Cat
+NSalary
+DDay job
+I
+B4,000.00
+^
+NGroceries
+E
+B400
+B500
+^
+NLoan:Car
+E
+B320.00
+^
+NGifts
+E
+^
+!Account
+NChecking
+TBank
+^
+!Type:Bank
+D1/ 3'25
+T-15.49
+PNETFLIX.COM
+MStreaming
+LEntertainment
+^
+D01/15/2025
+T2,500.00
+PACME CORP PAYROLL
+^
+D01/15/2025
+T2,500.00
+PACME CORP PAYROLL
+^
+!Type:CCard
+D2025-02-05
+T-60.00
+MCITY GYM
+^
+!Type:Invst
+D1/1/25
+NBuy
+YVanguard
+^
+";
+
+    #[test]
+    fn test_parse_categories_and_transactions() {
+        let import = parse(QIF, &QifOptions::default()).unwrap();
+
+        let records: Vec<FinancialRecord> = import.records.into_iter().map(|r| r.outcome.unwrap()).collect();
+        let summary: Vec<(&str, f64, RecordType)> = records.iter().map(|r| (r.name.as_str(), r.amount, r.record_type)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Salary", 4000.0, RecordType::Income),
+                ("Groceries", 450.0, RecordType::Expense),
+                ("Car", 320.0, RecordType::Debt),
+            ]
+        );
+        assert_eq!(records[0].notes.as_deref(), Some("Day job"));
+
+        let t = &import.transactions;
+        assert_eq!(t.len(), 4);
+        // the AutoSwitch account list doesn't switch accounts, the later !Account does
+        assert_eq!((t[0].account.as_str(), t[0].account_kind), ("Checking", AccountKind::Bank));
+        assert_eq!(t[0].posted_on, NaiveDate::from_ymd_opt(2025, 1, 3).unwrap());
+        assert_eq!((t[0].payee.as_str(), t[0].amount, t[0].memo.as_deref()), ("NETFLIX.COM", -15.49, Some("Streaming")));
+        // two identical paychecks are still two transactions
+        assert_ne!(t[1].fitid, t[2].fitid);
+        assert_eq!((t[3].account_kind, t[3].payee.as_str(), t[3].memo.as_deref()), (AccountKind::CreditCard, "CITY GYM", None));
+    }
+
+    #[test]
+    fn test_parse_is_stable_and_reports_errors() {
+        let first = parse(QIF, &QifOptions::default()).unwrap();
+        let again = parse(QIF, &QifOptions::default()).unwrap();
+        let ids = |i: &QifImport| i.transactions.iter().map(|t| t.fitid.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&first), ids(&again));
+
+        let err = parse("!Type:Bank\nD13/45/2025\nT1\n^\n", &QifOptions::default()).unwrap_err();
+        assert!(err.0.contains("line 2"), "{}", err);
+        let day_first = QifOptions { day_first: true, ..QifOptions::default() };
+        let t = parse("!Type:Bank\nD13/01/2025\nT1\n^\n", &day_first).unwrap().transactions;
+        assert_eq!(t[0].posted_on, NaiveDate::from_ymd_opt(2025, 1, 13).unwrap());
+
+        let bad = parse("!Type:Cat\nNRent\nBlots\n^\n", &QifOptions::default()).unwrap();
+        assert!(bad.records[0].outcome.is_err());
+    }
+
+    #[test]
+    fn test_parse_european_amounts() {
+        let qif = "!Type:Cat\nNRent\nE\nB1.234,50\n^\n!Type:Bank\nD03/01/2025\nT-1.234,56\nPLANDLORD\n^\n";
+        let import = parse(qif, &QifOptions::default()).unwrap();
+
+        assert_eq!(import.records[0].outcome.as_ref().unwrap().amount, 1234.5);
+        assert_eq!(import.transactions[0].amount, -1234.56);
+        assert!(parse("!Type:Bank\nD03/01/2025\nT1.234,5,6\n^\n", &QifOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_write_round_trips() {
+        let rent = FinancialRecord::new("Rent: downtown", 1500.0, Frequency::Monthly, RecordType::Expense);
+        let car = FinancialRecord::new("Car", 900.0, Frequency::Quarterly, RecordType::Debt);
+        let salary = FinancialRecord::new("Salary", 48000.0, Frequency::Yearly, RecordType::Income);
+        let mut payment = LedgerEntry::new("Visa", NaiveDate::from_ymd_opt(2025, 3, 1).unwrap(), -300.0, "AUTO LOAN");
+        payment.account_kind = AccountKind::CreditCard;
+        payment.record_id = Some(car.id);
+        let records = vec![rent, car, salary];
+
+        let mut out = Vec::new();
+        write(&records, std::slice::from_ref(&payment), &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("!Account\nNVisa\nTCCard\n^\n!Type:CCard\nD03/01/2025\nT-300.00\nPAUTO LOAN\nLDebt:Car\n^"), "{}", text);
+
+        let import = parse(&text, &QifOptions::default()).unwrap();
+        let back: Vec<(String, f64, RecordType)> = import
+            .records
+            .into_iter()
+            .map(|r| r.outcome.unwrap())
+            .map(|r| (r.name, r.amount, r.record_type))
+            .collect();
+        assert_eq!(
+            back,
+            vec![
+                ("Rent- downtown".to_string(), 1500.0, RecordType::Expense),
+                ("Car".to_string(), 300.0, RecordType::Debt),
+                ("Salary".to_string(), 4000.0, RecordType::Income),
+            ]
+        );
+        let t = &import.transactions[0];
+        assert_eq!((t.account.as_str(), t.account_kind, t.amount, t.posted_on), ("Visa", AccountKind::CreditCard, -300.0, payment.posted_on));
+    }
+}
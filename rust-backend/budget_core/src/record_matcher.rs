@@ -0,0 +1,150 @@
+use crate::models::{FinancialRecord, LedgerEntry, RecordMatch, RecordType};
+
+use std::collections::HashSet;
+
+// below this a record isn't worth suggesting at all
+pub const SUGGEST_THRESHOLD: f64 = 0.4;
+// at or above this an imported transaction is linked to the record without asking
+pub const AUTO_LINK_THRESHOLD: f64 = 0.75;
+
+const NAME_WEIGHT: f64 = 0.6;
+const AMOUNT_WEIGHT: f64 = 0.4;
+
+// Bank descriptions are noisy ("NETFLIX.COM 866-579-7172 CA"), so compare
+// lowercase alphabetic words only and drop one and two letter fragments.
+fn tokens(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphabetic())
+        .filter(|word| word.chars().count() > 2)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+// The words of a payee that identify it, in order: "SQ *COFFEE 1234" and
+// "Sq *Coffee 5678" both become "coffee"
+pub fn normalize_payee(text: &str) -> String {
+    text.split(|c: char| !c.is_alphabetic())
+        .filter(|word| word.chars().count() > 2)
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// share of the record's words found in the transaction description,
+// the description usually being the longer of the two
+fn name_similarity(record: &FinancialRecord, entry: &LedgerEntry) -> f64 {
+    let mut record_words = tokens(&record.name);
+    if let Some(payee) = &record.payee {
+        record_words.extend(tokens(payee));
+    }
+    let mut entry_words = tokens(&entry.payee);
+    if let Some(name) = &entry.name {
+        entry_words.extend(tokens(name));
+    }
+    if let Some(memo) = &entry.memo {
+        entry_words.extend(tokens(memo));
+    }
+    if record_words.is_empty() {
+        return 0.0;
+    }
+    let found = record_words
+        .iter()
+        .filter(|word| entry_words.iter().any(|e| e.starts_with(word.as_str()) || word.starts_with(e.as_str())))
+        .count();
+    found as f64 / record_words.len() as f64
+}
+
+// 1.0 within 5% (or a dollar) of the budgeted amount, falling off linearly
+// to 0.0 at 50% off
+fn amount_similarity(record: &FinancialRecord, entry: &LedgerEntry) -> f64 {
+    let expected = record.amount.abs();
+    let actual = entry.amount.abs();
+    let difference = (expected - actual).abs();
+    if difference <= (expected * 0.05).max(1.0) {
+        return 1.0;
+    }
+    if expected == 0.0 {
+        return 0.0;
+    }
+    (1.0 - difference / (expected * 0.5)).clamp(0.0, 1.0)
+}
+
+// money in can only be income, money out only an expense or debt payment
+fn direction_matches(record: &FinancialRecord, entry: &LedgerEntry) -> bool {
+    match record.record_type {
+        RecordType::Income => entry.amount > 0.0,
+        RecordType::Expense | RecordType::Debt => entry.amount < 0.0,
+    }
+}
+
+pub fn score(record: &FinancialRecord, entry: &LedgerEntry) -> f64 {
+    if !direction_matches(record, entry) {
+        return 0.0;
+    }
+    let name = name_similarity(record, entry);
+    // an amount alone says nothing about which bill it was
+    if name == 0.0 {
+        return 0.0;
+    }
+    NAME_WEIGHT * name + AMOUNT_WEIGHT * amount_similarity(record, entry)
+}
+
+// The best scoring record for the transaction, if any scores at least SUGGEST_THRESHOLD.
+pub fn best_match(records: &[FinancialRecord], entry: &LedgerEntry) -> Option<RecordMatch> {
+    records
+        .iter()
+        .map(|record| (record, score(record, entry)))
+        .filter(|(_, score)| *score >= SUGGEST_THRESHOLD)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(record, score)| RecordMatch {
+            record_id: record.id,
+            record_name: record.name.clone(),
+            score,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Frequency;
+    use chrono::NaiveDate;
+
+    fn entry(payee: &str, amount: f64) -> LedgerEntry {
+        LedgerEntry::new("acct", NaiveDate::from_ymd_opt(2025, 1, 3).unwrap(), amount, payee)
+    }
+
+    fn records() -> Vec<FinancialRecord> {
+        vec![
+            FinancialRecord::new("Netflix", 15.49, Frequency::Monthly, RecordType::Expense),
+            FinancialRecord::new("Salary", 2500.0, Frequency::Monthly, RecordType::Income),
+            FinancialRecord {
+                payee: Some("Acme Corp".to_string()),
+                ..FinancialRecord::new("Paycheck", 2500.0, Frequency::Weekly, RecordType::Income)
+            },
+            FinancialRecord::new("Car loan", 320.0, Frequency::Monthly, RecordType::Debt),
+        ]
+    }
+
+    #[test]
+    fn test_matches_noisy_bank_description() {
+        let records = records();
+        let netflix = best_match(&records, &entry("NETFLIX.COM 866-579-7172 CA", -15.49)).unwrap();
+        assert_eq!(netflix.record_name, "Netflix");
+        assert!(netflix.score >= AUTO_LINK_THRESHOLD);
+
+        // matched through the record's payee
+        let pay = best_match(&records, &entry("ACME CORP PAYROLL", 2480.0)).unwrap();
+        assert_eq!(pay.record_name, "Paycheck");
+    }
+
+    #[test]
+    fn test_amount_and_direction() {
+        let records = records();
+        // a refund from Netflix is not the Netflix expense
+        assert_eq!(best_match(&records, &entry("NETFLIX.COM", 15.49)), None);
+        // price went up: still suggested, but not linked automatically
+        let raised = best_match(&records, &entry("NETFLIX.COM", -22.99)).unwrap();
+        assert!(raised.score < AUTO_LINK_THRESHOLD && raised.score >= SUGGEST_THRESHOLD);
+        // right amount, unrelated payee
+        assert_eq!(best_match(&records, &entry("SHELL OIL 1234", -320.0)), None);
+    }
+}
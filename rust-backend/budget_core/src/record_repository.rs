@@ -0,0 +1,577 @@
+use crate::models::FinancialRecord;
+use crate::models::Frequency;
+use crate::models::RecordType;
+use crate::models::SearchHit;
+use crate::models::TrashedRecord;
+use crate::models::ChangeAction;
+use crate::db::with_savepoint;
+use crate::history_repository::record_change;
+use crate::models::search_hit::{MATCH_START, MATCH_END};
+
+use log::{debug, info};
+use rusqlite::{params, Connection, Result, Row};
+use uuid::Uuid;
+
+// qualified so the list can also be used in joins against record_search,
+// which has name/notes/payee columns of its own
+const RECORD_COLUMNS: &str = "financial_record.id, financial_record.name, financial_record.amount,
+    financial_record.frequency, financial_record.record_type, financial_record.notes,
+    financial_record.payee, financial_record.starts_on";
+// queries selecting more than RECORD_COLUMNS find their extra columns from here
+const RECORD_COLUMN_COUNT: usize = 8;
+
+// timestamp format used for deleted_at, sorts the same as it reads
+const NOW: &str = "strftime('%Y-%m-%dT%H:%M:%SZ', 'now')";
+
+// Every mutation below is logged to record_history in the same savepoint as
+// the change itself, attributed to `actor`.
+
+pub fn insert_record(conn: &Connection, record: &FinancialRecord, actor: &str) -> Result<()> {
+    debug!("insert_record({})", record);
+    with_savepoint(conn, || {
+        conn.execute(
+            "INSERT INTO financial_record (id, name, amount, frequency, record_type, notes, payee, starts_on)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                &record.id,
+                &record.name,
+                &record.amount,
+                &record.frequency,
+                &record.record_type,
+                &record.notes,
+                &record.payee,
+                &record.starts_on
+            ],
+        )?;
+        record_change(conn, &record.id, ChangeAction::Insert, actor, None, Some(record))
+    })
+}
+
+// Moves the record to the trash. Returns false if there was no live record
+// with that id.
+pub fn delete_record(conn: &Connection, id: &Uuid, actor: &str) -> Result<bool> {
+    debug!("delete_record(id={})", id);
+    with_savepoint(conn, || {
+        let before = match get_record_by_id(conn, id) {
+            Ok(record) => record,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        conn.execute(
+            &format!(
+                "UPDATE financial_record SET deleted_at = {}
+                 WHERE id = ?1 AND deleted_at IS NULL",
+                NOW
+            ),
+            params![id],
+        )?;
+        record_change(conn, id, ChangeAction::Delete, actor, Some(&before), None)?;
+        Ok(true)
+    })
+}
+
+// Takes the record back out of the trash. Returns false if it wasn't trashed.
+pub fn restore_record(conn: &Connection, id: &Uuid, actor: &str) -> Result<bool> {
+    debug!("restore_record(id={})", id);
+    with_savepoint(conn, || {
+        let changed = conn.execute(
+            "UPDATE financial_record SET deleted_at = NULL
+             WHERE id = ?1 AND deleted_at IS NOT NULL",
+            params![id],
+        )?;
+        if changed == 0 {
+            return Ok(false);
+        }
+        let after = get_record_by_id(conn, id)?;
+        record_change(conn, id, ChangeAction::Restore, actor, None, Some(&after))?;
+        Ok(true)
+    })
+}
+
+// Permanently removes a trashed record. Live records are never purged, they
+// have to go through the trash first. Returns false if nothing was removed.
+pub fn purge_record(conn: &Connection, id: &Uuid, actor: &str) -> Result<bool> {
+    debug!("purge_record(id={})", id);
+    with_savepoint(conn, || {
+        let before = match get_trashed_record(conn, id) {
+            Ok(record) => record,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        conn.execute(
+            "DELETE FROM financial_record WHERE id = ?1 AND deleted_at IS NOT NULL",
+            params![id],
+        )?;
+        record_change(conn, id, ChangeAction::Purge, actor, Some(&before), None)?;
+        Ok(true)
+    })
+}
+
+// a record that is in the trash, by id
+pub fn get_trashed_record(conn: &Connection, id: &Uuid) -> Result<FinancialRecord> {
+    conn.query_row(
+        &format!("SELECT {} FROM financial_record WHERE id = ?1 AND deleted_at IS NOT NULL", RECORD_COLUMNS),
+        params![id],
+        record_from_row,
+    )
+}
+
+pub fn get_trashed_records(conn: &Connection) -> Result<Vec<TrashedRecord>> {
+    debug!("getting trashed records");
+    let sql = format!(
+        "SELECT {}, financial_record.deleted_at FROM financial_record
+         WHERE deleted_at IS NOT NULL
+         ORDER BY deleted_at DESC",
+        RECORD_COLUMNS
+    );
+    conn.prepare(&sql)?
+        .query_map([], |row| {
+            Ok(TrashedRecord {
+                record: record_from_row(row)?,
+                deleted_at: row.get(RECORD_COLUMN_COUNT)?,
+            })
+        })?
+        .collect()
+}
+
+// ids of records that have been in the trash for at least `retention_days`
+pub fn get_expired_trash(conn: &Connection, retention_days: u32) -> Result<Vec<Uuid>> {
+    debug!("get_expired_trash(retention_days={})", retention_days);
+    conn.prepare(
+        "SELECT id FROM financial_record
+         WHERE deleted_at IS NOT NULL
+           AND deleted_at <= strftime('%Y-%m-%dT%H:%M:%SZ', 'now', ?1)",
+    )?
+    .query_map(params![format!("-{} days", retention_days)], |row| row.get(0))?
+    .collect()
+}
+
+pub fn update_record(conn: &Connection, record: &FinancialRecord, actor: &str) -> Result<()> {
+    debug!("update_record({})", record);
+    with_savepoint(conn, || {
+        let before = match get_record_by_id(conn, &record.id) {
+            Ok(before) => before,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        conn.execute(
+            "UPDATE financial_record SET name = ?1, amount = ?2, frequency = ?3, record_type = ?4,
+                notes = ?5, payee = ?6, starts_on = ?7
+             WHERE id = ?8 AND deleted_at IS NULL",
+            params![
+                record.name,
+                record.amount,
+                record.frequency,
+                record.record_type,
+                record.notes,
+                record.payee,
+                record.starts_on,
+                record.id
+            ],
+        )?;
+        record_change(conn, &record.id, ChangeAction::Update, actor, Some(&before), Some(record))
+    })
+}
+
+// map a row selected with RECORD_COLUMNS (in that order) to a FinancialRecord
+fn record_from_row(row: &Row) -> Result<FinancialRecord> {
+    Ok(FinancialRecord {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        amount: row.get(2)?,
+        frequency: row.get(3)?,
+        record_type: row.get(4)?,
+        notes: row.get(5)?,
+        payee: row.get(6)?,
+        starts_on: row.get(7)?,
+    })
+}
+
+pub fn get_records_by_type(conn: &Connection, record_type: RecordType) -> Result<Vec<FinancialRecord>> {
+    debug!("getting records by type={}", record_type);
+    let sql = format!("SELECT {} FROM financial_record WHERE record_type = ?1 AND deleted_at IS NULL", RECORD_COLUMNS);
+    conn.prepare(&sql)?
+        .query_map(params![record_type], record_from_row)?
+        .collect()
+}
+
+#[allow(dead_code)]
+pub fn get_records_by_freq(conn: &Connection, freq: Frequency) -> Result<Vec<FinancialRecord>> {
+    debug!("getting records by frequency={}", freq);
+    let sql = format!("SELECT {} FROM financial_record WHERE frequency = ?1 AND deleted_at IS NULL", RECORD_COLUMNS);
+    conn.prepare(&sql)?
+        .query_map(params![freq], record_from_row)?
+        .collect()
+}
+
+// live records, optionally narrowed to one type and/or frequency, in a stable
+// order (type, then name) so repeated exports diff cleanly
+pub fn get_records_filtered(
+    conn: &Connection,
+    record_type: Option<RecordType>,
+    frequency: Option<Frequency>,
+) -> Result<Vec<FinancialRecord>> {
+    debug!("get_records_filtered(record_type={:?}, frequency={:?})", record_type, frequency);
+    let sql = format!(
+        "SELECT {} FROM financial_record
+         WHERE deleted_at IS NULL
+           AND (?1 IS NULL OR record_type = ?1)
+           AND (?2 IS NULL OR frequency = ?2)
+         ORDER BY record_type, name, id",
+        RECORD_COLUMNS
+    );
+    conn.prepare(&sql)?
+        .query_map(params![record_type, frequency], record_from_row)?
+        .collect()
+}
+
+pub fn get_records(conn: &Connection) -> Result<Vec<FinancialRecord>> {
+    debug!("getting all records");
+    let sql = format!("SELECT {} FROM financial_record WHERE deleted_at IS NULL", RECORD_COLUMNS);
+    let records: Vec<FinancialRecord> = conn
+        .prepare(&sql)?
+        .query_map([], record_from_row)?
+        // collect up the inner Results, then propagate any error with `?`
+        .collect::<Result<Vec<FinancialRecord>, rusqlite::Error>>()?;
+
+    Ok(records)
+}
+
+pub fn get_record_by_id(conn: &Connection, id: &Uuid) -> Result<FinancialRecord, rusqlite::Error> {
+    debug!("get_record_by_id(id={})", id);
+    conn.query_row(
+        &format!("SELECT {} FROM financial_record WHERE id = ?1 AND deleted_at IS NULL", RECORD_COLUMNS),
+        params![id],
+        record_from_row,
+    )
+}
+
+// Turn free user input into an FTS5 query: every whitespace separated term is
+// quoted (so punctuation like `*` or `"` can't break the syntax) and treated
+// as a prefix, so "netf" already finds "Netflix" while typing.
+fn to_match_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Full-text search over record names, notes and payees.
+///
+/// Hits are ordered best match first. A match in the name weighs more than
+/// one in the payee, which weighs more than one in the notes.
+pub fn search(conn: &Connection, query: &str, limit: usize) -> Result<Vec<SearchHit>> {
+    debug!("search(query={:?}, limit={})", query, limit);
+    let match_query = to_match_query(query);
+    if match_query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let sql = format!(
+        "SELECT {},
+            highlight(record_search, 1, ?2, ?3),
+            snippet(record_search, -1, ?2, ?3, '…', 12),
+            bm25(record_search, 0.0, 10.0, 2.0, 5.0) AS score
+         FROM record_search
+         JOIN financial_record ON financial_record.id = record_search.record_id
+         WHERE record_search MATCH ?1 AND financial_record.deleted_at IS NULL
+         ORDER BY score
+         LIMIT ?4",
+        RECORD_COLUMNS
+    );
+
+    conn.prepare(&sql)?
+        .query_map(
+            params![match_query, MATCH_START, MATCH_END, limit as i64],
+            |row| {
+                Ok(SearchHit {
+                    record: record_from_row(row)?,
+                    name_highlight: row.get(RECORD_COLUMN_COUNT)?,
+                    snippet: row.get(RECORD_COLUMN_COUNT + 1)?,
+                    rank: row.get(RECORD_COLUMN_COUNT + 2)?,
+                })
+            },
+        )?
+        .collect()
+}
+
+/// Rebuilds record_search if it has fallen out of step with financial_record.
+/// Returns whether it had.
+///
+/// Index rows are keyed by the rowid of their record, and SQLite may renumber
+/// the rowids of a table without an INTEGER PRIMARY KEY when it copies it:
+/// VACUUM, or anything else rewriting the file. Then the triggers update and
+/// delete the wrong index rows, or none. Checking costs a rowid lookup per
+/// record, so it's done whenever a database is opened or restored.
+pub(crate) fn sync_search_index(conn: &Connection) -> Result<bool> {
+    let in_step: bool = conn.query_row(
+        "SELECT NOT EXISTS (
+                SELECT 1 FROM financial_record
+                LEFT JOIN record_search ON record_search.rowid = financial_record.rowid
+                WHERE record_search.record_id IS NOT financial_record.id
+            )
+            AND (SELECT COUNT(*) FROM record_search) = (SELECT COUNT(*) FROM financial_record)",
+        [],
+        |row| row.get(0),
+    )?;
+    if in_step {
+        return Ok(false);
+    }
+
+    info!("Rebuilding the search index");
+    with_savepoint(conn, || {
+        conn.execute_batch(
+            "DELETE FROM record_search;
+            INSERT INTO record_search (rowid, record_id, name, notes, payee)
+            SELECT rowid, id, name, notes, payee FROM financial_record;",
+        )
+    })?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{FinancialRecord, Frequency, RecordType};
+    use uuid::Uuid;
+
+    fn setup_conn() -> Connection {
+        crate::db::init_db(":memory:").expect("failed to init memory db")
+    }
+
+    #[test]
+    fn test_insert_record() {
+        let conn = setup_conn();
+        let record = FinancialRecord {
+            id: Uuid::new_v4(),
+            name: "Test Income".into(),
+            amount: 1000.0,
+            frequency: Frequency::Monthly,
+            record_type: RecordType::Income,
+            notes: None,
+            payee: None,
+            starts_on: None,
+        };
+
+        insert_record(&conn, &record, "test").expect("Insert failed");
+
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM financial_record", [], |row| row.get(0))
+            .unwrap();
+
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_delete_record() {
+        let conn = setup_conn();
+        let record = FinancialRecord {
+            id: Uuid::new_v4(),
+            name: "To Delete".into(),
+            amount: 200.0,
+            frequency: Frequency::Weekly,
+            record_type: RecordType::Expense,
+            notes: None,
+            payee: None,
+            starts_on: None,
+        };
+        insert_record(&conn, &record, "test").unwrap();
+        assert!(delete_record(&conn, &record.id, "test").unwrap());
+
+        // soft deleted: hidden from queries but still in the table
+        assert!(get_records(&conn).unwrap().is_empty());
+        assert!(get_record_by_id(&conn, &record.id).is_err());
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM financial_record", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_restore_record() {
+        let conn = setup_conn();
+        let record = FinancialRecord::new("Oops", 10.0, Frequency::Weekly, RecordType::Expense);
+        insert_record(&conn, &record, "test").unwrap();
+        delete_record(&conn, &record.id, "test").unwrap();
+
+        let trash = get_trashed_records(&conn).unwrap();
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].record, record);
+
+        assert!(restore_record(&conn, &record.id, "test").unwrap());
+        assert!(!restore_record(&conn, &record.id, "test").unwrap());
+        assert_eq!(get_record_by_id(&conn, &record.id).unwrap(), record);
+        assert!(get_trashed_records(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_purge_only_removes_trashed_records() {
+        let conn = setup_conn();
+        let record = FinancialRecord::new("Old Bill", 10.0, Frequency::Monthly, RecordType::Expense);
+        insert_record(&conn, &record, "test").unwrap();
+
+        assert!(!purge_record(&conn, &record.id, "test").unwrap());
+        delete_record(&conn, &record.id, "test").unwrap();
+        assert!(purge_record(&conn, &record.id, "test").unwrap());
+
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM financial_record", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_get_expired_trash() {
+        let conn = setup_conn();
+        let old = FinancialRecord::new("Old", 10.0, Frequency::Monthly, RecordType::Expense);
+        let recent = FinancialRecord::new("Recent", 10.0, Frequency::Monthly, RecordType::Expense);
+        insert_record(&conn, &old, "test").unwrap();
+        insert_record(&conn, &recent, "test").unwrap();
+        delete_record(&conn, &recent.id, "test").unwrap();
+        conn.execute(
+            "UPDATE financial_record SET deleted_at = '2020-01-01T00:00:00Z' WHERE id = ?1",
+            params![old.id],
+        )
+        .unwrap();
+
+        assert_eq!(get_expired_trash(&conn, 30).unwrap(), vec![old.id]);
+    }
+
+    #[test]
+    fn test_update_record() {
+        let conn = setup_conn();
+        let mut record = FinancialRecord {
+            id: Uuid::new_v4(),
+            name: "Old Name".into(),
+            amount: 50.0,
+            frequency: Frequency::Daily,
+            record_type: RecordType::Expense,
+            notes: None,
+            payee: None,
+            starts_on: None,
+        };
+        insert_record(&conn, &record, "test").unwrap();
+
+        // Update name and amount
+        record.name = "Updated Name".into();
+        record.amount = 75.0;
+        update_record(&conn, &record, "test").unwrap();
+
+        let (name, amount): (String, f64) = conn
+            .query_row(
+                "SELECT name, amount FROM financial_record WHERE id = ?1",
+                [record.id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+
+        assert_eq!(name, "Updated Name");
+        assert_eq!(amount, 75.0);
+    }
+
+    #[test]
+    fn test_get_records_filtered() {
+        let conn = setup_conn();
+        let rent = FinancialRecord::new("Rent", 1500.0, Frequency::Monthly, RecordType::Expense);
+        let gym = FinancialRecord::new("Gym", 30.0, Frequency::Monthly, RecordType::Expense);
+        let salary = FinancialRecord::new("Salary", 4000.0, Frequency::Monthly, RecordType::Income);
+        let insurance = FinancialRecord::new("Insurance", 600.0, Frequency::Yearly, RecordType::Expense);
+        for r in [&rent, &gym, &salary, &insurance] {
+            insert_record(&conn, r, "test").unwrap();
+        }
+
+        let expenses = get_records_filtered(&conn, Some(RecordType::Expense), Some(Frequency::Monthly)).unwrap();
+        assert_eq!(expenses, vec![gym.clone(), rent.clone()]);
+        assert_eq!(get_records_filtered(&conn, None, None).unwrap().len(), 4);
+        assert_eq!(get_records_filtered(&conn, None, Some(Frequency::Yearly)).unwrap(), vec![insurance]);
+    }
+
+    #[test]
+    fn test_search_ranks_name_matches_first() {
+        let conn = setup_conn();
+        let mut netflix = FinancialRecord::new("Netflix", 15.49, Frequency::Monthly, RecordType::Expense);
+        netflix.payee = Some("NETFLIX.COM".into());
+        let mut groceries = FinancialRecord::new("Groceries", 400.0, Frequency::Monthly, RecordType::Expense);
+        groceries.notes = Some("cancel netflix before the trial ends".into());
+        let rent = FinancialRecord::new("Rent", 1500.0, Frequency::Monthly, RecordType::Expense);
+        for r in [&netflix, &groceries, &rent] {
+            insert_record(&conn, r, "test").unwrap();
+        }
+
+        let hits = search(&conn, "netfl", 10).unwrap();
+
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].record.id, netflix.id);
+        assert_eq!(hits[0].name_highlight, format!("{}Netflix{}", MATCH_START, MATCH_END));
+        assert_eq!(hits[1].record.id, groceries.id);
+        assert!(hits[1].snippet.contains(&format!("{}netflix{}", MATCH_START, MATCH_END)));
+    }
+
+    #[test]
+    fn test_search_index_follows_updates_and_deletes() {
+        let conn = setup_conn();
+        let mut record = FinancialRecord::new("Gym", 30.0, Frequency::Monthly, RecordType::Expense);
+        insert_record(&conn, &record, "test").unwrap();
+
+        record.name = "Climbing Gym".into();
+        update_record(&conn, &record, "test").unwrap();
+        assert_eq!(search(&conn, "climbing", 10).unwrap().len(), 1);
+
+        delete_record(&conn, &record.id, "test").unwrap();
+        assert!(search(&conn, "climbing", 10).unwrap().is_empty());
+
+        restore_record(&conn, &record.id, "test").unwrap();
+        assert_eq!(search(&conn, "climbing", 10).unwrap().len(), 1);
+
+        delete_record(&conn, &record.id, "test").unwrap();
+        purge_record(&conn, &record.id, "test").unwrap();
+        let indexed: i64 = conn
+            .query_row("SELECT COUNT(*) FROM record_search", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(indexed, 0);
+    }
+
+    #[test]
+    fn test_search_index_survives_vacuum() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("budget.db");
+        let path = path.to_str().unwrap();
+        let conn = crate::db::init_db(path).unwrap();
+        let add = |name: &str| {
+            let record = FinancialRecord::new(name, 10.0, Frequency::Monthly, RecordType::Expense);
+            insert_record(&conn, &record, "test").unwrap();
+            record
+        };
+        let (gym, mut water) = (add("Gym"), add("Water"));
+        add("Phone");
+        delete_record(&conn, &gym.id, "test").unwrap();
+        purge_record(&conn, &gym.id, "test").unwrap();
+        // VACUUM is free to close the gap Gym left in the rowids, though
+        // this SQLite keeps them; renumber them the way it may
+        conn.execute_batch("VACUUM; UPDATE financial_record SET rowid = rowid + 10").unwrap();
+        drop(conn);
+
+        let conn = crate::db::init_db(path).unwrap();
+        assert!(!sync_search_index(&conn).unwrap());
+        water.name = "Electricity".into();
+        update_record(&conn, &water, "test").unwrap();
+
+        let hits = |query: &str| -> Vec<String> {
+            search(&conn, query, 10).unwrap().into_iter().map(|hit| hit.record.name).collect()
+        };
+        assert_eq!(hits("electricity"), ["Electricity"]);
+        assert!(hits("water").is_empty());
+        assert_eq!(hits("phone"), ["Phone"]);
+    }
+
+    #[test]
+    fn test_search_tolerates_fts_syntax_in_query() {
+        let conn = setup_conn();
+        insert_record(&conn, &FinancialRecord::new("AT&T \"Unlimited\"", 80.0, Frequency::Monthly, RecordType::Expense), "test").unwrap();
+
+        assert_eq!(search(&conn, "\"unlim", 10).unwrap().len(), 1);
+        assert!(search(&conn, "  ", 10).unwrap().is_empty());
+        assert!(search(&conn, "OR NOT *", 10).unwrap().is_empty());
+    }
+}
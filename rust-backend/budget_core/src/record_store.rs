@@ -0,0 +1,223 @@
+use crate::models::{ChangeAction, FinancialRecord, Frequency, HistoryEntry, JournalEntry, RecordType, SearchHit, TrashedRecord};
+use crate::db::with_savepoint;
+use crate::record_repository;
+use crate::history_repository;
+use crate::journal_repository;
+
+use rusqlite::{Connection, Result};
+use std::ops::Deref;
+use std::sync::{Mutex, PoisonError};
+use uuid::Uuid;
+
+/// Where budget records live, along with their change history and the undo
+/// journal.
+///
+/// The SQLite [`Connection`] is the store the apps use; [`MemoryStore`]
+/// keeps everything in memory. Services get at a store through a
+/// [`StoreHandle`] for each call.
+///
+/// Errors are `rusqlite` errors whatever the backend, and a lookup that
+/// finds nothing fails with [`rusqlite::Error::QueryReturnedNoRows`], so
+/// services can treat every store alike.
+///
+/// Every mutation is logged to the history in the same unit of work as the
+/// change itself, attributed to `actor`.
+///
+/// [`MemoryStore`]: crate::memory_store::MemoryStore
+pub trait RecordStore: Send + 'static {
+    /// Runs `f` as a single unit: everything it changed through this store is
+    /// kept if it returns Ok and undone if it returns Err. Calls nest.
+    fn atomically<T, E: From<rusqlite::Error>>(
+        &self,
+        f: impl FnOnce() -> std::result::Result<T, E>,
+    ) -> std::result::Result<T, E>;
+
+    fn insert_record(&self, record: &FinancialRecord, actor: &str) -> Result<()>;
+    /// Replaces a live record. Does nothing if there is no live record with
+    /// its id.
+    fn update_record(&self, record: &FinancialRecord, actor: &str) -> Result<()>;
+    /// Moves a record to the trash. Returns false if there was no live record
+    /// with that id.
+    fn delete_record(&self, id: &Uuid, actor: &str) -> Result<bool>;
+    /// Takes a record back out of the trash. Returns false if it wasn't
+    /// trashed.
+    fn restore_record(&self, id: &Uuid, actor: &str) -> Result<bool>;
+    /// Permanently removes a trashed record. Returns false if nothing was
+    /// removed.
+    fn purge_record(&self, id: &Uuid, actor: &str) -> Result<bool>;
+
+    fn get_record_by_id(&self, id: &Uuid) -> Result<FinancialRecord>;
+    /// a record that is in the trash, by id
+    fn get_trashed_record(&self, id: &Uuid) -> Result<FinancialRecord>;
+    /// every live record
+    fn get_records(&self) -> Result<Vec<FinancialRecord>>;
+    /// live records, optionally narrowed to one type and/or frequency, by
+    /// type, then name
+    fn get_records_filtered(
+        &self,
+        record_type: Option<RecordType>,
+        frequency: Option<Frequency>,
+    ) -> Result<Vec<FinancialRecord>>;
+    /// the trash, most recently deleted first
+    fn get_trashed_records(&self) -> Result<Vec<TrashedRecord>>;
+    /// ids of records that have been in the trash for at least
+    /// `retention_days`
+    fn get_expired_trash(&self, retention_days: u32) -> Result<Vec<Uuid>>;
+    /// Full-text search over record names, notes and payees, best match
+    /// first. Every term of `query` is matched as a word prefix.
+    fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>>;
+
+    /// every change to one record, oldest first
+    fn get_history_for_record(&self, record_id: &Uuid) -> Result<Vec<HistoryEntry>>;
+    /// Most recent changes across all records, newest first. Pass the
+    /// smallest id of the previous page as `before_id` to page further back.
+    fn get_activity(&self, limit: usize, before_id: Option<i64>) -> Result<Vec<HistoryEntry>>;
+
+    /// Pushes an operation on the undo stack, dropping the redo stack.
+    fn push_journal(
+        &self,
+        record_id: &Uuid,
+        action: ChangeAction,
+        actor: &str,
+        before: Option<&FinancialRecord>,
+        after: Option<&FinancialRecord>,
+    ) -> Result<()>;
+    /// top `n` of the undo stack, most recent first
+    fn get_undoable(&self, n: usize) -> Result<Vec<JournalEntry>>;
+    /// top `n` of the redo stack, most recently undone first
+    fn get_redoable(&self, n: usize) -> Result<Vec<JournalEntry>>;
+    fn set_undone(&self, id: i64, undone: bool) -> Result<()>;
+}
+
+/// Shared access to a [`RecordStore`], as handed to the services: reads go
+/// through [`read`](Self::read), anything that changes the store through
+/// [`write`](Self::write).
+///
+/// For the SQLite database that's a [`Pool`](crate::pool::Pool), where reads
+/// run alongside each other and alongside a write. Any store can also be
+/// shared behind a plain mutex ([`Shared`](crate::types::Shared)), which
+/// takes calls one at a time.
+pub trait StoreHandle: Clone + Send + Sync + 'static {
+    type Store: RecordStore;
+
+    fn read(&self) -> Result<impl Deref<Target = Self::Store> + '_>;
+    fn write(&self) -> Result<impl Deref<Target = Self::Store> + '_>;
+}
+
+impl StoreHandle for crate::pool::Pool {
+    type Store = Connection;
+
+    fn read(&self) -> Result<impl Deref<Target = Connection> + '_> {
+        crate::pool::Pool::read(self)
+    }
+
+    fn write(&self) -> Result<impl Deref<Target = Connection> + '_> {
+        crate::pool::Pool::write(self)
+    }
+}
+
+impl<S: RecordStore> StoreHandle for crate::types::Shared<S> {
+    type Store = S;
+
+    fn read(&self) -> Result<impl Deref<Target = S> + '_> {
+        self.write()
+    }
+
+    // a panic elsewhere doesn't lock everyone else out for good
+    fn write(&self) -> Result<impl Deref<Target = S> + '_> {
+        Ok(Mutex::lock(self).unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+// The SQLite store is the repositories
+impl RecordStore for Connection {
+    fn atomically<T, E: From<rusqlite::Error>>(
+        &self,
+        f: impl FnOnce() -> std::result::Result<T, E>,
+    ) -> std::result::Result<T, E> {
+        with_savepoint(self, f)
+    }
+
+    fn insert_record(&self, record: &FinancialRecord, actor: &str) -> Result<()> {
+        record_repository::insert_record(self, record, actor)
+    }
+
+    fn update_record(&self, record: &FinancialRecord, actor: &str) -> Result<()> {
+        record_repository::update_record(self, record, actor)
+    }
+
+    fn delete_record(&self, id: &Uuid, actor: &str) -> Result<bool> {
+        record_repository::delete_record(self, id, actor)
+    }
+
+    fn restore_record(&self, id: &Uuid, actor: &str) -> Result<bool> {
+        record_repository::restore_record(self, id, actor)
+    }
+
+    fn purge_record(&self, id: &Uuid, actor: &str) -> Result<bool> {
+        record_repository::purge_record(self, id, actor)
+    }
+
+    fn get_record_by_id(&self, id: &Uuid) -> Result<FinancialRecord> {
+        record_repository::get_record_by_id(self, id)
+    }
+
+    fn get_trashed_record(&self, id: &Uuid) -> Result<FinancialRecord> {
+        record_repository::get_trashed_record(self, id)
+    }
+
+    fn get_records(&self) -> Result<Vec<FinancialRecord>> {
+        record_repository::get_records(self)
+    }
+
+    fn get_records_filtered(
+        &self,
+        record_type: Option<RecordType>,
+        frequency: Option<Frequency>,
+    ) -> Result<Vec<FinancialRecord>> {
+        record_repository::get_records_filtered(self, record_type, frequency)
+    }
+
+    fn get_trashed_records(&self) -> Result<Vec<TrashedRecord>> {
+        record_repository::get_trashed_records(self)
+    }
+
+    fn get_expired_trash(&self, retention_days: u32) -> Result<Vec<Uuid>> {
+        record_repository::get_expired_trash(self, retention_days)
+    }
+
+    fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        record_repository::search(self, query, limit)
+    }
+
+    fn get_history_for_record(&self, record_id: &Uuid) -> Result<Vec<HistoryEntry>> {
+        history_repository::get_history_for_record(self, record_id)
+    }
+
+    fn get_activity(&self, limit: usize, before_id: Option<i64>) -> Result<Vec<HistoryEntry>> {
+        history_repository::get_activity(self, limit, before_id)
+    }
+
+    fn push_journal(
+        &self,
+        record_id: &Uuid,
+        action: ChangeAction,
+        actor: &str,
+        before: Option<&FinancialRecord>,
+        after: Option<&FinancialRecord>,
+    ) -> Result<()> {
+        journal_repository::push(self, record_id, action, actor, before, after)
+    }
+
+    fn get_undoable(&self, n: usize) -> Result<Vec<JournalEntry>> {
+        journal_repository::get_undoable(self, n)
+    }
+
+    fn get_redoable(&self, n: usize) -> Result<Vec<JournalEntry>> {
+        journal_repository::get_redoable(self, n)
+    }
+
+    fn set_undone(&self, id: i64, undone: bool) -> Result<()> {
+        journal_repository::set_undone(self, id, undone)
+    }
+}
@@ -0,0 +1,214 @@
+use crate::models::{FinancialRecord, Frequency, LedgerEntry, RecordType, RecurringSuggestion};
+use crate::record_matcher::{self, normalize_payee};
+
+use chrono::{Days, Months, NaiveDate};
+use std::collections::BTreeMap;
+
+// fewer transactions than this don't make a pattern...
+const MIN_OCCURRENCES: usize = 3;
+// ...except for quarterly and yearly ones, where a couple of years of
+// statements only hold a few
+const MIN_OCCURRENCES_LONG_PERIOD: usize = 2;
+// an interval this far off the frequency's length still counts as on time
+const INTERVAL_TOLERANCE: f64 = 0.25;
+// an amount this far off the typical one still counts as the same bill
+const AMOUNT_TOLERANCE: f64 = 0.10;
+// suggestions below this confidence aren't shown
+pub const MIN_CONFIDENCE: f64 = 0.6;
+
+const TIMING_WEIGHT: f64 = 0.6;
+const AMOUNT_WEIGHT: f64 = 0.4;
+
+// average length of each frequency in days
+fn period_days(frequency: Frequency) -> f64 {
+    365.25 / frequency.occurrences_per_year()
+}
+
+// the frequency whose length the interval is within tolerance of, if any
+fn frequency_for(interval_days: f64) -> Option<Frequency> {
+    [Frequency::Daily, Frequency::Weekly, Frequency::Monthly, Frequency::Quarterly, Frequency::Yearly]
+        .into_iter()
+        .find(|f| ((interval_days - period_days(*f)) / period_days(*f)).abs() <= INTERVAL_TOLERANCE)
+}
+
+fn next_date(last: NaiveDate, frequency: Frequency) -> Option<NaiveDate> {
+    match frequency {
+        Frequency::Daily => last.checked_add_days(Days::new(1)),
+        Frequency::Weekly => last.checked_add_days(Days::new(7)),
+        Frequency::Monthly => last.checked_add_months(Months::new(1)),
+        Frequency::Quarterly => last.checked_add_months(Months::new(3)),
+        Frequency::Yearly => last.checked_add_months(Months::new(12)),
+    }
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+fn title_case(words: &str) -> String {
+    words
+        .split(' ')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Propose recurring records from ledger transactions.
+///
+/// Transactions not yet linked to a record are grouped by normalized name
+/// and direction (money in or out). A group becomes a suggestion when its
+/// typical interval is close to one of the `Frequency` lengths and both the
+/// timing and the amounts are regular enough, unless an existing record
+/// already covers it. Most confident first.
+pub fn detect(entries: &[LedgerEntry], records: &[FinancialRecord]) -> Vec<RecurringSuggestion> {
+    let mut groups: BTreeMap<String, Vec<&LedgerEntry>> = BTreeMap::new();
+    for entry in entries.iter().filter(|e| e.record_id.is_none() && e.amount != 0.0) {
+        let payee = normalize_payee(entry.display_name());
+        if payee.is_empty() {
+            continue;
+        }
+        let direction = if entry.amount > 0.0 { "in" } else { "out" };
+        groups.entry(format!("{}:{}", direction, payee)).or_default().push(entry);
+    }
+
+    let mut suggestions: Vec<RecurringSuggestion> = groups
+        .into_iter()
+        .filter_map(|(key, group)| suggest(key, group, records))
+        .filter(|s| s.confidence >= MIN_CONFIDENCE)
+        .collect();
+    suggestions.sort_by(|a, b| b.confidence.total_cmp(&a.confidence).then_with(|| a.key.cmp(&b.key)));
+    suggestions
+}
+
+fn suggest(key: String, mut group: Vec<&LedgerEntry>, records: &[FinancialRecord]) -> Option<RecurringSuggestion> {
+    if group.len() < MIN_OCCURRENCES_LONG_PERIOD {
+        return None;
+    }
+    group.sort_by_key(|e| e.posted_on);
+    let first = group.first()?;
+    let last = group.last()?;
+
+    let intervals: Vec<f64> = group
+        .windows(2)
+        .map(|pair| (pair[1].posted_on - pair[0].posted_on).num_days() as f64)
+        .collect();
+    let frequency = frequency_for(median(&mut intervals.clone()))?;
+    let needed = match frequency {
+        Frequency::Quarterly | Frequency::Yearly => MIN_OCCURRENCES_LONG_PERIOD,
+        _ => MIN_OCCURRENCES,
+    };
+    if group.len() < needed {
+        return None;
+    }
+
+    let period = period_days(frequency);
+    let on_time = intervals
+        .iter()
+        .filter(|days| ((*days - period) / period).abs() <= INTERVAL_TOLERANCE)
+        .count();
+    let timing = on_time as f64 / intervals.len() as f64;
+
+    let amount = median(&mut group.iter().map(|e| e.amount.abs()).collect::<Vec<_>>());
+    let stable = group
+        .iter()
+        .filter(|e| (e.amount.abs() - amount).abs() <= (amount * AMOUNT_TOLERANCE).max(1.0))
+        .count();
+    let stability = stable as f64 / group.len() as f64;
+
+    // already budgeted
+    if record_matcher::best_match(records, last).is_some_and(|m| m.score >= record_matcher::AUTO_LINK_THRESHOLD) {
+        return None;
+    }
+
+    let record_type = match last.record_type {
+        Some(record_type) => record_type,
+        None if last.amount > 0.0 => RecordType::Income,
+        None => RecordType::Expense,
+    };
+    let name = title_case(key.split_once(':').map(|(_, payee)| payee).unwrap_or(&key));
+    let mut record = FinancialRecord::new(name, (amount * 100.0).round() / 100.0, frequency, record_type);
+    record.payee = Some(last.payee.clone());
+
+    Some(RecurringSuggestion {
+        record,
+        occurrences: group.len(),
+        first_seen: first.posted_on,
+        last_seen: last.posted_on,
+        next_expected: next_date(last.posted_on, frequency)?,
+        confidence: TIMING_WEIGHT * timing + AMOUNT_WEIGHT * stability,
+        entry_ids: group.iter().map(|e| e.id).collect(),
+        key,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(payee: &str, date: (i32, u32, u32), amount: f64) -> LedgerEntry {
+        LedgerEntry::new("acct", NaiveDate::from_ymd_opt(date.0, date.1, date.2).unwrap(), amount, payee)
+    }
+
+    #[test]
+    fn test_detects_monthly_and_yearly_bills() {
+        let entries = vec![
+            entry("NETFLIX.COM 866-579", (2025, 1, 3), -15.49),
+            entry("NETFLIX.COM 866-579", (2025, 2, 4), -15.49),
+            entry("NETFLIX.COM 866-580", (2025, 3, 3), -15.49),
+            entry("NETFLIX.COM 866-579", (2025, 4, 2), -17.99),
+            entry("STATE FARM INS", (2024, 6, 10), -820.0),
+            entry("STATE FARM INS", (2025, 6, 9), -845.0),
+            entry("ACME PAYROLL", (2025, 1, 10), 1200.0),
+            entry("ACME PAYROLL", (2025, 1, 24), 1200.0),
+            entry("ACME PAYROLL", (2025, 2, 7), 1200.0),
+        ];
+
+        let suggestions = detect(&entries, &[]);
+        let found: Vec<(&str, Frequency, RecordType, f64)> = suggestions
+            .iter()
+            .map(|s| (s.record.name.as_str(), s.record.frequency, s.record.record_type, s.record.amount))
+            .collect();
+
+        assert!(found.contains(&("Netflix Com", Frequency::Monthly, RecordType::Expense, 15.49)), "{:?}", found);
+        assert!(found.contains(&("State Farm Ins", Frequency::Yearly, RecordType::Expense, 832.5)), "{:?}", found);
+        // every two weeks is no Frequency this app has
+        assert!(!found.iter().any(|f| f.0 == "Acme Payroll"), "{:?}", found);
+
+        let netflix = suggestions.iter().find(|s| s.key == "out:netflix com").unwrap();
+        assert_eq!((netflix.occurrences, netflix.entry_ids.len()), (4, 4));
+        assert_eq!(netflix.next_expected, NaiveDate::from_ymd_opt(2025, 5, 2).unwrap());
+        assert_eq!(netflix.record.payee.as_deref(), Some("NETFLIX.COM 866-579"));
+    }
+
+    #[test]
+    fn test_skips_irregular_and_budgeted_payees() {
+        let coffee = vec![
+            entry("SQ *COFFEE 1234", (2025, 1, 2), -4.50),
+            entry("SQ *COFFEE 1234", (2025, 1, 19), -12.00),
+            entry("SQ *COFFEE 1234", (2025, 3, 30), -3.75),
+        ];
+        assert!(detect(&coffee, &[]).is_empty());
+
+        let gym = vec![
+            entry("CITY GYM", (2025, 1, 5), -60.0),
+            entry("CITY GYM", (2025, 2, 5), -60.0),
+            entry("CITY GYM", (2025, 3, 5), -60.0),
+        ];
+        assert_eq!(detect(&gym, &[]).len(), 1);
+        let budgeted = FinancialRecord::new("City Gym", 60.0, Frequency::Monthly, RecordType::Expense);
+        assert!(detect(&gym, &[budgeted]).is_empty());
+
+        let mut linked = gym.clone();
+        linked.iter_mut().for_each(|e| e.record_id = Some(uuid::Uuid::new_v4()));
+        assert!(detect(&linked, &[]).is_empty());
+    }
+}
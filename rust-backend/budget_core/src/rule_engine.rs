@@ -0,0 +1,166 @@
+use crate::models::{LedgerEntry, Rule};
+use crate::app_error::AppError;
+
+use regex::Regex;
+
+// A rule with its regex compiled, ready to run over many entries
+pub struct CompiledRule {
+    pub rule: Rule,
+    regex: Option<Regex>,
+    // lowercased once instead of per entry
+    contains: Option<String>,
+}
+
+impl CompiledRule {
+    pub fn new(rule: Rule) -> Result<Self, AppError> {
+        let regex = rule
+            .payee_regex
+            .as_deref()
+            .map(Regex::new)
+            .transpose()
+            .map_err(|e| AppError(format!("rule `{}`: invalid payee regex: {}", rule.name, e)))?;
+        let contains = rule.payee_contains.as_deref().map(str::to_lowercase);
+        Ok(Self { rule, regex, contains })
+    }
+
+    pub fn matches(&self, entry: &LedgerEntry) -> bool {
+        let rule = &self.rule;
+        rule.enabled
+            && rule.has_conditions()
+            && self.contains.as_ref().is_none_or(|needle| entry.payee.to_lowercase().contains(needle))
+            && self.regex.as_ref().is_none_or(|regex| regex.is_match(&entry.payee))
+            && rule.min_amount.is_none_or(|min| entry.amount.abs() >= min)
+            && rule.max_amount.is_none_or(|max| entry.amount.abs() <= max)
+            && rule.account.as_ref().is_none_or(|account| entry.account.eq_ignore_ascii_case(account))
+    }
+
+    pub fn apply(&self, entry: &mut LedgerEntry) {
+        let rule = &self.rule;
+        if let Some(name) = &rule.set_name {
+            entry.name = Some(name.clone());
+        }
+        if let Some(record_type) = rule.set_record_type {
+            entry.record_type = Some(record_type);
+        }
+        if let Some(category) = &rule.set_category {
+            entry.category = Some(category.clone());
+        }
+        for tag in &rule.add_tags {
+            if !entry.tags.contains(tag) {
+                entry.tags.push(tag.clone());
+            }
+        }
+    }
+}
+
+// Checks a rule before it's saved: it needs a name, a condition, an action,
+// a valid regex and a sensible amount range
+pub fn validate(rule: &Rule) -> Result<(), AppError> {
+    if rule.name.trim().is_empty() {
+        return Err(AppError("a rule needs a name".to_string()));
+    }
+    if !rule.has_conditions() {
+        return Err(AppError(format!("rule `{}` has no conditions, it would never match", rule.name)));
+    }
+    if !rule.has_actions() {
+        return Err(AppError(format!("rule `{}` has no actions, it would do nothing", rule.name)));
+    }
+    if let (Some(min), Some(max)) = (rule.min_amount, rule.max_amount)
+        && min > max
+    {
+        return Err(AppError(format!("rule `{}`: minimum amount {} is above maximum {}", rule.name, min, max)));
+    }
+    CompiledRule::new(rule.clone()).map(|_| ())
+}
+
+pub fn compile(rules: Vec<Rule>) -> Result<Vec<CompiledRule>, AppError> {
+    rules.into_iter().map(CompiledRule::new).collect()
+}
+
+/// Classify an entry from scratch: clears what earlier runs set, then applies
+/// every matching rule in order, later ones overriding earlier ones (tags
+/// accumulate). Returns whether the entry's classification changed.
+pub fn apply(rules: &[CompiledRule], entry: &mut LedgerEntry) -> bool {
+    let before = (entry.name.take(), entry.record_type.take(), entry.category.take(), std::mem::take(&mut entry.tags));
+    for rule in rules {
+        if rule.matches(entry) {
+            rule.apply(entry);
+        }
+    }
+    before != (entry.name.clone(), entry.record_type, entry.category.clone(), entry.tags.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RecordType;
+    use chrono::NaiveDate;
+
+    fn entry(payee: &str, amount: f64) -> LedgerEntry {
+        LedgerEntry::new("Checking", NaiveDate::from_ymd_opt(2025, 1, 3).unwrap(), amount, payee)
+    }
+
+    fn coffee_rule() -> Rule {
+        let mut rule = Rule::new("Coffee shops");
+        rule.payee_regex = Some(r"(?i)^SQ \*COFFEE".to_string());
+        rule.max_amount = Some(20.0);
+        rule.set_name = Some("Coffee".to_string());
+        rule.set_record_type = Some(RecordType::Expense);
+        rule.set_category = Some("Food:Coffee".to_string());
+        rule.add_tags = vec!["treats".to_string()];
+        rule
+    }
+
+    #[test]
+    fn test_conditions_must_all_match() {
+        let rule = CompiledRule::new(coffee_rule()).unwrap();
+        assert!(rule.matches(&entry("SQ *COFFEE 1234", -4.50)));
+        assert!(!rule.matches(&entry("SQ *COFFEE 1234", -45.0)));
+        assert!(!rule.matches(&entry("STARBUCKS", -4.50)));
+
+        let mut account_only = Rule::new("Card");
+        account_only.account = Some("checking".to_string());
+        assert!(CompiledRule::new(account_only.clone()).unwrap().matches(&entry("anything", 1.0)));
+        account_only.enabled = false;
+        assert!(!CompiledRule::new(account_only).unwrap().matches(&entry("anything", 1.0)));
+        // no conditions never matches
+        assert!(!CompiledRule::new(Rule::new("Empty")).unwrap().matches(&entry("anything", 1.0)));
+    }
+
+    #[test]
+    fn test_apply_overrides_in_order_and_reruns_cleanly() {
+        let mut tag_small = Rule::new("Small");
+        tag_small.max_amount = Some(10.0);
+        tag_small.set_category = Some("Small stuff".to_string());
+        tag_small.add_tags = vec!["small".to_string(), "treats".to_string()];
+        let rules = compile(vec![coffee_rule(), tag_small]).unwrap();
+
+        let mut coffee = entry("SQ *COFFEE 1234", -4.50);
+        assert!(apply(&rules, &mut coffee));
+        assert_eq!(coffee.display_name(), "Coffee");
+        assert_eq!(coffee.category.as_deref(), Some("Small stuff"));
+        assert_eq!(coffee.tags, vec!["treats", "small"]);
+        // running again with the same rules changes nothing
+        assert!(!apply(&rules, &mut coffee));
+        // with no rules left the entry goes back to unclassified
+        assert!(apply(&[], &mut coffee));
+        assert_eq!((coffee.display_name(), coffee.tags.len()), ("SQ *COFFEE 1234", 0));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(&coffee_rule()).is_ok());
+        let mut bad = coffee_rule();
+        bad.payee_regex = Some("(unclosed".to_string());
+        assert!(validate(&bad).is_err());
+        let mut bad = coffee_rule();
+        bad.min_amount = Some(30.0);
+        assert!(validate(&bad).is_err());
+        let mut bad = coffee_rule();
+        bad.set_name = None;
+        bad.set_record_type = None;
+        bad.set_category = None;
+        bad.add_tags.clear();
+        assert!(validate(&bad).is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use crate::models::Rule;
+use crate::ledger_repository::{tags_from_row, tags_to_json};
+
+use log::debug;
+use rusqlite::{params, Connection, Result, Row};
+use uuid::Uuid;
+
+const RULE_COLUMNS: &str = "id, name, position, enabled, payee_contains, payee_regex, min_amount, max_amount,
+    account, set_name, set_record_type, set_category, add_tags";
+
+fn rule_from_row(row: &Row) -> Result<Rule> {
+    Ok(Rule {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        position: row.get(2)?,
+        enabled: row.get(3)?,
+        payee_contains: row.get(4)?,
+        payee_regex: row.get(5)?,
+        min_amount: row.get(6)?,
+        max_amount: row.get(7)?,
+        account: row.get(8)?,
+        set_name: row.get(9)?,
+        set_record_type: row.get(10)?,
+        set_category: row.get(11)?,
+        add_tags: tags_from_row(row, 12)?,
+    })
+}
+
+pub fn insert_rule(conn: &Connection, rule: &Rule) -> Result<()> {
+    debug!("insert_rule(name={})", rule.name);
+    conn.execute(
+        &format!("INSERT INTO rule ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)", RULE_COLUMNS),
+        params![
+            &rule.id,
+            &rule.name,
+            &rule.position,
+            &rule.enabled,
+            &rule.payee_contains,
+            &rule.payee_regex,
+            &rule.min_amount,
+            &rule.max_amount,
+            &rule.account,
+            &rule.set_name,
+            &rule.set_record_type,
+            &rule.set_category,
+            tags_to_json(&rule.add_tags)
+        ],
+    )?;
+    Ok(())
+}
+
+// Returns false if there is no such rule
+pub fn update_rule(conn: &Connection, rule: &Rule) -> Result<bool> {
+    debug!("update_rule(id={})", rule.id);
+    let updated = conn.execute(
+        "UPDATE rule SET name = ?2, position = ?3, enabled = ?4, payee_contains = ?5, payee_regex = ?6,
+            min_amount = ?7, max_amount = ?8, account = ?9, set_name = ?10, set_record_type = ?11,
+            set_category = ?12, add_tags = ?13
+        WHERE id = ?1",
+        params![
+            &rule.id,
+            &rule.name,
+            &rule.position,
+            &rule.enabled,
+            &rule.payee_contains,
+            &rule.payee_regex,
+            &rule.min_amount,
+            &rule.max_amount,
+            &rule.account,
+            &rule.set_name,
+            &rule.set_record_type,
+            &rule.set_category,
+            tags_to_json(&rule.add_tags)
+        ],
+    )?;
+    Ok(updated > 0)
+}
+
+pub fn delete_rule(conn: &Connection, id: &Uuid) -> Result<bool> {
+    debug!("delete_rule(id={})", id);
+    Ok(conn.execute("DELETE FROM rule WHERE id = ?1", params![id])? > 0)
+}
+
+pub fn get_rule_by_id(conn: &Connection, id: &Uuid) -> Result<Rule> {
+    debug!("get_rule_by_id(id={})", id);
+    conn.query_row(
+        &format!("SELECT {} FROM rule WHERE id = ?1", RULE_COLUMNS),
+        params![id],
+        rule_from_row,
+    )
+}
+
+// in the order they are applied
+pub fn get_rules(conn: &Connection) -> Result<Vec<Rule>> {
+    debug!("get_rules()");
+    conn.prepare(&format!("SELECT {} FROM rule ORDER BY position, created_at, id", RULE_COLUMNS))?
+        .query_map([], rule_from_row)?
+        .collect()
+}
+
+// position that puts a new rule after all existing ones
+pub fn next_position(conn: &Connection) -> Result<i64> {
+    conn.query_row("SELECT COALESCE(MAX(position), 0) + 1 FROM rule", [], |row| row.get(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+    use crate::models::RecordType;
+
+    #[test]
+    fn test_rule_crud() {
+        let conn = init_db(":memory:").unwrap();
+        let mut coffee = Rule::new("Coffee");
+        coffee.payee_regex = Some("(?i)coffee|starbucks".to_string());
+        coffee.max_amount = Some(20.0);
+        coffee.set_name = Some("Coffee".to_string());
+        coffee.set_record_type = Some(RecordType::Expense);
+        coffee.add_tags = vec!["treats".to_string()];
+        coffee.position = next_position(&conn).unwrap();
+        insert_rule(&conn, &coffee).unwrap();
+        let mut rent = Rule::new("Rent");
+        rent.position = next_position(&conn).unwrap();
+        insert_rule(&conn, &rent).unwrap();
+
+        assert_eq!(get_rule_by_id(&conn, &coffee.id).unwrap(), coffee);
+        assert_eq!(get_rules(&conn).unwrap().iter().map(|r| r.position).collect::<Vec<_>>(), vec![1, 2]);
+
+        rent.position = 0;
+        rent.enabled = false;
+        assert!(update_rule(&conn, &rent).unwrap());
+        assert_eq!(get_rules(&conn).unwrap()[0], rent);
+
+        assert!(delete_rule(&conn, &coffee.id).unwrap());
+        assert!(!delete_rule(&conn, &coffee.id).unwrap());
+        assert_eq!(get_rules(&conn).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::models::{FinancialRecord, Frequency};
+
+use chrono::{Datelike, Days, Months, NaiveDate};
+
+// The `n`th due date of a schedule starting on `start`. Months are counted
+// from the start each time, so a schedule on the 31st stays on month ends.
+fn nth_occurrence(start: NaiveDate, frequency: Frequency, n: u32) -> Option<NaiveDate> {
+    match frequency {
+        Frequency::Daily => start.checked_add_days(Days::new(n.into())),
+        Frequency::Weekly => start.checked_add_days(Days::new(7 * u64::from(n))),
+        Frequency::Monthly => start.checked_add_months(Months::new(n)),
+        Frequency::Quarterly => start.checked_add_months(Months::new(3 * n)),
+        Frequency::Yearly => start.checked_add_months(Months::new(12 * n)),
+    }
+}
+
+/// What a record is due in the month starting on `from` (a 1st). Without a
+/// start date there's no schedule to follow, so that's the normalized
+/// monthly amount.
+pub fn amount_in_month(record: &FinancialRecord, from: NaiveDate) -> f64 {
+    let Some(start) = record.starts_on else {
+        return record.monthly_amount();
+    };
+    let to = from + Months::new(1);
+    // skip straight to (about) the first occurrence in range
+    let mut n = if from > start {
+        let months = (from.year() - start.year()) * 12 + from.month() as i32 - start.month() as i32;
+        let skip = match record.frequency {
+            Frequency::Daily => (from - start).num_days(),
+            Frequency::Weekly => (from - start).num_days() / 7,
+            Frequency::Monthly => i64::from(months - 1),
+            Frequency::Quarterly => i64::from(months / 3 - 1),
+            Frequency::Yearly => i64::from(months / 12 - 1),
+        };
+        u32::try_from(skip.max(0)).unwrap_or(u32::MAX)
+    } else {
+        0
+    };
+    let mut total = 0.0;
+    while let Some(date) = nth_occurrence(start, record.frequency, n) {
+        if date >= to {
+            break;
+        }
+        if date >= from {
+            total += record.amount;
+        }
+        n += 1;
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RecordType;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_amount_in_month_follows_schedule() {
+        let mut rent = FinancialRecord::new("Rent", 1000.0, Frequency::Monthly, RecordType::Expense);
+        rent.starts_on = Some(date(2024, 1, 31));
+        assert_eq!(amount_in_month(&rent, date(2025, 2, 1)), 1000.0);
+        assert_eq!(amount_in_month(&rent, date(2023, 2, 1)), 0.0);
+
+        let mut pay = FinancialRecord::new("Pay", 500.0, Frequency::Weekly, RecordType::Income);
+        pay.starts_on = Some(date(2025, 1, 3));
+        // Fridays in January 2025: 3, 10, 17, 24, 31
+        assert_eq!(amount_in_month(&pay, date(2025, 1, 1)), 2500.0);
+
+        let mut insurance = FinancialRecord::new("Insurance", 300.0, Frequency::Quarterly, RecordType::Expense);
+        insurance.starts_on = Some(date(2020, 2, 15));
+        assert_eq!(amount_in_month(&insurance, date(2025, 5, 1)), 300.0);
+        assert_eq!(amount_in_month(&insurance, date(2025, 6, 1)), 0.0);
+
+        let unscheduled = FinancialRecord::new("Food", 120.0, Frequency::Weekly, RecordType::Expense);
+        assert_eq!(amount_in_month(&unscheduled, date(2025, 1, 1)), 120.0 * 52.0 / 12.0);
+    }
+}
@@ -0,0 +1,1057 @@
+use crate::models::{RecordType, Frequency, FinancialRecord, SearchHit, Attachment, TrashedRecord, HistoryEntry, ChangeAction, JournalEntry, ImportRow, ImportReport, Summary, LedgerEntry, LedgerImportRow, LedgerImportReport, RecurringSuggestion, Rule, RuleTestResult};
+use crate::types::Db;
+use crate::record_store::{RecordStore, StoreHandle};
+use crate::record_repository;
+use crate::history_repository;
+use crate::attachment_repository;
+use crate::ledger_repository;
+use crate::record_matcher;
+use crate::recurring_detector;
+use crate::rule_repository;
+use crate::rule_engine;
+use crate::db::with_savepoint;
+use crate::attachment_store::AttachmentStore;
+use crate::backup::{Backup, BackupStore};
+use crate::archive::{self, Archive, ArchiveFile, ArchiveImportReport, ImportMode};
+use crate::app_error::AppError;
+use crate::qif::QifImport;
+use crate::charts::{self, MonthTotals};
+
+use chrono::{NaiveDate, Utc};
+use uuid::Uuid;
+use log::{info, error};
+use rusqlite::{Connection, Result};
+
+// most hits a single search returns
+const SEARCH_LIMIT: usize = 25;
+
+// most before/after examples a rule test returns
+const RULE_TEST_EXAMPLES: usize = 50;
+
+// actor recorded in the history for changes the app makes on its own
+pub const SYSTEM_ACTOR: &str = "system";
+
+// The record services below work on any RecordStore; the ones further down
+// that also need ledger entries, rules or attachments are SQLite only (Db).
+
+pub fn get_all_records<H: StoreHandle>(db: &H) -> Result<Vec<FinancialRecord>> {
+    info!("Service get_all_records request");
+
+    let store = db.read()?;
+    let records = store.get_records()?;
+    Ok(records)
+}
+
+pub fn get_all_income<H: StoreHandle>(db: &H) -> Result<Vec<FinancialRecord>> {
+    info!("Service get_all_income request");
+    let store = db.read()?;
+    store.get_records_filtered(Some(RecordType::Income), None)
+}
+
+pub fn get_all_expenses<H: StoreHandle>(db: &H) -> Result<Vec<FinancialRecord>> {
+    info!("Service get_all_expenses request");
+    let store = db.read()?;
+    store.get_records_filtered(Some(RecordType::Expense), None)
+}
+
+pub fn get_records_filtered<H: StoreHandle>(
+    db: &H,
+    record_type: Option<RecordType>,
+    frequency: Option<Frequency>,
+) -> Result<Vec<FinancialRecord>> {
+    info!("Service get_records_filtered(record_type={:?}, frequency={:?}) request", record_type, frequency);
+    let store = db.read()?;
+    store.get_records_filtered(record_type, frequency)
+}
+
+// every live record normalized to monthly amounts and totalled per type
+pub fn get_summary<H: StoreHandle>(db: &H) -> Result<Summary> {
+    info!("Service get_summary request");
+    let store = db.read()?;
+    let records = store.get_records()?;
+    Ok(Summary::from_records(&records))
+}
+
+// Totals for each of `months` months starting with the one `first_month` is
+// in, following every record's schedule
+pub fn get_forecast<H: StoreHandle>(db: &H, first_month: NaiveDate, months: u32) -> Result<Vec<MonthTotals>> {
+    info!("Service get_forecast(first_month={}, months={}) request", first_month, months);
+    let store = db.read()?;
+    let records = store.get_records()?;
+    Ok(charts::month_totals(&records, first_month, months))
+}
+
+pub fn get_record_by_id<H: StoreHandle>(db: &H, id: &Uuid) -> Result<FinancialRecord> {
+    info!("Service get_record_by_id(id={}) request", id);
+    let store = db.read()?;
+    let record = store.get_record_by_id(id)?;
+    Ok(record)
+}
+
+pub fn search_records<H: StoreHandle>(db: &H, query: &str) -> Result<Vec<SearchHit>> {
+    info!("Service search_records(query={:?}) request", query);
+    let store = db.read()?;
+    let hits = store.search(query, SEARCH_LIMIT)?;
+    Ok(hits)
+}
+
+pub fn get_record_history<H: StoreHandle>(db: &H, id: &Uuid) -> Result<Vec<HistoryEntry>> {
+    info!("Service get_record_history(id={}) request", id);
+    let store = db.read()?;
+    store.get_history_for_record(id)
+}
+
+pub fn get_activity<H: StoreHandle>(db: &H, limit: usize, before_id: Option<i64>) -> Result<Vec<HistoryEntry>> {
+    info!("Service get_activity(limit={}, before_id={:?}) request", limit, before_id);
+    let store = db.read()?;
+    store.get_activity(limit, before_id)
+}
+
+// Stores a copy of `record` under a new id and returns it
+pub fn add_record<H: StoreHandle>(db: &H, record: &FinancialRecord, actor: &str) -> Result<FinancialRecord, AppError> {
+    let store = db.write()?;
+    if record.amount <= 0.0 {
+        return Err(AppError("Amount must be positive.".to_string()));
+    }
+
+    let record = FinancialRecord {
+        id: Uuid::new_v4(),
+        name: record.name.to_string(),
+        amount: record.amount,
+        frequency: record.frequency,
+        record_type: record.record_type,
+        notes: record.notes.clone(),
+        payee: record.payee.clone(),
+        starts_on: record.starts_on,
+    };
+
+    info!("Adding new FinancialRecord {}", record);
+
+    if let Err(e) = insert_journaled(&*store, &record, actor) {
+        error!("Failed to add record {}: {}", record, e);
+        return Err(e.into());
+    }
+    Ok(record)
+}
+
+// Replaces every field of an existing record, keeping its id
+pub fn update_record<H: StoreHandle>(db: &H, record: &FinancialRecord, actor: &str) -> Result<FinancialRecord, AppError> {
+    info!("Service update_record(id={})", record.id);
+    if record.amount <= 0.0 {
+        return Err(AppError("Amount must be positive.".to_string()));
+    }
+    let store = db.write()?;
+    // fails for records that don't exist or are in the trash
+    store.get_record_by_id(&record.id)?;
+    update_journaled(&*store, record, actor)?;
+    Ok(record.clone())
+}
+
+pub fn update_notes<H: StoreHandle>(db: &H, id: &Uuid, notes: Option<String>, actor: &str) -> Result<FinancialRecord> {
+    info!("Service update_notes(id={})", id);
+    let store = db.write()?;
+    let mut record = store.get_record_by_id(id)?;
+    // an emptied textarea clears the notes
+    record.notes = notes.filter(|n| !n.trim().is_empty());
+    update_journaled(&*store, &record, actor)?;
+    Ok(record)
+}
+
+// Moves a record to the trash, it can be restored until it is purged
+pub fn delete_record<H: StoreHandle>(db: &H, id: &Uuid, actor: &str) -> Result<()>  {
+    info!("Service delete_record(id={})", id);
+    let store = db.write()?;
+    delete_journaled(&*store, id, actor)
+}
+
+pub fn get_trash<H: StoreHandle>(db: &H) -> Result<Vec<TrashedRecord>> {
+    info!("Service get_trash request");
+    let store = db.read()?;
+    store.get_trashed_records()
+}
+
+pub fn restore_record<H: StoreHandle>(db: &H, id: &Uuid, actor: &str) -> Result<()> {
+    info!("Service restore_record(id={})", id);
+    let store = db.write()?;
+    if !store.restore_record(id, actor)? {
+        return Err(rusqlite::Error::QueryReturnedNoRows);
+    }
+    Ok(())
+}
+
+// Permanently removes a trashed record along with its attachments
+pub fn purge_record(db: &Db, store: &AttachmentStore, id: &Uuid, actor: &str) -> Result<(), AppError> {
+    info!("Service purge_record(id={})", id);
+    let conn = db.write()?;
+    purge(&conn, store, id, actor)?;
+    Ok(())
+}
+
+// Purges everything that has been in the trash longer than `retention_days`.
+// Returns how many records were removed.
+pub fn purge_expired_trash(db: &Db, store: &AttachmentStore, retention_days: u32) -> Result<usize, AppError> {
+    info!("Service purge_expired_trash(retention_days={})", retention_days);
+    let conn = db.write()?;
+    let expired = record_repository::get_expired_trash(&conn, retention_days)?;
+    for id in &expired {
+        purge(&conn, store, id, SYSTEM_ACTOR)?;
+    }
+    Ok(expired.len())
+}
+
+// purge_expired_trash for a store without attachments, such as PostgreSQL
+pub fn purge_expired_records<H: StoreHandle>(db: &H, retention_days: u32) -> Result<usize> {
+    info!("Service purge_expired_records(retention_days={})", retention_days);
+    let store = db.write()?;
+    let store = &*store;
+    let expired = store.get_expired_trash(retention_days)?;
+    store.atomically(|| {
+        for id in &expired {
+            store.purge_record(id, SYSTEM_ACTOR)?;
+        }
+        Ok(expired.len())
+    })
+}
+
+// Snapshots the database into `backups`. Others keep reading and writing
+// meanwhile.
+pub fn backup_database(db: &Db, backups: &BackupStore) -> Result<Backup, AppError> {
+    info!("Service backup_database request");
+    backups.take(&*db.read()?, Utc::now())
+}
+
+// Replaces the database with the backup `name`, after snapshotting it as it
+// is, so that a restore can be undone by restoring that. Returns the backup
+// restored and the one taken first.
+pub fn restore_backup(db: &Db, backups: &BackupStore, name: &str) -> Result<(Backup, Backup), AppError> {
+    info!("Service restore_backup(name={})", name);
+    // don't leave a snapshot behind for a restore that can't happen
+    backups.verify(name)?;
+    let before = backups.take(&*db.read()?, Utc::now())?;
+    let restored = backups.restore(name, &mut *db.write()?)?;
+    Ok((restored, before))
+}
+
+pub fn export_archive(db: &Db) -> Result<Archive, AppError> {
+    info!("Service export_archive request");
+    Ok(archive::export(&*db.read()?)?)
+}
+
+// All or nothing: a failed import leaves the database as it was
+pub fn import_archive(
+    db: &Db,
+    store: &AttachmentStore,
+    file: &ArchiveFile,
+    mode: ImportMode,
+) -> Result<ArchiveImportReport, AppError> {
+    info!("Service import_archive(mode={:?})", mode);
+    archive::import(&*db.write()?, store, file, mode)
+}
+
+fn purge(conn: &Connection, store: &AttachmentStore, id: &Uuid, actor: &str) -> Result<(), AppError> {
+    let attachments = attachment_repository::get_attachments_for_record(conn, id)?;
+    // attachment rows go with the record (ON DELETE CASCADE)
+    if !record_repository::purge_record(conn, id, actor)? {
+        return Err(AppError(format!("record `{}` is not in the trash", id)));
+    }
+    remove_unreferenced_content(conn, store, &attachments)?;
+    Ok(())
+}
+
+pub fn add_attachment(
+    db: &Db,
+    store: &AttachmentStore,
+    record_id: &Uuid,
+    file_name: &str,
+    content_type: &str,
+    bytes: &[u8],
+) -> Result<Attachment, AppError> {
+    info!("Service add_attachment(record_id={}, file_name={})", record_id, file_name);
+    let conn = db.write()?;
+    // fail before touching the disk if the record doesn't exist
+    record_repository::get_record_by_id(&conn, record_id)?;
+
+    // the row first and the file last: a failed insert leaves nothing on
+    // disk, and a failed write rolls the row back
+    with_savepoint(&conn, || {
+        let attachment = attachment_repository::insert_attachment(
+            &conn,
+            record_id,
+            file_name,
+            content_type,
+            bytes.len() as i64,
+            &AttachmentStore::hash(bytes),
+        )?;
+        store.put(bytes)?;
+        Ok(attachment)
+    })
+}
+
+pub fn get_attachments(db: &Db, record_id: &Uuid) -> Result<Vec<Attachment>> {
+    info!("Service get_attachments(record_id={})", record_id);
+    let conn = db.read()?;
+    attachment_repository::get_attachments_for_record(&conn, record_id)
+}
+
+pub fn get_attachment_content(
+    db: &Db,
+    store: &AttachmentStore,
+    id: &Uuid,
+) -> Result<(Attachment, Vec<u8>), AppError> {
+    info!("Service get_attachment_content(id={})", id);
+    let conn = db.read()?;
+    let attachment = attachment_repository::get_attachment_by_id(&conn, id)?;
+    let bytes = store.get(&attachment.sha256)?;
+    Ok((attachment, bytes))
+}
+
+// drop stored files that no attachment row points at anymore
+fn remove_unreferenced_content(
+    conn: &Connection,
+    store: &AttachmentStore,
+    attachments: &[Attachment],
+) -> Result<(), AppError> {
+    for attachment in attachments {
+        if attachment_repository::count_by_hash(conn, &attachment.sha256)? == 0 {
+            store.remove(&attachment.sha256)?;
+        }
+    }
+    Ok(())
+}
+
+// Inserts the parsed rows of an import (CSV, OFX, ...) in one transaction.
+//
+// If any row failed to parse nothing is inserted, unless `skip_invalid` is
+// set, in which case only the good rows are. A dry run never inserts
+// anything and just reports what would happen.
+pub fn import_records<H: StoreHandle>(
+    db: &H,
+    rows: Vec<ImportRow>,
+    dry_run: bool,
+    skip_invalid: bool,
+    actor: &str,
+) -> Result<ImportReport, AppError> {
+    info!("Service import_records(rows={}, dry_run={}, skip_invalid={})", rows.len(), dry_run, skip_invalid);
+    let mut report = ImportReport { rows, imported: 0, dry_run };
+    if dry_run || (report.error_count() > 0 && !skip_invalid) {
+        return Ok(report);
+    }
+
+    let store = db.write()?;
+    store.atomically(|| insert_import_rows(&*store, &mut report, actor))?;
+
+    info!("Imported {} records", report.imported);
+    Ok(report)
+}
+
+fn insert_import_rows<S: RecordStore>(store: &S, report: &mut ImportReport, actor: &str) -> Result<()> {
+    for row in &report.rows {
+        if let Ok(record) = &row.outcome {
+            insert_journaled(store, record, actor)?;
+            report.imported += 1;
+        }
+    }
+    Ok(())
+}
+
+// Stores statement transactions (OFX, ...) in one transaction, skipping the
+// ones already imported from an earlier, overlapping statement. Each new
+// transaction gets the best matching budget record as a suggestion and is
+// linked to it when the match is good enough. A dry run only reports.
+pub fn import_ledger_entries(db: &Db, entries: Vec<LedgerEntry>, dry_run: bool) -> Result<LedgerImportReport, AppError> {
+    info!("Service import_ledger_entries(entries={}, dry_run={})", entries.len(), dry_run);
+    let conn = db.write()?;
+    let tx = conn.unchecked_transaction()?;
+    let report = insert_ledger_entries(&tx, entries, dry_run)?;
+    tx.commit()?;
+
+    info!("Imported {} ledger entries, skipped {} duplicates", report.imported, report.duplicates);
+    Ok(report)
+}
+
+fn insert_ledger_entries(conn: &Connection, entries: Vec<LedgerEntry>, dry_run: bool) -> Result<LedgerImportReport, AppError> {
+    let records = record_repository::get_records(conn)?;
+    let rules = rule_engine::compile(rule_repository::get_rules(conn)?)?;
+    let mut report = LedgerImportReport { rows: Vec::new(), imported: 0, duplicates: 0, dry_run };
+    let mut seen = std::collections::HashSet::new();
+    for mut entry in entries {
+        rule_engine::apply(&rules, &mut entry);
+        let suggestion = record_matcher::best_match(&records, &entry);
+        if let Some(suggestion) = suggestion.as_ref().filter(|s| s.score >= record_matcher::AUTO_LINK_THRESHOLD) {
+            entry.record_id = Some(suggestion.record_id);
+        }
+
+        // the same FITID twice within one file counts as a duplicate too
+        let repeated = entry.fitid.is_some() && !seen.insert((entry.account.clone(), entry.fitid.clone()));
+        let duplicate = if dry_run {
+            repeated || ledger_repository::exists(conn, &entry.account, entry.fitid.as_deref())?
+        } else {
+            !ledger_repository::insert_entry(conn, &entry)?
+        };
+        if duplicate {
+            report.duplicates += 1;
+        } else if !dry_run {
+            report.imported += 1;
+        }
+        report.rows.push(LedgerImportRow { entry, duplicate, suggestion });
+    }
+    Ok(report)
+}
+
+// Imports the budget records and the transactions of a QIF file in one
+// transaction, records first so transactions can be matched against them.
+// Same rules as import_records: with invalid records and no `skip_invalid`
+// nothing at all is imported.
+pub fn import_qif(
+    db: &Db,
+    qif: QifImport,
+    dry_run: bool,
+    skip_invalid: bool,
+    actor: &str,
+) -> Result<(ImportReport, LedgerImportReport), AppError> {
+    info!(
+        "Service import_qif(records={}, transactions={}, dry_run={}, skip_invalid={})",
+        qif.records.len(), qif.transactions.len(), dry_run, skip_invalid
+    );
+    let mut records = ImportReport { rows: qif.records, imported: 0, dry_run };
+    let blocked = records.error_count() > 0 && !skip_invalid;
+
+    let conn = db.write()?;
+    let tx = conn.unchecked_transaction()?;
+    if !dry_run && !blocked {
+        insert_import_rows(&*tx, &mut records, actor)?;
+    }
+    let mut transactions = insert_ledger_entries(&tx, qif.transactions, dry_run || blocked)?;
+    transactions.dry_run = dry_run;
+    if dry_run || blocked {
+        // nothing was written, the transaction only served the duplicate checks
+        tx.rollback()?;
+    } else {
+        tx.commit()?;
+    }
+
+    info!("Imported {} records and {} ledger entries", records.imported, transactions.imported);
+    Ok((records, transactions))
+}
+
+pub fn get_ledger_entries(db: &Db, limit: usize) -> Result<Vec<LedgerEntry>> {
+    info!("Service get_ledger_entries(limit={}) request", limit);
+    let conn = db.read()?;
+    ledger_repository::get_entries(&conn, limit)
+}
+
+pub fn get_all_ledger_entries(db: &Db) -> Result<Vec<LedgerEntry>> {
+    info!("Service get_all_ledger_entries request");
+    let conn = db.read()?;
+    ledger_repository::get_all_entries(&conn)
+}
+
+// "create recurring record?" proposals from the imported transactions
+pub fn get_recurring_suggestions(db: &Db) -> Result<Vec<RecurringSuggestion>> {
+    info!("Service get_recurring_suggestions request");
+    let conn = db.read()?;
+    let entries = ledger_repository::get_all_entries(&conn)?;
+    let records = record_repository::get_records(&conn)?;
+    Ok(recurring_detector::detect(&entries, &records))
+}
+
+// Creates the record a recurring suggestion proposes, optionally under
+// another name, and links the suggestion's transactions to it.
+pub fn accept_recurring_suggestion(db: &Db, key: &str, name: Option<String>, actor: &str) -> Result<FinancialRecord, AppError> {
+    info!("Service accept_recurring_suggestion(key={:?})", key);
+    let conn = db.write()?;
+    let entries = ledger_repository::get_all_entries(&conn)?;
+    let records = record_repository::get_records(&conn)?;
+    let suggestion = recurring_detector::detect(&entries, &records)
+        .into_iter()
+        .find(|s| s.key == key)
+        .ok_or_else(|| AppError(format!("no recurring suggestion `{}` (already created?)", key)))?;
+
+    let mut record = suggestion.record;
+    if let Some(name) = name {
+        record.name = name;
+    }
+    let tx = conn.unchecked_transaction()?;
+    insert_journaled(&*tx, &record, actor)?;
+    for id in &suggestion.entry_ids {
+        ledger_repository::set_record_id(&tx, id, Some(&record.id))?;
+    }
+    tx.commit()?;
+
+    info!("Created recurring record {} from {} transactions", record, suggestion.entry_ids.len());
+    Ok(record)
+}
+
+// Every live record with the date its schedule starts from, for the calendar
+// feed. That is the record's own start date if it has one, otherwise the last
+// transaction linked to it, otherwise the day it first shows up in its
+// history. Records with none of those (older than the history) are left out
+// rather than anchored on a date that would move with every fetch.
+pub fn get_calendar_schedule(db: &Db) -> Result<Vec<(FinancialRecord, NaiveDate)>> {
+    info!("Service get_calendar_schedule request");
+    let conn = db.read()?;
+    let mut schedule = Vec::new();
+    for record in record_repository::get_records(&conn)? {
+        let start = match record.starts_on {
+            Some(date) => Some(date),
+            None => ledger_repository::get_last_posted_on(&conn, &record.id)?.or(
+                history_repository::get_created_on(&conn, &record.id)?
+                    .and_then(|day| NaiveDate::parse_from_str(&day, "%Y-%m-%d").ok()),
+            ),
+        };
+        match start {
+            Some(start) => schedule.push((record, start)),
+            None => info!("Leaving {} out of the calendar, it has no start date", record.id),
+        }
+    }
+    Ok(schedule)
+}
+
+pub fn get_rules(db: &Db) -> Result<Vec<Rule>> {
+    info!("Service get_rules request");
+    let conn = db.read()?;
+    rule_repository::get_rules(&conn)
+}
+
+pub fn get_rule_by_id(db: &Db, id: &Uuid) -> Result<Rule> {
+    info!("Service get_rule_by_id(id={}) request", id);
+    let conn = db.read()?;
+    rule_repository::get_rule_by_id(&conn, id)
+}
+
+// Validates the rule and adds it after the existing ones
+pub fn add_rule(db: &Db, mut rule: Rule) -> Result<Rule, AppError> {
+    info!("Service add_rule(name={})", rule.name);
+    rule_engine::validate(&rule)?;
+    let conn = db.write()?;
+    rule.position = rule_repository::next_position(&conn)?;
+    rule_repository::insert_rule(&conn, &rule)?;
+    Ok(rule)
+}
+
+pub fn update_rule(db: &Db, rule: &Rule) -> Result<(), AppError> {
+    info!("Service update_rule(id={})", rule.id);
+    rule_engine::validate(rule)?;
+    let conn = db.write()?;
+    if !rule_repository::update_rule(&conn, rule)? {
+        return Err(AppError(format!("no rule with id {}", rule.id)));
+    }
+    Ok(())
+}
+
+pub fn delete_rule(db: &Db, id: &Uuid) -> Result<bool> {
+    info!("Service delete_rule(id={})", id);
+    let conn = db.write()?;
+    rule_repository::delete_rule(&conn, id)
+}
+
+// Re-runs the current rules over every imported transaction. Returns how
+// many transactions changed.
+pub fn apply_rules(db: &Db) -> Result<usize, AppError> {
+    info!("Service apply_rules request");
+    let conn = db.write()?;
+    let rules = rule_engine::compile(rule_repository::get_rules(&conn)?)?;
+    let tx = conn.unchecked_transaction()?;
+    let mut changed = 0;
+    for mut entry in ledger_repository::get_all_entries(&tx)? {
+        if rule_engine::apply(&rules, &mut entry) {
+            ledger_repository::update_classification(&tx, &entry)?;
+            changed += 1;
+        }
+    }
+    tx.commit()?;
+
+    info!("Rules changed {} ledger entries", changed);
+    Ok(changed)
+}
+
+// How many transactions would be affected by a (possibly unsaved) rule, and
+// a few of them before/after. With a `sample` the rule is tested against
+// that transaction alone instead of the imported ones.
+pub fn test_rule(db: &Db, rule: Rule, sample: Option<LedgerEntry>) -> Result<RuleTestResult, AppError> {
+    info!("Service test_rule(name={})", rule.name);
+    let rule = rule_engine::CompiledRule::new(rule)?;
+    let entries = match sample {
+        Some(sample) => vec![sample],
+        None => {
+            let conn = db.read()?;
+            ledger_repository::get_all_entries(&conn)?
+        }
+    };
+
+    let mut result = RuleTestResult { matched: 0, examples: Vec::new() };
+    for entry in entries.into_iter().filter(|e| rule.matches(e)) {
+        result.matched += 1;
+        if result.examples.len() < RULE_TEST_EXAMPLES {
+            let mut after = entry.clone();
+            rule.apply(&mut after);
+            result.examples.push((entry, after));
+        }
+    }
+    Ok(result)
+}
+
+// Links a ledger entry to a live budget record, or unlinks it with None.
+pub fn link_ledger_entry(db: &Db, id: &Uuid, record_id: Option<&Uuid>) -> Result<LedgerEntry> {
+    info!("Service link_ledger_entry(id={}, record_id={:?})", id, record_id);
+    let conn = db.write()?;
+    if let Some(record_id) = record_id {
+        record_repository::get_record_by_id(&conn, record_id)?;
+    }
+    if !ledger_repository::set_record_id(&conn, id, record_id)? {
+        return Err(rusqlite::Error::QueryReturnedNoRows);
+    }
+    ledger_repository::get_entry_by_id(&conn, id)
+}
+
+// The *_journaled helpers make a change and push it on the undo stack in one
+// savepoint. Every insert/update/delete the service layer makes on behalf of
+// a user should go through them.
+
+fn insert_journaled<S: RecordStore>(store: &S, record: &FinancialRecord, actor: &str) -> Result<()> {
+    store.atomically(|| {
+        store.insert_record(record, actor)?;
+        store.push_journal(&record.id, ChangeAction::Insert, actor, None, Some(record))
+    })
+}
+
+fn update_journaled<S: RecordStore>(store: &S, record: &FinancialRecord, actor: &str) -> Result<()> {
+    store.atomically(|| {
+        let before = store.get_record_by_id(&record.id)?;
+        store.update_record(record, actor)?;
+        store.push_journal(&record.id, ChangeAction::Update, actor, Some(&before), Some(record))
+    })
+}
+
+fn delete_journaled<S: RecordStore>(store: &S, id: &Uuid, actor: &str) -> Result<()> {
+    store.atomically(|| {
+        let before = store.get_record_by_id(id)?;
+        store.delete_record(id, actor)?;
+        store.push_journal(id, ChangeAction::Delete, actor, Some(&before), None)
+    })
+}
+
+// Reverts the last `n` journaled operations, newest first. All or nothing: if
+// any record involved has changed since its operation, nothing is undone.
+// Returns the operations that were undone.
+pub fn undo<H: StoreHandle>(db: &H, n: usize, actor: &str) -> Result<Vec<JournalEntry>, AppError> {
+    info!("Service undo(n={})", n);
+    let store = db.write()?;
+    let store = &*store;
+    store.atomically(|| {
+        let entries = store.get_undoable(n)?;
+        for entry in &entries {
+            match entry.action {
+                // undoing an add moves the record to the trash
+                ChangeAction::Insert => {
+                    expect_live(store, entry, entry.after.as_ref())?;
+                    store.delete_record(&entry.record_id, actor)?;
+                }
+                ChangeAction::Update => {
+                    expect_live(store, entry, entry.after.as_ref())?;
+                    store.update_record(expect_snapshot(entry, entry.before.as_ref())?, actor)?;
+                }
+                ChangeAction::Delete => {
+                    expect_trashed(store, entry, entry.before.as_ref())?;
+                    store.restore_record(&entry.record_id, actor)?;
+                }
+                other => return Err(AppError(format!("can't undo a {} operation", other))),
+            }
+            store.set_undone(entry.id, true)?;
+        }
+        Ok(entries)
+    })
+}
+
+// Reapplies the last `n` undone operations, most recently undone first. All or
+// nothing, like undo. Returns the operations that were redone.
+pub fn redo<H: StoreHandle>(db: &H, n: usize, actor: &str) -> Result<Vec<JournalEntry>, AppError> {
+    info!("Service redo(n={})", n);
+    let store = db.write()?;
+    let store = &*store;
+    store.atomically(|| {
+        let entries = store.get_redoable(n)?;
+        for entry in &entries {
+            match entry.action {
+                ChangeAction::Insert => {
+                    expect_trashed(store, entry, entry.after.as_ref())?;
+                    store.restore_record(&entry.record_id, actor)?;
+                }
+                ChangeAction::Update => {
+                    expect_live(store, entry, entry.before.as_ref())?;
+                    store.update_record(expect_snapshot(entry, entry.after.as_ref())?, actor)?;
+                }
+                ChangeAction::Delete => {
+                    expect_live(store, entry, entry.before.as_ref())?;
+                    store.delete_record(&entry.record_id, actor)?;
+                }
+                other => return Err(AppError(format!("can't redo a {} operation", other))),
+            }
+            store.set_undone(entry.id, false)?;
+        }
+        Ok(entries)
+    })
+}
+
+fn expect_snapshot<'a>(entry: &JournalEntry, snapshot: Option<&'a FinancialRecord>) -> Result<&'a FinancialRecord, AppError> {
+    snapshot.ok_or_else(|| AppError(format!("journal entry {} is missing a snapshot", entry.id)))
+}
+
+// conflict detection: the record must be live and look exactly like `expected`
+fn expect_live<S: RecordStore>(store: &S, entry: &JournalEntry, expected: Option<&FinancialRecord>) -> Result<(), AppError> {
+    let expected = expect_snapshot(entry, expected)?;
+    match store.get_record_by_id(&entry.record_id) {
+        Ok(current) if &current == expected => Ok(()),
+        Ok(_) | Err(rusqlite::Error::QueryReturnedNoRows) => Err(conflict(entry, expected)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// conflict detection: the record must be in the trash and look like `expected`
+fn expect_trashed<S: RecordStore>(store: &S, entry: &JournalEntry, expected: Option<&FinancialRecord>) -> Result<(), AppError> {
+    let expected = expect_snapshot(entry, expected)?;
+    match store.get_trashed_record(&entry.record_id) {
+        Ok(current) if &current == expected => Ok(()),
+        Ok(_) | Err(rusqlite::Error::QueryReturnedNoRows) => Err(conflict(entry, expected)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn conflict(entry: &JournalEntry, expected: &FinancialRecord) -> AppError {
+    AppError(format!(
+        "record `{}` has changed since the {} at {}, nothing was changed",
+        expected.name, entry.action, entry.created_at
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_db() -> Db {
+        crate::pool::Pool::open(":memory:", 0).expect("failed to init memory db")
+    }
+
+    #[test]
+    fn test_add_attachment_is_all_or_nothing() {
+        let db = setup_db();
+        let dir = tempfile::tempdir().unwrap();
+        let store = AttachmentStore::new(dir.path().join("attachments"));
+        let record = FinancialRecord::new("Rent", 1500.0, Frequency::Monthly, RecordType::Expense);
+        let record = add_record(&db, &record, "test").unwrap();
+
+        // the insert fails: no file left behind
+        db.write()
+            .unwrap()
+            .execute_batch("CREATE TRIGGER no_attachments BEFORE INSERT ON attachment BEGIN SELECT RAISE(ABORT, 'no'); END")
+            .unwrap();
+        assert!(add_attachment(&db, &store, &record.id, "lease.txt", "text/plain", b"lease").is_err());
+        assert!(store.get(&AttachmentStore::hash(b"lease")).is_err());
+        db.write().unwrap().execute_batch("DROP TRIGGER no_attachments").unwrap();
+
+        // the file can't be written: no row left behind
+        std::fs::write(dir.path().join("attachments"), "not a directory").unwrap();
+        assert!(add_attachment(&db, &store, &record.id, "lease.txt", "text/plain", b"lease").is_err());
+        assert!(get_attachments(&db, &record.id).unwrap().is_empty());
+    }
+
+    // a monthly record
+    fn add<H: StoreHandle>(db: &H, name: &str, amount: f64, record_type: RecordType) -> FinancialRecord {
+        add_record(db, &FinancialRecord::new(name, amount, Frequency::Monthly, record_type), "test").unwrap()
+    }
+
+    fn names<H: StoreHandle>(db: &H) -> Vec<String> {
+        get_all_records(db).unwrap().into_iter().map(|r| r.name).collect()
+    }
+
+    // The record service tests are written once, generic over the store, and
+    // run against each backend
+    macro_rules! on_every_store {
+        ($($name:ident),* $(,)?) => {
+            mod sqlite {
+                $(#[test] fn $name() { super::$name(super::setup_db()) })*
+            }
+            mod memory {
+                use std::sync::{Arc, Mutex};
+                use crate::memory_store::MemoryStore;
+                $(#[test] fn $name() { super::$name(Arc::new(Mutex::new(MemoryStore::new()))) })*
+            }
+            // against BUDGET_TEST_POSTGRES, see pg_store::tests
+            #[cfg(feature = "postgres")]
+            mod postgres {
+                use crate::pg_store::tests::test_database;
+                $(#[test] #[ignore = "needs BUDGET_TEST_POSTGRES"] fn $name() { super::$name(test_database().pool.clone()) })*
+            }
+        };
+    }
+
+    on_every_store!(
+        undo_and_redo_insert_update_delete,
+        undo_detects_conflicts_and_changes_nothing,
+        import_is_all_or_nothing,
+        new_operation_clears_redo,
+        forecast_leaves_out_trashed_records,
+        update_record_is_undoable_and_skips_trash,
+        purge_expired_records_empties_old_trash,
+    );
+
+    fn undo_and_redo_insert_update_delete<H: StoreHandle>(db: H) {
+        add(&db, "Rent", 1500.0, RecordType::Expense);
+        let id = get_all_records(&db).unwrap()[0].id;
+        update_notes(&db, &id, Some("new lease".into()), "test").unwrap();
+        delete_record(&db, &id, "test").unwrap();
+        assert!(names(&db).is_empty());
+
+        // undo the delete, then the notes change, then the add
+        undo(&db, 1, "test").unwrap();
+        assert_eq!(get_record_by_id(&db, &id).unwrap().notes.as_deref(), Some("new lease"));
+        undo(&db, 1, "test").unwrap();
+        assert_eq!(get_record_by_id(&db, &id).unwrap().notes, None);
+        undo(&db, 1, "test").unwrap();
+        assert!(names(&db).is_empty());
+
+        // and redo all three at once
+        assert_eq!(redo(&db, 3, "test").unwrap().len(), 3);
+        assert!(names(&db).is_empty());
+        assert!(get_trash(&db).unwrap()[0].record.notes.as_deref() == Some("new lease"));
+    }
+
+    fn undo_detects_conflicts_and_changes_nothing<H: StoreHandle>(db: H) {
+        add(&db, "Rent", 1500.0, RecordType::Expense);
+        add(&db, "Gym", 30.0, RecordType::Expense);
+        let rent = get_all_records(&db).unwrap().into_iter().find(|r| r.name == "Rent").unwrap();
+
+        // change Rent behind the journal's back
+        {
+            let store = db.write().unwrap();
+            let changed = FinancialRecord { amount: 1600.0, ..rent.clone() };
+            store.update_record(&changed, "test").unwrap();
+        }
+
+        let err = undo(&db, 2, "test").unwrap_err();
+        assert!(err.0.contains("Rent"), "{}", err);
+        // the Gym add was not undone either
+        assert_eq!(names(&db).len(), 2);
+    }
+
+    fn import_is_all_or_nothing<H: StoreHandle>(db: H) {
+        let good = FinancialRecord::new("Rent", 1500.0, Frequency::Monthly, RecordType::Expense);
+        let rows = vec![
+            ImportRow { line: 2, outcome: Ok(good.clone()) },
+            ImportRow { line: 3, outcome: Err("invalid amount `ten`".into()) },
+        ];
+
+        let report = import_records(&db, rows.clone(), false, false, "test").unwrap();
+        assert_eq!((report.imported, report.error_count()), (0, 1));
+        assert!(names(&db).is_empty());
+
+        let report = import_records(&db, rows.clone(), true, true, "test").unwrap();
+        assert_eq!(report.imported, 0);
+        assert!(names(&db).is_empty());
+
+        let report = import_records(&db, rows, false, true, "test").unwrap();
+        assert_eq!(report.imported, 1);
+        assert_eq!(get_all_records(&db).unwrap(), vec![good]);
+    }
+
+    fn new_operation_clears_redo<H: StoreHandle>(db: H) {
+        add(&db, "Rent", 1500.0, RecordType::Expense);
+        undo(&db, 1, "test").unwrap();
+        add(&db, "Gym", 30.0, RecordType::Expense);
+
+        assert!(redo(&db, 1, "test").unwrap().is_empty());
+        assert_eq!(names(&db), vec!["Gym"]);
+    }
+
+    fn forecast_leaves_out_trashed_records<H: StoreHandle>(db: H) {
+        add(&db, "Rent", 1500.0, RecordType::Expense);
+        add(&db, "Gym", 30.0, RecordType::Expense);
+        let gym = get_all_records(&db).unwrap().into_iter().find(|r| r.name == "Gym").unwrap();
+        delete_record(&db, &gym.id, "test").unwrap();
+
+        let first = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let months = get_forecast(&db, first, 3).unwrap();
+        assert_eq!(months.len(), 3);
+        assert!(months.iter().all(|m| m.expenses == 1500.0 && m.income == 0.0));
+    }
+
+    fn purge_expired_records_empties_old_trash<H: StoreHandle>(db: H) {
+        let rent = add_record(&db, &FinancialRecord::new("Rent", 1500.0, Frequency::Monthly, RecordType::Expense), "test").unwrap();
+        add_record(&db, &FinancialRecord::new("Gym", 30.0, Frequency::Monthly, RecordType::Expense), "test").unwrap();
+        delete_record(&db, &rent.id, "test").unwrap();
+
+        assert_eq!(purge_expired_records(&db, 30).unwrap(), 0);
+        assert_eq!(purge_expired_records(&db, 0).unwrap(), 1);
+        assert!(get_trash(&db).unwrap().is_empty());
+        assert_eq!(names(&db), vec!["Gym"]);
+    }
+
+    fn update_record_is_undoable_and_skips_trash<H: StoreHandle>(db: H) {
+        let rent = add_record(&db, &FinancialRecord::new("Rent", 1500.0, Frequency::Monthly, RecordType::Expense), "test").unwrap();
+
+        let edited = FinancialRecord { name: "Rent (new place)".to_string(), amount: 1600.0, ..rent.clone() };
+        update_record(&db, &edited, "test").unwrap();
+        assert_eq!(get_record_by_id(&db, &rent.id).unwrap(), edited);
+        assert!(update_record(&db, &FinancialRecord { amount: 0.0, ..edited.clone() }, "test").is_err());
+
+        undo(&db, 1, "test").unwrap();
+        assert_eq!(get_record_by_id(&db, &rent.id).unwrap(), rent);
+
+        delete_record(&db, &rent.id, "test").unwrap();
+        assert!(update_record(&db, &edited, "test").is_err());
+    }
+
+    #[test]
+    fn test_ledger_import_skips_duplicates_and_links_matches() {
+        let db = setup_db();
+        add_record(&db, &FinancialRecord::new("Netflix", 15.49, Frequency::Monthly, RecordType::Expense), "test").unwrap();
+        let netflix = get_all_records(&db).unwrap().remove(0);
+        let statement = || {
+            let date = chrono::NaiveDate::from_ymd_opt(2025, 1, 3).unwrap();
+            vec![
+                LedgerEntry { fitid: Some("1".to_string()), ..LedgerEntry::new("acct", date, -15.49, "NETFLIX.COM") },
+                LedgerEntry { fitid: Some("2".to_string()), ..LedgerEntry::new("acct", date, -4.50, "CORNER CAFE") },
+            ]
+        };
+
+        let preview = import_ledger_entries(&db, statement(), true).unwrap();
+        assert_eq!((preview.imported, preview.duplicates), (0, 0));
+        assert!(get_ledger_entries(&db, 10).unwrap().is_empty());
+
+        let first = import_ledger_entries(&db, statement(), false).unwrap();
+        assert_eq!((first.imported, first.duplicates), (2, 0));
+        assert_eq!(first.rows[0].entry.record_id, Some(netflix.id));
+        assert_eq!(first.rows[1].suggestion, None);
+
+        let again = import_ledger_entries(&db, statement(), false).unwrap();
+        assert_eq!((again.imported, again.duplicates), (0, 2));
+        assert_eq!(get_ledger_entries(&db, 10).unwrap().len(), 2);
+
+        let cafe = &first.rows[1].entry;
+        assert_eq!(link_ledger_entry(&db, &cafe.id, Some(&netflix.id)).unwrap().record_id, Some(netflix.id));
+        assert!(link_ledger_entry(&db, &cafe.id, Some(&Uuid::new_v4())).is_err());
+    }
+
+    #[test]
+    fn test_qif_import_links_transactions_to_new_records() {
+        let db = setup_db();
+        let text = "!Type:Cat\nNNetflix\nE\nB15.49\n^\n!Type:Bank\nD01/03/2025\nT-15.49\nPNETFLIX.COM\n^\n";
+        let file = || crate::qif::parse(text, &Default::default()).unwrap();
+
+        let (records, transactions) = import_qif(&db, file(), false, false, "test").unwrap();
+        assert_eq!((records.imported, transactions.imported), (1, 1));
+        let netflix = get_all_records(&db).unwrap().remove(0);
+        assert_eq!(get_all_ledger_entries(&db).unwrap()[0].record_id, Some(netflix.id));
+
+        // same file again: the transaction is recognised, the category is not
+        let (_, again) = import_qif(&db, file(), true, false, "test").unwrap();
+        assert_eq!(again.duplicates, 1);
+    }
+
+    #[test]
+    fn test_accept_recurring_suggestion_creates_and_links() {
+        let db = setup_db();
+        let gym = |month| {
+            LedgerEntry::new("acct", chrono::NaiveDate::from_ymd_opt(2025, month, 5).unwrap(), -60.0, "CITY GYM 42")
+        };
+        import_ledger_entries(&db, vec![gym(1), gym(2), gym(3)], false).unwrap();
+
+        let suggestions = get_recurring_suggestions(&db).unwrap();
+        assert_eq!(suggestions.len(), 1);
+        let record = accept_recurring_suggestion(&db, &suggestions[0].key, Some("Gym".to_string()), "test").unwrap();
+
+        assert_eq!(get_record_by_id(&db, &record.id).unwrap().name, "Gym");
+        assert!(get_all_ledger_entries(&db).unwrap().iter().all(|e| e.record_id == Some(record.id)));
+        assert!(get_recurring_suggestions(&db).unwrap().is_empty());
+        assert!(accept_recurring_suggestion(&db, &suggestions[0].key, None, "test").is_err());
+    }
+
+    #[test]
+    fn test_calendar_schedule_start_dates() {
+        let db = setup_db();
+        let day = |m, d| chrono::NaiveDate::from_ymd_opt(2025, m, d).unwrap();
+        let mut rent = FinancialRecord::new("Rent", 1500.0, Frequency::Monthly, RecordType::Expense);
+        rent.starts_on = Some(day(2, 1));
+        add_record(&db, &rent, "test").unwrap();
+        let record = accept_recurring_suggestion_fixture(&db);
+        add(&db, "Salary", 2500.0, RecordType::Income);
+
+        let schedule = get_calendar_schedule(&db).unwrap();
+        let start = |name: &str| schedule.iter().find(|(r, _)| r.name == name).unwrap().1;
+        assert_eq!(start("Rent"), day(2, 1));
+        assert_eq!(start(&record.name), day(3, 5));
+        assert_eq!(start("Salary"), chrono::Utc::now().date_naive());
+
+        // from before the history: nothing to anchor it on
+        db.write()
+            .unwrap()
+            .execute(
+                "INSERT INTO financial_record (id, name, amount, frequency, record_type)
+                VALUES (?1, 'Old', 9, 'Monthly', 'Expense')",
+                [Uuid::new_v4()],
+            )
+            .unwrap();
+        let schedule = get_calendar_schedule(&db).unwrap();
+        assert_eq!(schedule.len(), 3);
+        assert!(schedule.iter().all(|(r, _)| r.name != "Old"));
+    }
+
+    // a record with three linked monthly transactions, the last on 2025-03-05
+    fn accept_recurring_suggestion_fixture(db: &Db) -> FinancialRecord {
+        let gym = |month| {
+            LedgerEntry::new("acct", chrono::NaiveDate::from_ymd_opt(2025, month, 5).unwrap(), -60.0, "CITY GYM 42")
+        };
+        import_ledger_entries(db, vec![gym(1), gym(2), gym(3)], false).unwrap();
+        let key = get_recurring_suggestions(db).unwrap()[0].key.clone();
+        accept_recurring_suggestion(db, &key, None, "test").unwrap()
+    }
+
+    #[test]
+    fn test_rules_apply_on_import_and_rerun() {
+        let db = setup_db();
+        let mut rule = Rule::new("Coffee");
+        rule.payee_contains = Some("coffee".to_string());
+        rule.set_name = Some("Coffee".to_string());
+        let rule = add_rule(&db, rule).unwrap();
+        let date = chrono::NaiveDate::from_ymd_opt(2025, 1, 3).unwrap();
+        import_ledger_entries(&db, vec![LedgerEntry::new("acct", date, -4.5, "SQ *COFFEE 1234")], false).unwrap();
+        assert_eq!(get_all_ledger_entries(&db).unwrap()[0].display_name(), "Coffee");
+
+        let mut renamed = rule.clone();
+        renamed.set_name = Some("Café".to_string());
+        assert_eq!(test_rule(&db, renamed.clone(), None).unwrap().examples[0].1.display_name(), "Café");
+        update_rule(&db, &renamed).unwrap();
+        assert_eq!(apply_rules(&db).unwrap(), 1);
+        assert_eq!(apply_rules(&db).unwrap(), 0);
+        assert_eq!(get_all_ledger_entries(&db).unwrap()[0].display_name(), "Café");
+
+        delete_rule(&db, &rule.id).unwrap();
+        apply_rules(&db).unwrap();
+        assert_eq!(get_all_ledger_entries(&db).unwrap()[0].display_name(), "SQ *COFFEE 1234");
+    }
+
+    #[test]
+    fn test_restore_backup_can_be_undone() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = crate::pool::Pool::open(dir.path().join("budget.db").to_str().unwrap(), 1).unwrap();
+        let backups = BackupStore::new(dir.path().join("backups"), None);
+        let record = |name| FinancialRecord::new(name, 10.0, Frequency::Monthly, RecordType::Expense);
+
+        add_record(&db, &record("Rent"), "test").unwrap();
+        let backup = backup_database(&db, &backups).unwrap();
+        add_record(&db, &record("Gym"), "test").unwrap();
+
+        let (restored, before) = restore_backup(&db, &backups, &backup.name).unwrap();
+        assert_eq!(restored, backup);
+        assert_eq!(names(&db), vec!["Rent"]);
+
+        // nothing is snapshotted for a restore that fails
+        assert!(restore_backup(&db, &backups, "budget-20200101T000000.000Z.db").is_err());
+        assert_eq!(backups.list().unwrap().len(), 2);
+
+        restore_backup(&db, &backups, &before.name).unwrap();
+        let mut after = names(&db);
+        after.sort();
+        assert_eq!(after, vec!["Gym", "Rent"]);
+    }
+}
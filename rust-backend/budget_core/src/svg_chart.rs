@@ -0,0 +1,330 @@
+use crate::charts::{axis_step, money, short_money, MonthTotals, Slice};
+use crate::models::Summary;
+use crate::workbook::escape_xml;
+
+use std::f64::consts::{FRAC_PI_2, TAU};
+use std::fmt::Write;
+
+const WIDTH: f64 = 640.0;
+const HEIGHT: f64 = 320.0;
+// room for the value axis on the left and the labels below
+const PLOT_LEFT: f64 = 64.0;
+const PLOT_RIGHT: f64 = WIDTH - 20.0;
+const PLOT_TOP: f64 = 36.0;
+const PLOT_BOTTOM: f64 = HEIGHT - 44.0;
+
+const INCOME: &str = "#3a9a5a";
+const EXPENSE: &str = "#cc4d40";
+const DEBT: &str = "#e0a030";
+const NET: &str = "#3366cc";
+const GRID: &str = "#dddddd";
+const PALETTE: [&str; 8] = ["#cc4d40", "#e0a030", "#3366cc", "#3a9a5a", "#8e5ea2", "#2bb3c0", "#e377c2", "#999999"];
+
+// Numbers in attributes: two decimals at most
+fn n(value: f64) -> String {
+    let s = format!("{:.2}", value);
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn open(svg: &mut String, title: &str) {
+    let _ = write!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {w} {h}\" width=\"{w}\" height=\"{h}\" \
+         role=\"img\" aria-label=\"{t}\">\
+         <style>text{{font-family:system-ui,-apple-system,sans-serif;font-size:12px;fill:#444}}\
+         .title{{font-size:15px;font-weight:600;fill:#222}}</style>\
+         <text class=\"title\" x=\"{x}\" y=\"22\">{t}</text>",
+        w = n(WIDTH),
+        h = n(HEIGHT),
+        t = escape_xml(title),
+        x = n(PLOT_LEFT),
+    );
+}
+
+fn empty_chart(title: &str, message: &str) -> String {
+    let mut svg = String::new();
+    open(&mut svg, title);
+    let _ = write!(
+        svg,
+        "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text></svg>",
+        n(WIDTH / 2.0),
+        n(HEIGHT / 2.0),
+        escape_xml(message)
+    );
+    svg
+}
+
+// Value axis from `min` to `max` (both widened to a round step), returns a
+// function mapping a value to its y coordinate
+fn value_axis(svg: &mut String, min: f64, max: f64) -> impl Fn(f64) -> f64 + use<> {
+    let step = axis_step(max.max(-min).max(1.0));
+    let low = (min / step).floor().min(0.0) * step;
+    let high = (max / step).ceil().max(1.0) * step;
+    let scale = (PLOT_BOTTOM - PLOT_TOP) / (high - low);
+    let y = move |value: f64| PLOT_BOTTOM - (value - low) * scale;
+
+    let mut value = low;
+    while value <= high + step / 2.0 {
+        let _ = write!(
+            svg,
+            "<line x1=\"{l}\" x2=\"{r}\" y1=\"{y}\" y2=\"{y}\" stroke=\"{c}\" stroke-width=\"{w}\"/>\
+             <text x=\"{tx}\" y=\"{ty}\" text-anchor=\"end\">{label}</text>",
+            l = n(PLOT_LEFT),
+            r = n(PLOT_RIGHT),
+            y = n(y(value)),
+            c = if value == 0.0 { "#999999" } else { GRID },
+            w = if value == 0.0 { "1" } else { "0.5" },
+            tx = n(PLOT_LEFT - 8.0),
+            ty = n(y(value) + 4.0),
+            label = short_money(value),
+        );
+        value += step;
+    }
+    y
+}
+
+fn legend(svg: &mut String, entries: &[(&str, &str)], x: f64, y: f64) {
+    let mut x = x;
+    for (label, color) in entries {
+        let _ = write!(
+            svg,
+            "<rect x=\"{}\" y=\"{}\" width=\"10\" height=\"10\" fill=\"{}\"/><text x=\"{}\" y=\"{}\">{}</text>",
+            n(x),
+            n(y - 9.0),
+            color,
+            n(x + 14.0),
+            n(y),
+            escape_xml(label)
+        );
+        x += 28.0 + label.len() as f64 * 7.0;
+    }
+}
+
+/// Bars for the monthly income, expenses and debt payments, with the net
+/// in the corner
+pub fn income_vs_expenses(summary: &Summary) -> String {
+    let title = "Income vs. expenses per month";
+    let bars = [
+        ("Income", summary.monthly_income, INCOME),
+        ("Expenses", summary.monthly_expenses, EXPENSE),
+        ("Debt", summary.monthly_debt, DEBT),
+    ];
+    if bars.iter().all(|(_, value, _)| *value == 0.0) {
+        return empty_chart(title, "No records yet");
+    }
+
+    let mut svg = String::new();
+    open(&mut svg, title);
+    let y = value_axis(&mut svg, 0.0, bars.iter().map(|b| b.1).fold(0.0, f64::max));
+    let slot = (PLOT_RIGHT - PLOT_LEFT) / bars.len() as f64;
+    let width = slot * 0.5;
+    for (i, (label, value, color)) in bars.iter().enumerate() {
+        let center = PLOT_LEFT + slot * (i as f64 + 0.5);
+        let _ = write!(
+            svg,
+            "<rect x=\"{x}\" y=\"{top}\" width=\"{w}\" height=\"{h}\" fill=\"{color}\"><title>{label}: {value}</title></rect>\
+             <text x=\"{c}\" y=\"{vy}\" text-anchor=\"middle\">{value}</text>\
+             <text x=\"{c}\" y=\"{ly}\" text-anchor=\"middle\">{label}</text>",
+            x = n(center - width / 2.0),
+            top = n(y(*value)),
+            w = n(width),
+            h = n(y(0.0) - y(*value)),
+            c = n(center),
+            vy = n(y(*value) - 6.0),
+            ly = n(PLOT_BOTTOM + 18.0),
+            value = money(*value),
+        );
+    }
+    let _ = write!(
+        svg,
+        "<text x=\"{}\" y=\"22\" text-anchor=\"end\" fill=\"{}\">Net {}</text></svg>",
+        n(PLOT_RIGHT),
+        if summary.monthly_net < 0.0 { EXPENSE } else { INCOME },
+        money(summary.monthly_net)
+    );
+    svg
+}
+
+// SVG path for a pie slice between two angles, clockwise from 12 o'clock
+fn slice_path(cx: f64, cy: f64, r: f64, start: f64, end: f64) -> String {
+    let point = |angle: f64| (cx + r * (angle - FRAC_PI_2).cos(), cy + r * (angle - FRAC_PI_2).sin());
+    let (x1, y1) = point(start);
+    let (x2, y2) = point(end);
+    let large_arc = if end - start > TAU / 2.0 { 1 } else { 0 };
+    format!(
+        "M{} {}L{} {}A{} {} 0 {} 1 {} {}Z",
+        n(cx), n(cy), n(x1), n(y1), n(r), n(r), large_arc, n(x2), n(y2)
+    )
+}
+
+/// Pie of the monthly expenses by record, with a legend of amounts and shares
+pub fn expense_breakdown(slices: &[Slice]) -> String {
+    let title = "Monthly expenses by record";
+    let total: f64 = slices.iter().map(|s| s.monthly).sum();
+    if total <= 0.0 {
+        return empty_chart(title, "No expenses yet");
+    }
+
+    let mut svg = String::new();
+    open(&mut svg, title);
+    let (cx, cy, r) = (170.0, 176.0, 120.0);
+    let mut angle = 0.0;
+    for (i, slice) in slices.iter().enumerate() {
+        let color = PALETTE[i % PALETTE.len()];
+        let sweep = slice.monthly / total * TAU;
+        let share = slice.monthly / total * 100.0;
+        let tooltip = format!("{}: {} ({:.1}%)", escape_xml(&slice.label), money(slice.monthly), share);
+        // an arc can't go all the way round, a single slice is a circle
+        if slices.len() == 1 {
+            let _ = write!(
+                svg,
+                "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"><title>{}</title></circle>",
+                n(cx),
+                n(cy),
+                n(r),
+                color,
+                tooltip
+            );
+        } else {
+            let _ = write!(
+                svg,
+                "<path d=\"{}\" fill=\"{}\" stroke=\"#ffffff\" stroke-width=\"1\"><title>{}</title></path>",
+                slice_path(cx, cy, r, angle, angle + sweep),
+                color,
+                tooltip
+            );
+        }
+        angle += sweep;
+
+        let ly = 70.0 + i as f64 * 24.0;
+        let _ = write!(
+            svg,
+            "<rect x=\"330\" y=\"{}\" width=\"12\" height=\"12\" fill=\"{}\"/>\
+             <text x=\"350\" y=\"{}\">{}</text>\
+             <text x=\"{}\" y=\"{}\" text-anchor=\"end\">{} · {:.0}%</text>",
+            n(ly - 10.0),
+            color,
+            n(ly),
+            escape_xml(&shorten(&slice.label, 22)),
+            n(PLOT_RIGHT),
+            n(ly),
+            money(slice.monthly),
+            share
+        );
+    }
+    let _ = write!(
+        svg,
+        "<text x=\"330\" y=\"{}\" style=\"font-weight:600\">Total {}</text></svg>",
+        n(70.0 + slices.len() as f64 * 24.0 + 8.0),
+        money(total)
+    );
+    svg
+}
+
+fn shorten(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max_chars - 1).collect();
+    format!("{}…", cut.trim_end())
+}
+
+/// Lines for the income and outgoing money of each month ahead, plus the
+/// running balance if nothing else changed
+pub fn projection(months: &[MonthTotals]) -> String {
+    let title = format!("Projection, next {} months", months.len());
+    if months.is_empty() {
+        return empty_chart(&title, "Nothing to project");
+    }
+
+    let balances: Vec<f64> = months
+        .iter()
+        .scan(0.0, |balance, m| {
+            *balance += m.net();
+            Some(*balance)
+        })
+        .collect();
+    let values = months.iter().flat_map(|m| [m.income, m.outgoing()]).chain(balances.iter().copied());
+    let (min, max) = values.fold((0.0f64, 0.0f64), |(min, max), v| (min.min(v), max.max(v)));
+
+    let mut svg = String::new();
+    open(&mut svg, &title);
+    let y = value_axis(&mut svg, min, max);
+    let step = (PLOT_RIGHT - PLOT_LEFT) / months.len() as f64;
+    let x = |i: usize| PLOT_LEFT + step * (i as f64 + 0.5);
+
+    let line = |svg: &mut String, values: &[f64], color: &str, dashed: bool| {
+        let points: Vec<String> = values.iter().enumerate().map(|(i, v)| format!("{},{}", n(x(i)), n(y(*v)))).collect();
+        let _ = write!(
+            svg,
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"{}/>",
+            points.join(" "),
+            color,
+            if dashed { " stroke-dasharray=\"6 4\"" } else { "" }
+        );
+        for (i, value) in values.iter().enumerate() {
+            let _ = write!(
+                svg,
+                "<circle cx=\"{}\" cy=\"{}\" r=\"3\" fill=\"{}\"><title>{}</title></circle>",
+                n(x(i)),
+                n(y(*value)),
+                color,
+                money(*value)
+            );
+        }
+    };
+    line(&mut svg, &months.iter().map(|m| m.income).collect::<Vec<_>>(), INCOME, false);
+    line(&mut svg, &months.iter().map(|m| m.outgoing()).collect::<Vec<_>>(), EXPENSE, false);
+    line(&mut svg, &balances, NET, true);
+
+    for (i, m) in months.iter().enumerate() {
+        let _ = write!(
+            svg,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+            n(x(i)),
+            n(PLOT_BOTTOM + 18.0),
+            m.month.format("%b")
+        );
+    }
+    legend(&mut svg, &[("Income", INCOME), ("Expenses and debt", EXPENSE), ("Balance", NET)], PLOT_LEFT, HEIGHT - 8.0);
+    svg.push_str("</svg>");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::charts;
+    use crate::models::{FinancialRecord, Frequency, RecordType};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_slice_path_half_circle() {
+        assert_eq!(slice_path(100.0, 100.0, 50.0, 0.0, TAU / 2.0), "M100 100L100 50A50 50 0 0 1 100 150Z");
+        assert!(slice_path(0.0, 0.0, 1.0, 0.0, TAU * 0.75).contains(" 0 1 1 "));
+    }
+
+    #[test]
+    fn test_charts_render_escaped_svg() {
+        let records = vec![
+            FinancialRecord::new("Pay", 3000.0, Frequency::Monthly, RecordType::Income),
+            FinancialRecord::new("<Rent & co>", 1200.0, Frequency::Monthly, RecordType::Expense),
+            FinancialRecord::new("Food", 100.0, Frequency::Weekly, RecordType::Expense),
+        ];
+        let bars = income_vs_expenses(&Summary::from_records(&records));
+        assert!(bars.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(bars.contains("Net $1,366.67"));
+
+        let pie = expense_breakdown(&charts::breakdown(&records, RecordType::Expense, 8));
+        assert!(pie.contains("&lt;Rent &amp; co&gt;"));
+        assert!(!pie.contains("<Rent"));
+        assert_eq!(pie.matches("<path").count(), 2);
+
+        let months = charts::month_totals(&records, NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(), 12);
+        let line = projection(&months);
+        assert_eq!(line.matches("<polyline").count(), 3);
+        assert!(line.ends_with("</svg>"));
+
+        assert!(expense_breakdown(&[]).contains("No expenses yet"));
+    }
+}
@@ -0,0 +1,7 @@
+use std::sync::{Arc, Mutex};
+
+/// Any store behind a mutex, for stores that can only take one call at a time
+pub type Shared<S> = Arc<Mutex<S>>;
+
+/// The SQLite database the apps run on
+pub type Db = crate::pool::Pool;
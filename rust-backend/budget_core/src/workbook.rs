@@ -0,0 +1,410 @@
+use crate::models::{FinancialRecord, RecordType, Summary};
+use crate::app_error::AppError;
+use crate::{charts, ods, xlsx};
+
+use chrono::{Datelike, NaiveDate};
+use std::io::Write;
+use std::str::FromStr;
+
+// how many months the projection sheet covers, starting with the current one
+const PROJECTION_MONTHS: u32 = 12;
+const RECORD_TYPES: [RecordType; 3] = [RecordType::Income, RecordType::Expense, RecordType::Debt];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkbookFormat {
+    Xlsx,
+    Ods,
+}
+
+impl WorkbookFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            WorkbookFormat::Xlsx => "xlsx",
+            WorkbookFormat::Ods => "ods",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            WorkbookFormat::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            WorkbookFormat::Ods => "application/vnd.oasis.opendocument.spreadsheet",
+        }
+    }
+}
+
+impl FromStr for WorkbookFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "xlsx" => Ok(WorkbookFormat::Xlsx),
+            "ods" => Ok(WorkbookFormat::Ods),
+            other => Err(format!("unknown workbook format `{}` (expected xlsx or ods)", other)),
+        }
+    }
+}
+
+// How a cell is displayed. Each writer maps these onto its own style table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Default,
+    Header,
+    Currency,
+    CurrencyTotal,
+    Percent,
+    Date,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Empty,
+    Text(String),
+    Number(f64),
+    Date(NaiveDate),
+    /// `formula` in A1 syntax without the leading `=` (`SUM(D2:D9)`,
+    /// `Records!B2`), `value` is its result as computed here, shown until the
+    /// spreadsheet recalculates
+    Formula { formula: String, value: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cell {
+    pub value: Value,
+    pub style: Style,
+}
+
+impl Cell {
+    fn empty() -> Self {
+        Self { value: Value::Empty, style: Style::Default }
+    }
+
+    fn text(text: impl Into<String>) -> Self {
+        Self { value: Value::Text(text.into()), style: Style::Default }
+    }
+
+    fn header(text: impl Into<String>) -> Self {
+        Self { value: Value::Text(text.into()), style: Style::Header }
+    }
+
+    fn number(value: f64, style: Style) -> Self {
+        Self { value: Value::Number(value), style }
+    }
+
+    fn date(date: NaiveDate) -> Self {
+        Self { value: Value::Date(date), style: Style::Date }
+    }
+
+    fn formula(formula: impl Into<String>, value: f64, style: Style) -> Self {
+        Self { value: Value::Formula { formula: formula.into(), value }, style }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sheet {
+    pub name: String,
+    /// column widths in characters
+    pub widths: Vec<f64>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Workbook {
+    pub sheets: Vec<Sheet>,
+}
+
+// Column letters for a 0-based column index: 0 -> A, 25 -> Z, 26 -> AA
+pub fn column_name(mut col: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'A' + (col % 26) as u8);
+        if col < 26 {
+            break;
+        }
+        col = col / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).expect("column letters are ASCII")
+}
+
+// A1 reference for a 0-based column and a 1-based row
+pub fn cell_ref(col: usize, row: usize) -> String {
+    format!("{}{}", column_name(col), row)
+}
+
+pub(crate) fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // control characters aren't allowed in XML 1.0 at all
+            c if c.is_control() && !matches!(c, '\n' | '\t') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Build the report workbook: every record, the monthly summary, a per-type
+/// breakdown and a projection of the next 12 months starting with `today`'s.
+/// Totals are spreadsheet formulas, so they follow edits made to the sheets.
+pub fn build(records: &[FinancialRecord], today: NaiveDate) -> Workbook {
+    Workbook {
+        sheets: vec![
+            records_sheet(records),
+            summary_sheet(records),
+            breakdown_sheet(records),
+            projection_sheet(records, today),
+        ],
+    }
+}
+
+pub fn write(workbook: &Workbook, format: WorkbookFormat, out: impl Write) -> Result<(), AppError> {
+    match format {
+        WorkbookFormat::Xlsx => xlsx::write(workbook, out),
+        WorkbookFormat::Ods => ods::write(workbook, out),
+    }
+}
+
+// Records columns the other sheets refer to
+const RECORDS_SHEET: &str = "Records";
+const TYPE_COLUMN: usize = 1;
+const MONTHLY_COLUMN: usize = 4;
+
+fn records_sheet(records: &[FinancialRecord]) -> Sheet {
+    let mut rows = vec![
+        ["Name", "Type", "Frequency", "Amount", "Monthly", "Yearly", "Starts", "Payee", "Notes"]
+            .into_iter()
+            .map(Cell::header)
+            .collect(),
+    ];
+    for (i, record) in records.iter().enumerate() {
+        let row = i + 2;
+        let monthly = record.monthly_amount();
+        rows.push(vec![
+            Cell::text(&record.name),
+            Cell::text(record.record_type.to_string()),
+            Cell::text(record.frequency.to_string()),
+            Cell::number(record.amount, Style::Currency),
+            Cell::formula(
+                format!("{}*{}/12", cell_ref(3, row), record.frequency.occurrences_per_year()),
+                monthly,
+                Style::Currency,
+            ),
+            Cell::formula(format!("{}*12", cell_ref(MONTHLY_COLUMN, row)), monthly * 12.0, Style::Currency),
+            record.starts_on.map(Cell::date).unwrap_or_else(Cell::empty),
+            Cell::text(record.payee.clone().unwrap_or_default()),
+            Cell::text(record.notes.clone().unwrap_or_default()),
+        ]);
+    }
+    Sheet {
+        name: RECORDS_SHEET.to_string(),
+        widths: vec![28.0, 10.0, 11.0, 13.0, 13.0, 14.0, 12.0, 24.0, 40.0],
+        rows,
+    }
+}
+
+// Per-type totals, summed straight from the Records sheet
+fn summary_sheet(records: &[FinancialRecord]) -> Sheet {
+    let summary = Summary::from_records(records);
+    // at least one data row, so the ranges never run backwards into the header
+    let last = records.len().max(1) + 1;
+    let range = |col: usize| {
+        format!(
+            "{}!${}$2:${}${}",
+            RECORDS_SHEET,
+            column_name(col),
+            column_name(col),
+            last
+        )
+    };
+
+    let mut rows = vec![vec![Cell::header("Type"), Cell::header("Records"), Cell::header("Monthly"), Cell::header("Yearly")]];
+    for (i, line) in summary.lines.iter().enumerate() {
+        let row = i + 2;
+        rows.push(vec![
+            Cell::text(line.record_type.to_string()),
+            Cell::formula(
+                format!("COUNTIF({},\"{}\")", range(TYPE_COLUMN), line.record_type),
+                line.record_count as f64,
+                Style::Default,
+            ),
+            Cell::formula(
+                format!("SUMIF({},\"{}\",{})", range(TYPE_COLUMN), line.record_type, range(MONTHLY_COLUMN)),
+                line.monthly_total,
+                Style::Currency,
+            ),
+            Cell::formula(format!("{}*12", cell_ref(2, row)), line.yearly_total, Style::Currency),
+        ]);
+    }
+    // lines are always Income, Expense, Debt: rows 2, 3 and 4
+    let net = |col: usize| format!("{0}2-{0}3-{0}4", column_name(col));
+    rows.push(vec![
+        Cell::header("Net"),
+        Cell::empty(),
+        Cell::formula(net(2), summary.monthly_net, Style::CurrencyTotal),
+        Cell::formula(net(3), summary.monthly_net * 12.0, Style::CurrencyTotal),
+    ]);
+    let savings_rate = if summary.monthly_income == 0.0 { 0.0 } else { summary.monthly_net / summary.monthly_income };
+    rows.push(vec![
+        Cell::header("Savings rate"),
+        Cell::empty(),
+        Cell::formula("IF(C2=0,0,C5/C2)", savings_rate, Style::Percent),
+    ]);
+    Sheet {
+        name: "Summary".to_string(),
+        widths: vec![16.0, 10.0, 14.0, 14.0],
+        rows,
+    }
+}
+
+// One section per record type, largest monthly amounts first, with each
+// record's share of its type
+fn breakdown_sheet(records: &[FinancialRecord]) -> Sheet {
+    let mut rows: Vec<Vec<Cell>> = Vec::new();
+    for record_type in RECORD_TYPES {
+        if !rows.is_empty() {
+            rows.push(Vec::new());
+        }
+        let mut of_type: Vec<&FinancialRecord> = records.iter().filter(|r| r.record_type == record_type).collect();
+        of_type.sort_by(|a, b| b.monthly_amount().total_cmp(&a.monthly_amount()));
+        let total: f64 = of_type.iter().fold(0.0, |sum, r| sum + r.monthly_amount());
+
+        rows.push(vec![
+            Cell::header(record_type.to_string()),
+            Cell::header("Frequency"),
+            Cell::header("Monthly"),
+            Cell::header("Share"),
+        ]);
+        let first = rows.len() + 1;
+        let total_row = first + of_type.len();
+        for (i, record) in of_type.iter().enumerate() {
+            let row = first + i;
+            let share = if total == 0.0 { 0.0 } else { record.monthly_amount() / total };
+            rows.push(vec![
+                Cell::text(&record.name),
+                Cell::text(record.frequency.to_string()),
+                Cell::number(record.monthly_amount(), Style::Currency),
+                Cell::formula(
+                    format!("IF({1}=0,0,{0}/{1})", cell_ref(2, row), cell_ref(2, total_row)),
+                    share,
+                    Style::Percent,
+                ),
+            ]);
+        }
+        let total_cell = if of_type.is_empty() {
+            Cell::number(0.0, Style::CurrencyTotal)
+        } else {
+            Cell::formula(
+                format!("SUM({}:{})", cell_ref(2, first), cell_ref(2, total_row - 1)),
+                total,
+                Style::CurrencyTotal,
+            )
+        };
+        rows.push(vec![Cell::header(format!("Total {}", record_type)), Cell::empty(), total_cell]);
+    }
+    Sheet {
+        name: "By Type".to_string(),
+        widths: vec![28.0, 11.0, 14.0, 9.0],
+        rows,
+    }
+}
+
+fn projection_sheet(records: &[FinancialRecord], today: NaiveDate) -> Sheet {
+    let mut rows = vec![
+        ["Month", "Income", "Expenses", "Debt", "Net", "Cumulative"].into_iter().map(Cell::header).collect(),
+    ];
+    let first_month = today.with_day(1).expect("every month has a 1st");
+    let mut cumulative = 0.0;
+    for (i, totals) in charts::month_totals(records, first_month, PROJECTION_MONTHS).iter().enumerate() {
+        let row = i + 2;
+        cumulative += totals.net();
+        let cumulative_formula = if row == 2 {
+            cell_ref(4, row)
+        } else {
+            format!("{}+{}", cell_ref(5, row - 1), cell_ref(4, row))
+        };
+        rows.push(vec![
+            Cell::text(totals.month.format("%b %Y").to_string()),
+            Cell::number(totals.income, Style::Currency),
+            Cell::number(totals.expenses, Style::Currency),
+            Cell::number(totals.debt, Style::Currency),
+            Cell::formula(format!("B{0}-C{0}-D{0}", row), totals.net(), Style::Currency),
+            Cell::formula(cumulative_formula, cumulative, Style::Currency),
+        ]);
+    }
+    let last = PROJECTION_MONTHS as usize + 1;
+    let mut totals = vec![Cell::header("Total")];
+    for col in 1..=4 {
+        let value = rows[1..].iter().map(|r| cell_value(&r[col])).sum();
+        totals.push(Cell::formula(
+            format!("SUM({}:{})", cell_ref(col, 2), cell_ref(col, last)),
+            value,
+            Style::CurrencyTotal,
+        ));
+    }
+    rows.push(totals);
+    Sheet {
+        name: "Projection".to_string(),
+        widths: vec![12.0, 13.0, 13.0, 13.0, 13.0, 13.0],
+        rows,
+    }
+}
+
+fn cell_value(cell: &Cell) -> f64 {
+    match cell.value {
+        Value::Number(value) | Value::Formula { value, .. } => value,
+        _ => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Frequency;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_column_names() {
+        assert_eq!(column_name(0), "A");
+        assert_eq!(column_name(25), "Z");
+        assert_eq!(column_name(26), "AA");
+        assert_eq!(column_name(701), "ZZ");
+        assert_eq!(column_name(702), "AAA");
+    }
+
+    #[test]
+    fn test_build_sheets_and_totals() {
+        let records = vec![
+            FinancialRecord::new("Salary", 3000.0, Frequency::Monthly, RecordType::Income),
+            FinancialRecord::new("Rent", 1200.0, Frequency::Monthly, RecordType::Expense),
+            FinancialRecord::new("Phone", 40.0, Frequency::Monthly, RecordType::Expense),
+        ];
+        let workbook = build(&records, date(2025, 11, 20));
+        let names: Vec<&str> = workbook.sheets.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["Records", "Summary", "By Type", "Projection"]);
+
+        let summary = &workbook.sheets[1];
+        assert_eq!(
+            summary.rows[2][2].value,
+            Value::Formula { formula: "SUMIF(Records!$B$2:$B$4,\"Expense\",Records!$E$2:$E$4)".to_string(), value: 1240.0 }
+        );
+        assert_eq!(cell_value(&summary.rows[4][2]), 1760.0);
+
+        let breakdown = &workbook.sheets[2];
+        assert_eq!(breakdown.rows[5][0].value, Value::Text("Rent".to_string()));
+        assert_eq!(breakdown.rows[7][2].value, Value::Formula { formula: "SUM(C6:C7)".to_string(), value: 1240.0 });
+
+        let projection = &workbook.sheets[3];
+        assert_eq!(projection.rows[1][0].value, Value::Text("Nov 2025".to_string()));
+        assert_eq!(projection.rows[12][0].value, Value::Text("Oct 2026".to_string()));
+        assert_eq!(cell_value(&projection.rows[12][5]), 1760.0 * 12.0);
+        assert_eq!(cell_value(&projection.rows[13][4]), 1760.0 * 12.0);
+    }
+}
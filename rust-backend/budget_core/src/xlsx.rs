@@ -0,0 +1,230 @@
+use crate::workbook::{cell_ref, escape_xml, Cell, Sheet, Style, Value, Workbook};
+use crate::app_error::AppError;
+
+use chrono::NaiveDate;
+use std::io::{Cursor, Write};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const XML_HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n";
+const MAIN_NS: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+const REL_NS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+// Excel sheet names are limited to 31 characters
+const MAX_SHEET_NAME: usize = 31;
+
+// Index into cellXfs in STYLES
+fn style_index(style: Style) -> usize {
+    match style {
+        Style::Default => 0,
+        Style::Header => 1,
+        Style::Currency => 2,
+        Style::CurrencyTotal => 3,
+        Style::Percent => 4,
+        Style::Date => 5,
+    }
+}
+
+// numFmt 164 is our currency format, 10 (0.00%) and 14 (short date) are built in
+const STYLES: &str = concat!(
+    "<numFmts count=\"1\"><numFmt numFmtId=\"164\" formatCode=\"&quot;$&quot;#,##0.00\"/></numFmts>",
+    "<fonts count=\"2\">",
+    "<font><sz val=\"11\"/><name val=\"Calibri\"/></font>",
+    "<font><b/><sz val=\"11\"/><name val=\"Calibri\"/></font>",
+    "</fonts>",
+    "<fills count=\"2\"><fill><patternFill patternType=\"none\"/></fill><fill><patternFill patternType=\"gray125\"/></fill></fills>",
+    "<borders count=\"1\"><border><left/><right/><top/><bottom/><diagonal/></border></borders>",
+    "<cellStyleXfs count=\"1\"><xf numFmtId=\"0\" fontId=\"0\" fillId=\"0\" borderId=\"0\"/></cellStyleXfs>",
+    "<cellXfs count=\"6\">",
+    "<xf numFmtId=\"0\" fontId=\"0\" fillId=\"0\" borderId=\"0\" xfId=\"0\"/>",
+    "<xf numFmtId=\"0\" fontId=\"1\" fillId=\"0\" borderId=\"0\" xfId=\"0\" applyFont=\"1\"/>",
+    "<xf numFmtId=\"164\" fontId=\"0\" fillId=\"0\" borderId=\"0\" xfId=\"0\" applyNumberFormat=\"1\"/>",
+    "<xf numFmtId=\"164\" fontId=\"1\" fillId=\"0\" borderId=\"0\" xfId=\"0\" applyNumberFormat=\"1\" applyFont=\"1\"/>",
+    "<xf numFmtId=\"10\" fontId=\"0\" fillId=\"0\" borderId=\"0\" xfId=\"0\" applyNumberFormat=\"1\"/>",
+    "<xf numFmtId=\"14\" fontId=\"0\" fillId=\"0\" borderId=\"0\" xfId=\"0\" applyNumberFormat=\"1\"/>",
+    "</cellXfs>",
+    "<cellStyles count=\"1\"><cellStyle name=\"Normal\" xfId=\"0\" builtinId=\"0\"/></cellStyles>",
+);
+
+// Days since Excel's epoch, which is 1899-12-30 to account for the 1900 leap
+// year bug
+fn date_serial(date: NaiveDate) -> i64 {
+    let epoch = NaiveDate::from_ymd_opt(1899, 12, 30).expect("valid date");
+    (date - epoch).num_days()
+}
+
+fn sheet_name(name: &str) -> String {
+    // characters Excel refuses in sheet names
+    let cleaned: String = name.chars().filter(|c| !"[]:*?/\\".contains(*c)).collect();
+    escape_xml(&cleaned.chars().take(MAX_SHEET_NAME).collect::<String>())
+}
+
+fn content_types(sheets: usize) -> String {
+    let mut xml = format!(
+        "{}<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+         <Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+         <Default Extension=\"xml\" ContentType=\"application/xml\"/>\
+         <Override PartName=\"/xl/workbook.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml\"/>\
+         <Override PartName=\"/xl/styles.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml\"/>",
+        XML_HEADER
+    );
+    for i in 1..=sheets {
+        xml.push_str(&format!(
+            "<Override PartName=\"/xl/worksheets/sheet{}.xml\" \
+             ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml\"/>",
+            i
+        ));
+    }
+    xml.push_str("</Types>");
+    xml
+}
+
+fn package_rels() -> String {
+    format!(
+        "{}<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+         <Relationship Id=\"rId1\" Type=\"{}/officeDocument\" Target=\"xl/workbook.xml\"/>\
+         </Relationships>",
+        XML_HEADER, REL_NS
+    )
+}
+
+fn workbook_xml(workbook: &Workbook) -> String {
+    let mut xml = format!("{}<workbook xmlns=\"{}\" xmlns:r=\"{}\"><sheets>", XML_HEADER, MAIN_NS, REL_NS);
+    for (i, sheet) in workbook.sheets.iter().enumerate() {
+        xml.push_str(&format!(
+            "<sheet name=\"{}\" sheetId=\"{}\" r:id=\"rId{}\"/>",
+            sheet_name(&sheet.name),
+            i + 1,
+            i + 1
+        ));
+    }
+    // the cached formula results are ours, have Excel compute its own
+    xml.push_str("</sheets><calcPr fullCalcOnLoad=\"1\"/></workbook>");
+    xml
+}
+
+fn workbook_rels(sheets: usize) -> String {
+    let mut xml = format!(
+        "{}<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">",
+        XML_HEADER
+    );
+    for i in 1..=sheets {
+        xml.push_str(&format!(
+            "<Relationship Id=\"rId{0}\" Type=\"{1}/worksheet\" Target=\"worksheets/sheet{0}.xml\"/>",
+            i, REL_NS
+        ));
+    }
+    xml.push_str(&format!(
+        "<Relationship Id=\"rId{}\" Type=\"{}/styles\" Target=\"styles.xml\"/></Relationships>",
+        sheets + 1,
+        REL_NS
+    ));
+    xml
+}
+
+fn cell_xml(cell: &Cell, col: usize, row: usize) -> Option<String> {
+    let reference = cell_ref(col, row);
+    let style = style_index(cell.style);
+    let xml = match &cell.value {
+        Value::Empty if cell.style == Style::Default => return None,
+        Value::Empty => format!("<c r=\"{}\" s=\"{}\"/>", reference, style),
+        Value::Text(text) if text.is_empty() => return None,
+        Value::Text(text) => format!(
+            "<c r=\"{}\" s=\"{}\" t=\"inlineStr\"><is><t xml:space=\"preserve\">{}</t></is></c>",
+            reference,
+            style,
+            escape_xml(text)
+        ),
+        Value::Number(value) => format!("<c r=\"{}\" s=\"{}\"><v>{}</v></c>", reference, style, value),
+        Value::Date(date) => format!("<c r=\"{}\" s=\"{}\"><v>{}</v></c>", reference, style, date_serial(*date)),
+        Value::Formula { formula, value } => format!(
+            "<c r=\"{}\" s=\"{}\"><f>{}</f><v>{}</v></c>",
+            reference,
+            style,
+            escape_xml(formula),
+            value
+        ),
+    };
+    Some(xml)
+}
+
+fn sheet_xml(sheet: &Sheet) -> String {
+    let mut xml = format!("{}<worksheet xmlns=\"{}\">", XML_HEADER, MAIN_NS);
+    if !sheet.widths.is_empty() {
+        xml.push_str("<cols>");
+        for (i, width) in sheet.widths.iter().enumerate() {
+            xml.push_str(&format!(
+                "<col min=\"{0}\" max=\"{0}\" width=\"{1}\" customWidth=\"1\"/>",
+                i + 1,
+                width
+            ));
+        }
+        xml.push_str("</cols>");
+    }
+    xml.push_str("<sheetData>");
+    for (i, cells) in sheet.rows.iter().enumerate() {
+        let row = i + 1;
+        let cells: String = cells.iter().enumerate().filter_map(|(col, cell)| cell_xml(cell, col, row)).collect();
+        if !cells.is_empty() {
+            xml.push_str(&format!("<row r=\"{}\">{}</row>", row, cells));
+        }
+    }
+    xml.push_str("</sheetData></worksheet>");
+    xml
+}
+
+/// Write the workbook as an Office Open XML (.xlsx) spreadsheet
+pub fn write(workbook: &Workbook, mut out: impl Write) -> Result<(), AppError> {
+    let sheets = workbook.sheets.len();
+    let mut parts = vec![
+        ("[Content_Types].xml".to_string(), content_types(sheets)),
+        ("_rels/.rels".to_string(), package_rels()),
+        ("xl/workbook.xml".to_string(), workbook_xml(workbook)),
+        ("xl/_rels/workbook.xml.rels".to_string(), workbook_rels(sheets)),
+        ("xl/styles.xml".to_string(), format!("{}<styleSheet xmlns=\"{}\">{}</styleSheet>", XML_HEADER, MAIN_NS, STYLES)),
+    ];
+    for (i, sheet) in workbook.sheets.iter().enumerate() {
+        parts.push((format!("xl/worksheets/sheet{}.xml", i + 1), sheet_xml(sheet)));
+    }
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (name, xml) in parts {
+        zip.start_file(name, options)?;
+        zip.write_all(xml.as_bytes())?;
+    }
+    out.write_all(&zip.finish()?.into_inner())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{FinancialRecord, Frequency, RecordType};
+    use crate::workbook;
+    use std::io::Read;
+
+    #[test]
+    fn test_write_xlsx_package() {
+        let mut rent = FinancialRecord::new("Rent & <utilities>", 1500.0, Frequency::Monthly, RecordType::Expense);
+        rent.starts_on = NaiveDate::from_ymd_opt(2025, 1, 1);
+        let book = workbook::build(&[rent], NaiveDate::from_ymd_opt(2025, 1, 15).unwrap());
+
+        let mut out = Vec::new();
+        write(&book, &mut out).unwrap();
+        let mut zip = zip::ZipArchive::new(Cursor::new(out)).unwrap();
+        for name in ["[Content_Types].xml", "xl/workbook.xml", "xl/styles.xml", "xl/worksheets/sheet4.xml"] {
+            assert!(zip.by_name(name).is_ok(), "missing {}", name);
+        }
+
+        let mut records = String::new();
+        zip.by_name("xl/worksheets/sheet1.xml").unwrap().read_to_string(&mut records).unwrap();
+        assert!(records.contains("<t xml:space=\"preserve\">Rent &amp; &lt;utilities&gt;</t>"));
+        assert!(records.contains("<c r=\"D2\" s=\"2\"><v>1500</v></c>"));
+        assert!(records.contains("<c r=\"E2\" s=\"2\"><f>D2*12/12</f><v>1500</v></c>"));
+        assert!(records.contains("<c r=\"G2\" s=\"5\"><v>45658</v></c>"));
+
+        let mut workbook_xml = String::new();
+        zip.by_name("xl/workbook.xml").unwrap().read_to_string(&mut workbook_xml).unwrap();
+        assert!(workbook_xml.contains("<sheet name=\"By Type\" sheetId=\"3\" r:id=\"rId3\"/>"));
+    }
+}
@@ -0,0 +1,101 @@
+use budget_core::backup::Retention;
+use budget_core::encryption::{DbKey, KdfSettings};
+use log::warn;
+use std::env;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+// Runtime settings, read from the environment at startup
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// days a deleted record stays in the trash before it is purged for good
+    /// (BUDGET_TRASH_RETENTION_DAYS)
+    pub trash_retention_days: u32,
+    /// when set, the calendar feed is only served with `?token=<this>`, so
+    /// its URL can be handed to a calendar app without opening up the rest
+    /// (BUDGET_CALENDAR_TOKEN)
+    pub calendar_token: Option<String>,
+    /// read-only database connections, so that many requests can read at
+    /// once; writes always go through a single connection. On PostgreSQL
+    /// the pool has this many connections plus one, any of which takes
+    /// reads and writes (BUDGET_DB_READERS)
+    pub db_readers: usize,
+    /// passphrase of an encrypted SQLite database; a new database is created
+    /// encrypted when it's set (BUDGET_DB_PASSPHRASE)
+    pub db_passphrase: Option<String>,
+    /// how the key of an encrypted database is derived from its passphrase,
+    /// which has to match what it was encrypted with (BUDGET_DB_KDF_ITER,
+    /// BUDGET_DB_KDF_ALGORITHM)
+    pub db_kdf: KdfSettings,
+    /// where database snapshots go [default: backups/ in the app data dir]
+    /// (BUDGET_BACKUP_DIR)
+    pub backup_dir: Option<PathBuf>,
+    /// hours between scheduled snapshots, 0 for none
+    /// (BUDGET_BACKUP_INTERVAL_HOURS)
+    pub backup_interval_hours: u64,
+    /// which snapshots are kept after each scheduled one
+    /// (BUDGET_BACKUP_KEEP_DAILY, BUDGET_BACKUP_KEEP_WEEKLY,
+    /// BUDGET_BACKUP_KEEP_MONTHLY)
+    pub backup_retention: Retention,
+    /// when set, the admin API (backups and restores) is served under
+    /// /api/admin to requests with `Authorization: Bearer <this>`; without
+    /// it there is no admin API (BUDGET_ADMIN_TOKEN)
+    pub admin_token: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            trash_retention_days: 30,
+            calendar_token: None,
+            db_readers: 4,
+            db_passphrase: None,
+            db_kdf: KdfSettings::default(),
+            backup_dir: None,
+            backup_interval_hours: 24,
+            backup_retention: Retention::default(),
+            admin_token: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            trash_retention_days: env_or("BUDGET_TRASH_RETENTION_DAYS", default.trash_retention_days),
+            calendar_token: env::var("BUDGET_CALENDAR_TOKEN").ok().filter(|t| !t.trim().is_empty()),
+            db_readers: env_or("BUDGET_DB_READERS", default.db_readers),
+            db_passphrase: env::var("BUDGET_DB_PASSPHRASE").ok().filter(|p| !p.is_empty()),
+            db_kdf: KdfSettings {
+                iterations: env_or("BUDGET_DB_KDF_ITER", default.db_kdf.iterations),
+                algorithm: env_or("BUDGET_DB_KDF_ALGORITHM", default.db_kdf.algorithm),
+            },
+            backup_dir: env::var("BUDGET_BACKUP_DIR").ok().filter(|d| !d.trim().is_empty()).map(PathBuf::from),
+            backup_interval_hours: env_or("BUDGET_BACKUP_INTERVAL_HOURS", default.backup_interval_hours),
+            backup_retention: Retention {
+                daily: env_or("BUDGET_BACKUP_KEEP_DAILY", default.backup_retention.daily),
+                weekly: env_or("BUDGET_BACKUP_KEEP_WEEKLY", default.backup_retention.weekly),
+                monthly: env_or("BUDGET_BACKUP_KEEP_MONTHLY", default.backup_retention.monthly),
+            },
+            admin_token: env::var("BUDGET_ADMIN_TOKEN").ok().filter(|t| !t.trim().is_empty()),
+        }
+    }
+
+    /// The key to open the SQLite database with, if it's encrypted
+    pub fn db_key(&self) -> Option<DbKey> {
+        self.db_passphrase.as_ref().map(|passphrase| DbKey::new(passphrase.as_str(), self.db_kdf))
+    }
+}
+
+// parse an env var, falling back to `default` (with a warning) if it's unset
+// or can't be parsed
+fn env_or<T: FromStr>(key: &str, default: T) -> T {
+    match env::var(key) {
+        Ok(value) => value.parse().unwrap_or_else(|_| {
+            warn!("Ignoring invalid {}={:?}", key, value);
+            default
+        }),
+        Err(_) => default,
+    }
+}
@@ -0,0 +1,121 @@
+use budget_core::models::HistoryEntry;
+use budget_core::service;
+use crate::controllers::{message, render, with_db, StoreState};
+use budget_core::record_store::StoreHandle;
+
+use askama::Template;
+use log::{info, error};
+use axum::{
+    extract::{State, Query},
+    response::Html,
+    routing::get,
+    Router};
+use serde::Deserialize;
+use serde_json::Value;
+
+// page size of the activity feed when the client doesn't ask for one
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 500;
+
+#[derive(Deserialize)]
+pub struct ActivityParams {
+    pub limit: Option<usize>,
+    /// only entries older than this history id, for paging
+    pub before: Option<i64>,
+}
+
+/// A list of record changes, plus a loader for the next page of the feed
+#[derive(Template)]
+#[template(path = "activity/history.html")]
+pub(crate) struct History<'a> {
+    items: Vec<HistoryItem<'a>>,
+    next_page: Option<String>,
+}
+
+struct HistoryItem<'a> {
+    entry: &'a HistoryEntry,
+    name: &'a str,
+    changes: String,
+}
+
+impl<'a> History<'a> {
+    pub(crate) fn new(entries: &'a [HistoryEntry], next_page: Option<String>) -> Self {
+        let items = entries
+            .iter()
+            .map(|entry| HistoryItem {
+                entry,
+                name: entry.after.as_ref().or(entry.before.as_ref())
+                    .and_then(|r| r["name"].as_str())
+                    .unwrap_or(""),
+                changes: describe_changes(entry.before.as_ref(), entry.after.as_ref()),
+            })
+            .collect();
+        History { items, next_page }
+    }
+}
+
+pub fn routes<H: StoreHandle>(db: H) -> Router {
+    let state = StoreState {
+        database: db,
+    };
+
+    Router::new()
+        .route("/", get(get_activity::<H>))
+        .with_state(state)
+}
+
+// Global feed of record changes, newest first. The last item of a full page
+// loads the next one when scrolled into view.
+pub async fn get_activity<H: StoreHandle>(
+    Query(params): Query<ActivityParams>,
+    State(state): State<StoreState<H>>,
+) -> Html<String> {
+    info!("GET /activity request");
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    let entries = match with_db(&state.database, move |db| service::get_activity(db, limit, params.before)).await {
+        Ok(data) => data,
+        Err(e) => {
+            error!("Failed to fetch activity: {:?}", e);
+            return message("Error retrieving activity");
+        }
+    };
+
+    if entries.is_empty() {
+        return if params.before.is_some() { Html(String::new()) } else { message("No activity yet") };
+    }
+
+    let next_page = (entries.len() == limit).then(|| {
+        let oldest = entries.last().map(|e| e.id).unwrap_or_default();
+        format!("/api/activity?limit={}&before={}", limit, oldest)
+    });
+    render(&History::new(&entries, next_page))
+}
+
+// "amount: 1500.0 → 1600.0, name: Rent → Rent (new place)" for updates,
+// empty for anything that doesn't have both snapshots
+fn describe_changes(before: Option<&Value>, after: Option<&Value>) -> String {
+    let (Some(Value::Object(before)), Some(Value::Object(after))) = (before, after) else {
+        return String::new();
+    };
+
+    after
+        .iter()
+        .filter(|(field, value)| before.get(*field) != Some(value))
+        .map(|(field, value)| format!(
+            "{}: {} → {}",
+            field,
+            before.get(field).map(display_value).unwrap_or_default(),
+            display_value(value)
+        ))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => "(none)".to_string(),
+        other => other.to_string(),
+    }
+}
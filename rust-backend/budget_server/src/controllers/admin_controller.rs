@@ -0,0 +1,82 @@
+use budget_core::backup::{Backup, BackupStore};
+use budget_core::service;
+use crate::http_error::HttpError;
+use crate::controllers::with_db;
+use budget_core::types::Db;
+
+use log::{info, warn};
+use axum::{
+    extract::{Path, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json,
+    Router};
+use serde::Serialize;
+
+#[derive(Clone)]
+pub struct AdminState {
+    pub database: Db,
+    pub backups: BackupStore,
+}
+
+#[derive(Serialize)]
+pub struct RestoreResponse {
+    pub restored: Backup,
+    /// the database as it was before the restore
+    pub previous: Backup,
+}
+
+// Only mounted when BUDGET_ADMIN_TOKEN is set; every request has to carry it
+// as `Authorization: Bearer <token>`
+pub fn routes(db: Db, backups: BackupStore, token: String) -> Router {
+    let state = AdminState {
+        database: db,
+        backups,
+    };
+
+    Router::new()
+        .route("/backups", get(list_backups).post(take_backup))
+        .route("/backups/:name/restore", post(restore_backup))
+        .layer(middleware::from_fn(move |request, next| require_token(token.clone(), request, next)))
+        .with_state(state)
+}
+
+async fn require_token(token: String, request: Request, next: Next) -> Response {
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if bearer != Some(token.as_str()) {
+        warn!("Admin request to {} with a missing or wrong token", request.uri());
+        return (StatusCode::UNAUTHORIZED, "invalid admin token").into_response();
+    }
+    next.run(request).await
+}
+
+// GET /admin/backups the snapshots in the backup directory, newest first
+async fn list_backups(State(state): State<AdminState>) -> Result<Json<Vec<Backup>>, HttpError> {
+    info!("GET /admin/backups request");
+    let backups = with_db(&state, |state| state.backups.list()).await?;
+    Ok(Json(backups))
+}
+
+// POST /admin/backups snapshot the database now
+async fn take_backup(State(state): State<AdminState>) -> Result<Json<Backup>, HttpError> {
+    info!("POST /admin/backups request");
+    let backup = with_db(&state, |state| service::backup_database(&state.database, &state.backups)).await?;
+    Ok(Json(backup))
+}
+
+// POST /admin/backups/:name/restore replace the database with a snapshot
+async fn restore_backup(
+    Path(name): Path<String>,
+    State(state): State<AdminState>,
+) -> Result<Json<RestoreResponse>, HttpError> {
+    info!("POST /admin/backups/{}/restore request", name);
+    let (restored, previous) =
+        with_db(&state, move |state| service::restore_backup(&state.database, &state.backups, &name)).await?;
+    Ok(Json(RestoreResponse { restored, previous }))
+}
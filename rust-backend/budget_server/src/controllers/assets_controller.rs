@@ -0,0 +1,29 @@
+use log::info;
+use axum::{
+    http::header,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router};
+
+// Compiled into the binary, so the UI works from wherever the server runs
+const APP_CSS: &str = include_str!("../../static/app.css");
+const APP_JS: &str = include_str!("../../static/app.js");
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/app.css", get(|| async { asset(APP_CSS, "text/css; charset=utf-8") }))
+        .route("/app.js", get(|| async { asset(APP_JS, "text/javascript; charset=utf-8") }))
+}
+
+fn asset(body: &'static str, content_type: &'static str) -> Response {
+    info!("GET static asset ({})", content_type);
+    (
+        [
+            (header::CONTENT_TYPE, content_type),
+            // they only change with the binary, let the browser revalidate
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        body,
+    )
+        .into_response()
+}
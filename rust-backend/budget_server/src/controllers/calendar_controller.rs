@@ -0,0 +1,64 @@
+use budget_core::icalendar;
+use budget_core::service;
+use crate::http_error::HttpError;
+use crate::controllers::with_db;
+use budget_core::types::Db;
+
+use log::{info, warn};
+use axum::{
+    extract::{State, Query},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router};
+use chrono::Utc;
+use serde::Deserialize;
+
+#[derive(Clone)]
+pub struct CalendarState {
+    pub database: Db,
+    pub token: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CalendarParams {
+    /// must match BUDGET_CALENDAR_TOKEN when that is set
+    pub token: Option<String>,
+}
+
+pub fn routes(db: Db, token: Option<String>) -> Router {
+    let state = CalendarState {
+        database: db,
+        token,
+    };
+
+    Router::new()
+        .route("/calendar.ics", get(calendar_feed))
+        .with_state(state)
+}
+
+// GET /calendar.ics?token=... subscribable feed of every recurring record
+async fn calendar_feed(
+    Query(params): Query<CalendarParams>,
+    State(state): State<CalendarState>,
+) -> Result<Response, HttpError> {
+    info!("GET /calendar.ics request");
+    if let Some(expected) = &state.token
+        && params.token.as_deref() != Some(expected.as_str())
+    {
+        warn!("Calendar feed requested with a missing or wrong token");
+        return Ok((StatusCode::UNAUTHORIZED, "invalid calendar token").into_response());
+    }
+
+    let schedule = with_db(&state.database, service::get_calendar_schedule).await?;
+    let mut body = Vec::new();
+    icalendar::write(&schedule, Utc::now(), &mut body)?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/calendar; charset=utf-8"),
+            (header::CONTENT_DISPOSITION, "inline; filename=\"budget.ics\""),
+        ],
+        body,
+    )
+        .into_response())
+}
@@ -0,0 +1,80 @@
+use budget_core::charts;
+use budget_core::svg_chart;
+use budget_core::models::RecordType;
+use budget_core::service;
+use crate::http_error::HttpError;
+use crate::controllers::{render, with_db, StoreState};
+use budget_core::record_store::StoreHandle;
+
+use askama::Template;
+use log::info;
+use axum::{
+    extract::State,
+    http::header,
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Router};
+use chrono::{Datelike, Local};
+
+// months the projection chart looks ahead
+const PROJECTION_MONTHS: u32 = 12;
+// expense records shown in the pie before the rest become "Other"
+const BREAKDOWN_SLICES: usize = 8;
+
+pub fn routes<H: StoreHandle>(db: H) -> Router {
+    let state = StoreState {
+        database: db,
+    };
+
+    Router::new()
+        .route("/", get(dashboard))
+        .route("/income-expense.svg", get(income_expense::<H>))
+        .route("/expense-breakdown.svg", get(expense_breakdown::<H>))
+        .route("/projection.svg", get(projection::<H>))
+        .with_state(state)
+}
+
+fn svg(body: String) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, "image/svg+xml"),
+            // the charts follow the records, never serve a stale one
+            (header::CACHE_CONTROL, "no-store"),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+#[derive(Template)]
+#[template(path = "charts/dashboard.html")]
+struct Dashboard {
+    months: u32,
+}
+
+// GET /charts: all charts as one fragment, for hx-get into the dashboard
+async fn dashboard() -> Html<String> {
+    info!("GET /charts request");
+    render(&Dashboard { months: PROJECTION_MONTHS })
+}
+
+async fn income_expense<H: StoreHandle>(State(state): State<StoreState<H>>) -> Result<Response, HttpError> {
+    info!("GET /charts/income-expense.svg request");
+    let summary = with_db(&state.database, service::get_summary).await?;
+    Ok(svg(svg_chart::income_vs_expenses(&summary)))
+}
+
+async fn expense_breakdown<H: StoreHandle>(State(state): State<StoreState<H>>) -> Result<Response, HttpError> {
+    info!("GET /charts/expense-breakdown.svg request");
+    let records = with_db(&state.database, service::get_all_records).await?;
+    let slices = charts::breakdown(&records, RecordType::Expense, BREAKDOWN_SLICES);
+    Ok(svg(svg_chart::expense_breakdown(&slices)))
+}
+
+async fn projection<H: StoreHandle>(State(state): State<StoreState<H>>) -> Result<Response, HttpError> {
+    info!("GET /charts/projection.svg request");
+    let today = Local::now().date_naive();
+    let first_month = today.with_day(1).expect("every month has a 1st");
+    let months = with_db(&state.database, move |db| service::get_forecast(db, first_month, PROJECTION_MONTHS)).await?;
+    Ok(svg(svg_chart::projection(&months)))
+}
@@ -0,0 +1,202 @@
+use budget_core::export::{self, ExportFormat};
+use budget_core::qif;
+use budget_core::hledger;
+use budget_core::workbook::{self, WorkbookFormat};
+use budget_core::models::{Frequency, RecordType};
+use budget_core::service;
+use crate::http_error::HttpError;
+use crate::controllers::{with_db, StoreState};
+use budget_core::record_store::StoreHandle;
+use budget_core::types::Db;
+
+use std::str::FromStr;
+use log::info;
+use chrono::Local;
+use axum::{
+    extract::{State, Query},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router};
+use serde::Deserialize;
+
+// State of the exports that include ledger transactions, which are in the
+// SQLite database
+#[derive(Clone)]
+pub struct ExportState {
+    pub database: Db,
+}
+
+#[derive(Deserialize)]
+pub struct ExportParams {
+    /// csv (default) or json, xlsx (default) or ods for the workbook
+    pub format: Option<String>,
+    /// only records of this type
+    #[serde(rename = "type")]
+    pub record_type: Option<String>,
+    /// only records with this frequency
+    pub frequency: Option<String>,
+}
+
+pub fn routes<H: StoreHandle>(db: H) -> Router {
+    let state = StoreState {
+        database: db,
+    };
+
+    Router::new()
+        .route("/", get(export_records::<H>))
+        .route("/summary", get(export_summary::<H>))
+        .route("/workbook", get(export_workbook::<H>))
+        .with_state(state)
+}
+
+pub fn ledger_routes(db: Db) -> Router {
+    let state = ExportState {
+        database: db,
+    };
+
+    Router::new()
+        .route("/qif", get(export_qif))
+        .route("/journal", get(export_journal))
+        .with_state(state)
+}
+
+// GET /export?format=csv|json&type=Expense&frequency=Monthly
+async fn export_records<H: StoreHandle>(
+    Query(params): Query<ExportParams>,
+    State(state): State<StoreState<H>>,
+) -> Result<Response, HttpError> {
+    info!("GET /export request");
+    let format = match parse_format(params.format.as_deref()) {
+        Ok(format) => format,
+        Err(e) => return Ok(bad_request(e)),
+    };
+    let record_type = match params.record_type.as_deref().filter(|t| !t.is_empty()) {
+        Some(t) => match RecordType::parse_lenient(t) {
+            Some(t) => Some(t),
+            None => return Ok(bad_request(format!("unknown record type `{}`", t))),
+        },
+        None => None,
+    };
+    let frequency = match params.frequency.as_deref().filter(|f| !f.is_empty()) {
+        Some(f) => match Frequency::parse_lenient(f) {
+            Some(f) => Some(f),
+            None => return Ok(bad_request(format!("unknown frequency `{}`", f))),
+        },
+        None => None,
+    };
+
+    let records = with_db(&state.database, move |db| service::get_records_filtered(db, record_type, frequency)).await?;
+    let mut body = Vec::new();
+    export::write_records(&records, format, &mut body)?;
+    Ok(download(body, format, "budget-records"))
+}
+
+// GET /export/summary?format=csv|json
+async fn export_summary<H: StoreHandle>(
+    Query(params): Query<ExportParams>,
+    State(state): State<StoreState<H>>,
+) -> Result<Response, HttpError> {
+    info!("GET /export/summary request");
+    let format = match parse_format(params.format.as_deref()) {
+        Ok(format) => format,
+        Err(e) => return Ok(bad_request(e)),
+    };
+
+    let summary = with_db(&state.database, service::get_summary).await?;
+    let mut body = Vec::new();
+    export::write_summary(&summary, format, &mut body)?;
+    Ok(download(body, format, "budget-summary"))
+}
+
+// GET /export/qif: every record as a budgeted category plus all ledger transactions
+async fn export_qif(State(state): State<ExportState>) -> Result<Response, HttpError> {
+    info!("GET /export/qif request");
+    let (records, entries) = with_db(&state.database, |db| {
+        Ok::<_, rusqlite::Error>((service::get_all_records(db)?, service::get_all_ledger_entries(db)?))
+    })
+    .await?;
+    let mut body = Vec::new();
+    qif::write(&records, &entries, &mut body)?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/qif".to_string()),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"budget.qif\"".to_string()),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+// GET /export/journal: records as periodic transactions plus the actual
+// transactions, for ledger/hledger
+async fn export_journal(State(state): State<ExportState>) -> Result<Response, HttpError> {
+    info!("GET /export/journal request");
+    let (records, entries) = with_db(&state.database, |db| {
+        Ok::<_, rusqlite::Error>((service::get_all_records(db)?, service::get_all_ledger_entries(db)?))
+    })
+    .await?;
+    let mut body = Vec::new();
+    hledger::write(&records, &entries, &mut body)?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/plain; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"budget.journal\"".to_string()),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+// GET /export/workbook?format=xlsx|ods: records, summary, per-type breakdown
+// and a 12-month projection as one spreadsheet
+async fn export_workbook<H: StoreHandle>(
+    Query(params): Query<ExportParams>,
+    State(state): State<StoreState<H>>,
+) -> Result<Response, HttpError> {
+    info!("GET /export/workbook request");
+    let format = match params.format.as_deref().filter(|f| !f.is_empty()) {
+        Some(f) => match WorkbookFormat::from_str(f) {
+            Ok(format) => format,
+            Err(e) => return Ok(bad_request(e)),
+        },
+        None => WorkbookFormat::Xlsx,
+    };
+
+    let records = with_db(&state.database, service::get_all_records).await?;
+    let mut body = Vec::new();
+    workbook::write(&workbook::build(&records, Local::now().date_naive()), format, &mut body)?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"budget.{}\"", format.extension()),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+fn parse_format(format: Option<&str>) -> Result<ExportFormat, String> {
+    format.filter(|f| !f.is_empty()).map(ExportFormat::from_str).unwrap_or(Ok(ExportFormat::Csv))
+}
+
+fn bad_request(message: String) -> Response {
+    (StatusCode::BAD_REQUEST, message).into_response()
+}
+
+fn download(body: Vec<u8>, format: ExportFormat, file_stem: &str) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}.{}\"", file_stem, format.extension()),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
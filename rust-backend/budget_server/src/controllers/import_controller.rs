@@ -0,0 +1,308 @@
+use budget_core::models::{Frequency, ImportReport, LedgerImportReport, RecordType};
+use budget_core::csv_import::{self, ColumnMapping};
+use budget_core::ofx_import;
+use budget_core::qif::{self, QifOptions};
+use budget_core::hledger;
+use budget_core::service;
+use crate::controllers::{message, render, with_db, Actor, StoreState};
+use budget_core::record_store::StoreHandle;
+use budget_core::types::Db;
+
+use askama::Template;
+use std::collections::HashMap;
+use log::{info, error};
+use axum::{
+    extract::{DefaultBodyLimit, Multipart, State},
+    response::Html,
+    routing::post,
+    Router};
+
+// statements with a few years of history can get big
+const MAX_IMPORT_BYTES: usize = 25 * 1024 * 1024;
+
+// State of the statement imports, whose transactions go to the ledger in the
+// SQLite database
+#[derive(Clone)]
+pub struct ImportState {
+    pub database: Db,
+}
+
+#[derive(Template)]
+#[template(path = "import/report.html")]
+struct ReportView<'a> {
+    report: &'a ImportReport,
+    summary: String,
+}
+
+#[derive(Template)]
+#[template(path = "import/ledger_report.html")]
+struct LedgerReportView<'a> {
+    report: &'a LedgerImportReport,
+    summary: String,
+}
+
+#[derive(Template)]
+#[template(path = "import/qif_report.html")]
+struct QifReportView<'a> {
+    records: ReportView<'a>,
+    transactions: LedgerReportView<'a>,
+}
+
+// Imports of budget records
+pub fn routes<H: StoreHandle>(db: H) -> Router {
+    let state = StoreState {
+        database: db,
+    };
+
+    Router::new()
+        .route("/csv", post(import_csv::<H>))
+        .route("/journal", post(import_journal::<H>))
+        .layer(DefaultBodyLimit::max(MAX_IMPORT_BYTES))
+        .with_state(state)
+}
+
+// Imports of bank statements, which also bring in ledger transactions
+pub fn ledger_routes(db: Db) -> Router {
+    let state = ImportState {
+        database: db,
+    };
+
+    Router::new()
+        .route("/ofx", post(import_ofx))
+        .route("/qif", post(import_qif))
+        .layer(DefaultBodyLimit::max(MAX_IMPORT_BYTES))
+        .with_state(state)
+}
+
+// An uploaded file plus the other (text) fields of an import form
+pub(crate) struct ImportUpload {
+    pub file: Vec<u8>,
+    pub fields: HashMap<String, String>,
+}
+
+impl ImportUpload {
+    pub(crate) async fn read(mut multipart: Multipart) -> Result<Self, String> {
+        let mut upload = ImportUpload { file: Vec::new(), fields: HashMap::new() };
+        while let Some(field) = multipart.next_field().await.map_err(|e| e.body_text())? {
+            let name = field.name().unwrap_or_default().to_string();
+            if name == "file" {
+                upload.file = field.bytes().await.map_err(|e| e.body_text())?.to_vec();
+            } else {
+                let value = field.text().await.map_err(|e| e.body_text())?;
+                upload.fields.insert(name, value);
+            }
+        }
+        Ok(upload)
+    }
+
+    // a text field, None if it's missing or blank
+    pub(crate) fn field(&self, name: &str) -> Option<String> {
+        self.fields.get(name).map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+    }
+
+    // checkbox style flag: present and not "false"/"off"
+    pub(crate) fn flag(&self, name: &str) -> bool {
+        self.field(name).is_some_and(|v| v != "false" && v != "off" && v != "0")
+    }
+}
+
+// Multipart form: `file`, optional `<field>_column` mappings (name, amount,
+// frequency, type, notes, payee), `default_frequency`, `default_record_type`,
+// `delimiter`, and the `dry_run` / `skip_invalid` flags.
+async fn import_csv<H: StoreHandle>(
+    State(state): State<StoreState<H>>,
+    Actor(actor): Actor,
+    multipart: Multipart,
+) -> Html<String> {
+    info!("POST /import/csv request");
+
+    let upload = match ImportUpload::read(multipart).await {
+        Ok(upload) => upload,
+        Err(e) => {
+            error!("Malformed CSV upload: {}", e);
+            return message(format!("Error reading upload: {}", e));
+        }
+    };
+
+    let mapping = match mapping_from_upload(&upload) {
+        Ok(mapping) => mapping,
+        Err(e) => return message(e),
+    };
+
+    // parsing a big statement takes a while too, so it goes along
+    let result = with_db(&state.database, move |db| {
+        csv_import::parse(upload.file.as_slice(), &mapping).and_then(|rows| {
+            service::import_records(db, rows, upload.flag("dry_run"), upload.flag("skip_invalid"), &actor)
+        })
+    })
+    .await;
+
+    match result {
+        Ok(report) => render(&report_view(&report)),
+        Err(e) => {
+            error!("CSV import failed: {}", e);
+            message(format!("Import failed: {}", e))
+        }
+    }
+}
+
+// Multipart form: `file` (an OFX or QFX statement) and the `dry_run` flag.
+// Transactions go to the ledger, not the budget records.
+async fn import_ofx(
+    State(state): State<ImportState>,
+    multipart: Multipart,
+) -> Html<String> {
+    info!("POST /import/ofx request");
+
+    let upload = match ImportUpload::read(multipart).await {
+        Ok(upload) => upload,
+        Err(e) => {
+            error!("Malformed OFX upload: {}", e);
+            return message(format!("Error reading upload: {}", e));
+        }
+    };
+
+    // OFX 1.x files are often Latin-1/CP1252, don't reject them over a stray byte
+    let result = with_db(&state.database, move |db| {
+        let text = String::from_utf8_lossy(&upload.file);
+        ofx_import::parse(&text).and_then(|entries| service::import_ledger_entries(db, entries, upload.flag("dry_run")))
+    })
+    .await;
+
+    match result {
+        Ok(report) => render(&ledger_report_view(&report)),
+        Err(e) => {
+            error!("OFX import failed: {}", e);
+            message(format!("Import failed: {}", e))
+        }
+    }
+}
+
+// Multipart form: `file`, `account` (for transactions outside an !Account
+// block), and the `day_first` / `dry_run` / `skip_invalid` flags. Budgeted
+// categories become records, transactions go to the ledger.
+async fn import_qif(
+    State(state): State<ImportState>,
+    Actor(actor): Actor,
+    multipart: Multipart,
+) -> Html<String> {
+    info!("POST /import/qif request");
+
+    let upload = match ImportUpload::read(multipart).await {
+        Ok(upload) => upload,
+        Err(e) => {
+            error!("Malformed QIF upload: {}", e);
+            return message(format!("Error reading upload: {}", e));
+        }
+    };
+
+    let options = QifOptions {
+        account: upload.field("account").unwrap_or_else(|| QifOptions::default().account),
+        day_first: upload.flag("day_first"),
+    };
+    // desktop tools often write QIF in the system code page
+    let result = with_db(&state.database, move |db| {
+        let text = String::from_utf8_lossy(&upload.file);
+        qif::parse(&text, &options).and_then(|file| {
+            service::import_qif(db, file, upload.flag("dry_run"), upload.flag("skip_invalid"), &actor)
+        })
+    })
+    .await;
+
+    match result {
+        Ok((records, transactions)) => render(&QifReportView {
+            records: report_view(&records),
+            transactions: ledger_report_view(&transactions),
+        }),
+        Err(e) => {
+            error!("QIF import failed: {}", e);
+            message(format!("Import failed: {}", e))
+        }
+    }
+}
+
+// Multipart form: `file` (a ledger/hledger journal) and the `dry_run` /
+// `skip_invalid` flags. Only periodic transactions are imported, as records.
+async fn import_journal<H: StoreHandle>(
+    State(state): State<StoreState<H>>,
+    Actor(actor): Actor,
+    multipart: Multipart,
+) -> Html<String> {
+    info!("POST /import/journal request");
+
+    let upload = match ImportUpload::read(multipart).await {
+        Ok(upload) => upload,
+        Err(e) => {
+            error!("Malformed journal upload: {}", e);
+            return message(format!("Error reading upload: {}", e));
+        }
+    };
+
+    let result = with_db(&state.database, move |db| {
+        let rows = hledger::parse(&String::from_utf8_lossy(&upload.file));
+        service::import_records(db, rows, upload.flag("dry_run"), upload.flag("skip_invalid"), &actor)
+    })
+    .await;
+    match result {
+        Ok(report) => render(&report_view(&report)),
+        Err(e) => {
+            error!("Journal import failed: {}", e);
+            message(format!("Import failed: {}", e))
+        }
+    }
+}
+
+fn mapping_from_upload(upload: &ImportUpload) -> Result<ColumnMapping, String> {
+    let default_frequency = match upload.field("default_frequency") {
+        Some(f) => Frequency::parse_lenient(&f).ok_or_else(|| format!("Unknown default frequency `{}`", f))?,
+        None => Frequency::Monthly,
+    };
+    let default_record_type = upload
+        .field("default_record_type")
+        .map(|t| RecordType::parse_lenient(&t).ok_or_else(|| format!("Unknown default record type `{}`", t)))
+        .transpose()?;
+    let delimiter = match upload.field("delimiter").as_deref() {
+        None => b',',
+        Some("\\t") | Some("tab") => b'\t',
+        Some(d) if d.len() == 1 => d.as_bytes()[0],
+        Some(d) => return Err(format!("Delimiter must be a single character, got `{}`", d)),
+    };
+
+    Ok(ColumnMapping {
+        name: upload.field("name_column"),
+        amount: upload.field("amount_column"),
+        frequency: upload.field("frequency_column"),
+        record_type: upload.field("type_column"),
+        notes: upload.field("notes_column"),
+        payee: upload.field("payee_column"),
+        starts_on: upload.field("starts_on_column"),
+        default_frequency,
+        default_record_type,
+        delimiter,
+    })
+}
+
+// Summary line plus a row-by-row table, errors included
+fn report_view(report: &ImportReport) -> ReportView<'_> {
+    let errors = report.error_count();
+    let summary = if report.dry_run {
+        format!("Dry run: {} rows would be imported, {} have errors", report.rows.len() - errors, errors)
+    } else if report.imported == 0 && errors > 0 {
+        format!("Nothing imported: {} rows have errors. Fix them or skip invalid rows.", errors)
+    } else {
+        format!("Imported {} records, skipped {} rows with errors", report.imported, errors)
+    };
+    ReportView { report, summary }
+}
+
+fn ledger_report_view(report: &LedgerImportReport) -> LedgerReportView<'_> {
+    let summary = if report.dry_run {
+        format!(
+            "Dry run: {} transactions would be imported, {} were imported before",
+            report.rows.len() - report.duplicates, report.duplicates
+        )
+    } else {
+        format!("Imported {} transactions, skipped {} already imported", report.imported, report.duplicates)
+    };
+    LedgerReportView { report, summary }
+}
@@ -0,0 +1,175 @@
+use budget_core::models::{FinancialRecord, LedgerEntry, RecurringSuggestion};
+use budget_core::service;
+use crate::controllers::{message, render, with_db, Actor};
+use budget_core::types::Db;
+
+use askama::Template;
+use uuid::Uuid;
+use log::{info, error};
+use axum::{
+    extract::{State, Path, Query, Form},
+    response::Html,
+    routing::{get, post},
+    Router};
+use axum_macros::debug_handler;
+use serde::Deserialize;
+
+const DEFAULT_LIMIT: usize = 200;
+const MAX_LIMIT: usize = 2000;
+
+#[derive(Clone)]
+pub struct LedgerState {
+    pub database: Db,
+}
+
+#[derive(Deserialize)]
+pub struct LedgerParams {
+    pub limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct LinkForm {
+    /// blank to unlink
+    pub record_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AcceptForm {
+    pub key: String,
+    /// record name to use instead of the suggested one
+    pub name: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "ledger/table.html")]
+struct LedgerTable<'a> {
+    entries: &'a [LedgerEntry],
+    /// choices for the record picker
+    records: &'a [FinancialRecord],
+}
+
+#[derive(Template)]
+#[template(path = "ledger/entry.html")]
+struct LedgerRow<'a> {
+    entry: &'a LedgerEntry,
+    records: &'a [FinancialRecord],
+}
+
+#[derive(Template)]
+#[template(path = "ledger/recurring.html")]
+struct RecurringList<'a> {
+    suggestions: &'a [RecurringSuggestion],
+}
+
+#[derive(Template)]
+#[template(path = "ledger/accepted.html")]
+struct Accepted<'a> {
+    record: &'a FinancialRecord,
+}
+
+pub fn routes(db: Db) -> Router {
+    let state = LedgerState {
+        database: db,
+    };
+
+    Router::new()
+        .route("/", get(get_ledger))
+        .route("/:id/link", post(link_entry))
+        .route("/recurring", get(get_recurring))
+        .route("/recurring/accept", post(accept_recurring))
+        .with_state(state)
+}
+
+// Imported statement transactions, newest first, each with a picker to link
+// it to the budget record it belongs to.
+#[debug_handler]
+pub async fn get_ledger(
+    Query(params): Query<LedgerParams>,
+    State(state): State<LedgerState>,
+) -> Html<String> {
+    info!("GET /ledger request");
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    let result = with_db(&state.database, move |db| {
+        service::get_ledger_entries(db, limit).and_then(|entries| Ok((entries, service::get_all_records(db)?)))
+    })
+    .await;
+    let (entries, records) = match result {
+        Ok(data) => data,
+        Err(e) => {
+            error!("Failed to fetch ledger: {:?}", e);
+            return message("Error retrieving ledger");
+        }
+    };
+
+    if entries.is_empty() {
+        return message("No transactions imported yet");
+    }
+    render(&LedgerTable { entries: &entries, records: &records })
+}
+
+async fn link_entry(
+    Path(id): Path<Uuid>,
+    State(state): State<LedgerState>,
+    Form(form): Form<LinkForm>,
+) -> Html<String> {
+    info!("POST /ledger/{}/link request", id);
+
+    let record_id = match form.record_id.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+        None => None,
+        Some(raw) => match Uuid::parse_str(raw) {
+            Ok(record_id) => Some(record_id),
+            Err(_) => return message(format!("Invalid record id `{}`", raw)),
+        },
+    };
+
+    let result = with_db(&state.database, move |db| {
+        service::link_ledger_entry(db, &id, record_id.as_ref()).and_then(|entry| Ok((entry, service::get_all_records(db)?)))
+    })
+    .await;
+    match result {
+        Ok((entry, records)) => render(&LedgerRow { entry: &entry, records: &records }),
+        Err(e) => {
+            error!("Failed to link ledger entry {}: {:?}", id, e);
+            message("Error linking transaction")
+        }
+    }
+}
+
+// Recurring records proposed from the transaction history, each with a
+// "create recurring record?" form
+#[debug_handler]
+pub async fn get_recurring(State(state): State<LedgerState>) -> Html<String> {
+    info!("GET /ledger/recurring request");
+
+    let suggestions = match with_db(&state.database, service::get_recurring_suggestions).await {
+        Ok(data) => data,
+        Err(e) => {
+            error!("Failed to detect recurring transactions: {:?}", e);
+            return message("Error detecting recurring transactions");
+        }
+    };
+
+    if suggestions.is_empty() {
+        return message("No recurring transactions found");
+    }
+    render(&RecurringList { suggestions: &suggestions })
+}
+
+async fn accept_recurring(
+    State(state): State<LedgerState>,
+    Actor(actor): Actor,
+    Form(form): Form<AcceptForm>,
+) -> Html<String> {
+    info!("POST /ledger/recurring/accept request");
+
+    let name = form.name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+    let key = form.key.clone();
+    match with_db(&state.database, move |db| service::accept_recurring_suggestion(db, &key, name, &actor)).await {
+        Ok(record) => render(&Accepted { record: &record }),
+        Err(e) => {
+            error!("Failed to accept recurring suggestion {:?}: {}", form.key, e);
+            message(format!("Error: {}", e))
+        }
+    }
+}
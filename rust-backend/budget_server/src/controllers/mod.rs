@@ -0,0 +1,142 @@
+pub mod records_controller;
+pub mod trash_controller;
+pub mod activity_controller;
+pub mod undo_controller;
+pub mod import_controller;
+pub mod export_controller;
+pub mod ledger_controller;
+pub mod rules_controller;
+pub mod calendar_controller;
+pub mod reports_controller;
+pub mod charts_controller;
+pub mod pages_controller;
+pub mod assets_controller;
+pub mod admin_controller;
+
+use std::panic;
+use askama::Template;
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::request::Parts,
+    response::Html,
+    Router};
+use log::error;
+use budget_core::attachment_store::AttachmentStore;
+use budget_core::backup::BackupStore;
+use budget_core::record_store::StoreHandle;
+use budget_core::types::Db;
+use crate::config::Config;
+
+// Top level Router for the SQLite database, which has everything. add a
+// route for each file you add to the controllers dir
+pub fn routes(conn: Db, attachments: AttachmentStore, backups: BackupStore, config: &Config) -> Router {
+    let router = record_routes(conn.clone(), config)
+        .nest("/records", records_controller::attachment_routes(conn.clone(), attachments.clone()))
+        .nest("/trash", trash_controller::purge_routes(conn.clone(), attachments, config.trash_retention_days))
+        .nest("/import", import_controller::ledger_routes(conn.clone()))
+        .nest("/export", export_controller::ledger_routes(conn.clone()))
+        .nest("/ledger", ledger_controller::routes(conn.clone()))
+        .nest("/rules", rules_controller::routes(conn.clone()))
+        .merge(calendar_controller::routes(conn.clone(), config.calendar_token.clone()));
+
+    match &config.admin_token {
+        Some(token) => router.nest("/admin", admin_controller::routes(conn, backups, token.clone())),
+        None => router,
+    }
+}
+
+// The routes that work on any RecordStore: records, their history and
+// undo, imports and exports of records, reports and charts. That's all a
+// server on PostgreSQL has.
+pub fn record_routes<H: StoreHandle>(db: H, config: &Config) -> Router {
+    Router::new()
+        .nest("/records", records_controller::routes(db.clone()))
+        .nest("/trash", trash_controller::routes(db.clone(), config.trash_retention_days))
+        .nest("/activity", activity_controller::routes(db.clone()))
+        .nest("/import", import_controller::routes(db.clone()))
+        .nest("/export", export_controller::routes(db.clone()))
+        .nest("/reports", reports_controller::routes(db.clone()))
+        .nest("/charts", charts_controller::routes(db.clone()))
+        .merge(undo_controller::routes(db))
+}
+
+// The browser UI: full pages and the CSS/JS they load, served from the root
+pub fn pages<H: StoreHandle>(conn: H) -> Router {
+    Router::new()
+        .merge(pages_controller::routes(conn))
+        .nest("/static", assets_controller::routes())
+}
+
+// State of the controllers that only deal with records, which work on any
+// RecordStore. The server always hands them the SQLite pool.
+#[derive(Clone)]
+pub struct StoreState<H> {
+    pub database: H,
+}
+
+// Runs `f` on the blocking thread pool, so a slow query holds up one of
+// those threads rather than the async runtime. `db` is usually the database,
+// or a controller's whole state when the call needs more than that (the
+// attachment store, say); `f` gets a clone of it. A panic in `f` carries on
+// in the handler, as if it had been called directly.
+pub(crate) async fn with_db<D, T>(db: &D, f: impl FnOnce(&D) -> T + Send + 'static) -> T
+where
+    D: Clone + Send + 'static,
+    T: Send + 'static,
+{
+    let db = db.clone();
+    match tokio::task::spawn_blocking(move || f(&db)).await {
+        Ok(value) => value,
+        Err(e) => panic::resume_unwind(e.into_panic()),
+    }
+}
+
+// Every page and fragment is an askama template (under templates/), so
+// anything interpolated into the markup is HTML-escaped unless the template
+// says otherwise. Rendering only fails on a bug in a template.
+pub(crate) fn render(template: &impl Template) -> Html<String> {
+    match template.render() {
+        Ok(html) => Html(html),
+        Err(e) => {
+            error!("Failed to render template: {}", e);
+            Html("<p>Error rendering page</p>".to_string())
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "message.html")]
+struct Message<'a> {
+    text: &'a str,
+}
+
+// A one line status or error message, e.g. message(format!("Error retrieving record `{}`", id))
+pub(crate) fn message(text: impl AsRef<str>) -> Html<String> {
+    render(&Message { text: text.as_ref() })
+}
+
+// header a client sets to say who is making a change, recorded in the history
+pub const ACTOR_HEADER: &str = "x-budget-actor";
+
+// Who is making the request, for the change history. There are no user
+// accounts, so this is whatever the client puts in ACTOR_HEADER, or
+// "anonymous" if it doesn't.
+pub struct Actor(pub String);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Actor {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let actor = parts
+            .headers
+            .get(ACTOR_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .unwrap_or("anonymous");
+        Ok(Actor(actor.to_string()))
+    }
+}
+
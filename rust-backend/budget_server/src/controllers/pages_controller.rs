@@ -0,0 +1,229 @@
+use budget_core::models::{FinancialRecord, Frequency, RecordType, Summary};
+use budget_core::service;
+use crate::controllers::{render, with_db, Actor, StoreState};
+use crate::controllers::records_controller::{NewRecord, RecordFields};
+use budget_core::record_store::StoreHandle;
+
+use askama::Template;
+use uuid::Uuid;
+use log::{info, error};
+use axum::{
+    extract::{Form, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::get,
+    Router};
+
+#[derive(Template)]
+#[template(path = "dashboard.html")]
+struct DashboardPage {
+    summary: Summary,
+}
+
+#[derive(Template)]
+#[template(path = "records/index.html")]
+struct RecordsPage<'a> {
+    records: &'a [FinancialRecord],
+}
+
+#[derive(Template)]
+#[template(path = "records/show.html")]
+struct RecordPage<'a> {
+    record: &'a FinancialRecord,
+}
+
+#[derive(Template)]
+#[template(path = "records/new.html")]
+struct NewRecordPage<'a> {
+    fields: RecordFields,
+    error: Option<&'a str>,
+}
+
+#[derive(Template)]
+#[template(path = "records/edit.html")]
+struct EditRecordPage<'a> {
+    id: Uuid,
+    /// the saved name, for the heading
+    name: &'a str,
+    fields: RecordFields,
+    error: Option<&'a str>,
+}
+
+// Pages that are a heading and fragments loaded from the API
+#[derive(Template)]
+#[template(path = "ledger.html")]
+struct LedgerPage;
+
+#[derive(Template)]
+#[template(path = "rules.html")]
+struct RulesPage;
+
+#[derive(Template)]
+#[template(path = "import.html")]
+struct ImportPage;
+
+#[derive(Template)]
+#[template(path = "activity.html")]
+struct ActivityPage;
+
+#[derive(Template)]
+#[template(path = "trash.html")]
+struct TrashPage;
+
+#[derive(Template)]
+#[template(path = "error.html")]
+struct ErrorPage<'a> {
+    text: &'a str,
+}
+
+pub fn routes<H: StoreHandle>(db: H) -> Router {
+    let state = StoreState {
+        database: db,
+    };
+
+    Router::new()
+        .route("/", get(dashboard::<H>))
+        .route("/records", get(records::<H>))
+        .route("/records/new", get(new_record).post(create_record::<H>))
+        .route("/records/:id", get(show_record::<H>))
+        .route("/records/:id/edit", get(edit_record::<H>).post(save_record::<H>))
+        .route("/ledger", get(|| async { render(&LedgerPage) }))
+        .route("/rules", get(|| async { render(&RulesPage) }))
+        .route("/import", get(|| async { render(&ImportPage) }))
+        .route("/activity", get(|| async { render(&ActivityPage) }))
+        .route("/trash", get(|| async { render(&TrashPage) }))
+        .with_state(state)
+}
+
+fn not_found(text: &str) -> Response {
+    (StatusCode::NOT_FOUND, render(&ErrorPage { text })).into_response()
+}
+
+fn server_error(text: &str) -> Response {
+    (StatusCode::INTERNAL_SERVER_ERROR, render(&ErrorPage { text })).into_response()
+}
+
+// Where to go after a form was saved. Plain form posts get a 303; boosted
+// ones (sent by htmx) get HX-Location, so htmx loads the page itself and
+// pushes it to the browser history.
+fn see_other(headers: &HeaderMap, location: &str) -> Response {
+    if headers.contains_key("HX-Request") {
+        ([("HX-Location", location.to_string())], Html(String::new())).into_response()
+    } else {
+        Redirect::to(location).into_response()
+    }
+}
+
+async fn dashboard<H: StoreHandle>(State(state): State<StoreState<H>>) -> Response {
+    info!("GET / request");
+    match with_db(&state.database, service::get_summary).await {
+        Ok(summary) => render(&DashboardPage { summary }).into_response(),
+        Err(e) => {
+            error!("Failed to build summary: {:?}", e);
+            server_error("Error retrieving the budget summary")
+        }
+    }
+}
+
+async fn records<H: StoreHandle>(State(state): State<StoreState<H>>) -> Response {
+    info!("GET /records request");
+    match with_db(&state.database, service::get_all_records).await {
+        Ok(records) => render(&RecordsPage { records: &records }).into_response(),
+        Err(e) => {
+            error!("Failed to fetch records: {:?}", e);
+            server_error("Error retrieving records")
+        }
+    }
+}
+
+async fn show_record<H: StoreHandle>(
+    Path(id): Path<Uuid>,
+    State(state): State<StoreState<H>>,
+) -> Response {
+    info!("GET /records/{} request", id);
+    match with_db(&state.database, move |db| service::get_record_by_id(db, &id)).await {
+        Ok(record) => render(&RecordPage { record: &record }).into_response(),
+        Err(e) => {
+            error!("Failed to fetch record `{}`: {:?}", id, e);
+            not_found("That record doesn't exist, or it is in the trash.")
+        }
+    }
+}
+
+async fn new_record() -> Html<String> {
+    info!("GET /records/new request");
+    render(&NewRecordPage { fields: RecordFields::default(), error: None })
+}
+
+async fn create_record<H: StoreHandle>(
+    State(state): State<StoreState<H>>,
+    Actor(actor): Actor,
+    headers: HeaderMap,
+    Form(form): Form<NewRecord>,
+) -> Response {
+    info!("POST /records/new request");
+    let result = match form.to_record() {
+        Ok(record) => with_db(&state.database, move |db| service::add_record(db, &record, &actor))
+            .await
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(record) => see_other(&headers, &format!("/records/{}", record.id)),
+        Err(e) => render(&NewRecordPage { fields: RecordFields::from(&form), error: Some(&e) }).into_response(),
+    }
+}
+
+async fn edit_record<H: StoreHandle>(
+    Path(id): Path<Uuid>,
+    State(state): State<StoreState<H>>,
+) -> Response {
+    info!("GET /records/{}/edit request", id);
+    match with_db(&state.database, move |db| service::get_record_by_id(db, &id)).await {
+        Ok(record) => render(&EditRecordPage {
+            id,
+            name: &record.name,
+            fields: RecordFields::from(&record),
+            error: None,
+        })
+        .into_response(),
+        Err(e) => {
+            error!("Failed to fetch record `{}`: {:?}", id, e);
+            not_found("That record doesn't exist, or it is in the trash.")
+        }
+    }
+}
+
+async fn save_record<H: StoreHandle>(
+    Path(id): Path<Uuid>,
+    State(state): State<StoreState<H>>,
+    Actor(actor): Actor,
+    headers: HeaderMap,
+    Form(form): Form<NewRecord>,
+) -> Response {
+    info!("POST /records/{}/edit request", id);
+    let record = match with_db(&state.database, move |db| service::get_record_by_id(db, &id)).await {
+        Ok(record) => record,
+        Err(e) => {
+            error!("Failed to fetch record `{}`: {:?}", id, e);
+            return not_found("That record doesn't exist, or it is in the trash.");
+        }
+    };
+
+    let result = match form.apply(record.clone()) {
+        Ok(edited) => with_db(&state.database, move |db| service::update_record(db, &edited, &actor))
+            .await
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(_) => see_other(&headers, &format!("/records/{}", id)),
+        Err(e) => render(&EditRecordPage {
+            id,
+            name: &record.name,
+            fields: RecordFields::from(&form),
+            error: Some(&e),
+        })
+        .into_response(),
+    }
+}
@@ -0,0 +1,668 @@
+use budget_core::{models::{Attachment, FinancialRecord, Frequency, RecordType, SearchHit}, service};
+use budget_core::models::search_hit::{MATCH_START, MATCH_END};
+use budget_core::attachment_store::AttachmentStore;
+use budget_core::csv_import::parse_amount;
+
+use uuid::Uuid;
+use askama::Template;
+use chrono::NaiveDate;
+use log::{info, debug, error};
+use std::str::FromStr;
+use axum::{
+    extract::{DefaultBodyLimit, Form, Multipart, State, Path, Query},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+    routing::{get, post},
+    Router};
+use axum_macros::debug_handler;
+use serde::Deserialize;
+use budget_core::record_store::StoreHandle;
+use budget_core::types::Db;
+use crate::http_error::HttpError;
+use crate::controllers::{message, render, with_db, Actor, StoreState};
+use crate::controllers::activity_controller::History;
+
+// Receipts and PDF statements are well over axum's 2MB default body limit
+const MAX_ATTACHMENT_BYTES: usize = 25 * 1024 * 1024;
+// where the records page shows a failed inline add
+const ADD_ERROR_TARGET: &str = "#add-record-error";
+
+// State of the attachment routes, which need the SQLite database
+#[derive(Clone)]
+pub struct RecordState {
+    pub database: Db,
+    pub attachments: AttachmentStore,
+}
+
+#[derive(Deserialize)]
+pub struct NewRecord {
+    pub name: String,
+    /// text so a typo comes back as a form error, "$1,500" is fine
+    pub amount: String,
+    pub frequency: String,
+    pub record_type: String,
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// first due date, YYYY-MM-DD (from an <input type="date">), may be blank
+    #[serde(default)]
+    pub starts_on: Option<String>,
+}
+
+impl NewRecord {
+    // `record` with the form's values filled in. Notes and the start date are
+    // only touched when the form has those fields, so the inline edit row
+    // (which doesn't) keeps them.
+    pub(crate) fn apply(&self, record: FinancialRecord) -> Result<FinancialRecord, String> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err("Name is required".to_string());
+        }
+        let amount = parse_amount(&self.amount).map_err(|e| format!("Amount: {}", e))?;
+        if amount <= 0.0 {
+            return Err("Amount must be positive".to_string());
+        }
+        let frequency = Frequency::from_str(&self.frequency)
+            .map_err(|_| format!("Unknown frequency `{}`", self.frequency))?;
+        let record_type = RecordType::from_str(&self.record_type)
+            .map_err(|_| format!("Unknown record type `{}`", self.record_type))?;
+
+        let mut record = FinancialRecord {
+            name: name.to_string(),
+            amount,
+            frequency,
+            record_type,
+            ..record
+        };
+        if let Some(notes) = &self.notes {
+            record.notes = Some(notes.clone()).filter(|n| !n.trim().is_empty());
+        }
+        if let Some(starts_on) = self.starts_on.as_deref().map(str::trim) {
+            record.starts_on = match starts_on {
+                "" => None,
+                date => Some(
+                    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                        .map_err(|_| format!("First due date must be YYYY-MM-DD, got `{}`", date))?,
+                ),
+            };
+        }
+        Ok(record)
+    }
+
+    // a new record, the service gives it its id
+    pub(crate) fn to_record(&self) -> Result<FinancialRecord, String> {
+        self.apply(FinancialRecord::new(String::new(), 0.0, Frequency::Monthly, RecordType::Expense))
+    }
+}
+
+/// What a record form shows: the record being edited, or what was typed in
+/// last time when it didn't validate
+pub(crate) struct RecordFields {
+    pub name: String,
+    pub amount: String,
+    pub frequency: String,
+    pub record_type: String,
+    pub notes: String,
+    pub starts_on: String,
+}
+
+impl Default for RecordFields {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            amount: String::new(),
+            frequency: Frequency::Monthly.to_string(),
+            record_type: RecordType::Expense.to_string(),
+            notes: String::new(),
+            starts_on: String::new(),
+        }
+    }
+}
+
+impl From<&FinancialRecord> for RecordFields {
+    fn from(record: &FinancialRecord) -> Self {
+        Self {
+            name: record.name.clone(),
+            amount: format!("{:.2}", record.amount),
+            frequency: record.frequency.to_string(),
+            record_type: record.record_type.to_string(),
+            notes: record.notes.clone().unwrap_or_default(),
+            starts_on: record.starts_on.map(|d| d.to_string()).unwrap_or_default(),
+        }
+    }
+}
+
+impl From<&NewRecord> for RecordFields {
+    fn from(form: &NewRecord) -> Self {
+        Self {
+            name: form.name.clone(),
+            amount: form.amount.clone(),
+            frequency: form.frequency.clone(),
+            record_type: form.record_type.clone(),
+            notes: form.notes.clone().unwrap_or_default(),
+            starts_on: form.starts_on.clone().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NotesForm {
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SearchParams {
+    #[serde(default)]
+    pub q: String,
+}
+
+#[derive(Template)]
+#[template(path = "records/table.html")]
+pub(crate) struct RecordTable<'a> {
+    pub records: &'a [FinancialRecord],
+}
+
+#[derive(Template)]
+#[template(path = "records/row.html")]
+struct RecordRow<'a> {
+    record: &'a FinancialRecord,
+}
+
+#[derive(Template)]
+#[template(path = "records/edit_row.html")]
+struct RecordEditRow<'a> {
+    id: Uuid,
+    fields: RecordFields,
+    error: Option<&'a str>,
+}
+
+#[derive(Template)]
+#[template(path = "records/deleted_row.html")]
+struct DeletedRow<'a> {
+    record: &'a FinancialRecord,
+}
+
+#[derive(Template)]
+#[template(path = "records/deleted.html")]
+struct Deleted<'a> {
+    record: &'a FinancialRecord,
+}
+
+#[derive(Template)]
+#[template(path = "records/detail.html")]
+struct RecordDetail<'a> {
+    record: &'a FinancialRecord,
+}
+
+#[derive(Template)]
+#[template(path = "records/search.html")]
+struct SearchResults {
+    hits: Vec<HighlightedHit>,
+}
+
+// A search hit with its highlights split into (matched, text) runs
+struct HighlightedHit {
+    record: FinancialRecord,
+    name: Vec<(bool, String)>,
+    snippet: Vec<(bool, String)>,
+}
+
+#[derive(Template)]
+#[template(path = "records/attachments.html")]
+struct Attachments<'a> {
+    record_id: &'a Uuid,
+    attachments: &'a [Attachment],
+}
+
+pub fn routes<H: StoreHandle>(db: H) -> Router {
+    let state = StoreState {
+        database: db,
+    };
+
+    Router::new()
+        .route("/all", get(get_all::<H>))
+        .route("/income", get(get_all_income::<H>))
+        .route("/expenses", get(get_all_expenses::<H>))
+        .route("/search", get(search_records::<H>))
+        .route("/add", post(add_record::<H>))
+        .route("/delete/:id", post(delete_record::<H>))
+        .route("/:id", get(get_record_by_id::<H>).post(update_record::<H>).delete(delete_record_row::<H>))
+        .route("/:id/row", get(get_record_row::<H>))
+        .route("/:id/edit", get(edit_record_row::<H>))
+        .route("/:id/restore", post(restore_record_row::<H>))
+        .route("/:id/notes", post(update_notes::<H>))
+        .route("/:id/history", get(get_record_history::<H>))
+        .with_state(state)
+}
+
+// Attachments are kept in the SQLite database only
+pub fn attachment_routes(db: Db, attachments: AttachmentStore) -> Router {
+    let state = RecordState {
+        database: db,
+        attachments,
+    };
+
+    Router::new()
+        .route("/:id/attachments", get(get_attachments)
+            .post(upload_attachment)
+            .layer(DefaultBodyLimit::max(MAX_ATTACHMENT_BYTES)))
+        .route("/attachments/:attachment_id", get(download_attachment))
+        .with_state(state)
+}
+
+pub async fn get_all<H: StoreHandle>(State(state): State<StoreState<H>>) -> Html<String> {
+    info!("GET /records/ request");
+
+    match with_db(&state.database, service::get_all_records).await {
+        Ok(records) => {
+            info!("Retrieved {} records from DB", records.len());
+            render(&RecordTable { records: &records })
+        }
+        Err(e) => {
+            error!("Failed to fetch records: {:?}", e);
+            message("Error retrieving records")
+        }
+    }
+}
+
+pub async fn get_record_by_id<H: StoreHandle>(
+    Path(id): Path<Uuid>,
+    State(state): State<StoreState<H>>,
+) -> Html<String> {
+
+    info!("GET /records/{} request", id);
+    match with_db(&state.database, move |db| service::get_record_by_id(db, &id)).await {
+        Ok(record) => render(&RecordDetail { record: &record }),
+        Err(e) => {
+            error!("Failed to fetch record `{}`: {:?}", id, e);
+            message(format!("Error retrieving record `{}`", id))
+        }
+    }
+}
+
+pub async fn get_all_income<H: StoreHandle>(State(state): State<StoreState<H>>) -> Html<String> {
+    info!("GET /records/income request");
+
+    match with_db(&state.database, service::get_all_income).await {
+        Ok(records) => {
+            info!("Retrieved {} income records from DB", records.len());
+            render(&RecordTable { records: &records })
+        }
+        Err(e) => {
+            error!("Failed to fetch records: {:?}", e);
+            message("Error retrieving records")
+        }
+    }
+}
+
+pub async fn get_all_expenses<H: StoreHandle>(State(state): State<StoreState<H>>) -> Html<String> {
+    info!("GET /records/expenses request");
+
+    match with_db(&state.database, service::get_all_expenses).await {
+        Ok(records) => {
+            info!("Retrieved {} expense records from DB", records.len());
+            render(&RecordTable { records: &records })
+        }
+        Err(e) => {
+            error!("Failed to fetch records: {:?}", e);
+            message("Error retrieving records")
+        }
+    }
+}
+
+// Live search results, meant to be swapped into the page by htmx, e.g.
+// <input name="q" hx-get="/api/records/search" hx-trigger="keyup changed delay:300ms" hx-target="#results">
+pub async fn search_records<H: StoreHandle>(
+    Query(params): Query<SearchParams>,
+    State(state): State<StoreState<H>>,
+) -> Html<String> {
+    info!("GET /records/search?q={} request", params.q);
+
+    let hits = match with_db(&state.database, move |db| service::search_records(db, &params.q)).await {
+        Ok(data) => data,
+        Err(e) => {
+            error!("Failed to search records: {:?}", e);
+            return message("Error searching records");
+        }
+    };
+
+    let hits = hits
+        .into_iter()
+        .map(|hit: SearchHit| HighlightedHit {
+            name: highlights(&hit.name_highlight),
+            snippet: highlights(&hit.snippet),
+            record: hit.record,
+        })
+        .collect();
+    render(&SearchResults { hits })
+}
+
+// Split search text on the match markers: "a\u{2}b\u{3}c" is
+// [(false, "a"), (true, "b"), (false, "c")]
+fn highlights(text: &str) -> Vec<(bool, String)> {
+    let mut runs = Vec::new();
+    for (i, part) in text.split(MATCH_START).enumerate() {
+        // everything but the first part starts with a match
+        let (marked, rest) = match part.split_once(MATCH_END) {
+            Some((marked, rest)) if i > 0 => (marked, rest),
+            _ => ("", part),
+        };
+        runs.extend([(true, marked), (false, rest)]
+            .into_iter()
+            .filter(|(_, text)| !text.is_empty())
+            .map(|(m, text)| (m, text.to_string())));
+    }
+    runs
+}
+
+// Responds with the new record's table row, for hx-swap="afterbegin" into
+// the records table. A form that doesn't validate comes back as a 422 and
+// is retargeted at the form's error slot.
+async fn add_record<H: StoreHandle>(
+    State(state): State<StoreState<H>>,
+    Actor(actor): Actor,
+    Form(form): Form<NewRecord>,
+) -> Response {
+    info!("Serving add_record request");
+    debug!("Adding {}", form.record_type.as_str());
+
+    let result = match form.to_record() {
+        Ok(record) => with_db(&state.database, move |db| service::add_record(db, &record, &actor))
+            .await
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(record) => render(&RecordRow { record: &record }).into_response(),
+        Err(e) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            [("HX-Retarget", ADD_ERROR_TARGET), ("HX-Reswap", "innerHTML")],
+            message(e),
+        )
+            .into_response(),
+    }
+}
+
+// Saves the inline edit row. Responds with the updated row, or the edit row
+// again with the error if the form doesn't validate.
+async fn update_record<H: StoreHandle>(
+    Path(id): Path<Uuid>,
+    State(state): State<StoreState<H>>,
+    Actor(actor): Actor,
+    Form(form): Form<NewRecord>,
+) -> Html<String> {
+    info!("POST /records/{} request", id);
+
+    let fields = RecordFields::from(&form);
+    let result = with_db(&state.database, move |db| {
+        service::get_record_by_id(db, &id)
+            .map_err(|e| e.to_string())
+            .and_then(|record| form.apply(record))
+            .and_then(|record| service::update_record(db, &record, &actor).map_err(|e| e.to_string()))
+    })
+    .await;
+    match result {
+        Ok(record) => render(&RecordRow { record: &record }),
+        Err(e) => {
+            error!("Failed to update record `{}`: {}", id, e);
+            render(&RecordEditRow { id, fields, error: Some(&e) })
+        }
+    }
+}
+
+pub async fn get_record_row<H: StoreHandle>(
+    Path(id): Path<Uuid>,
+    State(state): State<StoreState<H>>,
+) -> Html<String> {
+    info!("GET /records/{}/row request", id);
+
+    match with_db(&state.database, move |db| service::get_record_by_id(db, &id)).await {
+        Ok(record) => render(&RecordRow { record: &record }),
+        Err(e) => {
+            error!("Failed to fetch record `{}`: {:?}", id, e);
+            message(format!("Error retrieving record `{}`", id))
+        }
+    }
+}
+
+pub async fn edit_record_row<H: StoreHandle>(
+    Path(id): Path<Uuid>,
+    State(state): State<StoreState<H>>,
+) -> Html<String> {
+    info!("GET /records/{}/edit request", id);
+
+    match with_db(&state.database, move |db| service::get_record_by_id(db, &id)).await {
+        Ok(record) => render(&RecordEditRow { id, fields: RecordFields::from(&record), error: None }),
+        Err(e) => {
+            error!("Failed to fetch record `{}`: {:?}", id, e);
+            message(format!("Error retrieving record `{}`", id))
+        }
+    }
+}
+
+// Soft delete, the record goes to the trash and can be restored from there
+async fn delete_record<H: StoreHandle>(
+    Path(id): Path<Uuid>,
+    State(state): State<StoreState<H>>,
+    Actor(actor): Actor,
+) -> Html<String> {
+    info!("Serving delete_record request");
+
+    let result = with_db(&state.database, move |db| {
+        service::get_record_by_id(db, &id).and_then(|record| service::delete_record(db, &id, &actor).map(|()| record))
+    })
+    .await;
+    match result {
+        Ok(record) => render(&Deleted { record: &record }),
+        Err(e) => {
+            error!("Failed to delete record `{}`: {:?}", id, e);
+            message(format!("Error deleting record `{}`", id))
+        }
+    }
+}
+
+// DELETE from the records table: same soft delete, but the row stays in
+// place with an undo button
+async fn delete_record_row<H: StoreHandle>(
+    Path(id): Path<Uuid>,
+    State(state): State<StoreState<H>>,
+    Actor(actor): Actor,
+) -> Html<String> {
+    info!("DELETE /records/{} request", id);
+
+    let result = with_db(&state.database, move |db| {
+        service::get_record_by_id(db, &id).and_then(|record| service::delete_record(db, &id, &actor).map(|()| record))
+    })
+    .await;
+    match result {
+        Ok(record) => render(&DeletedRow { record: &record }),
+        Err(e) => {
+            error!("Failed to delete record `{}`: {:?}", id, e);
+            message(format!("Error deleting record `{}`", id))
+        }
+    }
+}
+
+// Undo for a deleted row: restores the record and responds with its row
+async fn restore_record_row<H: StoreHandle>(
+    Path(id): Path<Uuid>,
+    State(state): State<StoreState<H>>,
+    Actor(actor): Actor,
+) -> Html<String> {
+    info!("POST /records/{}/restore request", id);
+
+    let result = with_db(&state.database, move |db| {
+        service::restore_record(db, &id, &actor).and_then(|()| service::get_record_by_id(db, &id))
+    })
+    .await;
+    match result {
+        Ok(record) => render(&RecordRow { record: &record }),
+        Err(e) => {
+            error!("Failed to restore record `{}`: {:?}", id, e);
+            message(format!("Error restoring record `{}`", id))
+        }
+    }
+}
+
+async fn update_notes<H: StoreHandle>(
+    Path(id): Path<Uuid>,
+    State(state): State<StoreState<H>>,
+    Actor(actor): Actor,
+    Form(form): Form<NotesForm>,
+) -> Html<String> {
+    info!("POST /records/{}/notes request", id);
+
+    match with_db(&state.database, move |db| service::update_notes(db, &id, form.notes, &actor)).await {
+        Ok(r) => message(r.notes.as_deref().unwrap_or("")),
+        Err(e) => {
+            error!("Failed to update notes on `{}`: {:?}", id, e);
+            message(format!("Error updating notes on record `{}`", id))
+        }
+    }
+}
+
+pub async fn get_record_history<H: StoreHandle>(
+    Path(id): Path<Uuid>,
+    State(state): State<StoreState<H>>,
+) -> Html<String> {
+    info!("GET /records/{}/history request", id);
+
+    match with_db(&state.database, move |db| service::get_record_history(db, &id)).await {
+        Ok(history) if history.is_empty() => message("No changes recorded"),
+        Ok(history) => render(&History::new(&history, None)),
+        Err(e) => {
+            error!("Failed to fetch history for `{}`: {:?}", id, e);
+            message(format!("Error retrieving history for record `{}`", id))
+        }
+    }
+}
+
+#[debug_handler]
+pub async fn get_attachments(
+    Path(id): Path<Uuid>,
+    State(state): State<RecordState>,
+) -> Html<String> {
+    info!("GET /records/{}/attachments request", id);
+
+    match with_db(&state.database, move |db| service::get_attachments(db, &id)).await {
+        Ok(attachments) => render(&Attachments { record_id: &id, attachments: &attachments }),
+        Err(e) => {
+            error!("Failed to fetch attachments for `{}`: {:?}", id, e);
+            message(format!("Error retrieving attachments for record `{}`", id))
+        }
+    }
+}
+
+// Expects a multipart form with one or more `file` fields. Responds with the
+// refreshed attachment list so htmx can swap it in place.
+async fn upload_attachment(
+    Path(id): Path<Uuid>,
+    State(state): State<RecordState>,
+    mut multipart: Multipart,
+) -> Html<String> {
+    info!("POST /records/{}/attachments request", id);
+
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) => {
+                error!("Malformed attachment upload for `{}`: {:?}", id, e);
+                return message(format!("Error uploading attachment: {}", e.body_text()));
+            }
+        };
+        if field.name() != Some("file") {
+            continue;
+        }
+
+        let file_name = sanitize_file_name(field.file_name().unwrap_or("attachment"));
+        let content_type = field
+            .content_type()
+            .unwrap_or("application/octet-stream")
+            .to_string();
+        let bytes = match field.bytes().await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                error!("Failed reading upload `{}` for `{}`: {:?}", file_name, id, e);
+                return message(format!("Error uploading attachment: {}", e.body_text()));
+            }
+        };
+
+        let stored = with_db(&state, {
+            let file_name = file_name.clone();
+            move |state| {
+                service::add_attachment(&state.database, &state.attachments, &id, &file_name, &content_type, &bytes)
+            }
+        })
+        .await;
+        if let Err(e) = stored {
+            error!("Failed to store attachment `{}` for `{}`: {:?}", file_name, id, e);
+            return message(format!("Error storing attachment `{}`", file_name));
+        }
+    }
+
+    get_attachments(Path(id), State(state)).await
+}
+
+async fn download_attachment(
+    Path(attachment_id): Path<Uuid>,
+    State(state): State<RecordState>,
+) -> Result<Response, HttpError> {
+    info!("GET /records/attachments/{} request", attachment_id);
+
+    let (attachment, bytes) = with_db(&state, move |state| {
+        service::get_attachment_content(&state.database, &state.attachments, &attachment_id)
+    })
+    .await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, attachment.content_type),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", attachment.file_name),
+            ),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
+// Keep only the last path component and drop characters that would break
+// the Content-Disposition header on download
+fn sanitize_file_name(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or("");
+    let cleaned: String = base
+        .chars()
+        .filter(|c| !c.is_control() && *c != '"')
+        .collect();
+    if cleaned.trim().is_empty() {
+        "attachment".to_string()
+    } else {
+        cleaned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_fields_are_escaped() {
+        let mut record = FinancialRecord::new("<script>alert(1)</script>", 10.0, Frequency::Monthly, RecordType::Expense);
+        record.notes = Some("<img src=x onerror=alert(1)>".into());
+        record.payee = Some("\"Tom & Jerry\"".into());
+        let records = [record];
+
+        let list = RecordTable { records: &records }.render().unwrap();
+        let detail = RecordDetail { record: &records[0] }.render().unwrap();
+
+        for html in [&list, &detail] {
+            assert!(!html.contains("<script>"), "unescaped name in {}", html);
+            assert!(html.contains("&#60;script&#62;alert(1)&#60;/script&#62;"), "{}", html);
+        }
+        assert!(!detail.contains("<img"));
+        assert!(detail.contains("&#60;img src=x onerror=alert(1)&#62;"));
+        assert!(detail.contains("&#34;Tom &#38; Jerry&#34;"));
+    }
+}
@@ -0,0 +1,61 @@
+use budget_core::pdf_report::{self, ReportPeriod};
+use budget_core::service;
+use crate::http_error::HttpError;
+use crate::controllers::{with_db, StoreState};
+use budget_core::record_store::StoreHandle;
+
+use log::info;
+use axum::{
+    extract::{State, Query},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router};
+use chrono::Local;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct ReportParams {
+    /// first month of the period, YYYY-MM (defaults to this month)
+    pub from: Option<String>,
+    /// last month of the period, YYYY-MM (defaults to `from`)
+    pub to: Option<String>,
+}
+
+pub fn routes<H: StoreHandle>(db: H) -> Router {
+    let state = StoreState {
+        database: db,
+    };
+
+    Router::new()
+        .route("/pdf", get(pdf_report::<H>))
+        .with_state(state)
+}
+
+// GET /reports/pdf?from=2025-01&to=2025-03
+async fn pdf_report<H: StoreHandle>(
+    Query(params): Query<ReportParams>,
+    State(state): State<StoreState<H>>,
+) -> Result<Response, HttpError> {
+    info!("GET /reports/pdf request");
+    let today = Local::now().date_naive();
+    let period = match ReportPeriod::parse(params.from.as_deref(), params.to.as_deref(), today) {
+        Ok(period) => period,
+        Err(e) => return Ok((StatusCode::BAD_REQUEST, e).into_response()),
+    };
+
+    let records = with_db(&state.database, service::get_all_records).await?;
+    let mut body = Vec::new();
+    pdf_report::write(&records, &period, today, &mut body)?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/pdf".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("inline; filename=\"budget-report-{}.pdf\"", period.first_month.format("%Y-%m")),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
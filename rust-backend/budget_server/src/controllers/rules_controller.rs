@@ -0,0 +1,377 @@
+use budget_core::models::{LedgerEntry, RecordType, Rule, RuleTestResult};
+use budget_core::csv_import::parse_amount;
+use budget_core::service;
+use crate::controllers::{message, render, with_db};
+use budget_core::types::Db;
+
+use askama::Template;
+use chrono::Local;
+use uuid::Uuid;
+use log::{info, error};
+use axum::{
+    extract::{State, Path, Form},
+    response::Html,
+    routing::{get, post},
+    Router};
+use axum_macros::debug_handler;
+use serde::Deserialize;
+
+#[derive(Clone)]
+pub struct RulesState {
+    pub database: Db,
+}
+
+// Every field of the rule form is text; blank means "not set"
+#[derive(Deserialize, Default)]
+pub struct RuleForm {
+    pub name: Option<String>,
+    pub position: Option<String>,
+    /// checkbox: present when enabled
+    pub enabled: Option<String>,
+
+    pub payee_contains: Option<String>,
+    pub payee_regex: Option<String>,
+    pub min_amount: Option<String>,
+    pub max_amount: Option<String>,
+    pub account: Option<String>,
+
+    pub set_name: Option<String>,
+    pub set_record_type: Option<String>,
+    pub set_category: Option<String>,
+    /// comma separated
+    pub add_tags: Option<String>,
+
+    // only used by /test: a made-up transaction to try the rule on
+    pub sample_payee: Option<String>,
+    pub sample_amount: Option<String>,
+    pub sample_account: Option<String>,
+}
+
+fn text(value: &Option<String>) -> Option<String> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string)
+}
+
+fn amount(value: &Option<String>, field: &str) -> Result<Option<f64>, String> {
+    text(value)
+        .map(|v| parse_amount(&v).map_err(|e| format!("{}: {}", field, e)))
+        .transpose()
+}
+
+impl RuleForm {
+    // The rule the form describes. `id` is kept when editing an existing rule.
+    fn to_rule(&self, id: Option<Uuid>) -> Result<Rule, String> {
+        let set_record_type = text(&self.set_record_type)
+            .map(|t| RecordType::parse_lenient(&t).ok_or_else(|| format!("Unknown record type `{}`", t)))
+            .transpose()?;
+        let position = text(&self.position)
+            .map(|p| p.parse::<i64>().map_err(|_| format!("Position must be a whole number, got `{}`", p)))
+            .transpose()?;
+
+        let mut rule = Rule::new(text(&self.name).unwrap_or_default());
+        if let Some(id) = id {
+            rule.id = id;
+        }
+        rule.position = position.unwrap_or(0);
+        rule.enabled = self.enabled.is_some();
+        rule.payee_contains = text(&self.payee_contains);
+        rule.payee_regex = text(&self.payee_regex);
+        rule.min_amount = amount(&self.min_amount, "minimum amount")?.map(f64::abs);
+        rule.max_amount = amount(&self.max_amount, "maximum amount")?.map(f64::abs);
+        rule.account = text(&self.account);
+        rule.set_name = text(&self.set_name);
+        rule.set_record_type = set_record_type;
+        rule.set_category = text(&self.set_category);
+        rule.add_tags = text(&self.add_tags)
+            .map(|tags| tags.split(',').map(str::trim).filter(|t| !t.is_empty()).map(str::to_string).collect())
+            .unwrap_or_default();
+        Ok(rule)
+    }
+
+    fn sample(&self) -> Result<Option<LedgerEntry>, String> {
+        let Some(payee) = text(&self.sample_payee) else {
+            return Ok(None);
+        };
+        let amount = amount(&self.sample_amount, "sample amount")?.unwrap_or(0.0);
+        let account = text(&self.sample_account).unwrap_or_default();
+        Ok(Some(LedgerEntry::new(account, Local::now().date_naive(), amount, payee)))
+    }
+}
+
+// A rule with its conditions and actions spelled out
+struct RuleView<'a> {
+    rule: &'a Rule,
+    conditions: String,
+    actions: String,
+}
+
+impl<'a> RuleView<'a> {
+    fn new(rule: &'a Rule) -> Self {
+        let (conditions, actions) = describe(rule);
+        RuleView { rule, conditions, actions }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "rules/list.html")]
+struct RuleList<'a> {
+    items: Vec<RuleView<'a>>,
+}
+
+#[derive(Template)]
+#[template(path = "rules/rule.html")]
+struct RuleItem<'a> {
+    item: RuleView<'a>,
+}
+
+// Blank for a new rule
+#[derive(Template, Default)]
+#[template(path = "rules/form.html")]
+struct RuleFormView {
+    form_id: String,
+    action: String,
+    target: String,
+    name: String,
+    position: String,
+    enabled: bool,
+    payee_contains: String,
+    payee_regex: String,
+    min_amount: String,
+    max_amount: String,
+    account: String,
+    set_name: String,
+    set_record_type: String,
+    set_category: String,
+    add_tags: String,
+}
+
+#[derive(Template)]
+#[template(path = "rules/test_result.html")]
+struct TestResultView<'a> {
+    matched: usize,
+    rows: Vec<TestRow<'a>>,
+}
+
+struct TestRow<'a> {
+    before: &'a LedgerEntry,
+    after: &'a LedgerEntry,
+    /// category and tags set by the rule
+    labels: String,
+}
+
+pub fn routes(db: Db) -> Router {
+    let state = RulesState {
+        database: db,
+    };
+
+    Router::new()
+        .route("/", get(get_rules).post(add_rule))
+        .route("/new", get(new_rule_form))
+        .route("/test", post(test_rule))
+        .route("/apply", post(apply_rules))
+        .route("/:id", get(edit_rule_form).post(update_rule))
+        .route("/:id/delete", post(delete_rule))
+        .with_state(state)
+}
+
+#[debug_handler]
+pub async fn get_rules(State(state): State<RulesState>) -> Html<String> {
+    info!("GET /rules request");
+
+    let rules = match with_db(&state.database, service::get_rules).await {
+        Ok(data) => data,
+        Err(e) => {
+            error!("Failed to fetch rules: {:?}", e);
+            return message("Error retrieving rules");
+        }
+    };
+
+    if rules.is_empty() {
+        return message("No rules yet");
+    }
+    render(&RuleList { items: rules.iter().map(RuleView::new).collect() })
+}
+
+pub async fn new_rule_form() -> Html<String> {
+    info!("GET /rules/new request");
+    render(&rule_form(None))
+}
+
+pub async fn edit_rule_form(
+    Path(id): Path<Uuid>,
+    State(state): State<RulesState>,
+) -> Html<String> {
+    info!("GET /rules/{} request", id);
+    match with_db(&state.database, move |db| service::get_rule_by_id(db, &id)).await {
+        Ok(rule) => render(&rule_form(Some(&rule))),
+        Err(e) => {
+            error!("Failed to fetch rule `{}`: {:?}", id, e);
+            message(format!("Error retrieving rule `{}`", id))
+        }
+    }
+}
+
+async fn add_rule(
+    State(state): State<RulesState>,
+    Form(form): Form<RuleForm>,
+) -> Html<String> {
+    info!("POST /rules request");
+    let result = match form.to_rule(None) {
+        Ok(rule) => with_db(&state.database, move |db| service::add_rule(db, rule)).await.map_err(|e| e.to_string()),
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(rule) => render(&RuleItem { item: RuleView::new(&rule) }),
+        Err(e) => message(format!("Error: {}", e)),
+    }
+}
+
+async fn update_rule(
+    Path(id): Path<Uuid>,
+    State(state): State<RulesState>,
+    Form(form): Form<RuleForm>,
+) -> Html<String> {
+    info!("POST /rules/{} request", id);
+    let result = with_db(&state.database, move |db| {
+        form.to_rule(Some(id)).and_then(|mut rule| {
+            // a blank position keeps the rule where it is
+            if text(&form.position).is_none() {
+                rule.position = service::get_rule_by_id(db, &id).map_err(|e| e.to_string())?.position;
+            }
+            service::update_rule(db, &rule).map_err(|e| e.to_string())?;
+            Ok(rule)
+        })
+    })
+    .await;
+    match result {
+        Ok(rule) => render(&RuleItem { item: RuleView::new(&rule) }),
+        Err(e) => message(format!("Error: {}", e)),
+    }
+}
+
+async fn delete_rule(
+    Path(id): Path<Uuid>,
+    State(state): State<RulesState>,
+) -> Html<String> {
+    info!("POST /rules/{}/delete request", id);
+    match with_db(&state.database, move |db| service::delete_rule(db, &id)).await {
+        Ok(true) => Html(String::new()),
+        Ok(false) => message(format!("No rule `{}`", id)),
+        Err(e) => {
+            error!("Failed to delete rule `{}`: {:?}", id, e);
+            message("Error deleting rule")
+        }
+    }
+}
+
+// Try a rule without saving it: against the sample transaction if the form
+// has one, else against every imported transaction
+async fn test_rule(
+    State(state): State<RulesState>,
+    Form(form): Form<RuleForm>,
+) -> Html<String> {
+    info!("POST /rules/test request");
+    let result = with_db(&state.database, move |db| {
+        form.to_rule(None).and_then(|mut rule| {
+            // an unsaved rule may well be unnamed or disabled, test it anyway
+            rule.enabled = true;
+            if rule.name.is_empty() {
+                rule.name = "test".to_string();
+            }
+            service::test_rule(db, rule, form.sample()?).map_err(|e| e.to_string())
+        })
+    })
+    .await;
+    match result {
+        Ok(result) => render(&test_result(&result)),
+        Err(e) => message(format!("Error: {}", e)),
+    }
+}
+
+async fn apply_rules(State(state): State<RulesState>) -> Html<String> {
+    info!("POST /rules/apply request");
+    match with_db(&state.database, service::apply_rules).await {
+        Ok(changed) => message(format!("Rules updated {} transactions", changed)),
+        Err(e) => {
+            error!("Failed to apply rules: {}", e);
+            message(format!("Error applying rules: {}", e))
+        }
+    }
+}
+
+fn describe(rule: &Rule) -> (String, String) {
+    let mut conditions = Vec::new();
+    if let Some(needle) = &rule.payee_contains {
+        conditions.push(format!("payee contains \"{}\"", needle));
+    }
+    if let Some(regex) = &rule.payee_regex {
+        conditions.push(format!("payee matches /{}/", regex));
+    }
+    match (rule.min_amount, rule.max_amount) {
+        (Some(min), Some(max)) => conditions.push(format!("amount ${:.2} to ${:.2}", min, max)),
+        (Some(min), None) => conditions.push(format!("amount at least ${:.2}", min)),
+        (None, Some(max)) => conditions.push(format!("amount at most ${:.2}", max)),
+        (None, None) => {}
+    }
+    if let Some(account) = &rule.account {
+        conditions.push(format!("account is {}", account));
+    }
+
+    let mut actions = Vec::new();
+    if let Some(name) = &rule.set_name {
+        actions.push(format!("rename to \"{}\"", name));
+    }
+    if let Some(record_type) = rule.set_record_type {
+        actions.push(format!("mark as {}", record_type));
+    }
+    if let Some(category) = &rule.set_category {
+        actions.push(format!("categorize as {}", category));
+    }
+    if !rule.add_tags.is_empty() {
+        actions.push(format!("tag {}", rule.add_tags.join(", ")));
+    }
+    (conditions.join(" and "), actions.join(", "))
+}
+
+fn rule_form(rule: Option<&Rule>) -> RuleFormView {
+    let Some(rule) = rule else {
+        return RuleFormView {
+            form_id: "rule-form-new".to_string(),
+            action: "/api/rules".to_string(),
+            target: "this".to_string(),
+            enabled: true,
+            ..Default::default()
+        };
+    };
+    let text = |v: &Option<String>| v.clone().unwrap_or_default();
+    let amount = |v: Option<f64>| v.map(|a| format!("{:.2}", a)).unwrap_or_default();
+    RuleFormView {
+        form_id: format!("rule-form-{}", rule.id),
+        action: format!("/api/rules/{}", rule.id),
+        target: format!("#rule-{}", rule.id),
+        name: rule.name.clone(),
+        position: rule.position.to_string(),
+        enabled: rule.enabled,
+        payee_contains: text(&rule.payee_contains),
+        payee_regex: text(&rule.payee_regex),
+        min_amount: amount(rule.min_amount),
+        max_amount: amount(rule.max_amount),
+        account: text(&rule.account),
+        set_name: text(&rule.set_name),
+        set_record_type: rule.set_record_type.map(|t| t.to_string()).unwrap_or_default(),
+        set_category: text(&rule.set_category),
+        add_tags: rule.add_tags.join(", "),
+    }
+}
+
+fn test_result(result: &RuleTestResult) -> TestResultView<'_> {
+    let rows = result
+        .examples
+        .iter()
+        .map(|(before, after)| TestRow {
+            before,
+            after,
+            labels: after.category.iter().chain(&after.tags).cloned().collect::<Vec<_>>().join(", "),
+        })
+        .collect();
+    TestResultView { matched: result.matched, rows }
+}
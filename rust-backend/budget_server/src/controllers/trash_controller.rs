@@ -0,0 +1,123 @@
+use budget_core::service;
+use budget_core::attachment_store::AttachmentStore;
+use budget_core::models::TrashedRecord;
+use crate::controllers::{message, render, with_db, Actor};
+use budget_core::record_store::StoreHandle;
+use budget_core::types::Db;
+
+use askama::Template;
+use uuid::Uuid;
+use log::{info, error};
+use axum::{
+    extract::{State, Path},
+    response::Html,
+    routing::{get, post},
+    Router};
+
+#[derive(Clone)]
+pub struct TrashState<H> {
+    pub database: H,
+    pub retention_days: u32,
+}
+
+// State of the purge routes: purging also removes a record's attachments,
+// which are in the SQLite database
+#[derive(Clone)]
+pub struct PurgeState {
+    pub database: Db,
+    pub attachments: AttachmentStore,
+    pub retention_days: u32,
+}
+
+#[derive(Template)]
+#[template(path = "trash/list.html")]
+struct TrashList<'a> {
+    trash: &'a [TrashedRecord],
+    retention_days: u32,
+}
+
+pub fn routes<H: StoreHandle>(db: H, retention_days: u32) -> Router {
+    let state = TrashState {
+        database: db,
+        retention_days,
+    };
+
+    Router::new()
+        .route("/", get(get_trash::<H>))
+        .route("/:id/restore", post(restore_record::<H>))
+        .with_state(state)
+}
+
+pub fn purge_routes(db: Db, attachments: AttachmentStore, retention_days: u32) -> Router {
+    let state = PurgeState {
+        database: db,
+        attachments,
+        retention_days,
+    };
+
+    Router::new()
+        .route("/purge-expired", post(purge_expired))
+        .route("/:id/purge", post(purge_record))
+        .with_state(state)
+}
+
+pub async fn get_trash<H: StoreHandle>(State(state): State<TrashState<H>>) -> Html<String> {
+    info!("GET /trash request");
+
+    let trash = match with_db(&state.database, service::get_trash).await {
+        Ok(data) => data,
+        Err(e) => {
+            error!("Failed to fetch trash: {:?}", e);
+            return message("Error retrieving trash");
+        }
+    };
+
+    if trash.is_empty() {
+        return message("Trash is empty");
+    }
+    render(&TrashList { trash: &trash, retention_days: state.retention_days })
+}
+
+async fn restore_record<H: StoreHandle>(
+    Path(id): Path<Uuid>,
+    State(state): State<TrashState<H>>,
+    Actor(actor): Actor,
+) -> Html<String> {
+    info!("POST /trash/{}/restore request", id);
+
+    match with_db(&state.database, move |db| service::restore_record(db, &id, &actor)).await {
+        Ok(()) => message("Record restored"),
+        Err(e) => {
+            error!("Failed to restore record `{}`: {:?}", id, e);
+            message(format!("Error restoring record `{}`", id))
+        }
+    }
+}
+
+async fn purge_record(
+    Path(id): Path<Uuid>,
+    State(state): State<PurgeState>,
+    Actor(actor): Actor,
+) -> Html<String> {
+    info!("POST /trash/{}/purge request", id);
+
+    match with_db(&state, move |state| service::purge_record(&state.database, &state.attachments, &id, &actor)).await {
+        Ok(()) => message("Record permanently deleted"),
+        Err(e) => {
+            error!("Failed to purge record `{}`: {:?}", id, e);
+            message(format!("Error permanently deleting record `{}`", id))
+        }
+    }
+}
+
+async fn purge_expired(State(state): State<PurgeState>) -> Html<String> {
+    info!("POST /trash/purge-expired request");
+
+    match with_db(&state, |state| service::purge_expired_trash(&state.database, &state.attachments, state.retention_days)).await {
+        Ok(n) => message(format!("Permanently deleted {} expired records", n)),
+        Err(e) => {
+            error!("Failed to purge expired trash: {:?}", e);
+            message("Error emptying expired trash")
+        }
+    }
+}
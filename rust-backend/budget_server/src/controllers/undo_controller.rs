@@ -0,0 +1,88 @@
+use budget_core::models::JournalEntry;
+use budget_core::service;
+use crate::controllers::{message, with_db, Actor, StoreState};
+use budget_core::record_store::StoreHandle;
+
+use log::{info, error};
+use axum::{
+    extract::{State, Query},
+    response::{IntoResponse, Response},
+    routing::post,
+    Router};
+use serde::Deserialize;
+
+// most operations a single undo/redo request may revert
+const MAX_STEPS: usize = 50;
+
+// event an undo/redo that changed something raises on the page (through
+// HX-Trigger), so it reloads what's now stale
+const RECORDS_CHANGED: &str = "records-changed";
+
+#[derive(Deserialize)]
+pub struct UndoParams {
+    /// how many operations to undo/redo, defaults to 1
+    pub n: Option<usize>,
+}
+
+pub fn routes<H: StoreHandle>(db: H) -> Router {
+    let state = StoreState {
+        database: db,
+    };
+
+    Router::new()
+        .route("/undo", post(undo::<H>))
+        .route("/redo", post(redo::<H>))
+        .with_state(state)
+}
+
+async fn undo<H: StoreHandle>(
+    Query(params): Query<UndoParams>,
+    State(state): State<StoreState<H>>,
+    Actor(actor): Actor,
+) -> Response {
+    info!("POST /undo request");
+    let n = params.n.unwrap_or(1).clamp(1, MAX_STEPS);
+
+    match with_db(&state.database, move |db| service::undo(db, n, &actor)).await {
+        Ok(entries) if entries.is_empty() => message("Nothing to undo").into_response(),
+        Ok(entries) => changed(format!("Undid {}", describe(&entries))),
+        Err(e) => {
+            error!("Failed to undo: {}", e);
+            message(format!("Can't undo: {}", e)).into_response()
+        }
+    }
+}
+
+async fn redo<H: StoreHandle>(
+    Query(params): Query<UndoParams>,
+    State(state): State<StoreState<H>>,
+    Actor(actor): Actor,
+) -> Response {
+    info!("POST /redo request");
+    let n = params.n.unwrap_or(1).clamp(1, MAX_STEPS);
+
+    match with_db(&state.database, move |db| service::redo(db, n, &actor)).await {
+        Ok(entries) if entries.is_empty() => message("Nothing to redo").into_response(),
+        Ok(entries) => changed(format!("Redid {}", describe(&entries))),
+        Err(e) => {
+            error!("Failed to redo: {}", e);
+            message(format!("Can't redo: {}", e)).into_response()
+        }
+    }
+}
+
+fn changed(text: String) -> Response {
+    ([("HX-Trigger", RECORDS_CHANGED)], message(text)).into_response()
+}
+
+// "Insert of Rent, Update of Gym"
+fn describe(entries: &[JournalEntry]) -> String {
+    entries
+        .iter()
+        .map(|e| {
+            let name = e.after.as_ref().or(e.before.as_ref()).map(|r| r.name.as_str()).unwrap_or("");
+            format!("{} of {}", e.action, name)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
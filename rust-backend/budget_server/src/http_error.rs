@@ -0,0 +1,22 @@
+use budget_core::app_error::AppError;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+
+// An AppError on its way out of a handler, as a 500 with the message as the
+// body. Anything that converts into an AppError converts into this, so
+// handlers can keep using `?`.
+pub struct HttpError(AppError);
+
+impl<E: Into<AppError>> From<E> for HttpError {
+    fn from(e: E) -> Self {
+        HttpError(e.into())
+    }
+}
+
+impl IntoResponse for HttpError {
+    fn into_response(self) -> Response {
+        (StatusCode::INTERNAL_SERVER_ERROR, self.0.0).into_response()
+    }
+}
@@ -0,0 +1,168 @@
+mod config;
+mod controllers;
+mod http_error;
+
+use log::info;
+
+use budget_core::pg_store::PgPool;
+use budget_core::pool::Pool;
+use budget_core::service;
+use budget_core::app_error::AppError;
+use budget_core::attachment_store::AttachmentStore;
+use budget_core::backup::BackupStore;
+use config::Config;
+
+use axum::Router;
+use directories::ProjectDirs;
+use clap::Parser;
+
+use std::{
+    net::SocketAddr,
+    time::Duration,
+};
+
+// how often trash past its retention window is purged
+const TRASH_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Parser)]
+#[command(name = "budget_server", about = "Overkill budget app: HTTP API server and web UI")]
+struct Args {
+    /// SQLite database file, or a postgres:// URL to keep the records in
+    /// PostgreSQL. The ledger, rules, attachments, calendar feed and backups
+    /// need SQLite and aren't served with PostgreSQL
+    #[arg(long, default_value = "budget.db")]
+    db: String,
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    let args = Args::parse();
+
+    log::info!("App Starting...");
+    let config = Config::from_env();
+
+    let addr = SocketAddr::from(([127, 0, 0, 1], 8000));
+    let app = if is_postgres_url(&args.db) {
+        postgres_app(&args.db, &config).await
+    } else {
+        sqlite_app(&args.db, &config)
+    };
+    info!("Running on http://{}", addr);
+
+    // run our app with hyper, listening globally on port 3000
+    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+    axum::serve(listener, app).await.unwrap();
+}
+
+fn is_postgres_url(db: &str) -> bool {
+    db.starts_with("postgres://") || db.starts_with("postgresql://")
+}
+
+// Records in PostgreSQL. Only the record routes are served: the ledger,
+// rules, attachments and calendar feed are stored in SQLite only.
+async fn postgres_app(url: &str, config: &Config) -> Router {
+    // as many connections as the SQLite pool has, though any of them takes
+    // writes here. The postgres client blocks, so connecting (and migrating)
+    // happens off the async runtime
+    let size = config.db_readers + 1;
+    let url = url.to_string();
+    let pool = tokio::task::spawn_blocking(move || PgPool::open(&url, size))
+        .await
+        .expect("DB connect panicked")
+        .expect("DB failed");
+    log::warn!(
+        "Records are stored in PostgreSQL; the ledger, rules, attachments, \
+         calendar feed and backups need a SQLite database and are not available"
+    );
+
+    {
+        let db = pool.clone();
+        let retention_days = config.trash_retention_days;
+        spawn_trash_purge(move || Ok(service::purge_expired_records(&db, retention_days)?));
+    }
+
+    Router::new()
+        .merge(controllers::pages(pool.clone()))
+        .nest("/api", controllers::record_routes(pool, config))
+}
+
+// Runs `purge` now and then every TRASH_PURGE_INTERVAL, on the blocking
+// thread pool
+fn spawn_trash_purge<F>(purge: F)
+where
+    F: Fn() -> Result<usize, AppError> + Clone + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(TRASH_PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match tokio::task::spawn_blocking(purge.clone()).await {
+                Ok(Ok(n)) if n > 0 => info!("Purged {} records from the trash", n),
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => log::error!("Failed to purge expired trash: {}", e),
+                Err(e) => log::error!("Trash purge panicked: {}", e),
+            }
+        }
+    });
+}
+
+fn sqlite_app(db: &str, config: &Config) -> Router {
+    // one writer and a few readers, shared by every request; handlers run
+    // their queries on the blocking thread pool
+    let pool = Pool::open_with_key(db, config.db_readers, config.db_key().as_ref()).expect("DB failed");
+
+    // uploaded receipts/statements live in the per-user app data dir
+    let dirs = ProjectDirs::from("com", "overkill", "budget")
+        .expect("Could not determine the app data directory");
+    let attachments = AttachmentStore::new(dirs.data_dir().join("attachments"));
+    info!("Storing attachments in {}", attachments.root().display());
+    let backups = BackupStore::new(
+        config.backup_dir.clone().unwrap_or_else(|| dirs.data_dir().join("backups")),
+        config.db_key(),
+    );
+    info!("Storing backups in {}", backups.dir().display());
+
+    // empty expired trash now and then every TRASH_PURGE_INTERVAL
+    {
+        let db = pool.clone();
+        let attachments = attachments.clone();
+        let retention_days = config.trash_retention_days;
+        spawn_trash_purge(move || service::purge_expired_trash(&db, &attachments, retention_days));
+    }
+
+    // snapshot the database every backup_interval_hours, starting one
+    // interval from now, and rotate out old snapshots
+    if config.backup_interval_hours > 0 {
+        let db = pool.clone();
+        let backups = backups.clone();
+        let retention = config.backup_retention;
+        let period = Duration::from_secs(config.backup_interval_hours * 60 * 60);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            loop {
+                interval.tick().await;
+                let (db, backups) = (db.clone(), backups.clone());
+                let done = tokio::task::spawn_blocking(move || {
+                    service::backup_database(&db, &backups)?;
+                    Ok::<_, AppError>(backups.rotate(&retention)?)
+                })
+                .await;
+                match done {
+                    Ok(Ok(removed)) if !removed.is_empty() => info!("Rotated out {} old backups", removed.len()),
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => log::error!("Scheduled backup failed: {}", e),
+                    Err(e) => log::error!("Scheduled backup panicked: {}", e),
+                }
+            }
+        });
+    }
+    if config.admin_token.is_none() {
+        info!("No BUDGET_ADMIN_TOKEN, the admin API is off");
+    }
+
+    // pass the SQLite pool into router
+    Router::new()
+        .merge(controllers::pages(pool.clone()))
+        .nest("/api", controllers::routes(pool, attachments, backups, config))
+}
@@ -0,0 +1,23 @@
+// Loaded on every page after htmx.
+
+// Every change is recorded in the history under the actor header, say it
+// was made from the browser UI
+document.addEventListener("htmx:configRequest", (event) => {
+  event.detail.headers["x-budget-actor"] = "web";
+});
+
+// Form validation errors come back as 422, retargeted at the form's error
+// slot. Swap those like a success instead of dropping them.
+htmx.config.responseHandling = [
+  { code: "204", swap: false },
+  { code: "[23]..", swap: true },
+  { code: "422", swap: true },
+  { code: "[45]..", swap: false, error: true },
+  { code: "...", swap: false },
+];
+
+// Undo/redo change records behind the current page's back and say so with
+// an HX-Trigger: reload the page so it shows what is actually saved
+document.body.addEventListener("records-changed", () => {
+  htmx.ajax("GET", window.location.pathname, { target: "main", select: "main" });
+});
@@ -1,23 +0,0 @@
-
-use std::{fmt};
-use axum::{
-    http::StatusCode,
-    response::{IntoResponse, Response},
-};
-
-#[derive(Debug)]
-pub struct AppError(pub String);
-
-impl fmt::Display for AppError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
-    }
-}
-
-impl std::error::Error for AppError {}
-
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        (StatusCode::INTERNAL_SERVER_ERROR, self.0).into_response()
-    }
-}
@@ -1,8 +0,0 @@
-pub mod financial_record;
-pub mod record_type;
-pub mod frequency;
-
-// Re-export for easier imports elsewhere:
-pub use financial_record::FinancialRecord;
-pub use record_type::RecordType;
-pub use frequency::Frequency;
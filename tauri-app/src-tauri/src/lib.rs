@@ -1,14 +1,145 @@
-// Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+//! The desktop app. The budget backend is linked in as a library and the
+//! frontend calls it through the commands below, against a database in the
+//! app data dir; there is no server to start.
+
+use budget_core::charts::MonthTotals;
+use budget_core::models::{FinancialRecord, Frequency, RecordType, Summary};
+use budget_core::pool::Pool;
+use budget_core::service;
+use budget_core::types::Db;
+use chrono::{Datelike, Local, NaiveDate};
+use serde::{Deserialize, Deserializer};
+use tauri::{Manager, State};
+use uuid::Uuid;
+
+// actor recorded in the history for changes made in the desktop app
+const ACTOR: &str = "desktop";
+
+// months the forecast covers when the frontend doesn't say
+const FORECAST_MONTHS: u32 = 12;
+
+/// The fields of a record the user can edit; the backend assigns the id.
+///
+/// The optional fields are left as they are when missing, and cleared when
+/// null.
+#[derive(Debug, Deserialize)]
+struct RecordInput {
+    name: String,
+    amount: f64,
+    frequency: Frequency,
+    record_type: RecordType,
+    #[serde(default, deserialize_with = "present")]
+    notes: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    starts_on: Option<Option<NaiveDate>>,
+}
+
+// Some(None) for a null field, so that it can be told apart from a missing
+// one (None, through #[serde(default)])
+fn present<'de, D: Deserializer<'de>, T: Deserialize<'de>>(d: D) -> Result<Option<Option<T>>, D::Error> {
+    Option::<T>::deserialize(d).map(Some)
+}
+
+impl RecordInput {
+    /// The record with this input's fields, and `existing`'s for the ones it
+    /// leaves out
+    fn into_record(self, id: Uuid, existing: Option<FinancialRecord>) -> FinancialRecord {
+        let (notes, payee, starts_on) = match existing {
+            Some(r) => (r.notes, r.payee, r.starts_on),
+            None => (None, None, None),
+        };
+        FinancialRecord {
+            id,
+            name: self.name,
+            amount: self.amount,
+            frequency: self.frequency,
+            record_type: self.record_type,
+            notes: self.notes.unwrap_or(notes).filter(|n| !n.trim().is_empty()),
+            payee,
+            starts_on: self.starts_on.unwrap_or(starts_on),
+        }
+    }
+}
+
+// Commands return their errors as strings, which is what the frontend shows
+fn to_message(e: impl std::fmt::Display) -> String {
+    e.to_string()
+}
+
+#[tauri::command]
+fn list_records(db: State<'_, Db>, record_type: Option<RecordType>) -> Result<Vec<FinancialRecord>, String> {
+    service::get_records_filtered(db.inner(), record_type, None).map_err(to_message)
+}
+
+#[tauri::command]
+fn get_record(db: State<'_, Db>, id: Uuid) -> Result<FinancialRecord, String> {
+    service::get_record_by_id(db.inner(), &id).map_err(to_message)
+}
+
 #[tauri::command]
-fn greet(name: &str) -> String {
-    format!("Hello, {}! You've been greeted from Rust!", name)
+fn add_record(db: State<'_, Db>, record: RecordInput) -> Result<FinancialRecord, String> {
+    let record = record.into_record(Uuid::nil(), None);
+    service::add_record(db.inner(), &record, ACTOR).map_err(to_message)
+}
+
+#[tauri::command]
+fn update_record(db: State<'_, Db>, id: Uuid, record: RecordInput) -> Result<FinancialRecord, String> {
+    let existing = service::get_record_by_id(db.inner(), &id).map_err(to_message)?;
+    let record = record.into_record(id, Some(existing));
+    service::update_record(db.inner(), &record, ACTOR).map_err(to_message)
+}
+
+#[tauri::command]
+fn delete_record(db: State<'_, Db>, id: Uuid) -> Result<(), String> {
+    service::delete_record(db.inner(), &id, ACTOR).map_err(to_message)
+}
+
+#[tauri::command]
+fn restore_record(db: State<'_, Db>, id: Uuid) -> Result<(), String> {
+    service::restore_record(db.inner(), &id, ACTOR).map_err(to_message)
+}
+
+#[tauri::command]
+fn get_summary(db: State<'_, Db>) -> Result<Summary, String> {
+    service::get_summary(db.inner()).map_err(to_message)
+}
+
+#[tauri::command]
+fn get_forecast(db: State<'_, Db>, months: Option<u32>) -> Result<Vec<MonthTotals>, String> {
+    let today = Local::now().date_naive();
+    let first_month = today.with_day(1).expect("every month has a 1st");
+    service::get_forecast(db.inner(), first_month, months.unwrap_or(FORECAST_MONTHS)).map_err(to_message)
+}
+
+// Opens (creating it on first run) the database in the app data dir
+fn open_database(app: &tauri::App) -> Result<Db, Box<dyn std::error::Error>> {
+    let dir = app.path().app_data_dir()?;
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("budget.db");
+    // commands run one at a time on the main thread, reader connections
+    // would sit idle
+    Ok(Pool::open(&path.to_string_lossy(), 0)?)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(tauri::generate_handler![greet])
+        .setup(|app| {
+            let db = open_database(app)?;
+            app.manage(db);
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            list_records,
+            get_record,
+            add_record,
+            update_record,
+            delete_record,
+            restore_record,
+            get_summary,
+            get_forecast,
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -1,18 +1,136 @@
+// Everything goes through the backend commands in src-tauri/src/lib.rs
 const { invoke } = window.__TAURI__.core;
 
-let greetInputEl;
-let greetMsgEl;
+const money = (amount) => amount.toFixed(2);
 
-async function greet() {
-  // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
-  greetMsgEl.textContent = await invoke("greet", { name: greetInputEl.value });
+// id of the last deleted record, for the undo button
+let lastDeleted = null;
+
+function cell(text) {
+  const td = document.createElement("td");
+  td.textContent = text;
+  return td;
+}
+
+function showError(e) {
+  document.querySelector("#error").textContent = e ?? "";
+}
+
+async function loadSummary() {
+  const summary = await invoke("get_summary");
+  const body = document.querySelector("#summary tbody");
+  body.replaceChildren(
+    ...summary.lines.map((line) => {
+      const tr = document.createElement("tr");
+      tr.append(
+        cell(line.record_type),
+        cell(line.record_count),
+        cell(money(line.monthly_total)),
+        cell(money(line.yearly_total)),
+      );
+      return tr;
+    }),
+  );
+  document.querySelector("#monthly-net").textContent = money(summary.monthly_net);
+}
+
+async function loadRecords() {
+  const records = await invoke("list_records");
+  const body = document.querySelector("#records tbody");
+  body.replaceChildren(
+    ...records.map((record) => {
+      const tr = document.createElement("tr");
+      const remove = document.createElement("button");
+      remove.textContent = "Delete";
+      remove.addEventListener("click", () => deleteRecord(record.id));
+      const actions = document.createElement("td");
+      actions.append(remove);
+      tr.append(
+        cell(record.name),
+        cell(money(record.amount)),
+        cell(record.frequency),
+        cell(record.record_type),
+        actions,
+      );
+      return tr;
+    }),
+  );
+}
+
+async function loadForecast() {
+  const months = await invoke("get_forecast", { months: 12 });
+  const body = document.querySelector("#forecast tbody");
+  body.replaceChildren(
+    ...months.map((month) => {
+      const tr = document.createElement("tr");
+      const net = month.income - month.expenses - month.debt;
+      tr.append(
+        cell(month.month.slice(0, 7)),
+        cell(money(month.income)),
+        cell(money(month.expenses)),
+        cell(money(month.debt)),
+        cell(money(net)),
+      );
+      return tr;
+    }),
+  );
+}
+
+async function refresh() {
+  try {
+    await Promise.all([loadSummary(), loadRecords(), loadForecast()]);
+  } catch (e) {
+    showError(e);
+  }
+}
+
+async function addRecord(form) {
+  const data = new FormData(form);
+  const record = {
+    name: data.get("name"),
+    amount: Number(data.get("amount")),
+    frequency: data.get("frequency"),
+    record_type: data.get("record_type"),
+    starts_on: data.get("starts_on") || null,
+  };
+  try {
+    await invoke("add_record", { record });
+    form.reset();
+    showError(null);
+    await refresh();
+  } catch (e) {
+    showError(e);
+  }
+}
+
+async function deleteRecord(id) {
+  try {
+    await invoke("delete_record", { id });
+    lastDeleted = id;
+    document.querySelector("#undo").hidden = false;
+    await refresh();
+  } catch (e) {
+    showError(e);
+  }
+}
+
+async function undoDelete() {
+  if (!lastDeleted) return;
+  try {
+    await invoke("restore_record", { id: lastDeleted });
+    lastDeleted = null;
+    document.querySelector("#undo").hidden = true;
+    await refresh();
+  } catch (e) {
+    showError(e);
+  }
 }
 
 window.addEventListener("DOMContentLoaded", () => {
-  greetInputEl = document.querySelector("#greet-input");
-  greetMsgEl = document.querySelector("#greet-msg");
-  document.querySelector("#greet-form").addEventListener("submit", (e) => {
+  document.querySelector("#record-form").addEventListener("submit", (e) => {
     e.preventDefault();
-    greet();
+    addRecord(e.target);
   });
+  document.querySelector("#undo button").addEventListener("click", undoDelete);
+  refresh();
 });